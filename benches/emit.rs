@@ -0,0 +1,158 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use tokio::sync::Mutex;
+
+use ocptv::output as tv;
+
+const ELEMENTS: u64 = 1000;
+
+fn series_elements(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut group = c.benchmark_group("emit");
+    group.throughput(Throughput::Elements(ELEMENTS));
+    group.bench_function("series_elements", |b| {
+        b.to_async(&rt).iter_batched(
+            || {
+                let buffer = Arc::new(Mutex::new(Vec::with_capacity(ELEMENTS as usize + 10)));
+                tv::TestRun::builder("bench", "1.0")
+                    .config(tv::Config::builder().with_buffer_output(buffer).build())
+                    .build()
+            },
+            |run| async move {
+                let run = run.start(tv::DutInfo::new("dut0")).await.unwrap();
+                let step = run.add_step("step").start().await.unwrap();
+                let series = step.add_measurement_series("series").start().await.unwrap();
+                for i in 0..ELEMENTS {
+                    series.add_measurement(i).await.unwrap();
+                }
+                series.end().await.unwrap();
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("series_elements_batch", |b| {
+        b.to_async(&rt).iter_batched(
+            || {
+                let buffer = Arc::new(Mutex::new(Vec::with_capacity(ELEMENTS as usize + 10)));
+                tv::TestRun::builder("bench", "1.0")
+                    .config(tv::Config::builder().with_buffer_output(buffer).build())
+                    .build()
+            },
+            |run| async move {
+                let run = run.start(tv::DutInfo::new("dut0")).await.unwrap();
+                let step = run.add_step("step").start().await.unwrap();
+                let series = step.add_measurement_series("series").start().await.unwrap();
+                series.add_measurements(0..ELEMENTS).await.unwrap();
+                series.end().await.unwrap();
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+const HARDWARE_INFOS: usize = 100;
+
+fn start_with_large_dut(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let mut dut = tv::DutInfo::new("dut0");
+    for i in 0..HARDWARE_INFOS {
+        dut.add_hardware_info(
+            tv::HardwareInfo::builder(&format!("dimm{}", i))
+                .location(&format!("board0/dimm{}", i))
+                .serial_no("1234")
+                .manufacturer("vendor")
+                .build(),
+        );
+    }
+    let run = || {
+        tv::TestRun::builder("bench", "1.0")
+            .config(
+                tv::Config::builder()
+                    .with_buffer_output(Arc::new(Mutex::new(vec![])))
+                    .build(),
+            )
+            .build()
+    };
+
+    let mut group = c.benchmark_group("start");
+    // note: the dut clone for the owned case is made in the untimed setup
+    group.bench_function("owned_dut", |b| {
+        b.to_async(&rt).iter_batched(
+            || (run(), dut.clone()),
+            |(run, dut)| async move { run.start(dut).await.unwrap() },
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("borrowed_dut", |b| {
+        b.to_async(&rt).iter_batched(
+            run,
+            |run| async { run.start(&dut).await.unwrap() },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+const MEASUREMENTS: usize = 500;
+
+fn measurements(c: &mut Criterion) {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+
+    let setup = || {
+        let buffer = Arc::new(Mutex::new(Vec::with_capacity(MEASUREMENTS + 10)));
+        let run = tv::TestRun::builder("bench", "1.0")
+            .config(tv::Config::builder().with_buffer_output(buffer).build())
+            .build();
+        let measurements = (0..MEASUREMENTS)
+            .map(|i| tv::Measurement::new(&format!("dimm{}_size", i), 32))
+            .collect::<Vec<_>>();
+        (run, measurements)
+    };
+
+    let mut group = c.benchmark_group("measurements");
+    group.throughput(Throughput::Elements(MEASUREMENTS as u64));
+    group.bench_function("loop", |b| {
+        b.to_async(&rt).iter_batched(
+            setup,
+            |(run, measurements)| async move {
+                let run = run.start(tv::DutInfo::new("dut0")).await.unwrap();
+                let step = run.add_step("step").start().await.unwrap();
+                for m in measurements {
+                    step.add_measurement_detail(m).await.unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.bench_function("batch", |b| {
+        b.to_async(&rt).iter_batched(
+            setup,
+            |(run, measurements)| async move {
+                let run = run.start(tv::DutInfo::new("dut0")).await.unwrap();
+                let step = run.add_step("step").start().await.unwrap();
+                step.add_measurements(measurements).await.unwrap();
+            },
+            BatchSize::SmallInput,
+        );
+    });
+    group.finish();
+}
+
+criterion_group!(benches, series_elements, start_with_large_dut, measurements);
+criterion_main!(benches);
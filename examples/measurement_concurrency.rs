@@ -7,7 +7,6 @@
 use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
-use rand;
 
 use ocptv::output as tv;
 use tv::{DutInfo, TestResult, TestRun, TestRunOutcome, TestStatus};
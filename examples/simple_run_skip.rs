@@ -12,6 +12,7 @@ use tv::{TestResult, TestStatus};
 /// Show a context-scoped run that automatically exits the whole func
 /// because of the marker exception that triggers SKIP outcome.
 #[tokio::main]
+#[allow(clippy::needless_return)]
 async fn main() -> Result<()> {
     let dut = tv::DutInfo::builder("dut0").build();
 
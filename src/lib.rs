@@ -5,4 +5,5 @@
 // https://opensource.org/licenses/MIT.
 
 pub mod output;
-mod spec;
+pub mod reader;
+pub mod spec;
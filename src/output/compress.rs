@@ -0,0 +1,99 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::io::{self, Write};
+
+/// Compression applied to the output stream.
+///
+/// Artifacts are compressed in independent frames, so a partially written output is
+/// still decodable up to the last complete frame. A frame is completed once enough
+/// artifacts were collected, and when the test run ends; until then the compressed
+/// artifacts are held in memory. Compression can only be used with the outputs that
+/// handle raw bytes: file, bytes and async writer outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Zstandard compression, with the given compression level (1-22, or 0 for the
+    /// zstd default).
+    Zstd { level: i32 },
+}
+
+// note: uncompressed size after which a frame is completed
+const FRAME_SIZE: usize = 64 * 1024;
+
+pub(crate) struct Compressor {
+    level: i32,
+    encoder: Option<zstd::stream::write::Encoder<'static, Vec<u8>>>,
+    pending: usize,
+}
+
+impl Compressor {
+    pub(crate) fn new(compression: Compression) -> Self {
+        match compression {
+            Compression::Zstd { level } => Compressor {
+                level,
+                encoder: None,
+                pending: 0,
+            },
+        }
+    }
+
+    /// Adds `data` to the current frame, and returns the frame if it is now complete.
+    pub(crate) fn compress(&mut self, data: &[u8]) -> Result<Option<Vec<u8>>, io::Error> {
+        let encoder = match &mut self.encoder {
+            Some(encoder) => encoder,
+            None => self
+                .encoder
+                .insert(zstd::stream::write::Encoder::new(vec![], self.level)?),
+        };
+        encoder.write_all(data)?;
+        self.pending += data.len();
+
+        if self.pending < FRAME_SIZE {
+            return Ok(None);
+        }
+        self.finish()
+    }
+
+    /// Completes the current frame, if any artifacts were added to it.
+    pub(crate) fn finish(&mut self) -> Result<Option<Vec<u8>>, io::Error> {
+        self.pending = 0;
+        match self.encoder.take() {
+            Some(encoder) => Ok(Some(encoder.finish()?)),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_frames_are_independent() -> Result<()> {
+        let mut compressor = Compressor::new(Compression::Zstd { level: 3 });
+        let line = [b'x'; 1000];
+
+        let mut frames = vec![];
+        for _ in 0..100 {
+            frames.extend(compressor.compress(&line)?);
+        }
+        frames.extend(compressor.finish()?);
+        assert_eq!(frames.len(), 2);
+        assert!(compressor.finish()?.is_none());
+
+        // each frame decodes on its own
+        let mut decoded = vec![];
+        zstd::stream::read::Decoder::new(&frames[0][..])?.read_to_end(&mut decoded)?;
+        assert_eq!(decoded.len(), 66 * 1000);
+
+        Ok(())
+    }
+}
@@ -4,19 +4,50 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use std::path::Path;
+use std::env;
+use std::ffi::OsString;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
+use futures::future::BoxFuture;
+use tokio::io::AsyncWrite;
 use tokio::sync::Mutex;
 
 use crate::output as tv;
-use crate::output::writer::{self, BufferWriter, FileWriter, StdoutWriter, WriterType};
+#[cfg(feature = "zstd")]
+use crate::output::compress::Compression;
+use crate::output::format::OutputFormat;
+use crate::output::redact;
+#[cfg(unix)]
+use crate::output::writer::FifoWriter;
+use crate::output::writer::{
+    self, AsyncWriter, BoundedBuffer, BufferWriter, BytesWriter, FileTemplateWriter, FileWriter,
+    FlushPolicy, Separator, SplitWriter, StdoutWriter, SyncPolicy, TypedBufferWriter, WriterType,
+};
+use crate::spec;
 
 /// The configuration repository for the TestRun.
 pub struct Config {
     // All fields are readable for any impl inside the crate.
     pub(crate) timestamp_provider: Box<dyn TimestampProvider + Send + Sync + 'static>,
     pub(crate) writer: WriterType,
+    pub(crate) sequence_start: u64,
+    pub(crate) emit_hooks: Vec<EmitHook>,
+    pub(crate) transforms: Vec<Transform>,
+    pub(crate) emit_schema_version: bool,
+    pub(crate) separator: Separator,
+    pub(crate) format: OutputFormat,
+    pub(crate) max_artifact_bytes: Option<usize>,
+    pub(crate) oversize_policy: OversizePolicy,
+    pub(crate) non_finite_policy: NonFinitePolicy,
+    pub(crate) write_timeout: Option<Duration>,
+    pub(crate) sync_policy: SyncPolicy,
+    pub(crate) file_uri_resolver: Option<FileUriResolver>,
+    pub(crate) min_log_severity: Option<spec::LogSeverity>,
+    #[cfg(feature = "zstd")]
+    pub(crate) compression: Option<Compression>,
 }
 
 impl Config {
@@ -30,12 +61,97 @@ impl Config {
     pub fn builder() -> ConfigBuilder {
         ConfigBuilder::new()
     }
+
+    /// Creates a config from the environment variables, with the defaults for anything
+    /// that is not set. See [`ConfigBuilder::apply_env`] for the supported variables.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::from_env()?;
+    ///
+    /// # Ok::<(), ConfigError>(())
+    /// ```
+    pub fn from_env() -> Result<Config, ConfigError> {
+        Ok(Config::builder().apply_env()?.build())
+    }
+}
+
+/// Errors for the invalid configurations.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ConfigError {
+    /// An environment variable has a malformed or unsupported value.
+    #[error("invalid value {value:?} for {var}: {reason}")]
+    InvalidEnv {
+        var: &'static str,
+        value: String,
+        reason: String,
+    },
+
+    /// The output file could not be opened, or the output path can't be used, eg. a
+    /// named pipe output whose path doesn't exist.
+    #[error("failed to open output file {path:?}")]
+    Output {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// More than one output was selected on the builder, and only the last one would
+    /// be used.
+    #[error("conflicting outputs selected: {}", .outputs.join(", "))]
+    ConflictingOutputs { outputs: Vec<&'static str> },
+
+    /// An option has a value out of its valid range.
+    #[error("invalid value {value} for {option}: {reason}")]
+    InvalidOption {
+        option: &'static str,
+        value: String,
+        reason: &'static str,
+    },
+
+    /// The selected output cannot be used with the other options, eg. a binary output
+    /// format with an output that only handles text.
+    #[error("the {output} output does not support {option}")]
+    UnsupportedOutput {
+        output: &'static str,
+        option: &'static str,
+    },
+
+    /// The [`ConfigTemplate`] output pattern has no `{}` placeholder, so all the
+    /// configs would write to the same file.
+    #[error("output pattern {0:?} has no {{}} placeholder")]
+    InvalidPattern(String),
+
+    /// The output file template is malformed or uses an unknown placeholder.
+    #[error("invalid output file template {template:?}: {reason}")]
+    InvalidTemplate { template: String, reason: String },
 }
 
 /// The builder for the [`Config`] object.
 pub struct ConfigBuilder {
     timestamp_provider: Box<dyn TimestampProvider + Send + Sync + 'static>,
     writer: Option<WriterType>,
+    sequence_start: u64,
+    emit_hooks: Vec<EmitHook>,
+    transforms: Vec<Transform>,
+    redacted_keys: Vec<String>,
+    emit_schema_version: bool,
+    separator: Separator,
+    format: OutputFormat,
+    max_artifact_bytes: Option<usize>,
+    oversize_policy: OversizePolicy,
+    non_finite_policy: NonFinitePolicy,
+    write_timeout: Option<Duration>,
+    sync_policy: SyncPolicy,
+    file_uri_resolver: Option<FileUriResolver>,
+    min_log_severity: Option<spec::LogSeverity>,
+    #[cfg(feature = "zstd")]
+    compression: Option<Compression>,
+
+    // note: the outputs selected so far, to detect conflicts in try_build
+    outputs: Vec<&'static str>,
 }
 
 impl ConfigBuilder {
@@ -43,6 +159,23 @@ impl ConfigBuilder {
         Self {
             timestamp_provider: Box::new(ConfiguredTzProvider { tz: chrono_tz::UTC }),
             writer: Some(WriterType::Stdout(StdoutWriter::new())),
+            sequence_start: 0,
+            emit_hooks: vec![],
+            transforms: vec![],
+            redacted_keys: vec![],
+            emit_schema_version: true,
+            separator: Separator::Newline,
+            format: OutputFormat::JsonLines,
+            max_artifact_bytes: None,
+            oversize_policy: OversizePolicy::Reject,
+            non_finite_policy: NonFinitePolicy::Reject,
+            write_timeout: None,
+            sync_policy: SyncPolicy::Never,
+            file_uri_resolver: None,
+            min_log_severity: None,
+            #[cfg(feature = "zstd")]
+            compression: None,
+            outputs: vec![],
         }
     }
 
@@ -60,37 +193,1011 @@ impl ConfigBuilder {
         self
     }
 
-    pub fn with_buffer_output(mut self, buffer: Arc<Mutex<Vec<String>>>) -> Self {
-        self.writer = Some(WriterType::Buffer(BufferWriter::new(buffer)));
-        self
+    pub fn with_buffer_output(self, buffer: Arc<Mutex<Vec<String>>>) -> Self {
+        self.with_writer("buffer", WriterType::Buffer(BufferWriter::new(buffer)))
     }
 
-    pub async fn with_file_output<P: AsRef<Path>>(
-        mut self,
+    /// Writes the output to a [`BoundedBuffer`], which holds a limited number of artifacts
+    /// and discards the rest according to its [`writer::OverflowPolicy`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let buffer = BoundedBuffer::new(100, OverflowPolicy::DropOldest);
+    /// let config = Config::builder()
+    ///     .with_bounded_buffer_output(buffer.clone())
+    ///     .build();
+    /// ```
+    pub fn with_bounded_buffer_output(self, buffer: BoundedBuffer) -> Self {
+        self.with_writer("bounded buffer", WriterType::BoundedBuffer(buffer))
+    }
+
+    /// Captures the artifacts as typed [`spec::Root`] values in `buffer`, eg. to match on
+    /// the artifact kinds in tests. See [`TypedBufferWriter`] for details.
+    ///
+    /// The artifacts are captured as emitted, before the emit hooks and the redaction
+    /// set with [`ConfigBuilder::with_redaction`] run.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use std::sync::Arc;
+    /// # use tokio::sync::Mutex;
+    /// # use ocptv::output::*;
+    /// # use ocptv::spec;
+    /// let buffer = Arc::new(Mutex::new(vec![]));
+    /// let config = Config::builder()
+    ///     .with_typed_buffer_output(Arc::clone(&buffer))
+    ///     .build();
+    ///
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::builder("run_name", "1.0").config(config).build();
+    /// run.start(dut).await?.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// assert!(matches!(
+    ///     buffer.lock().await[0].artifact,
+    ///     spec::RootImpl::SchemaVersion(_)
+    /// ));
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn with_typed_buffer_output(self, buffer: Arc<Mutex<Vec<spec::Root>>>) -> Self {
+        self.with_writer(
+            "typed buffer",
+            WriterType::TypedBuffer(TypedBufferWriter::new(buffer)),
+        )
+    }
+
+    /// Writes the raw output stream bytes, including the record framing, to `buffer`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use tokio::sync::Mutex;
+    /// # use ocptv::output::*;
+    /// let buffer = Arc::new(Mutex::new(vec![]));
+    /// let config = Config::builder().with_bytes_output(buffer).build();
+    /// ```
+    pub fn with_bytes_output(self, buffer: Arc<Mutex<Vec<u8>>>) -> Self {
+        self.with_writer("bytes", WriterType::Bytes(BytesWriter::new(buffer)))
+    }
+
+    pub async fn with_file_output<P: AsRef<Path>>(self, path: P) -> Result<Self, tv::OcptvError> {
+        let file = FileWriter::new(path).await?;
+        Ok(self.with_writer("file", WriterType::File(file)))
+    }
+
+    /// Writes the output to a file whose path is filled in from `template` when the test run
+    /// starts, eg. to give each run of a diagnostic against many DUTs its own file.
+    /// The placeholders are:
+    /// - `{run_name}`: the test run name;
+    /// - `{dut_id}`: the id of the DUT the run was started with;
+    /// - `{date}`: the start date, from the timestamp provider, as `YYYY-MM-DD`.
+    ///
+    /// Path separators in the values are replaced by `_`. If the file already exists,
+    /// eg. because another run resolved the same path, a numeric suffix is added before
+    /// the extension (`output_1.jsonl`, `output_2.jsonl`, ...). Runs sharing an
+    /// [`tv::Emitter`] write to the file created by the first one.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_file_output_template("results/{run_name}_{dut_id}_{date}.jsonl")?
+    ///     .build();
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// ```
+    pub fn with_file_output_template(self, template: &str) -> Result<Self, tv::OcptvError> {
+        let writer =
+            FileTemplateWriter::new(template).map_err(|reason| ConfigError::InvalidTemplate {
+                template: template.to_owned(),
+                reason,
+            })?;
+        Ok(self.with_writer("file template", WriterType::FileTemplate(writer)))
+    }
+
+    /// Appends the output to the file at `path` instead of truncating it.
+    /// Combine with [`ConfigBuilder::with_sequence_start`] and
+    /// [`ConfigBuilder::emit_schema_version`] to keep the whole file a valid stream.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_file_output_append("output.jsonl")
+    ///     .await?
+    ///     .build();
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn with_file_output_append<P: AsRef<Path>>(
+        self,
         path: P,
     ) -> Result<Self, tv::OcptvError> {
-        self.writer = Some(WriterType::File(FileWriter::new(path).await?));
+        let file = FileWriter::append(path).await?;
+        Ok(self.with_writer("file", WriterType::File(file)))
+    }
+
+    /// Writes the output into the named pipe (FIFO) at `path`. The pipe is only opened
+    /// when the first artifact is written, since opening it blocks until a reader attaches;
+    /// if there's no reader by then, emitting fails with [`tv::WriterError::NoReader`].
+    /// See [`FifoWriter`] for details.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder().with_fifo_output("/run/diag.fifo").build();
+    /// ```
+    #[cfg(unix)]
+    pub fn with_fifo_output<P: AsRef<Path>>(self, path: P) -> Self {
+        self.with_writer("fifo", WriterType::Fifo(FifoWriter::new(path)))
+    }
+
+    /// Same as [`ConfigBuilder::with_fifo_output`], but the first write waits up to
+    /// `timeout` for a reader to attach to the pipe.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_fifo_output_timeout("/run/diag.fifo", Duration::from_secs(10))
+    ///     .build();
+    /// ```
+    #[cfg(unix)]
+    pub fn with_fifo_output_timeout<P: AsRef<Path>>(self, path: P, timeout: Duration) -> Self {
+        self.with_writer(
+            "fifo",
+            WriterType::Fifo(FifoWriter::with_timeout(path, timeout)),
+        )
+    }
+
+    /// Sends every artifact to the systemd journal, as an entry tagged with `identifier`.
+    /// See [`tv::JournaldWriter`] for the entry fields.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder().with_journald_output("mydiag").build();
+    /// ```
+    #[cfg(all(feature = "journald", target_os = "linux"))]
+    pub fn with_journald_output(self, identifier: &str) -> Self {
+        self.with_writer(
+            "journald",
+            WriterType::Journald(tv::JournaldWriter::new(identifier)),
+        )
+    }
+
+    /// Writes the output into an already open [`AsyncWrite`] sink, eg. a [`tokio::fs::File`]
+    /// or a network stream. The sink is flushed after every artifact; see
+    /// [`ConfigBuilder::with_async_writer_flush`] to only flush at the end of the run.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_async_writer(Box::new(tokio::io::sink()))
+    ///     .build();
+    /// ```
+    pub fn with_async_writer(self, sink: Box<dyn AsyncWrite + Send + Unpin>) -> Self {
+        self.with_async_writer_flush(sink, FlushPolicy::EveryWrite)
+    }
+
+    /// Same as [`ConfigBuilder::with_async_writer`], but with a custom [`FlushPolicy`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_async_writer_flush(Box::new(tokio::io::sink()), FlushPolicy::OnEnd)
+    ///     .build();
+    /// ```
+    pub fn with_async_writer_flush(
+        self,
+        sink: Box<dyn AsyncWrite + Send + Unpin>,
+        policy: FlushPolicy,
+    ) -> Self {
+        self.with_writer(
+            "async writer",
+            WriterType::Async(AsyncWriter::new(sink, policy)),
+        )
+    }
+
+    /// Writes the measurement artifacts to the file at `measurements_path`, and everything
+    /// else to the file at `other_path`. Both files are json lines, and the sequence
+    /// numbers are global across them. See [`SplitWriter::measurements`].
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_split_output("measurements.jsonl", "other.jsonl")
+    ///     .await?
+    ///     .build();
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn with_split_output<P: AsRef<Path>>(
+        self,
+        measurements_path: P,
+        other_path: P,
+    ) -> Result<Self, tv::OcptvError> {
+        let measurements = FileWriter::new(measurements_path).await?;
+        let other = FileWriter::new(other_path).await?;
+
+        Ok(self.with_split_writer(SplitWriter::measurements(
+            Box::new(measurements),
+            Box::new(other),
+        )))
+    }
+
+    fn with_writer(mut self, name: &'static str, writer: WriterType) -> Self {
+        self.outputs.push(name);
+        self.writer = Some(writer);
+        self
+    }
+
+    /// Applies the configuration from the environment variables on top of this builder.
+    /// Unset variables leave the builder unchanged. The file output is opened
+    /// synchronously, so this can be used outside of an async context.
+    ///
+    /// | Variable | Values |
+    /// |---|---|
+    /// | `OCPTV_OUTPUT` | `stdout`, or `file:<path>` |
+    /// | `OCPTV_APPEND` | `true`/`1` to append to the `file:` output instead of truncating it, `false`/`0` |
+    /// | `OCPTV_TIMEZONE` | IANA timezone name, eg. `UTC` or `Europe/Rome` |
+    /// | `OCPTV_SEQUENCE_START` | first sequence number, see [`ConfigBuilder::with_sequence_start`] |
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .timezone(chrono_tz::Europe::Rome)
+    ///     .apply_env()?
+    ///     .build();
+    ///
+    /// # Ok::<(), ConfigError>(())
+    /// ```
+    pub fn apply_env(self) -> Result<Self, ConfigError> {
+        self.apply_vars(|name| env::var_os(name))
+    }
+
+    fn apply_vars<F>(mut self, var: F) -> Result<Self, ConfigError>
+    where
+        F: Fn(&str) -> Option<OsString>,
+    {
+        let read = |name: &'static str| -> Result<Option<String>, ConfigError> {
+            var(name)
+                .map(|value| {
+                    value
+                        .into_string()
+                        .map_err(|value| ConfigError::InvalidEnv {
+                            var: name,
+                            value: value.to_string_lossy().into_owned(),
+                            reason: "not valid unicode".to_owned(),
+                        })
+                })
+                .transpose()
+        };
+        let invalid = |var: &'static str, value: &str, reason: &str| ConfigError::InvalidEnv {
+            var,
+            value: value.to_owned(),
+            reason: reason.to_owned(),
+        };
+
+        if let Some(tz) = read("OCPTV_TIMEZONE")? {
+            let tz = tz
+                .parse::<chrono_tz::Tz>()
+                .map_err(|_| invalid("OCPTV_TIMEZONE", &tz, "unknown timezone"))?;
+            self = self.timezone(tz);
+        }
+
+        if let Some(start) = read("OCPTV_SEQUENCE_START")? {
+            let start = start
+                .parse::<u64>()
+                .map_err(|e| invalid("OCPTV_SEQUENCE_START", &start, &e.to_string()))?;
+            self = self.with_sequence_start(start);
+        }
+
+        let append = match read("OCPTV_APPEND")? {
+            None => false,
+            Some(v) => match v.as_str() {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Err(invalid("OCPTV_APPEND", &v, "expected true/false or 1/0")),
+            },
+        };
+
+        match read("OCPTV_OUTPUT")? {
+            None if append => {
+                return Err(invalid(
+                    "OCPTV_APPEND",
+                    "true",
+                    "needs a file output in OCPTV_OUTPUT",
+                ))
+            }
+            None => {}
+            Some(output) if output == "stdout" => {
+                if append {
+                    return Err(invalid("OCPTV_APPEND", "true", "stdout cannot be appended"));
+                }
+                // note: the environment overrides the output selected in code
+                self.outputs.clear();
+                self = self.with_writer("stdout", WriterType::Stdout(StdoutWriter::new()));
+            }
+            Some(output) => {
+                let Some(path) = output.strip_prefix("file:").filter(|p| !p.is_empty()) else {
+                    return Err(invalid(
+                        "OCPTV_OUTPUT",
+                        &output,
+                        "expected stdout or file:<path>",
+                    ));
+                };
+
+                let file = if append {
+                    FileWriter::append_blocking(path)
+                } else {
+                    FileWriter::create_blocking(path)
+                };
+                let file = file.map_err(|source| ConfigError::Output {
+                    path: PathBuf::from(path),
+                    source,
+                })?;
+                self.outputs.clear();
+                self = self.with_writer("file", WriterType::File(file));
+            }
+        }
+
         Ok(self)
     }
 
+    /// Routes each artifact to one of two writers, see [`SplitWriter`].
+    pub fn with_split_writer(self, writer: SplitWriter) -> Self {
+        self.with_writer("split", WriterType::Split(writer))
+    }
+
     pub fn with_custom_output(
-        mut self,
+        self,
         custom: Box<dyn writer::Writer + Send + Sync + 'static>,
     ) -> Self {
-        self.writer = Some(WriterType::Custom(custom));
+        self.with_writer("custom", WriterType::Custom(custom))
+    }
+
+    /// Sets the sequence number of the first emitted artifact (the schemaVersion).
+    /// This is useful when appending to an existing output, so that sequence numbers
+    /// keep increasing across the whole file. Defaults to 0.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder().with_sequence_start(42).build();
+    /// ```
+    pub fn with_sequence_start(mut self, n: u64) -> Self {
+        self.sequence_start = n;
+        self
+    }
+
+    /// Adds a hook that is called with every artifact before it is written.
+    /// The hook receives the artifact json object, without the `sequenceNumber`
+    /// and `timestamp` fields, and may mutate it. Those fields are always set by the
+    /// emitter after all hooks have run, so hooks cannot alter the sequence numbering.
+    /// Multiple hooks are called in the order they were added.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_emit_hook(Box::new(|artifact: &mut Value| {
+    ///         if let Some(start) = artifact.pointer_mut("/testRunArtifact/testRunStart") {
+    ///             start["metadata"]["fleet"] = "prod".into();
+    ///         }
+    ///     }))
+    ///     .build();
+    /// ```
+    pub fn with_emit_hook(mut self, hook: EmitHook) -> Self {
+        self.emit_hooks.push(hook);
+        self
+    }
+
+    /// Adds a transform that is called with every artifact, after the emit hooks and
+    /// right before the artifact is written. Unlike the emit hooks, the transform receives
+    /// the whole artifact, including the envelope fields (eg. `timestamp`), and returns
+    /// the artifact to write, so it can adapt the output to a superset of the OCPTV schema.
+    /// Multiple transforms are called in the order they were added.
+    ///
+    /// The `sequenceNumber` is always set back to the assigned value after the
+    /// transforms, so they cannot alter the sequence numbering. If a transform panics or
+    /// doesn't return a json object, the emitting call fails with
+    /// [`tv::WriterError::Transform`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_transform(Box::new(|mut artifact: Value| {
+    ///         artifact["site"] = "us-east".into();
+    ///         artifact
+    ///     }))
+    ///     .build();
+    /// ```
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transforms.push(transform);
+        self
+    }
+
+    /// Sets the resolver for the uri of the files added with
+    /// [`tv::StartedTestStep::add_file_from_path`], eg. to upload them to an object store
+    /// and reference the uploaded copy. The resolver gets the canonical path of the file,
+    /// and returns the uri to put in the file artifact, which must have a `file`, `http`
+    /// or `https` scheme. If the resolver fails, so does the call adding the file, and
+    /// nothing is emitted; the resolver should apply its own timeout.
+    /// Without a resolver, the `file://` uri of the path is used.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use futures::FutureExt;
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_file_uri_resolver(Box::new(|path| {
+    ///         let name = path.file_name().unwrap().to_string_lossy().into_owned();
+    ///         async move {
+    ///             // upload the file here
+    ///             Ok(format!("https://storage.example.com/artifacts/{}", name))
+    ///         }
+    ///         .boxed()
+    ///     }))
+    ///     .build();
+    /// ```
+    pub fn with_file_uri_resolver(mut self, resolver: FileUriResolver) -> Self {
+        self.file_uri_resolver = Some(resolver);
+        self
+    }
+
+    /// Replaces the values of `keys` with `"<redacted>"` before the artifacts are
+    /// written, eg. to keep credentials out of the output. This applies to the
+    /// `parameters` and all the `metadata` maps, at any depth and for every artifact kind,
+    /// and to the `commandLine` arguments. Keys are matched case insensitively.
+    ///
+    /// Redaction runs after all the emit hooks, so hooks cannot reintroduce the values.
+    /// It does not apply to the typed buffer output, which captures the artifacts before
+    /// the hooks run; [`ConfigBuilder::try_build`] rejects this combination.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_redaction(vec!["password".to_owned(), "token".to_owned()])
+    ///     .build();
+    /// ```
+    pub fn with_redaction(mut self, keys: Vec<String>) -> Self {
+        self.redacted_keys.extend(keys);
         self
     }
 
-    pub fn build(self) -> Config {
+    // note: redaction is always the last hook
+    fn take_hooks(&mut self) -> Vec<EmitHook> {
+        let mut hooks = std::mem::take(&mut self.emit_hooks);
+        if !self.redacted_keys.is_empty() {
+            hooks.push(redact::hook(std::mem::take(&mut self.redacted_keys)));
+        }
+        hooks
+    }
+
+    /// Controls whether the `schemaVersion` artifact is automatically emitted before
+    /// the first artifact of the output. Defaults to `true`.
+    ///
+    /// Disabling this is useful when multiple runs share the same output and the header
+    /// should only be present once. See [`tv::TestRun::emit_schema_version`] for emitting
+    /// it manually.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder().emit_schema_version(false).build();
+    /// ```
+    pub fn emit_schema_version(mut self, value: bool) -> Self {
+        self.emit_schema_version = value;
+        self
+    }
+
+    /// Sets the framing used between artifacts in the output stream.
+    /// Defaults to [`Separator::Newline`].
+    ///
+    /// This applies to the stdout, file and bytes outputs. The buffer output and
+    /// custom writers always receive a single unframed artifact per write.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder().with_record_separator(Separator::Rs).build();
+    /// ```
+    pub fn with_record_separator(mut self, separator: Separator) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Sets a timeout for each write to the output. If a write doesn't complete in time,
+    /// the emitting call returns a [`tv::WriterError::Timeout`] and the caller can decide
+    /// whether to abort the run or continue with a different output.
+    /// By default, writes are not time limited.
+    ///
+    /// Note that the artifact that timed out may have been partially written.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::time::Duration;
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_write_timeout(Duration::from_secs(5))
+    ///     .build();
+    /// ```
+    pub fn with_write_timeout(mut self, timeout: Duration) -> Self {
+        self.write_timeout = Some(timeout);
+        self
+    }
+
+    /// Limits the size of each serialized artifact, not including the record framing.
+    /// What happens to larger artifacts depends on the [`OversizePolicy`]. There is no
+    /// limit by default.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_max_artifact_bytes(1024 * 1024)
+    ///     .with_oversize_policy(OversizePolicy::Truncate)
+    ///     .build();
+    /// ```
+    pub fn with_max_artifact_bytes(mut self, limit: usize) -> Self {
+        self.max_artifact_bytes = Some(limit);
+        self
+    }
+
+    /// Drops the log artifacts less severe than `severity`, both in the run and in the
+    /// steps, without assigning them a sequence number. The number of dropped logs is
+    /// available in [`tv::EmitterStats::suppressed_logs`]. All logs are kept by default.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_min_log_severity(LogSeverity::Info)
+    ///     .build();
+    /// ```
+    pub fn with_min_log_severity(mut self, severity: spec::LogSeverity) -> Self {
+        self.min_log_severity = Some(severity);
+        self
+    }
+
+    /// Sets what happens to the artifacts over the [`ConfigBuilder::with_max_artifact_bytes`]
+    /// limit. Defaults to [`OversizePolicy::Reject`].
+    pub fn with_oversize_policy(mut self, policy: OversizePolicy) -> Self {
+        self.oversize_policy = policy;
+        self
+    }
+
+    /// Sets what happens to the NaN and infinite measurement, series element and validator
+    /// values, which json can't represent. Defaults to [`NonFinitePolicy::Reject`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_non_finite_policy(NonFinitePolicy::Encode)
+    ///     .build();
+    /// ```
+    pub fn with_non_finite_policy(mut self, policy: NonFinitePolicy) -> Self {
+        self.non_finite_policy = policy;
+        self
+    }
+
+    /// Sets the encoding of the artifacts in the output stream.
+    /// Defaults to [`OutputFormat::JsonLines`].
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use tokio::sync::Mutex;
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_bytes_output(Arc::new(Mutex::new(vec![])))
+    ///     .with_output_format(OutputFormat::JsonLines)
+    ///     .build();
+    /// ```
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Compresses the output stream, see [`Compression`]. Requires the `zstd` feature.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_file_output("output.jsonl.zst")
+    ///     .await?
+    ///     .with_compression(Compression::Zstd { level: 3 })
+    ///     .build();
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    #[cfg(feature = "zstd")]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Sets when the file output is synced to the storage device. This guarantees that,
+    /// after a sync, every artifact emitted so far survives a crash or power loss.
+    /// Only applies to the file output. Defaults to [`SyncPolicy::Never`].
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let config = Config::builder()
+    ///     .with_file_output("output.jsonl")
+    ///     .await?
+    ///     .with_sync_policy(SyncPolicy::EveryWrite)
+    ///     .build();
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn with_sync_policy(mut self, policy: SyncPolicy) -> Self {
+        self.sync_policy = policy;
+        self
+    }
+
+    /// Builds the config, without checking the options: when outputs conflict, the last
+    /// selected one wins, and out of range values are used as is (eg. a zero
+    /// `max_artifact_bytes` rejects every artifact). Use [`ConfigBuilder::try_build`]
+    /// to reject these.
+    pub fn build(mut self) -> Config {
+        let emit_hooks = self.take_hooks();
         Config {
             timestamp_provider: self.timestamp_provider,
             writer: self
                 .writer
                 .unwrap_or(WriterType::Stdout(StdoutWriter::new())),
+            sequence_start: self.sequence_start,
+            emit_hooks,
+            transforms: self.transforms,
+            emit_schema_version: self.emit_schema_version,
+            separator: self.separator,
+            format: self.format,
+            max_artifact_bytes: self.max_artifact_bytes,
+            oversize_policy: self.oversize_policy,
+            non_finite_policy: self.non_finite_policy,
+            write_timeout: self.write_timeout,
+            sync_policy: self.sync_policy,
+            file_uri_resolver: self.file_uri_resolver,
+            min_log_severity: self.min_log_severity,
+            #[cfg(feature = "zstd")]
+            compression: self.compression,
         }
     }
+
+    /// Builds the config, after checking that the options are consistent and in range,
+    /// and that the paths of the outputs opened later, eg. the named pipe, are usable.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use tokio::sync::Mutex;
+    /// # use ocptv::output::*;
+    /// let result = Config::builder()
+    ///     .with_buffer_output(Arc::new(Mutex::new(vec![])))
+    ///     .with_bytes_output(Arc::new(Mutex::new(vec![])))
+    ///     .try_build();
+    ///
+    /// assert!(matches!(result, Err(ConfigError::ConflictingOutputs { .. })));
+    /// ```
+    pub fn try_build(self) -> Result<Config, ConfigError> {
+        self.validate()?;
+        Ok(self.build())
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let invalid = |option, value: String, reason| ConfigError::InvalidOption {
+            option,
+            value,
+            reason,
+        };
+
+        if self.outputs.len() > 1 {
+            return Err(ConfigError::ConflictingOutputs {
+                outputs: self.outputs.clone(),
+            });
+        }
+        let output = self.outputs.last().copied().unwrap_or("stdout");
+
+        #[cfg(unix)]
+        if let Some(WriterType::Fifo(fifo)) = &self.writer {
+            fifo.check_path().map_err(|source| ConfigError::Output {
+                path: fifo.path().to_path_buf(),
+                source,
+            })?;
+        }
+
+        if self.sequence_start > i64::MAX as u64 {
+            return Err(invalid(
+                "sequence_start",
+                self.sequence_start.to_string(),
+                "sequence numbers must fit in a signed 64-bit integer",
+            ));
+        }
+        if self.max_artifact_bytes == Some(0) {
+            return Err(invalid(
+                "max_artifact_bytes",
+                "0".to_owned(),
+                "must be greater than zero",
+            ));
+        }
+        if self.write_timeout == Some(Duration::ZERO) {
+            return Err(invalid(
+                "write_timeout",
+                "0s".to_owned(),
+                "must be greater than zero",
+            ));
+        }
+        if self.sync_policy == SyncPolicy::EveryN(0) {
+            return Err(invalid(
+                "sync_policy",
+                "EveryN(0)".to_owned(),
+                "must sync after at least one artifact",
+            ));
+        }
+        if self.sync_policy != SyncPolicy::Never && !matches!(output, "file" | "file template") {
+            return Err(ConfigError::UnsupportedOutput {
+                output,
+                option: "sync_policy",
+            });
+        }
+
+        // note: the typed buffer captures the artifacts before the emit hooks run
+        if !self.redacted_keys.is_empty() && output == "typed buffer" {
+            return Err(ConfigError::UnsupportedOutput {
+                output,
+                option: "redaction",
+            });
+        }
+
+        let byte_oriented = self.writer.as_ref().map_or(true, |w| w.is_byte_oriented());
+        if self.format != OutputFormat::JsonLines && !byte_oriented {
+            return Err(ConfigError::UnsupportedOutput {
+                output,
+                option: "binary output formats",
+            });
+        }
+
+        #[cfg(feature = "zstd")]
+        if let Some(Compression::Zstd { level }) = self.compression {
+            if !byte_oriented {
+                return Err(ConfigError::UnsupportedOutput {
+                    output,
+                    option: "compression",
+                });
+            }
+            if !zstd::compression_level_range().contains(&level) {
+                return Err(invalid(
+                    "compression",
+                    level.to_string(),
+                    "not a valid zstd compression level",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Builds a [`ConfigTemplate`] that stamps out per-run configs writing to files named
+    /// after `output_pattern`. Any output set on this builder is ignored.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let template = Config::builder()
+    ///     .timezone(chrono_tz::Europe::Rome)
+    ///     .build_template("output_{}.jsonl");
+    /// ```
+    pub fn build_template(mut self, output_pattern: &str) -> ConfigTemplate {
+        let hooks = self.take_hooks();
+        let has_hooks = !hooks.is_empty();
+        ConfigTemplate {
+            timestamp_provider: Arc::from(self.timestamp_provider),
+            emit_hooks: Arc::new(move |value: &mut tv::Value| {
+                for hook in &hooks {
+                    hook(value);
+                }
+            }),
+            has_hooks,
+            transforms: Arc::new(self.transforms),
+            sequence_start: self.sequence_start,
+            emit_schema_version: self.emit_schema_version,
+            separator: self.separator,
+            format: self.format,
+            max_artifact_bytes: self.max_artifact_bytes,
+            oversize_policy: self.oversize_policy,
+            non_finite_policy: self.non_finite_policy,
+            write_timeout: self.write_timeout,
+            sync_policy: self.sync_policy,
+            file_uri_resolver: self.file_uri_resolver.map(Arc::from),
+            min_log_severity: self.min_log_severity,
+            #[cfg(feature = "zstd")]
+            compression: self.compression,
+            output_pattern: output_pattern.to_owned(),
+        }
+    }
+}
+
+/// Reusable configuration, for creating many similar test runs (eg. one per DUT shard).
+///
+/// The timestamp provider, the emit hooks, the transforms and the file uri resolver are
+/// shared by all the configs stamped out of the template; the output is created anew
+/// for every config. Created with [`ConfigBuilder::build_template`].
+pub struct ConfigTemplate {
+    timestamp_provider: Arc<dyn TimestampProvider + Send + Sync + 'static>,
+    emit_hooks: Arc<dyn Fn(&mut tv::Value) + Send + Sync + 'static>,
+    has_hooks: bool,
+    transforms: Arc<Vec<Transform>>,
+    sequence_start: u64,
+    emit_schema_version: bool,
+    separator: Separator,
+    format: OutputFormat,
+    max_artifact_bytes: Option<usize>,
+    oversize_policy: OversizePolicy,
+    non_finite_policy: NonFinitePolicy,
+    write_timeout: Option<Duration>,
+    sync_policy: SyncPolicy,
+    file_uri_resolver: Option<Arc<FileUriResolver>>,
+    min_log_severity: Option<spec::LogSeverity>,
+    #[cfg(feature = "zstd")]
+    compression: Option<Compression>,
+    output_pattern: String,
+}
+
+impl ConfigTemplate {
+    /// Returns a config writing to the file named after the template output pattern,
+    /// with every `{}` replaced by `key`. The file is created synchronously, so this can
+    /// be used outside of an async context.
+    ///
+    /// # Examples
+    /// ```rust,no_run
+    /// # use ocptv::output::*;
+    /// let template = Config::builder().build_template("output_{}.jsonl");
+    ///
+    /// for shard in ["shard0", "shard1"] {
+    ///     let run = TestRun::builder("run_name", "1.0")
+    ///         .config(template.config(shard)?)
+    ///         .build();
+    /// }
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// ```
+    pub fn config(&self, key: &str) -> Result<Config, tv::OcptvError> {
+        if !self.output_pattern.contains("{}") {
+            return Err(ConfigError::InvalidPattern(self.output_pattern.clone()).into());
+        }
+
+        let path = self.output_pattern.replace("{}", key);
+        let file = FileWriter::create_blocking(path)?;
+
+        Ok(self
+            .builder()
+            .with_writer("file", WriterType::File(file))
+            .build())
+    }
+
+    /// Returns a builder with the template settings, for using an output other than
+    /// the template file pattern.
+    ///
+    /// # Examples
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use tokio::sync::Mutex;
+    /// # use ocptv::output::*;
+    /// let template = Config::builder().build_template("output_{}.jsonl");
+    ///
+    /// let buffer = Arc::new(Mutex::new(vec![]));
+    /// let config = template.builder().with_buffer_output(buffer).build();
+    /// ```
+    pub fn builder(&self) -> ConfigBuilder {
+        let mut builder = ConfigBuilder::new()
+            .with_timestamp_provider(Box::new(SharedTsProvider(Arc::clone(
+                &self.timestamp_provider,
+            ))))
+            .with_sequence_start(self.sequence_start)
+            .emit_schema_version(self.emit_schema_version)
+            .with_record_separator(self.separator)
+            .with_output_format(self.format)
+            .with_oversize_policy(self.oversize_policy)
+            .with_non_finite_policy(self.non_finite_policy)
+            .with_sync_policy(self.sync_policy);
+
+        if let Some(timeout) = self.write_timeout {
+            builder = builder.with_write_timeout(timeout);
+        }
+        if let Some(limit) = self.max_artifact_bytes {
+            builder = builder.with_max_artifact_bytes(limit);
+        }
+        if let Some(severity) = &self.min_log_severity {
+            builder = builder.with_min_log_severity(severity.clone());
+        }
+        #[cfg(feature = "zstd")]
+        if let Some(compression) = self.compression {
+            builder = builder.with_compression(compression);
+        }
+        if self.has_hooks {
+            let hooks = Arc::clone(&self.emit_hooks);
+            builder = builder.with_emit_hook(Box::new(move |value| hooks(value)));
+        }
+        if let Some(resolver) = &self.file_uri_resolver {
+            let resolver = Arc::clone(resolver);
+            builder = builder.with_file_uri_resolver(Box::new(move |path| resolver(path)));
+        }
+        if !self.transforms.is_empty() {
+            let transforms = Arc::clone(&self.transforms);
+            builder = builder.with_transform(Box::new(move |value| {
+                transforms
+                    .iter()
+                    .fold(value, |value, transform| transform(value))
+            }));
+        }
+        builder
+    }
 }
 
+/// What the emitter does with an artifact over the configured size limit.
+/// See [`ConfigBuilder::with_max_artifact_bytes`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OversizePolicy {
+    /// Don't write the artifact, and fail the emitting call with
+    /// [`writer::WriterError::ArtifactTooLarge`]. This is the default.
+    #[default]
+    Reject,
+    /// Remove all the `metadata` maps from the artifact, and follow it with an error
+    /// artifact (symptom `ocptv-artifact-truncated`) describing the truncation. If the
+    /// artifact is still too large, it is rejected.
+    Truncate,
+}
+
+/// What happens to the NaN and infinite values of measurements, series elements and
+/// validators. See [`ConfigBuilder::with_non_finite_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonFinitePolicy {
+    /// Don't emit the artifact, and fail the emitting call with
+    /// [`tv::OcptvError::InvalidValue`]. This is the default.
+    #[default]
+    Reject,
+    /// Emit the value as one of the strings `"NaN"`, `"Inf"` or `"-Inf"`, and mark the
+    /// artifact with the `ocptv_non_finite` metadata key, set to `true`.
+    Encode,
+}
+
+/// Callback that observes and optionally mutates every artifact before it is written.
+/// See [`ConfigBuilder::with_emit_hook`].
+pub type EmitHook = Box<dyn Fn(&mut tv::Value) + Send + Sync + 'static>;
+
+/// Callback that maps every artifact, including its envelope, to the artifact to write.
+/// See [`ConfigBuilder::with_transform`].
+pub type Transform = Box<dyn Fn(tv::Value) -> tv::Value + Send + Sync + 'static>;
+
+/// Callback that resolves the uri of a local file added as a file artifact, eg. by
+/// uploading it. See [`ConfigBuilder::with_file_uri_resolver`].
+pub type FileUriResolver = Box<
+    dyn Fn(&Path) -> BoxFuture<'static, Result<String, tv::OcptvError>> + Send + Sync + 'static,
+>;
+
 /// TODO: docs
 pub trait TimestampProvider {
     fn now(&self) -> chrono::DateTime<chrono_tz::Tz>;
@@ -105,3 +1212,233 @@ impl TimestampProvider for ConfiguredTzProvider {
         chrono::Local::now().with_timezone(&self.tz)
     }
 }
+
+struct SharedTsProvider(Arc<dyn TimestampProvider + Send + Sync + 'static>);
+
+impl TimestampProvider for SharedTsProvider {
+    fn now(&self) -> chrono::DateTime<chrono_tz::Tz> {
+        self.0.now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    fn apply(vars: &[(&str, &str)]) -> Result<ConfigBuilder, ConfigError> {
+        let vars = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), OsString::from(v)))
+            .collect::<HashMap<_, _>>();
+        Config::builder().apply_vars(|name| vars.get(name).cloned())
+    }
+
+    fn invalid_var(result: Result<ConfigBuilder, ConfigError>) -> Option<&'static str> {
+        match result {
+            Err(ConfigError::InvalidEnv { var, .. }) => Some(var),
+            _ => None,
+        }
+    }
+
+    fn buffer() -> Arc<Mutex<Vec<String>>> {
+        Arc::new(Mutex::new(vec![]))
+    }
+
+    #[test]
+    fn test_try_build_accepts_defaults() -> Result<()> {
+        Config::builder().try_build()?;
+        Config::builder().with_buffer_output(buffer()).try_build()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_build_rejects_conflicting_outputs() -> Result<()> {
+        let result = Config::builder()
+            .with_buffer_output(buffer())
+            .with_bytes_output(Arc::new(Mutex::new(vec![])))
+            .try_build();
+
+        match result {
+            Err(ConfigError::ConflictingOutputs { outputs }) => {
+                assert_eq!(outputs, vec!["buffer", "bytes"]);
+            }
+            _ => panic!("expected conflicting outputs error"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_build_rejects_invalid_numeric_options() -> Result<()> {
+        let option = |builder: ConfigBuilder| match builder.try_build() {
+            Err(ConfigError::InvalidOption { option, .. }) => Some(option),
+            _ => None,
+        };
+
+        assert_eq!(
+            option(Config::builder().with_sequence_start(u64::MAX)),
+            Some("sequence_start")
+        );
+        assert_eq!(
+            option(Config::builder().with_max_artifact_bytes(0)),
+            Some("max_artifact_bytes")
+        );
+        assert_eq!(
+            option(Config::builder().with_write_timeout(Duration::ZERO)),
+            Some("write_timeout")
+        );
+        assert_eq!(
+            option(Config::builder().with_sync_policy(SyncPolicy::EveryN(0))),
+            Some("sync_policy")
+        );
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            option(
+                Config::builder()
+                    .with_bytes_output(Arc::new(Mutex::new(vec![])))
+                    .with_compression(Compression::Zstd { level: 100 })
+            ),
+            Some("compression")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_build_rejects_unsupported_output() -> Result<()> {
+        let result = Config::builder()
+            .with_buffer_output(buffer())
+            .with_sync_policy(SyncPolicy::EveryWrite)
+            .try_build();
+        assert!(matches!(
+            result,
+            Err(ConfigError::UnsupportedOutput {
+                output: "buffer",
+                option: "sync_policy"
+            })
+        ));
+
+        #[cfg(feature = "msgpack")]
+        assert!(matches!(
+            Config::builder()
+                .with_output_format(OutputFormat::MessagePack)
+                .try_build(),
+            Err(ConfigError::UnsupportedOutput {
+                output: "stdout",
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_build_rejects_redacted_typed_buffer() -> Result<()> {
+        let result = Config::builder()
+            .with_typed_buffer_output(Arc::new(Mutex::new(vec![])))
+            .with_redaction(vec!["password".to_owned()])
+            .try_build();
+        assert!(matches!(
+            result,
+            Err(ConfigError::UnsupportedOutput {
+                output: "typed buffer",
+                option: "redaction"
+            })
+        ));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_try_build_rejects_invalid_fifo_path() -> Result<()> {
+        let error_kind = |path: PathBuf| match Config::builder().with_fifo_output(path).try_build()
+        {
+            Err(ConfigError::Output { source, .. }) => Some(source.kind()),
+            _ => None,
+        };
+
+        let missing = std::env::temp_dir().join("ocptv_missing_dir/diag.fifo");
+        assert_eq!(error_kind(missing), Some(io::ErrorKind::NotFound));
+        // note: a directory exists, but is not a named pipe
+        assert_eq!(
+            error_kind(std::env::temp_dir()),
+            Some(io::ErrorKind::InvalidInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_output_template_rejects_invalid_templates() -> Result<()> {
+        for template in ["{foo}.jsonl", "{run_name.jsonl", "a}b"] {
+            let result = Config::builder().with_file_output_template(template);
+            assert!(matches!(
+                result,
+                Err(tv::OcptvError::Config(ConfigError::InvalidTemplate { .. }))
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_template_rejects_pattern_without_placeholder() -> Result<()> {
+        let template = Config::builder().build_template("output.jsonl");
+        assert!(matches!(
+            template.config("shard0"),
+            Err(tv::OcptvError::Config(ConfigError::InvalidPattern(_)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_env_values() -> Result<()> {
+        let builder = apply(&[
+            ("OCPTV_OUTPUT", "stdout"),
+            ("OCPTV_TIMEZONE", "Europe/Rome"),
+            ("OCPTV_SEQUENCE_START", "42"),
+        ])?;
+        assert!(matches!(builder.writer, Some(WriterType::Stdout(_))));
+        assert_eq!(builder.sequence_start, 42);
+        assert_eq!(
+            builder.timestamp_provider.now().timezone(),
+            chrono_tz::Europe::Rome
+        );
+
+        let builder = apply(&[])?;
+        assert_eq!(builder.sequence_start, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_env_rejects_malformed_values() -> Result<()> {
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_OUTPUT", "socket:1234")])),
+            Some("OCPTV_OUTPUT")
+        );
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_OUTPUT", "file:")])),
+            Some("OCPTV_OUTPUT")
+        );
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_TIMEZONE", "Mars/Olympus")])),
+            Some("OCPTV_TIMEZONE")
+        );
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_SEQUENCE_START", "-1")])),
+            Some("OCPTV_SEQUENCE_START")
+        );
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_APPEND", "yes")])),
+            Some("OCPTV_APPEND")
+        );
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_APPEND", "1")])),
+            Some("OCPTV_APPEND")
+        );
+        assert_eq!(
+            invalid_var(apply(&[("OCPTV_OUTPUT", "stdout"), ("OCPTV_APPEND", "1")])),
+            Some("OCPTV_APPEND")
+        );
+        Ok(())
+    }
+}
@@ -40,13 +40,22 @@ impl DutInfo {
         DutInfoBuilder::new(id).build()
     }
 
+    /// Returns the DUT identifier.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
     pub fn add_software_info(&mut self, info: SoftwareInfo) -> DutSoftwareInfo {
         let id = match &info.id {
             Ident::Auto => format!("{}_sw_{}", self.id, self.software_infos.len()),
             Ident::Exact(v) => v.to_owned(),
         };
 
-        let info = DutSoftwareInfo { id, source: info };
+        let info = DutSoftwareInfo {
+            id,
+            dut_id: self.id.clone(),
+            source: info,
+        };
         self.software_infos.push(info.clone());
         info
     }
@@ -80,8 +89,96 @@ impl DutInfo {
             metadata: self.metadata.option(),
         }
     }
+
+    /// Rebuilds the DUT from the `dutInfo` object of an emitted `testRunStart`, keeping
+    /// the software and hardware info ids. Unknown or malformed fields are ignored.
+    pub(crate) fn from_value(value: &tv::Value) -> DutInfo {
+        let text = |value: &tv::Value, key: &str| value[key].as_str().map(str::to_owned);
+        let list = |key: &str| value[key].as_array().cloned().unwrap_or_default();
+
+        let mut builder = DutInfo::builder(&text(value, "dutInfoId").unwrap_or_default());
+        if let Some(name) = text(value, "name") {
+            builder = builder.name(&name);
+        }
+        for info in list("platformInfos") {
+            if let Some(info) = text(&info, "info") {
+                builder = builder.add_platform_info(PlatformInfo::new(&info));
+            }
+        }
+        if let Some(metadata) = value["metadata"].as_object() {
+            for (key, value) in metadata {
+                builder = builder.add_metadata(key, value.clone());
+            }
+        }
+        let mut dut = builder.build();
+
+        for info in list("softwareInfos") {
+            let mut sw = SoftwareInfo::builder(&text(&info, "name").unwrap_or_default()).id(
+                Ident::Exact(text(&info, "softwareInfoId").unwrap_or_default()),
+            );
+            if let Some(v) = text(&info, "version") {
+                sw = sw.version(&v);
+            }
+            if let Some(v) = text(&info, "revision") {
+                sw = sw.revision(&v);
+            }
+            if let Some(v) = text(&info, "computerSystem") {
+                sw = sw.computer_system(&v);
+            }
+            let software_type = match text(&info, "softwareType").as_deref() {
+                Some("UNSPECIFIED") => Some(spec::SoftwareType::Unspecified),
+                Some("FIRMWARE") => Some(spec::SoftwareType::Firmware),
+                Some("SYSTEM") => Some(spec::SoftwareType::System),
+                Some("APPLICATION") => Some(spec::SoftwareType::Application),
+                _ => None,
+            };
+            if let Some(v) = software_type {
+                sw = sw.software_type(v);
+            }
+            dut.add_software_info(sw.build());
+        }
+
+        for info in list("hardwareInfos") {
+            let mut hw = HardwareInfo::builder(&text(&info, "name").unwrap_or_default()).id(
+                Ident::Exact(text(&info, "hardwareInfoId").unwrap_or_default()),
+            );
+            let setters: [(&str, HardwareSetter); 10] = [
+                ("version", HardwareInfoBuilder::version),
+                ("revision", HardwareInfoBuilder::revision),
+                ("location", HardwareInfoBuilder::location),
+                ("serialNumber", HardwareInfoBuilder::serial_no),
+                ("partNumber", HardwareInfoBuilder::part_no),
+                ("manufacturer", HardwareInfoBuilder::manufacturer),
+                (
+                    "manufacturerPartNumber",
+                    HardwareInfoBuilder::manufacturer_part_no,
+                ),
+                ("odataId", HardwareInfoBuilder::odata_id),
+                ("computerSystem", HardwareInfoBuilder::computer_system),
+                ("manager", HardwareInfoBuilder::manager),
+            ];
+            for (key, setter) in setters {
+                if let Some(v) = text(&info, key) {
+                    hw = setter(hw, &v);
+                }
+            }
+            dut.add_hardware_info(hw.build());
+        }
+
+        dut
+    }
 }
 
+/// Clones the DUT, so that it can be passed by reference where an owned one is expected,
+/// eg. to [`tv::TestRun::start`]. Pass it by value to avoid the clone.
+impl From<&DutInfo> for DutInfo {
+    fn from(dut: &DutInfo) -> Self {
+        dut.clone()
+    }
+}
+
+type HardwareSetter = fn(HardwareInfoBuilder, &str) -> HardwareInfoBuilder;
+
 /// TODO: docs
 #[derive(Default)]
 pub struct DutInfoBuilder {
@@ -261,10 +358,16 @@ impl SoftwareInfo {
 #[derive(Debug, Clone)]
 pub struct DutSoftwareInfo {
     id: String,
+    dut_id: String,
     source: SoftwareInfo,
 }
 
 impl DutSoftwareInfo {
+    /// Returns the id of the DUT this software info was registered on.
+    pub fn dut_id(&self) -> &str {
+        &self.dut_id
+    }
+
     pub(crate) fn to_spec(&self) -> spec::SoftwareInfo {
         let src = &self.source;
 
@@ -694,6 +797,7 @@ mod tests {
     fn test_infos_eq() -> Result<()> {
         let sw = DutSoftwareInfo {
             id: "sw0".to_owned(),
+            dut_id: "dut0".to_owned(),
             source: SoftwareInfo::builder("sw").build(),
         };
         assert_eq!(sw, sw);
@@ -4,33 +4,282 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::io;
+use std::panic;
 use std::sync::atomic::{self, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+use tokio::sync::broadcast;
 use unwrap_infallible::UnwrapInfallible;
 
+#[cfg(feature = "zstd")]
+use crate::output::compress::Compressor;
 use crate::output::{
-    config,
+    self as tv, config,
+    format::{OutputFormat, Record},
     writer::{self, WriterType},
 };
 use crate::spec;
 
+/// Statistics about the artifacts written by a test run.
+///
+/// Only artifacts that were successfully written are counted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EmitterStats {
+    /// Number of artifacts written, keyed by artifact type qualified by its container,
+    /// eg. `schemaVersion`, `testRunArtifact.log` or `testStepArtifact.measurement`.
+    pub artifacts: BTreeMap<&'static str, u64>,
+
+    /// Total size of the serialized artifacts, not including any record framing.
+    pub bytes: u64,
+
+    /// Earliest artifact timestamp.
+    pub first_timestamp: Option<chrono::DateTime<chrono_tz::Tz>>,
+
+    /// Latest artifact timestamp.
+    pub last_timestamp: Option<chrono::DateTime<chrono_tz::Tz>>,
+
+    /// Ids of the started test steps, in the order of their `testStepStart` artifacts.
+    pub started_steps: Vec<String>,
+
+    /// Number of log artifacts dropped for being below the configured minimum severity,
+    /// see [`tv::ConfigBuilder::with_min_log_severity`].
+    pub suppressed_logs: u64,
+}
+
+impl EmitterStats {
+    /// Returns the number of written artifacts of the given type.
+    pub fn count(&self, kind: &str) -> u64 {
+        self.artifacts.get(kind).copied().unwrap_or(0)
+    }
+
+    /// Returns the total number of written artifacts.
+    pub fn total(&self) -> u64 {
+        self.artifacts.values().sum()
+    }
+
+    fn record(
+        &mut self,
+        kind: &'static str,
+        bytes: usize,
+        timestamp: chrono::DateTime<chrono_tz::Tz>,
+    ) {
+        *self.artifacts.entry(kind).or_default() += 1;
+        self.bytes += bytes as u64;
+
+        // note: concurrent emitters may finish writing out of timestamp order
+        if self.first_timestamp.map_or(true, |first| timestamp < first) {
+            self.first_timestamp = Some(timestamp);
+        }
+        if self.last_timestamp.map_or(true, |last| timestamp > last) {
+            self.last_timestamp = Some(timestamp);
+        }
+    }
+}
+
+/// Handle to an output stream that can be shared by several sequential test runs.
+///
+/// Runs built with the same emitter write to the same output, with continuous
+/// sequence numbers; only the first run emits the `schemaVersion` artifact.
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// # use ocptv::output::*;
+/// let emitter = Emitter::new(Config::builder().build());
+///
+/// for name in ["first", "second"] {
+///     let dut = DutInfo::builder("my_dut").build();
+///     let run = TestRun::builder(name, "1.0").emitter(&emitter).build();
+///     run.start(dut).await?.end(TestStatus::Complete, TestResult::Pass).await?;
+/// }
+///
+/// # Ok::<(), OcptvError>(())
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct Emitter {
+    pub(crate) inner: Arc<JsonEmitter>,
+}
+
+impl Emitter {
+    pub fn new(config: config::Config) -> Self {
+        Emitter {
+            inner: Arc::new(JsonEmitter::new(config)),
+        }
+    }
+
+    /// Returns the sequence number that will be assigned to the next emitted artifact.
+    pub fn seqno(&self) -> u64 {
+        self.inner.seqno()
+    }
+
+    /// Returns a snapshot of the statistics for the artifacts written so far, by all
+    /// the runs sharing this emitter.
+    pub fn stats(&self) -> EmitterStats {
+        self.inner.stats()
+    }
+
+    /// Checks that the configured output is writable, without emitting any artifact,
+    /// eg. that a named pipe output has a reader attached. The check is writer specific;
+    /// custom writers implement it through [`writer::Writer::probe`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let emitter = Emitter::new(Config::builder().build());
+    /// emitter.probe().await?;
+    ///
+    /// # Ok::<(), WriterError>(())
+    /// # });
+    /// ```
+    pub async fn probe(&self) -> Result<(), writer::WriterError> {
+        self.inner.probe().await
+    }
+
+    /// Flushes any output still held back by the writer and closes it: async writer
+    /// sinks are shut down and named pipes closed, so their readers see the end of the
+    /// stream. The output must not be written after closing.
+    ///
+    /// When the last handle to the output is dropped without closing it, eg. because a run
+    /// returned early without ending, the output is still flushed on a best effort basis;
+    /// call this to observe the result instead.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let emitter = Emitter::new(Config::builder().build());
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::builder("run_name", "1.0").emitter(&emitter).build();
+    /// run.start(dut).await?.end(TestStatus::Complete, TestResult::Pass).await?;
+    /// emitter.close().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn close(&self) -> Result<(), tv::OcptvError> {
+        self.inner.close().await
+    }
+}
+
+const DROP_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
 pub struct JsonEmitter {
     timestamp_provider: Box<dyn config::TimestampProvider + Send + Sync + 'static>,
     writer: writer::WriterType,
+    hooks: Vec<config::EmitHook>,
+    transforms: Vec<config::Transform>,
+    separator: writer::Separator,
+    format: OutputFormat,
+    max_artifact_bytes: Option<usize>,
+    oversize_policy: config::OversizePolicy,
+    non_finite_policy: config::NonFinitePolicy,
+    write_timeout: Option<Duration>,
+    sync_policy: writer::SyncPolicy,
+    file_uri_resolver: Option<config::FileUriResolver>,
+    min_log_severity: Option<spec::LogSeverity>,
+    #[cfg(feature = "zstd")]
+    compressor: Option<tokio::sync::Mutex<Compressor>>,
+    write_count: atomic::AtomicU64,
+    // set by writes, cleared by flushing; checked when dropping the emitter
+    unflushed: atomic::AtomicBool,
+    auto_version: bool,
+    seqno_start: u64,
     seqno: Arc<atomic::AtomicU64>,
+    // held from the sequence number assignment until the artifact is written, so that
+    // concurrent steps still produce an output ordered by sequence number
+    emit_lock: tokio::sync::Mutex<()>,
+    stats: Mutex<EmitterStats>,
+    // created on the first subscription, dropped when the run ends
+    subscribers: Mutex<Option<broadcast::Sender<Arc<spec::Root>>>>,
 }
 
 impl JsonEmitter {
-    pub fn new(
-        timestamp_provider: Box<dyn config::TimestampProvider + Send + Sync + 'static>,
-        writer: writer::WriterType,
-    ) -> Self {
+    pub fn new(config: config::Config) -> Self {
         JsonEmitter {
-            timestamp_provider,
-            writer,
-            seqno: Arc::new(atomic::AtomicU64::new(0)),
+            timestamp_provider: config.timestamp_provider,
+            writer: config.writer,
+            hooks: config.emit_hooks,
+            transforms: config.transforms,
+            separator: config.separator,
+            format: config.format,
+            max_artifact_bytes: config.max_artifact_bytes,
+            oversize_policy: config.oversize_policy,
+            non_finite_policy: config.non_finite_policy,
+            write_timeout: config.write_timeout,
+            sync_policy: config.sync_policy,
+            file_uri_resolver: config.file_uri_resolver,
+            min_log_severity: config.min_log_severity,
+            #[cfg(feature = "zstd")]
+            compressor: config
+                .compression
+                .map(|c| tokio::sync::Mutex::new(Compressor::new(c))),
+            write_count: atomic::AtomicU64::new(0),
+            unflushed: atomic::AtomicBool::new(false),
+            auto_version: config.emit_schema_version,
+            seqno_start: config.sequence_start,
+            seqno: Arc::new(atomic::AtomicU64::new(config.sequence_start)),
+            emit_lock: tokio::sync::Mutex::new(()),
+            stats: Mutex::new(EmitterStats::default()),
+            subscribers: Mutex::new(None),
+        }
+    }
+
+    /// Returns a snapshot of the statistics for the artifacts written so far.
+    pub fn stats(&self) -> EmitterStats {
+        self.stats
+            .lock()
+            .expect("stats lock is never poisoned")
+            .clone()
+    }
+
+    /// Returns the sequence number that will be assigned to the next emitted artifact.
+    pub fn seqno(&self) -> u64 {
+        self.seqno.load(Ordering::Acquire)
+    }
+
+    pub fn file_uri_resolver(&self) -> Option<&config::FileUriResolver> {
+        self.file_uri_resolver.as_ref()
+    }
+
+    /// Subscribes to the artifacts written from now on, until the end of the test run.
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<spec::Root>> {
+        self.subscribers
+            .lock()
+            .expect("subscribers lock is never poisoned")
+            .get_or_insert_with(|| broadcast::channel(SUBSCRIBER_CAPACITY).0)
+            .subscribe()
+    }
+
+    fn publish(&self, root: &spec::Root) {
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .expect("subscribers lock is never poisoned");
+        let Some(sender) = subscribers.as_ref() else {
+            return;
+        };
+
+        // note: errors only mean there are no receivers left, which is fine
+        let _ = sender.send(Arc::new(root.clone()));
+
+        // dropping the sender ends the subscriptions, after the pending artifacts
+        if matches!(
+            root.artifact,
+            spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+                artifact: spec::TestRunArtifactImpl::TestRunEnd(_),
+            })
+        ) {
+            subscribers.take();
         }
     }
 
@@ -38,49 +287,593 @@ impl JsonEmitter {
         self.seqno.fetch_add(1, Ordering::AcqRel)
     }
 
-    async fn emit_version(&self) -> Result<(), io::Error> {
-        let s = self.serialize(&spec::RootImpl::SchemaVersion(
-            spec::SchemaVersion::default(),
-        ));
+    pub async fn emit_version(&self) -> Result<(), tv::OcptvError> {
+        self.emit_root(spec::RootImpl::SchemaVersion(spec::SchemaVersion::default()))
+            .await
+    }
+
+    async fn emit_root(&self, artifact: spec::RootImpl) -> Result<(), tv::OcptvError> {
+        let _ordering = self.emit_lock.lock().await;
+        self.emit_locked(artifact).await
+    }
 
-        self.write(s).await
+    fn is_suppressed(&self, artifact: &spec::RootImpl) -> bool {
+        let Some(min) = &self.min_log_severity else {
+            return false;
+        };
+        let log = match artifact {
+            spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+                artifact: spec::TestRunArtifactImpl::Log(log),
+            }) => log,
+            spec::RootImpl::TestStepArtifact(spec::TestStepArtifact {
+                artifact: spec::TestStepArtifactImpl::Log(log),
+                ..
+            }) => log,
+            _ => return false,
+        };
+        log.severity < *min
     }
 
-    fn serialize(&self, root: &spec::RootImpl) -> String {
+    // note: must only be called while holding the emit lock
+    async fn emit_locked(&self, artifact: spec::RootImpl) -> Result<(), tv::OcptvError> {
+        if self.is_suppressed(&artifact) {
+            let mut stats = self.stats.lock().expect("stats lock is never poisoned");
+            stats.suppressed_logs += 1;
+            return Ok(());
+        }
+
+        let kind = artifact.kind();
         let root = spec::Root {
-            artifact: root.clone(),
+            artifact,
             timestamp: self.timestamp_provider.now(),
             seqno: self.incr_seqno(),
         };
+        let timestamp = root.timestamp;
+        let context = writer::ArtifactContext {
+            kind,
+            seqno: root.seqno,
+        };
+
+        let mut record = self.serialize(&root)?;
+        let mut truncated = None;
+        if let Some(limit) = self
+            .max_artifact_bytes
+            .filter(|limit| record.len() > *limit)
+        {
+            let size = record.len();
+            record = self.truncate(&root, limit)?.ok_or_else(|| {
+                writer::WriterError::ArtifactTooLarge {
+                    artifact: context.clone(),
+                    size,
+                    limit,
+                }
+            })?;
+            truncated = Some((size, limit));
+        }
+
+        let bytes = record.len();
+        match &self.writer {
+            WriterType::TypedBuffer(buffer) => buffer.write(root.clone()).await.unwrap_infallible(),
+            _ => self.write(record, context.clone()).await?,
+        }
+
+        {
+            let mut stats = self.stats.lock().expect("stats lock is never poisoned");
+            stats.record(kind, bytes, timestamp);
+            if let spec::RootImpl::TestStepArtifact(spec::TestStepArtifact {
+                id,
+                artifact: spec::TestStepArtifactImpl::TestStepStart(_),
+            }) = &root.artifact
+            {
+                stats.started_steps.push(id.clone());
+            }
+        }
+        self.publish(&root);
+
+        if let Some((size, limit)) = truncated {
+            let error = Self::truncation_error(&root, &context, size, limit);
+            Box::pin(self.emit_locked(error)).await?;
+        }
+        Ok(())
+    }
+
+    fn serialize(&self, root: &spec::Root) -> Result<Record, writer::WriterError> {
+        if !self.hooks.is_empty() || !self.transforms.is_empty() {
+            return Ok(self.format.encode(&self.to_value(root)?));
+        }
+
+        Ok(self.format.encode(root))
+    }
+
+    /// Returns the artifact without its metadata maps, if the oversize policy allows it
+    /// and that makes it fit in the limit.
+    fn truncate(
+        &self,
+        root: &spec::Root,
+        limit: usize,
+    ) -> Result<Option<Record>, writer::WriterError> {
+        if self.oversize_policy != config::OversizePolicy::Truncate {
+            return Ok(None);
+        }
+
+        let mut value = self.to_value(root)?;
+        strip_metadata(&mut value);
+
+        let record = self.format.encode(&value);
+        Ok((record.len() <= limit).then_some(record))
+    }
+
+    fn to_value(&self, root: &spec::Root) -> Result<serde_json::Value, writer::WriterError> {
+        let value = self.apply_hooks(root);
+        if self.transforms.is_empty() {
+            return Ok(value);
+        }
+        self.apply_transforms(value, root)
+    }
+
+    fn apply_transforms(
+        &self,
+        mut value: serde_json::Value,
+        root: &spec::Root,
+    ) -> Result<serde_json::Value, writer::WriterError> {
+        let error = |message: &str| writer::WriterError::Transform {
+            artifact: writer::ArtifactContext {
+                kind: root.artifact.kind(),
+                seqno: root.seqno,
+            },
+            message: message.to_owned(),
+        };
+
+        for transform in &self.transforms {
+            // note: the transforms are user code, so don't let a panic take down the emitter
+            let input = value;
+            value = panic::catch_unwind(panic::AssertUnwindSafe(|| transform(input)))
+                .map_err(|_| error("transform panicked"))?;
+        }
+
+        let serde_json::Value::Object(map) = &mut value else {
+            return Err(error("transform did not return a json object"));
+        };
+        // the sequence numbering cannot be changed, same as for the hooks
+        map.insert("sequenceNumber".to_owned(), root.seqno.into());
+        Ok(value)
+    }
+
+    fn truncation_error(
+        root: &spec::Root,
+        context: &writer::ArtifactContext,
+        size: usize,
+        limit: usize,
+    ) -> spec::RootImpl {
+        let error = spec::Error {
+            symptom: "ocptv-artifact-truncated".to_owned(),
+            message: Some(format!(
+                "metadata removed from {}, as it was {} bytes, over the limit of {} bytes",
+                context, size, limit
+            )),
+            software_infos: None,
+            source_location: None,
+        };
+
+        match &root.artifact {
+            spec::RootImpl::TestStepArtifact(a) => {
+                spec::RootImpl::TestStepArtifact(spec::TestStepArtifact {
+                    id: a.id.clone(),
+                    artifact: spec::TestStepArtifactImpl::Error(error),
+                })
+            }
+            _ => spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+                artifact: spec::TestRunArtifactImpl::Error(error),
+            }),
+        }
+    }
+
+    fn apply_hooks(&self, root: &spec::Root) -> serde_json::Value {
+        let serde_json::Value::Object(mut root) =
+            serde_json::to_value(root).expect("artifact serialization is infallible")
+        else {
+            unreachable!("spec::Root always serializes to an object");
+        };
 
-        serde_json::json!(root).to_string()
+        // hooks only see the artifact itself; the envelope fields are put back afterwards
+        // so that the sequence numbering cannot be changed
+        let envelope = ["timestamp", "sequenceNumber"].map(|key| (key, root.remove(key)));
+
+        let mut artifact = serde_json::Value::Object(root);
+        for hook in &self.hooks {
+            hook(&mut artifact);
+        }
+
+        if let serde_json::Value::Object(map) = &mut artifact {
+            for (key, value) in envelope {
+                if let Some(value) = value {
+                    map.insert(key.to_owned(), value);
+                }
+            }
+        }
+
+        artifact
+    }
+
+    async fn write(
+        &self,
+        record: Record,
+        context: writer::ArtifactContext,
+    ) -> Result<(), writer::WriterError> {
+        let Some(timeout) = self.write_timeout else {
+            return self
+                .write_impl(record, context.kind)
+                .await
+                .map_err(|e| Self::writer_error(e, context));
+        };
+
+        match tokio::time::timeout(timeout, self.write_impl(record, context.kind)).await {
+            Ok(result) => result.map_err(|e| Self::writer_error(e, context)),
+            Err(_) => Err(writer::WriterError::Timeout {
+                artifact: context,
+                timeout,
+            }),
+        }
+    }
+
+    // note: writers report the typed errors wrapped in an io::Error, so unwrap them here
+    fn writer_error(e: io::Error, context: writer::ArtifactContext) -> writer::WriterError {
+        if !e
+            .get_ref()
+            .is_some_and(|inner| inner.is::<writer::WriterError>())
+        {
+            return writer::WriterError::Io {
+                artifact: context,
+                source: e,
+            };
+        }
+
+        match e
+            .into_inner()
+            .map(|inner| inner.downcast::<writer::WriterError>())
+        {
+            Some(Ok(we)) => we.with_artifact(context),
+            _ => unreachable!("checked above"),
+        }
     }
 
-    async fn write(&self, s: String) -> Result<(), io::Error> {
+    async fn write_impl(&self, record: Record, kind: &'static str) -> Result<(), io::Error> {
+        self.unflushed.store(true, Ordering::Release);
+
+        #[cfg(feature = "zstd")]
+        if let Some(compressor) = &self.compressor {
+            return self.write_compressed(compressor, record).await;
+        }
+
+        match record {
+            Record::Text(s) => self.write_text(s, kind).await,
+            #[cfg(any(feature = "msgpack", feature = "cbor"))]
+            Record::Binary(b) => self.write_binary(&b).await,
+        }
+    }
+
+    async fn write_text(&self, s: String, kind: &'static str) -> Result<(), io::Error> {
+        // note: only the json lines records are terminated by the separator, so only these
+        // need it inserted when appending to a file whose last record is not terminated
+        let terminator = Some(self.separator.terminator());
         match &self.writer {
-            WriterType::File(file) => file.write(&s).await?,
-            WriterType::Stdout(stdout) => stdout.write(&s).await.unwrap_infallible(),
+            WriterType::File(file) => {
+                self.write_file(file, self.separator.frame(&s).as_bytes(), terminator)
+                    .await?
+            }
+            WriterType::FileTemplate(template) => {
+                self.write_file(
+                    template.get()?,
+                    self.separator.frame(&s).as_bytes(),
+                    terminator,
+                )
+                .await?
+            }
+            WriterType::Stdout(stdout) => stdout
+                .write_raw(&self.separator.frame(&s))
+                .await
+                .unwrap_infallible(),
             WriterType::Buffer(buffer) => buffer.write(&s).await.unwrap_infallible(),
+            // note: the typed artifacts are captured before serialization, in emit_root
+            WriterType::TypedBuffer(_) => {}
+            WriterType::BoundedBuffer(buffer) => buffer.write(&s).await?,
+            WriterType::Bytes(bytes) => bytes
+                .write(self.separator.frame(&s).as_bytes())
+                .await
+                .unwrap_infallible(),
+            WriterType::Async(sink) => sink.write_raw(self.separator.frame(&s).as_bytes()).await?,
+            #[cfg(unix)]
+            WriterType::Fifo(fifo) => fifo.write_raw(self.separator.frame(&s).as_bytes()).await?,
 
+            #[cfg(all(feature = "journald", target_os = "linux"))]
+            WriterType::Journald(journal) => journal.write(kind, &s).await?,
+            WriterType::Split(split) => split.write(kind, &s).await?,
             WriterType::Custom(custom) => custom.write(&s).await?,
         }
 
         Ok(())
     }
 
+    #[cfg(feature = "zstd")]
+    async fn write_compressed(
+        &self,
+        compressor: &tokio::sync::Mutex<Compressor>,
+        record: Record,
+    ) -> Result<(), io::Error> {
+        if !self.writer.is_byte_oriented() {
+            return Err(Self::unsupported_output());
+        }
+
+        let data = match record {
+            Record::Text(s) => self.separator.frame(&s).into_bytes(),
+            #[cfg(any(feature = "msgpack", feature = "cbor"))]
+            Record::Binary(b) => b,
+        };
+
+        // note: hold the lock while writing, so that the frames are written in order
+        let mut compressor = compressor.lock().await;
+        match compressor.compress(&data)? {
+            Some(frame) => self.write_binary(&frame).await,
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(any(feature = "msgpack", feature = "cbor", feature = "zstd"))]
+    async fn write_binary(&self, b: &[u8]) -> Result<(), io::Error> {
+        match &self.writer {
+            WriterType::File(file) => self.write_file(file, b, None).await,
+            WriterType::FileTemplate(template) => self.write_file(template.get()?, b, None).await,
+            WriterType::Bytes(bytes) => {
+                bytes.write(b).await.unwrap_infallible();
+                Ok(())
+            }
+            WriterType::Async(sink) => sink.write_raw(b).await,
+            #[cfg(unix)]
+            WriterType::Fifo(fifo) => fifo.write_raw(b).await,
+            _ => Err(Self::unsupported_output()),
+        }
+    }
+
+    #[cfg(any(feature = "msgpack", feature = "cbor", feature = "zstd"))]
+    fn unsupported_output() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "binary output formats and compression need a file, fifo, bytes or async writer output",
+        )
+    }
+
+    async fn write_file(
+        &self,
+        file: &writer::FileWriter,
+        b: &[u8],
+        terminator: Option<u8>,
+    ) -> Result<(), io::Error> {
+        file.write_raw(b, terminator).await?;
+
+        let count = self.write_count.fetch_add(1, Ordering::AcqRel) + 1;
+        if self.sync_policy.sync_after(count) {
+            file.sync_data().await?;
+        }
+        Ok(())
+    }
+
+    pub async fn probe(&self) -> Result<(), writer::WriterError> {
+        self.writer.probe().await.map_err(|e| {
+            Self::writer_error(
+                e,
+                writer::ArtifactContext {
+                    kind: "probe",
+                    ..Default::default()
+                },
+            )
+        })
+    }
+
+    /// Creates the output, for the outputs that depend on the test run, eg. a file named
+    /// after the run and the DUT.
+    pub(crate) async fn open(&self, run_name: &str, dut_id: &str) -> Result<(), tv::OcptvError> {
+        if let WriterType::FileTemplate(template) = &self.writer {
+            let date = self.timestamp_provider.now().date_naive();
+            template.open(run_name, dut_id, date).await?;
+        }
+        Ok(())
+    }
+
+    /// Flushes any output that the writer may still be holding back, and syncs the file
+    /// output if requested by the sync policy.
+    pub async fn flush(&self) -> Result<(), tv::OcptvError> {
+        #[cfg(feature = "zstd")]
+        if let Some(compressor) = &self.compressor {
+            let mut compressor = compressor.lock().await;
+            if let Some(frame) = compressor.finish()? {
+                self.write_binary(&frame).await?;
+            }
+        }
+
+        match &self.writer {
+            WriterType::Async(sink) => sink.flush().await?,
+            WriterType::File(file) if self.sync_policy != writer::SyncPolicy::Never => {
+                file.sync_data().await?
+            }
+            WriterType::FileTemplate(template) if self.sync_policy != writer::SyncPolicy::Never => {
+                if let Ok(file) = template.get() {
+                    file.sync_data().await?
+                }
+            }
+            _ => {}
+        }
+
+        self.unflushed.store(false, Ordering::Release);
+        Ok(())
+    }
+
+    /// Flushes the output, then closes the writers that have a notion of closing:
+    /// async writer sinks are shut down and named pipes are closed, so their readers
+    /// see the end of the stream. The output should not be written after closing.
+    pub async fn close(&self) -> Result<(), tv::OcptvError> {
+        self.flush().await?;
+
+        match &self.writer {
+            WriterType::Async(sink) => sink.shutdown().await?,
+            #[cfg(unix)]
+            WriterType::Fifo(fifo) => fifo.close().await,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Returns whether the writer may hold back output until flushed.
+    fn buffers_output(&self) -> bool {
+        #[cfg(feature = "zstd")]
+        if self.compressor.is_some() {
+            return true;
+        }
+
+        match &self.writer {
+            WriterType::Async(_) => true,
+            WriterType::File(_) | WriterType::FileTemplate(_) => {
+                self.sync_policy != writer::SyncPolicy::Never
+            }
+            _ => false,
+        }
+    }
+
     pub fn timestamp_provider(&self) -> &(dyn config::TimestampProvider + Send + Sync + 'static) {
         &*self.timestamp_provider
     }
 
-    pub async fn emit(&self, root: &spec::RootImpl) -> Result<(), io::Error> {
-        if self.seqno.load(Ordering::Acquire) == 0 {
-            self.emit_version().await?;
+    pub(crate) fn non_finite_policy(&self) -> config::NonFinitePolicy {
+        self.non_finite_policy
+    }
+
+    /// Serializes and writes a single artifact.
+    ///
+    /// The artifact is taken by value so that the hot emit paths (eg. series elements)
+    /// do not need to clone it before serialization. The returned future resolves once
+    /// the underlying writer has accepted the line, so artifacts are always written in
+    /// the same order as their sequence numbers were assigned, for a single caller.
+    pub async fn emit(&self, root: spec::RootImpl) -> Result<(), tv::OcptvError> {
+        self.emit_if([root], || Ok(()), || {}).await
+    }
+
+    /// Emits the artifacts in order, with consecutive sequence numbers, taking the emit
+    /// lock only once, if `check` passes once the lock is taken; `commit` then runs under
+    /// the same lock, after all the artifacts were written. This keeps state changes made
+    /// by the artifacts, eg. a step being ended, atomic with their writes.
+    /// Errors are returned as is.
+    pub(crate) async fn emit_if<I, C, A>(
+        &self,
+        roots: I,
+        check: C,
+        commit: A,
+    ) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = spec::RootImpl>,
+        C: FnOnce() -> Result<(), tv::OcptvError>,
+        A: FnOnce(),
+    {
+        self.emit_gated(roots, check, commit, |_, e| e).await
+    }
+
+    /// Same as [`JsonEmitter::emit_if`], but fails with [`tv::OcptvError::BatchInterrupted`]
+    /// if an artifact can't be written, giving the number of artifacts written before it.
+    pub(crate) async fn emit_batch_if<I, C, A>(
+        &self,
+        roots: I,
+        check: C,
+        commit: A,
+    ) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = spec::RootImpl>,
+        C: FnOnce() -> Result<(), tv::OcptvError>,
+        A: FnOnce(),
+    {
+        self.emit_gated(roots, check, commit, |written, e| {
+            tv::OcptvError::BatchInterrupted(written, Box::new(e))
+        })
+        .await
+    }
+
+    async fn emit_gated<I, C, A, M>(
+        &self,
+        roots: I,
+        check: C,
+        commit: A,
+        map_err: M,
+    ) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = spec::RootImpl>,
+        C: FnOnce() -> Result<(), tv::OcptvError>,
+        A: FnOnce(),
+        M: Fn(usize, tv::OcptvError) -> tv::OcptvError,
+    {
+        let _ordering = self.emit_lock.lock().await;
+        check()?;
+        // note: checked under the emit lock, so that concurrent first emits on a shared
+        // emitter only write one version record, and a rejected emit writes none
+        if self.auto_version && self.seqno() == self.seqno_start {
+            self.emit_locked(spec::RootImpl::SchemaVersion(spec::SchemaVersion::default()))
+                .await?;
+        }
+        for (written, root) in roots.into_iter().enumerate() {
+            self.emit_locked(root)
+                .await
+                .map_err(|e| map_err(written, e))?;
         }
+        commit();
+        Ok(())
+    }
+}
 
-        self.write(self.serialize(root)).await
+fn strip_metadata(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.remove("metadata");
+            map.values_mut().for_each(strip_metadata);
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(strip_metadata),
+        _ => {}
     }
 }
 
+impl Drop for JsonEmitter {
+    fn drop(&mut self) {
+        // best effort: flush the output in case the run didn't end, eg. the pending
+        // compressed frame or the data held back by the async writer flush policy
+        if !*self.unflushed.get_mut() || !self.buffers_output() {
+            return;
+        }
+
+        let _ = block_on_in_drop(self.close());
+    }
+}
+
+/// Runs `fut` to completion from a drop implementation, giving up after a timeout.
+/// Returns `None` if the future did not complete.
+pub(crate) fn block_on_in_drop<F>(fut: F) -> Option<F::Output>
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    // note: there's no async drop, and blocking inside the current runtime panics
+    // (or deadlocks, for a current thread runtime), so block on the future in a new
+    // runtime on a scoped thread instead; the timeout bounds the wait on outputs
+    // whose progress depends on the blocked runtime
+    std::thread::scope(|scope| {
+        scope
+            .spawn(|| {
+                let rt = tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                    .ok()?;
+                rt.block_on(async { tokio::time::timeout(DROP_FLUSH_TIMEOUT, fut).await.ok() })
+            })
+            .join()
+            .ok()
+            .flatten()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use anyhow::{anyhow, Result};
@@ -95,7 +888,7 @@ mod tests {
     impl NullTimestampProvider {
         // warn: linter is wrong here, this is used in a serde_json::json! block
         #[allow(dead_code)]
-        pub const FORMATTED: &str = "1970-01-01T00:00:00.000Z";
+        pub const FORMATTED: &'static str = "1970-01-01T00:00:00.000Z";
     }
 
     impl config::TimestampProvider for NullTimestampProvider {
@@ -116,16 +909,15 @@ mod tests {
         });
 
         let buffer = Arc::new(Mutex::new(vec![]));
-        let writer = writer::BufferWriter::new(buffer.clone());
         let emitter = JsonEmitter::new(
-            Box::new(NullTimestampProvider {}),
-            writer::WriterType::Buffer(writer),
+            config::Config::builder()
+                .with_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(NullTimestampProvider {}))
+                .build(),
         );
 
         emitter
-            .emit(&spec::RootImpl::SchemaVersion(
-                spec::SchemaVersion::default(),
-            ))
+            .emit(spec::RootImpl::SchemaVersion(spec::SchemaVersion::default()))
             .await?;
 
         let deserialized = serde_json::from_str::<serde_json::Value>(
@@ -156,15 +948,16 @@ mod tests {
         });
 
         let buffer = Arc::new(Mutex::new(vec![]));
-        let writer = writer::BufferWriter::new(buffer.clone());
         let emitter = JsonEmitter::new(
-            Box::new(NullTimestampProvider {}),
-            writer::WriterType::Buffer(writer),
+            config::Config::builder()
+                .with_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(NullTimestampProvider {}))
+                .build(),
         );
 
         let version = spec::RootImpl::SchemaVersion(spec::SchemaVersion::default());
-        emitter.emit(&version).await?;
-        emitter.emit(&version).await?;
+        emitter.emit(version.clone()).await?;
+        emitter.emit(version).await?;
 
         let deserialized = serde_json::from_str::<serde_json::Value>(
             buffer.lock().await.first().ok_or(anyhow!("no outputs"))?,
@@ -178,4 +971,163 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_sequence_number_starts_at_offset() -> Result<()> {
+        let expected = json!({
+            "schemaVersion": {
+                "major": spec::SPEC_VERSION.0,
+                "minor": spec::SPEC_VERSION.1,
+            },
+            "sequenceNumber": 10,
+            "timestamp": NullTimestampProvider::FORMATTED,
+        });
+
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let emitter = JsonEmitter::new(
+            config::Config::builder()
+                .with_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(NullTimestampProvider {}))
+                .with_sequence_start(10)
+                .build(),
+        );
+        assert_eq!(emitter.seqno(), 10);
+
+        for _ in 0..3 {
+            emitter
+                .emit(spec::RootImpl::SchemaVersion(spec::SchemaVersion::default()))
+                .await?;
+        }
+
+        let buffer = buffer.lock().await;
+        let deserialized = serde_json::from_str::<serde_json::Value>(
+            buffer.first().ok_or(anyhow!("no outputs"))?,
+        )?;
+        assert_json_eq!(deserialized, expected);
+
+        // every record is numbered from the offset, after the automatic version record
+        let seqnos = buffer
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|a| a["sequenceNumber"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(seqnos, [json!(10), json!(11), json!(12), json!(13)]);
+        assert_eq!(emitter.seqno(), 14);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_emit_hooks_mutate_artifacts() -> Result<()> {
+        let expected = json!({
+            "schemaVersion": {
+                "major": spec::SPEC_VERSION.0,
+                "minor": spec::SPEC_VERSION.1,
+                "hooked": 2,
+            },
+            "sequenceNumber": 0,
+            "timestamp": NullTimestampProvider::FORMATTED,
+        });
+
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let emitter = JsonEmitter::new(
+            config::Config::builder()
+                .with_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(NullTimestampProvider {}))
+                .with_emit_hook(Box::new(|v| v["schemaVersion"]["hooked"] = 1.into()))
+                .with_emit_hook(Box::new(|v| {
+                    v["schemaVersion"]["hooked"] = 2.into();
+                    // attempts to change the envelope are ignored
+                    v["sequenceNumber"] = 42.into();
+                    v["timestamp"] = "never".into();
+                }))
+                .build(),
+        );
+
+        emitter
+            .emit(spec::RootImpl::SchemaVersion(spec::SchemaVersion::default()))
+            .await?;
+
+        let deserialized = serde_json::from_str::<serde_json::Value>(
+            buffer.lock().await.first().ok_or(anyhow!("no outputs"))?,
+        )?;
+        assert_json_eq!(deserialized, expected);
+
+        Ok(())
+    }
+
+    fn run_log(message: &str) -> spec::RootImpl {
+        spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+            artifact: spec::TestRunArtifactImpl::Log(spec::Log {
+                severity: spec::LogSeverity::Info,
+                message: message.to_owned(),
+                source_location: None,
+            }),
+        })
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_auto_version_emitted_once_for_concurrent_emits() -> Result<()> {
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let emitter = Arc::new(JsonEmitter::new(
+            config::Config::builder()
+                .with_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(NullTimestampProvider {}))
+                .build(),
+        ));
+
+        let tasks = (0..8).map(|i| {
+            let emitter = Arc::clone(&emitter);
+            tokio::spawn(async move { emitter.emit(run_log(&format!("log {}", i))).await })
+        });
+        for result in futures::future::join_all(tasks).await {
+            result??;
+        }
+
+        let buffer = buffer.lock().await;
+        let versions = buffer
+            .iter()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .filter(|a| a.get("schemaVersion").is_some())
+            .map(|a| a["sequenceNumber"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(versions, [json!(0)]);
+        assert_eq!(buffer.len(), 9);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_auto_version_not_emitted_for_rejected_emit() -> Result<()> {
+        let buffer = Arc::new(Mutex::new(vec![]));
+        let emitter = JsonEmitter::new(
+            config::Config::builder()
+                .with_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(NullTimestampProvider {}))
+                .build(),
+        );
+
+        let result = emitter
+            .emit_if(
+                [run_log("rejected")],
+                || Err(tv::OcptvError::RunAlreadyEnded),
+                || {},
+            )
+            .await;
+        assert!(matches!(result, Err(tv::OcptvError::RunAlreadyEnded)));
+        assert!(buffer.lock().await.is_empty());
+        assert_eq!(emitter.seqno(), 0);
+
+        // the version is still emitted before the first written artifact
+        emitter.emit(run_log("written")).await?;
+        let buffer = buffer.lock().await;
+        assert_eq!(buffer.len(), 2);
+        assert!(buffer[0].contains("schemaVersion"));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,104 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::output as tv;
+
+pub(crate) const HOSTNAME_KEY: &str = "ocptv_hostname";
+pub(crate) const OS_RELEASE_KEY: &str = "ocptv_os_release";
+pub(crate) const ARCH_KEY: &str = "ocptv_arch";
+pub(crate) const PID_KEY: &str = "ocptv_pid";
+pub(crate) const USER_KEY: &str = "ocptv_user";
+
+/// Gathers the environment metadata of the current process. The values are all strings;
+/// the ones that can't be determined are left out.
+pub(crate) fn gather() -> BTreeMap<String, tv::Value> {
+    let (hostname, os_release) = system_names();
+    let values = [
+        (HOSTNAME_KEY, hostname),
+        (OS_RELEASE_KEY, os_release),
+        (ARCH_KEY, Some(env::consts::ARCH.to_owned())),
+        (PID_KEY, Some(std::process::id().to_string())),
+        (USER_KEY, user()),
+    ];
+
+    values
+        .into_iter()
+        .filter_map(|(key, value)| {
+            value
+                .filter(|v| !v.is_empty())
+                .map(|v| (key.to_owned(), tv::Value::String(v)))
+        })
+        .collect()
+}
+
+/// Returns the host name and the kernel release.
+#[cfg(unix)]
+fn system_names() -> (Option<String>, Option<String>) {
+    let mut name = std::mem::MaybeUninit::<libc::utsname>::uninit();
+    // SAFETY: uname only writes to the given struct, which is initialized on success
+    if unsafe { libc::uname(name.as_mut_ptr()) } != 0 {
+        return (None, None);
+    }
+    let name = unsafe { name.assume_init() };
+    (
+        Some(c_chars_to_string(&name.nodename)),
+        Some(c_chars_to_string(&name.release)),
+    )
+}
+
+#[cfg(not(unix))]
+fn system_names() -> (Option<String>, Option<String>) {
+    (env::var("COMPUTERNAME").ok(), None)
+}
+
+fn user() -> Option<String> {
+    let from_env = ["USER", "LOGNAME", "USERNAME"]
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()));
+
+    #[cfg(unix)]
+    let from_env = from_env.or_else(passwd_user);
+    from_env
+}
+
+/// Returns the name of the effective user from the password database, for when the
+/// environment doesn't have it, eg. in containers or cron jobs.
+#[cfg(unix)]
+fn passwd_user() -> Option<String> {
+    let mut pwd = std::mem::MaybeUninit::<libc::passwd>::uninit();
+    let mut buf = vec![0 as libc::c_char; 4096];
+    let mut result = std::ptr::null_mut();
+
+    // SAFETY: the pointers are valid for the duration of the call, and `result` is
+    // either null or points to `pwd`, whose strings live in `buf`
+    let rc = unsafe {
+        libc::getpwuid_r(
+            libc::geteuid(),
+            pwd.as_mut_ptr(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr((*result).pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(unix)]
+fn c_chars_to_string(chars: &[libc::c_char]) -> String {
+    let bytes = chars
+        .iter()
+        .take_while(|c| **c != 0)
+        .map(|c| *c as u8)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
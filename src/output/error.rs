@@ -4,6 +4,8 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::any::Any;
+
 use crate::output as tv;
 use crate::spec;
 use tv::{dut, trait_ext::VecExt, DutSoftwareInfo};
@@ -22,6 +24,31 @@ impl Error {
         ErrorBuilder::new(symptom)
     }
 
+    /// Error recording a panic caught in a scope, with the panic message if there is one.
+    pub(crate) fn from_panic(payload: &(dyn Any + Send)) -> Error {
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned());
+
+        let mut builder = ErrorBuilder::new("panic");
+        if let Some(message) = message {
+            builder = builder.message(&message);
+        }
+        builder.build()
+    }
+
+    /// Checks that the referenced software infos were registered on the DUT `dut_id`.
+    pub(crate) fn check_dut(&self, dut_id: &str) -> Result<(), tv::OcptvError> {
+        match self.software_infos.iter().find(|s| s.dut_id() != dut_id) {
+            Some(info) => Err(tv::OcptvError::ForeignSoftwareInfo(
+                info.to_spec().id,
+                info.dut_id().to_owned(),
+            )),
+            None => Ok(()),
+        }
+    }
+
     pub fn to_artifact(&self) -> spec::Error {
         spec::Error {
             symptom: self.symptom.clone(),
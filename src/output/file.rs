@@ -5,12 +5,22 @@
 // https://opensource.org/licenses/MIT.
 
 use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
 
 use mime;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
 
-use crate::output::{self as tv, trait_ext::MapExt};
+use crate::output::{self as tv, config, trait_ext::MapExt};
 use crate::spec;
 
+/// Uri schemes accepted for file artifacts.
+const FILE_URI_SCHEMES: [&str; 3] = ["file", "http", "https"];
+
+pub(crate) const FILE_SIZE_KEY: &str = "ocptv_file_size";
+pub(crate) const FILE_SHA256_KEY: &str = "ocptv_sha256";
+
 /// This structure represents a File message.
 ///
 /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#file>
@@ -106,6 +116,102 @@ impl File {
             metadata: self.metadata.option(),
         }
     }
+
+    /// Builds a snapshot File object for a local file, named after the file name and
+    /// with the content type inferred from the extension. With `digest`, the file size
+    /// and sha256 digest are recorded in the metadata. The uri is the one returned by
+    /// the resolver if any, after the local file checks passed, or the `file://` uri.
+    pub(crate) async fn from_path(
+        path: &Path,
+        digest: bool,
+        resolver: Option<&config::FileUriResolver>,
+    ) -> Result<Self, tv::OcptvError> {
+        let to_error = |e| tv::OcptvError::FileArtifact(path.to_owned(), e);
+
+        let path = tokio::fs::canonicalize(path).await.map_err(to_error)?;
+        let stat = tokio::fs::metadata(&path).await.map_err(to_error)?;
+        if !stat.is_file() {
+            return Err(to_error(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a regular file",
+            )));
+        }
+
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let sha256 = if digest {
+            Some(sha256_of(&path).await.map_err(to_error)?)
+        } else {
+            None
+        };
+
+        let uri = match resolver {
+            Some(resolver) => {
+                let uri = resolver(&path).await?;
+                tv::Uri::parse(&uri).map_err(|_| tv::OcptvError::UnsupportedFileUri(uri))?
+            }
+            // canonical paths are absolute, so this can't fail
+            None => tv::Uri::from_file_path(&path)
+                .map_err(|_| tv::OcptvError::UnsupportedFileUri(path.display().to_string()))?,
+        };
+
+        let mut builder = FileBuilder::new(&name, uri).is_snapshot(true);
+        if let Some(content_type) = content_type_of(&path) {
+            builder = builder.content_type(content_type);
+        }
+        if let Some(sha256) = sha256 {
+            builder = builder
+                .add_metadata(FILE_SIZE_KEY, stat.len())
+                .add_metadata(FILE_SHA256_KEY, sha256);
+        }
+
+        Ok(builder.build())
+    }
+
+    pub(crate) fn check_uri(&self) -> Result<(), tv::OcptvError> {
+        if !FILE_URI_SCHEMES.contains(&self.uri.scheme()) {
+            return Err(tv::OcptvError::UnsupportedFileUri(self.uri.to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Infers the content type of a file from its extension, for the usual diagnostic
+/// outputs. Unknown extensions have no content type.
+fn content_type_of(path: &Path) -> Option<mime::Mime> {
+    let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+    let content_type = match ext.as_str() {
+        "txt" | "log" => mime::TEXT_PLAIN,
+        "json" | "jsonl" => mime::APPLICATION_JSON,
+        "csv" => mime::TEXT_CSV,
+        "xml" => mime::TEXT_XML,
+        "html" | "htm" => mime::TEXT_HTML,
+        "svg" => mime::IMAGE_SVG,
+        "png" => mime::IMAGE_PNG,
+        "jpg" | "jpeg" => mime::IMAGE_JPEG,
+        "pdf" => mime::APPLICATION_PDF,
+        "gz" => "application/gzip".parse().ok()?,
+        "bin" => mime::APPLICATION_OCTET_STREAM,
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+async fn sha256_of(path: &Path) -> io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 /// This structure builds a [`File`] object.
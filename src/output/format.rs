@@ -0,0 +1,121 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use serde::Serialize;
+
+/// Encoding of the artifacts in the output stream.
+///
+/// The binary formats are enabled by cargo features, and can only be used with the
+/// outputs that handle raw bytes: file, bytes and async writer outputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OutputFormat {
+    /// Json text, one artifact per record. The record framing is configured by
+    /// [`crate::output::ConfigBuilder::with_record_separator`]. This is the default.
+    #[default]
+    JsonLines,
+
+    /// MessagePack, with every artifact prefixed by its length as a big-endian `u32`.
+    /// Field names are kept as in the json spec. The record separator is not used.
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+
+    /// CBOR, as a sequence of items (RFC 8742), one per artifact. Field names and
+    /// timestamp strings are kept as in the json spec. The record separator is not used.
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+/// A serialized artifact, ready to be written.
+pub(crate) enum Record {
+    Text(String),
+    #[cfg(any(feature = "msgpack", feature = "cbor"))]
+    Binary(Vec<u8>),
+}
+
+impl Record {
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            Record::Text(s) => s.len(),
+            #[cfg(any(feature = "msgpack", feature = "cbor"))]
+            Record::Binary(b) => b.len(),
+        }
+    }
+}
+
+impl OutputFormat {
+    pub(crate) fn encode<T: Serialize>(&self, value: &T) -> Record {
+        match self {
+            OutputFormat::JsonLines => Record::Text(
+                serde_json::to_string(value).expect("artifact serialization is infallible"),
+            ),
+            #[cfg(feature = "msgpack")]
+            OutputFormat::MessagePack => {
+                // note: named encoding, so that structs are maps keyed by the spec field names
+                let payload =
+                    rmp_serde::to_vec_named(value).expect("artifact serialization is infallible");
+                Record::Binary(length_prefixed(payload))
+            }
+            #[cfg(feature = "cbor")]
+            OutputFormat::Cbor => {
+                let mut item = vec![];
+                ciborium::into_writer(value, &mut item)
+                    .expect("artifact serialization is infallible");
+                Record::Binary(item)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+fn length_prefixed(payload: Vec<u8>) -> Vec<u8> {
+    let len = u32::try_from(payload.len()).expect("artifact size fits in u32");
+
+    let mut record = Vec::with_capacity(4 + payload.len());
+    record.extend_from_slice(&len.to_be_bytes());
+    record.extend(payload);
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    #[allow(irrefutable_let_patterns)]
+    fn test_json_lines_encoding() -> Result<()> {
+        let Record::Text(s) = OutputFormat::JsonLines.encode(&json!({"a": 1})) else {
+            panic!("expected text record");
+        };
+        assert_eq!(s, r#"{"a":1}"#);
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_encoding_is_length_prefixed() -> Result<()> {
+        let Record::Binary(b) = OutputFormat::MessagePack.encode(&json!({"a": 1})) else {
+            panic!("expected binary record");
+        };
+        // fixmap(1), fixstr("a"), positive fixint(1)
+        assert_eq!(b, vec![0, 0, 0, 4, 0x81, 0xa1, b'a', 0x01]);
+        Ok(())
+    }
+
+    #[cfg(feature = "cbor")]
+    #[test]
+    fn test_cbor_encoding() -> Result<()> {
+        let Record::Binary(b) = OutputFormat::Cbor.encode(&json!({"a": 1})) else {
+            panic!("expected binary record");
+        };
+        // map(1), text("a"), unsigned(1)
+        assert_eq!(b, vec![0xa1, 0x61, b'a', 0x01]);
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Process wide test run, for small diagnostics made of free functions that would
+//! otherwise need to pass the run handle around.
+//!
+//! The global run is opt-in: it only exists after [`init_run`], and can be initialized
+//! once per process. The helpers and macros route to it through [`global`].
+//!
+//! # Examples
+//!
+//! ```rust
+//! # tokio_test::block_on(async {
+//! # use ocptv::output::*;
+//! use ocptv::ocptv_log_info;
+//!
+//! async fn check_fans() -> Result<(), OcptvError> {
+//!     global_log(LogSeverity::Info, "checking fans").await?;
+//!     ocptv_log_info!(global()?, "fans ok").await
+//! }
+//!
+//! let dut = DutInfo::builder("my_dut").build();
+//! init_run(TestRun::builder("diagnostic_name", "1.0"), dut).await?;
+//! check_fans().await?;
+//! shutdown(TestStatus::Complete, TestResult::Pass).await?;
+//!
+//! # Ok::<(), OcptvError>(())
+//! # });
+//! ```
+
+use std::sync::OnceLock;
+
+use crate::output as tv;
+use crate::spec;
+use tv::{dut, run};
+
+static GLOBAL_RUN: OnceLock<run::StartedTestRun> = OnceLock::new();
+
+/// Builds and starts the global test run. Fails with
+/// [`tv::OcptvError::GlobalRunAlreadyInitialized`] if it was already initialized, even
+/// if it since ended.
+pub async fn init_run<D: Into<dut::DutInfo>>(
+    builder: run::TestRunBuilder,
+    dut: D,
+) -> Result<(), tv::OcptvError> {
+    if GLOBAL_RUN.get().is_some() {
+        return Err(tv::OcptvError::GlobalRunAlreadyInitialized);
+    }
+
+    let run = builder.build().start(dut).await?;
+    // note: another initialization may have won the race while starting
+    GLOBAL_RUN
+        .set(run)
+        .map_err(|_| tv::OcptvError::GlobalRunAlreadyInitialized)
+}
+
+/// Returns the global test run, or [`tv::OcptvError::GlobalRunNotInitialized`] if
+/// [`init_run`] wasn't called.
+pub fn global() -> Result<&'static run::StartedTestRun, tv::OcptvError> {
+    GLOBAL_RUN
+        .get()
+        .ok_or(tv::OcptvError::GlobalRunNotInitialized)
+}
+
+/// Emits a log message in the global test run.
+/// See [`tv::StartedTestRun::add_log`].
+pub async fn global_log(severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError> {
+    global()?.add_log(severity, msg).await
+}
+
+/// Emits an error in the global test run.
+/// See [`tv::StartedTestRun::add_error_msg`].
+pub async fn global_error(symptom: &str, msg: &str) -> Result<(), tv::OcptvError> {
+    global()?.add_error_msg(symptom, msg).await
+}
+
+/// Ends the global test run, then flushes and closes its output, since the run lives
+/// in a static and is never dropped. Any later use of the global run fails with
+/// [`tv::OcptvError::RunAlreadyEnded`].
+/// See [`tv::StartedTestRun::end`] and [`tv::StartedTestRun::close`].
+pub async fn shutdown(
+    status: spec::TestStatus,
+    result: spec::TestResult,
+) -> Result<run::RunSummary, tv::OcptvError> {
+    let run = global()?;
+    let summary = run.end_impl(status, result).await?;
+    run.close_impl().await?;
+
+    Ok(summary)
+}
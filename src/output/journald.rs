@@ -0,0 +1,249 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::io;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::net::UnixDatagram;
+use tokio::sync::OnceCell;
+
+const JOURNAL_SOCKET: &str = "/run/systemd/journal/socket";
+
+// syslog priorities, as used by the journal PRIORITY field
+const PRIORITY_CRIT: u8 = 2;
+const PRIORITY_ERR: u8 = 3;
+const PRIORITY_WARNING: u8 = 4;
+const PRIORITY_INFO: u8 = 6;
+const PRIORITY_DEBUG: u8 = 7;
+
+/// Destination of the serialized journal entries.
+#[async_trait]
+pub(crate) trait JournalSink {
+    async fn send(&self, entry: &[u8]) -> Result<(), io::Error>;
+}
+
+/// The journald socket, connected when the first entry is sent.
+struct JournalSocket {
+    path: PathBuf,
+    socket: OnceCell<UnixDatagram>,
+}
+
+#[async_trait]
+impl JournalSink for JournalSocket {
+    async fn send(&self, entry: &[u8]) -> Result<(), io::Error> {
+        let socket = self
+            .socket
+            .get_or_try_init(|| async { UnixDatagram::unbound() })
+            .await?;
+        socket.send_to(entry, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// Writer sending every artifact to the systemd journal as a separate entry, using the
+/// journal native protocol.
+///
+/// The entry `MESSAGE` is the artifact json, so the output stream can be recovered with
+/// eg. `journalctl -t <identifier> -o cat`. The entries also carry these fields:
+/// - `SYSLOG_IDENTIFIER`: the identifier given when configuring the output;
+/// - `PRIORITY`: mapped from the log severity for log artifacts, error priority for error
+///   artifacts and info priority for everything else;
+/// - `OCPTV_SEQNO`: the artifact sequence number;
+/// - `OCPTV_ARTIFACT`: the artifact kind, eg. `testStepArtifact.measurement`.
+///
+/// Only available on Linux, with the `journald` feature.
+pub struct JournaldWriter {
+    identifier: String,
+    sink: Box<dyn JournalSink + Send + Sync + 'static>,
+}
+
+impl JournaldWriter {
+    /// Sends the entries to the local journald, tagged with `identifier`.
+    pub fn new(identifier: &str) -> Self {
+        Self::with_sink(
+            identifier,
+            Box::new(JournalSocket {
+                path: PathBuf::from(JOURNAL_SOCKET),
+                socket: OnceCell::new(),
+            }),
+        )
+    }
+
+    pub(crate) fn with_sink(
+        identifier: &str,
+        sink: Box<dyn JournalSink + Send + Sync + 'static>,
+    ) -> Self {
+        JournaldWriter {
+            identifier: identifier.to_owned(),
+            sink,
+        }
+    }
+
+    pub(crate) async fn write(&self, kind: &str, s: &str) -> Result<(), io::Error> {
+        self.sink.send(&self.entry(kind, s)).await
+    }
+
+    fn entry(&self, kind: &str, s: &str) -> Vec<u8> {
+        // note: the artifact was just serialized by the emitter, so it always parses
+        let artifact = serde_json::from_str::<Value>(s).unwrap_or_default();
+        let seqno = artifact["sequenceNumber"].as_u64().unwrap_or_default();
+
+        let mut entry = vec![];
+        append_field(&mut entry, "MESSAGE", s.as_bytes());
+        append_field(
+            &mut entry,
+            "PRIORITY",
+            priority(kind, &artifact).to_string().as_bytes(),
+        );
+        append_field(&mut entry, "SYSLOG_IDENTIFIER", self.identifier.as_bytes());
+        append_field(&mut entry, "OCPTV_SEQNO", seqno.to_string().as_bytes());
+        append_field(&mut entry, "OCPTV_ARTIFACT", kind.as_bytes());
+        entry
+    }
+}
+
+fn priority(kind: &str, artifact: &Value) -> u8 {
+    let Some((container, name)) = kind.split_once('.') else {
+        return PRIORITY_INFO;
+    };
+
+    match name {
+        "log" => match artifact[container]["log"]["severity"].as_str() {
+            Some("DEBUG") => PRIORITY_DEBUG,
+            Some("WARNING") => PRIORITY_WARNING,
+            Some("ERROR") => PRIORITY_ERR,
+            Some("FATAL") => PRIORITY_CRIT,
+            _ => PRIORITY_INFO,
+        },
+        "error" => PRIORITY_ERR,
+        _ => PRIORITY_INFO,
+    }
+}
+
+// ref: https://systemd.io/JOURNAL_NATIVE_PROTOCOL/
+fn append_field(entry: &mut Vec<u8>, key: &str, value: &[u8]) {
+    entry.extend_from_slice(key.as_bytes());
+    if value.contains(&b'\n') {
+        // values with newlines are sent length prefixed
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value);
+    entry.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use tokio::sync::Mutex;
+
+    use super::*;
+
+    struct MockSink {
+        entries: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    #[async_trait]
+    impl JournalSink for MockSink {
+        async fn send(&self, entry: &[u8]) -> Result<(), io::Error> {
+            self.entries.lock().await.push(entry.to_vec());
+            Ok(())
+        }
+    }
+
+    fn fields(entry: &[u8]) -> Vec<(String, String)> {
+        String::from_utf8_lossy(entry)
+            .lines()
+            .map(|l| {
+                let (k, v) = l.split_once('=').unwrap();
+                (k.to_owned(), v.to_owned())
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_journald_field_mapping() -> Result<()> {
+        let entries = Arc::new(Mutex::new(vec![]));
+        let writer = JournaldWriter::with_sink(
+            "mydiag",
+            Box::new(MockSink {
+                entries: Arc::clone(&entries),
+            }),
+        );
+
+        let log = r#"{"testStepArtifact":{"testStepId":"step0","log":{"message":"hot","severity":"WARNING"}},"sequenceNumber":7,"timestamp":"2022-01-01T00:00:00.000Z"}"#;
+        writer.write("testStepArtifact.log", log).await?;
+        let error = r#"{"testRunArtifact":{"error":{"symptom":"bad"}},"sequenceNumber":8,"timestamp":"2022-01-01T00:00:00.000Z"}"#;
+        writer.write("testRunArtifact.error", error).await?;
+
+        let entries = entries.lock().await;
+        let expected = |msg: &str, priority: &str, seqno: &str, kind: &str| {
+            [
+                ("MESSAGE", msg),
+                ("PRIORITY", priority),
+                ("SYSLOG_IDENTIFIER", "mydiag"),
+                ("OCPTV_SEQNO", seqno),
+                ("OCPTV_ARTIFACT", kind),
+            ]
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .to_vec()
+        };
+        assert_eq!(
+            fields(&entries[0]),
+            expected(log, "4", "7", "testStepArtifact.log")
+        );
+        assert_eq!(
+            fields(&entries[1]),
+            expected(error, "3", "8", "testRunArtifact.error")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_journald_priority() -> Result<()> {
+        let log = |severity: &str| {
+            serde_json::json!({
+                "testRunArtifact": {
+                    "log": { "severity": severity }
+                }
+            })
+        };
+
+        assert_eq!(
+            priority("testRunArtifact.log", &log("DEBUG")),
+            PRIORITY_DEBUG
+        );
+        assert_eq!(priority("testRunArtifact.log", &log("INFO")), PRIORITY_INFO);
+        assert_eq!(
+            priority("testRunArtifact.log", &log("FATAL")),
+            PRIORITY_CRIT
+        );
+        assert_eq!(
+            priority("testStepArtifact.error", &Value::Null),
+            PRIORITY_ERR
+        );
+        assert_eq!(priority("schemaVersion", &Value::Null), PRIORITY_INFO);
+        Ok(())
+    }
+
+    #[test]
+    fn test_journald_multiline_field() -> Result<()> {
+        let mut entry = vec![];
+        append_field(&mut entry, "MESSAGE", b"a\nb");
+
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&3u64.to_le_bytes());
+        expected.extend_from_slice(b"a\nb\n");
+        assert_eq!(entry, expected);
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@
 ///
 /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#error>
 ///
-/// Equivalent to the [`$crate::StartedTestRun::error_with_details`] method.
+/// Equivalent to the [`crate::output::StartedTestRun::add_error_detail`] method.
 ///
 /// It accepts both a symptom and a message, or just a symptom.
 /// Information about the source file and line number is automatically added.
@@ -78,7 +78,7 @@ macro_rules! ocptv_log {
         ///
         /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#log>
         ///
-        /// Equivalent to the [`$crate::StartedTestRun::log_with_details`] method.
+        /// Equivalent to the [`crate::output::StartedTestRun::add_log_detail`] method.
         ///
         /// They accept message as only parameter.
         /// Information about the source file and line number is automatically added.
@@ -122,13 +122,52 @@ ocptv_log!(ocptv_log_warning, ocptv::output::LogSeverity::Warning);
 ocptv_log!(ocptv_log_error, ocptv::output::LogSeverity::Error);
 ocptv_log!(ocptv_log_fatal, ocptv::output::LogSeverity::Fatal);
 
+/// Emit an artifact of type Log, with a message built from format arguments.
+///
+/// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#log>
+///
+/// Equivalent to the [`crate::output::StartedTestRun::add_log_detail`] method, with the message
+/// formatted exactly as [`format!`] would do it.
+///
+/// The severity is given by the [`crate::output::LogSeverity`] variant name.
+/// Information about the source file and line number is automatically added.
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// # use ocptv::output::*;
+/// use ocptv::ocptv_logf;
+///
+/// let dut = DutInfo::new("my_dut");
+/// let run = TestRun::new("run_name", "1.0").start(dut).await?;
+/// let voltage = 1.2;
+/// ocptv_logf!(run, Info, "voltage={voltage}V").await?;
+/// run.end(TestStatus::Complete, TestResult::Pass).await?;
+///
+/// # Ok::<(), OcptvError>(())
+/// # });
+/// ```
+#[macro_export]
+macro_rules! ocptv_logf {
+    ($artifact:expr, $severity:ident, $($arg:tt)+) => {
+        $artifact.add_log_detail(
+            $crate::output::Log::builder(&format!($($arg)+))
+                .severity($crate::output::LogSeverity::$severity)
+                .source(file!(), line!() as i32)
+                .build(),
+        )
+    };
+}
+
 macro_rules! ocptv_diagnosis {
     ($name:ident, $diagnosis_type:path) => {
         /// Emit an artifact of type Diagnosis.
         ///
         /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#diagnosis>
         ///
-        /// Equivalent to the [`$crate::StartedTestStep::diagnosis_with_details`] method.
+        /// Equivalent to the [`crate::output::StartedTestStep::add_diagnosis_detail`] method.
+        /// Without the source location, use [`crate::output::StartedTestStep::add_diagnosis`].
         ///
         /// They accept verdict as only parameter.
         /// Information about the source file and line number is automatically added.
@@ -4,17 +4,27 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::cmp::Ordering as CmpOrdering;
 use std::collections::BTreeMap;
 use std::future::Future;
+use std::panic;
+use std::pin::pin;
 use std::sync::atomic::{self, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
 
 use delegate::delegate;
+use futures::{Stream, StreamExt};
+use tokio::sync::oneshot;
+use tokio::time;
 
 use crate::output as tv;
-use crate::output::trait_ext::{MapExt, VecExt};
+use crate::output::trait_ext::MapExt;
 use crate::spec;
-use tv::{dut, step, Ident};
+use tv::{dut, emitter, step, value, Ident};
+
+// element metadata key listing the series validators failed by the element,
+// see `MeasurementSeriesDetailBuilder::enforce_validators`
+const FAILED_VALIDATORS_METADATA: &str = "ocptv_failed_validators";
 
 /// The measurement series.
 /// A Measurement Series is a time-series list of measurements.
@@ -68,7 +78,7 @@ impl MeasurementSeries {
             name: info.name.clone(),
             unit: info.unit.clone(),
             series_id: self.id.clone(),
-            validators: info.validators.map_option(Validator::to_spec),
+            validators: validators_to_spec(&info.validators, self.emitter.non_finite_policy())?,
             hardware_info: info
                 .hardware_info
                 .as_ref()
@@ -78,12 +88,18 @@ impl MeasurementSeries {
         };
 
         self.emitter
-            .emit(&spec::TestStepArtifactImpl::MeasurementSeriesStart(start))
+            .emit(spec::TestStepArtifactImpl::MeasurementSeriesStart(start))
             .await?;
 
         Ok(StartedMeasurementSeries {
             parent: self,
             seqno: Arc::new(atomic::AtomicU64::new(0)),
+            next_index: atomic::AtomicU64::new(0),
+            ended: atomic::AtomicBool::new(false),
+            emit_lock: tokio::sync::Mutex::new(()),
+            last_timestamp: Mutex::new(None),
+            stats: Mutex::new(SeriesStats::default()),
+            violations: atomic::AtomicU64::new(0),
         })
     }
 
@@ -94,6 +110,9 @@ impl MeasurementSeries {
     /// all the messages are emitted between the start and end messages, the order
     /// is respected and no messages is lost.
     ///
+    /// If the closure returns an error, the series is still ended, with the number of
+    /// elements emitted so far as its `totalCount`, then the closure error is returned.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -123,13 +142,118 @@ impl MeasurementSeries {
         F: FnOnce(ScopedMeasurementSeries) -> R + Send + 'static,
     {
         let series = Arc::new(self.start().await?);
-        func(ScopedMeasurementSeries {
+        let result = func(ScopedMeasurementSeries {
             series: Arc::clone(&series),
         })
-        .await?;
-        series.end_impl().await?;
+        .await;
+        let end = series.end_impl().await;
 
-        Ok(())
+        // note: the closure error is more relevant than a failure to end the series
+        result.and(end)
+    }
+
+    /// Starts the measurement series and spawns a task that adds one element, produced
+    /// by `func`, on every tick of `interval`. The first element is added immediately.
+    /// Element timestamps come from the configured timestamp provider, as with
+    /// [`StartedMeasurementSeries::add_measurement`].
+    ///
+    /// If sampling falls behind, eg. because the runtime was busy or the output writer
+    /// was slow, the missed ticks are skipped rather than sampled in a burst: a burst
+    /// would record several elements with nearly the same timestamp, which misrepresents
+    /// the sampled signal. The next sample is then taken on the following interval boundary.
+    ///
+    /// Sampling goes on until [`SeriesSampler::stop`] is called or the sampler is dropped;
+    /// either way the series is ended. If adding an element fails, sampling stops early
+    /// and the error is returned by `stop`. Must be called from within a tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use std::time::Duration;
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("fan_speed");
+    /// let sampler = series.sample_every(Duration::from_millis(10), || 1200).await?;
+    /// tokio::time::sleep(Duration::from_millis(35)).await;
+    /// let count = sampler.stop().await?;
+    /// assert!(count > 0);
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn sample_every<V, F>(
+        self,
+        interval: std::time::Duration,
+        mut func: F,
+    ) -> Result<SeriesSampler, tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        F: FnMut() -> V + Send + 'static,
+    {
+        let series = self.start().await?;
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+
+        let task = tokio::spawn(async move {
+            let mut ticks = time::interval(interval);
+            ticks.set_missed_tick_behavior(time::MissedTickBehavior::Skip);
+
+            let mut result = Ok(());
+            loop {
+                tokio::select! {
+                    // note: poll the stop signal first, so no element is added after `stop`
+                    biased;
+
+                    // note: a dropped sampler also resolves the receiver, and stops sampling
+                    _ = &mut stop_rx => break,
+                    _ = ticks.tick() => {
+                        let value: tv::MeasurementValue = func().into();
+                        if let Err(e) = series.add_measurement(value).await {
+                            result = Err(e);
+                            break;
+                        }
+                    }
+                }
+            }
+
+            let count = series.seqno.load(Ordering::Acquire);
+            let end = series.end_impl().await;
+
+            // note: the sampling error is more relevant than a failure to end the series
+            result.and(end).map(|_| count)
+        });
+
+        Ok(SeriesSampler {
+            stop: Some(stop_tx),
+            task,
+        })
+    }
+}
+
+/// Handle over a measurement series being sampled in a background task.
+/// See [`MeasurementSeries::sample_every`].
+pub struct SeriesSampler {
+    stop: Option<oneshot::Sender<()>>,
+    task: tokio::task::JoinHandle<Result<u64, tv::OcptvError>>,
+}
+
+impl SeriesSampler {
+    /// Stops sampling, ends the measurement series and returns the number of
+    /// elements that were added to it. If the sampling function panicked, the
+    /// panic is resumed here.
+    pub async fn stop(mut self) -> Result<u64, tv::OcptvError> {
+        if let Some(stop) = self.stop.take() {
+            // note: the task may have already stopped on an emit error
+            let _ = stop.send(());
+        }
+
+        match (&mut self.task).await {
+            Ok(result) => result,
+            Err(e) => panic::resume_unwind(e.into_panic()),
+        }
     }
 }
 
@@ -137,25 +261,132 @@ impl MeasurementSeries {
 pub struct StartedMeasurementSeries {
     parent: MeasurementSeries,
 
+    // number of written elements, reported as the series `totalCount`
     seqno: Arc<atomic::AtomicU64>,
+    // next element index; reserved before writing and only given back if the write
+    // failed, so that an element whose write was cancelled never shares its index with
+    // a later one
+    next_index: atomic::AtomicU64,
+    ended: atomic::AtomicBool,
+    // held while emitting elements and the end, so that element indexes are assigned in
+    // write order, and none is emitted after the end
+    emit_lock: tokio::sync::Mutex<()>,
+    // latest element timestamp, only tracked for series with strict timestamps
+    last_timestamp: Mutex<Option<chrono::DateTime<chrono_tz::Tz>>>,
+    // summary of the written elements, for `end_with_stats`
+    stats: Mutex<SeriesStats>,
+    // written elements failing the series validators, only counted when they're enforced
+    violations: atomic::AtomicU64,
 }
 
 impl StartedMeasurementSeries {
-    fn incr_seqno(&self) -> u64 {
-        self.seqno.fetch_add(1, Ordering::AcqRel)
+    fn already_ended(&self) -> tv::OcptvError {
+        tv::OcptvError::SeriesAlreadyEnded(self.parent.id.clone())
+    }
+
+    // note: must only be called while holding the emit lock
+    fn check_timestamps<'a, I>(&self, timestamps: I) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = &'a chrono::DateTime<chrono_tz::Tz>>,
+    {
+        if !self.parent.detail.strict_timestamps {
+            return Ok(());
+        }
+
+        let mut last = *self
+            .last_timestamp
+            .lock()
+            .expect("last timestamp lock is never poisoned");
+        for timestamp in timestamps {
+            if last.is_some_and(|last| *timestamp < last) {
+                return Err(tv::OcptvError::OutOfOrderElement(self.parent.id.clone()));
+            }
+            last = Some(*timestamp);
+        }
+        Ok(())
+    }
+
+    // returns the names of the series validators failed by the value, if they're enforced;
+    // a value the validator can't be evaluated against, eg. a string against a number
+    // or an encoded non finite number, fails it too
+    fn failed_validators(&self, value: &tv::MeasurementValue) -> Vec<String> {
+        let detail = &self.parent.detail;
+        if !detail.enforce_validators {
+            return vec![];
+        }
+
+        let value = tv::Value::from(value.clone());
+        detail
+            .validators
+            .iter()
+            .filter(|validator| !validator.validate(&value).unwrap_or(false))
+            .map(|validator| validator.label())
+            .collect()
+    }
+
+    // note: must only be called while holding the emit lock, after the elements were written
+    fn record_written<'a, I>(&self, elements: I)
+    where
+        I: IntoIterator<Item = (&'a chrono::DateTime<chrono_tz::Tz>, &'a Option<f64>, bool)>,
+    {
+        let mut stats = self.stats.lock().expect("stats lock is never poisoned");
+        let mut last = self
+            .last_timestamp
+            .lock()
+            .expect("last timestamp lock is never poisoned");
+        for (timestamp, number, violated) in elements {
+            self.seqno.fetch_add(1, Ordering::AcqRel);
+            if violated {
+                self.violations.fetch_add(1, Ordering::AcqRel);
+            }
+            stats.record(*number);
+            *last = Some(*timestamp);
+        }
     }
 
     // note: keep the self-consuming method for crate api, but use this one internally,
     // since `StartedMeasurementSeries::end` only needs to take ownership for syntactic reasons
     async fn end_impl(&self) -> Result<(), tv::OcptvError> {
+        let _emitting = self.emit_lock.lock().await;
+        if self.ended.swap(true, Ordering::AcqRel) {
+            return Err(self.already_ended());
+        }
+
+        let total_count = self.seqno.load(Ordering::Acquire);
+        let violations = self.violations.load(Ordering::Acquire);
+        if violations > 0 {
+            let detail = &self.parent.detail;
+            let msg = format!(
+                "{} of {} elements of series {} failed its validators",
+                violations, total_count, self.parent.id
+            );
+            let mut diagnosis = tv::Diagnosis::builder(
+                &format!("{}_validation_failed", detail.name),
+                spec::DiagnosisType::Fail,
+            )
+            .message(&msg);
+            if let Some(hardware_info) = &detail.hardware_info {
+                diagnosis = diagnosis.hardware_info(hardware_info);
+            }
+            if let Some(subcomponent) = &detail.subcomponent {
+                diagnosis = diagnosis.subcomponent(subcomponent);
+            }
+            self.parent
+                .emitter
+                .emit(spec::TestStepArtifactImpl::Diagnosis(
+                    diagnosis.build().to_artifact(),
+                ))
+                .await?;
+        }
+
         let end = spec::MeasurementSeriesEnd {
             series_id: self.parent.id.clone(),
-            total_count: self.seqno.load(Ordering::Acquire),
+            total_count,
         };
 
         self.parent
             .emitter
-            .emit(&spec::TestStepArtifactImpl::MeasurementSeriesEnd(end))
+            .emit(spec::TestStepArtifactImpl::MeasurementSeriesEnd(end))
             .await?;
 
         Ok(())
@@ -180,10 +411,155 @@ impl StartedMeasurementSeries {
     /// # Ok::<(), OcptvError>(())
     /// # });
     /// ```
+    ///
+    /// Ending consumes the series, so no elements can be added afterwards:
+    ///
+    /// ```compile_fail
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// # let dut = DutInfo::new("my_dut");
+    /// # let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// # let step = run.add_step("step_name").start().await?;
+    /// let series = step.add_measurement_series("name").start().await?;
+    /// series.end().await?;
+    /// series.add_measurement(60).await?;
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    ///
+    /// Handles that can't be consumed, like the `ScopedMeasurementSeries` shared with a
+    /// scope, are checked at runtime instead: using them after the series ended fails with
+    /// [`tv::OcptvError::SeriesAlreadyEnded`]. An element being added from another task
+    /// while the series ends is either emitted before `measurementSeriesEnd`, and counted
+    /// in its `totalCount`, or rejected with that error.
     pub async fn end(self) -> Result<(), tv::OcptvError> {
         self.end_impl().await
     }
 
+    /// Ends the measurement series, after emitting summary statistics of its numeric
+    /// elements as regular measurements, with the series unit and hardware info:
+    /// `{name}_min`, `{name}_max`, `{name}_mean`, `{name}_stddev` (population standard
+    /// deviation) and `{name}_count` (the number of numeric elements, without unit), where
+    /// `{name}` is the series name. Only the count is emitted if the series has no numeric
+    /// elements.
+    ///
+    /// Non-numeric elements are not part of the statistics; a `Warning` log with their
+    /// number is emitted if there are any.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("fan").start().await?;
+    /// series.add_measurements([1000, 1200, 1100]).await?;
+    /// // emits fan_min = 1000, fan_max = 1200, fan_mean = 1100, etc
+    /// series.end_with_stats().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn end_with_stats(self) -> Result<(), tv::OcptvError> {
+        if self.ended.load(Ordering::Acquire) {
+            return Err(self.already_ended());
+        }
+
+        let stats = self
+            .stats
+            .lock()
+            .expect("stats lock is never poisoned")
+            .clone();
+        if stats.skipped > 0 {
+            let msg = format!(
+                "skipped {} non-numeric elements in the statistics of series {}",
+                stats.skipped, self.parent.id
+            );
+            let log = tv::Log::builder(&msg)
+                .severity(spec::LogSeverity::Warning)
+                .build();
+            self.parent
+                .emitter
+                .emit(spec::TestStepArtifactImpl::Log(log.to_artifact()))
+                .await?;
+        }
+
+        let detail = &self.parent.detail;
+        let measurement = |stat: &str, value: tv::Value| spec::Measurement {
+            name: format!("{}_{}", detail.name, stat),
+            value,
+            unit: detail.unit.clone(),
+            validators: None,
+            hardware_info: detail
+                .hardware_info
+                .as_ref()
+                .map(dut::DutHardwareInfo::to_spec),
+            subcomponent: detail.subcomponent.as_ref().map(dut::Subcomponent::to_spec),
+            metadata: None,
+        };
+        let mut measurements = vec![];
+        if let (Some(min), Some(max)) = (stats.min, stats.max) {
+            measurements.extend([
+                measurement("min", min.into()),
+                measurement("max", max.into()),
+                measurement("mean", stats.mean.into()),
+                measurement("stddev", stats.stddev().into()),
+            ]);
+        }
+        measurements.push(spec::Measurement {
+            unit: None,
+            ..measurement("count", stats.count.into())
+        });
+        self.parent
+            .emitter
+            .emit_batch(
+                measurements
+                    .into_iter()
+                    .map(spec::TestStepArtifactImpl::Measurement),
+            )
+            .await?;
+
+        self.end_impl().await
+    }
+
+    /// Returns a guard over this series, which ends it when dropped, eg. on an early
+    /// return through `?`. The `totalCount` is the number of elements added so far.
+    /// Ending the series through [`MeasurementSeriesGuard::end`] disarms the guard.
+    ///
+    /// The end artifact is emitted on a best effort basis, since there's no async drop;
+    /// any error is lost. The series end must come before the end of its step: a series
+    /// guard created after a [`tv::TestStepGuard`] in the same scope is dropped before it.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("name").start().await?.into_guard();
+    /// series.add_measurement(60).await?;
+    /// series.end().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn into_guard(self) -> MeasurementSeriesGuard {
+        MeasurementSeriesGuard { series: Some(self) }
+    }
+
+    /// Returns the number of elements added so far that failed any of the series
+    /// validators, which is always zero unless they're enforced.
+    /// See [`MeasurementSeriesDetailBuilder::enforce_validators`].
+    pub fn violations(&self) -> u64 {
+        self.violations.load(Ordering::Acquire)
+    }
+
     /// Adds a measurement element to the measurement series.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurementserieselement>
@@ -203,7 +579,7 @@ impl StartedMeasurementSeries {
     /// # Ok::<(), OcptvError>(())
     /// # });
     /// ```
-    pub async fn add_measurement<V: Into<tv::Value>>(
+    pub async fn add_measurement<V: Into<tv::MeasurementValue>>(
         &self,
         value: V,
     ) -> Result<(), tv::OcptvError> {
@@ -214,6 +590,46 @@ impl StartedMeasurementSeries {
         .await
     }
 
+    /// Adds a measurement element to the measurement series, captured at `timestamp`
+    /// instead of now, eg. for buffered sensor samples. Only the element timestamp is
+    /// set; the artifact timestamp is still the emit time. Use
+    /// [`MeasurementElementDetailBuilder::timestamp`] for an element with metadata.
+    ///
+    /// Elements may be added out of timestamp order, unless the series was built with
+    /// [`MeasurementSeriesDetailBuilder::strict_timestamps`].
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurementserieselement>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use chrono::{Duration, Utc};
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("name").start().await?;
+    /// let captured = Utc::now().with_timezone(&chrono_tz::UTC) - Duration::seconds(5);
+    /// series.add_measurement_at(captured, 60).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_measurement_at<V: Into<tv::MeasurementValue>>(
+        &self,
+        timestamp: chrono::DateTime<chrono_tz::Tz>,
+        value: V,
+    ) -> Result<(), tv::OcptvError> {
+        self.add_measurement_detail(MeasurementElementDetail {
+            value: value.into(),
+            timestamp: Some(timestamp),
+            ..Default::default()
+        })
+        .await
+    }
+
     /// Adds a measurement element to the measurement series.
     /// This method accepts a full set of details for the measurement element.
     ///
@@ -237,27 +653,345 @@ impl StartedMeasurementSeries {
     /// ```
     pub async fn add_measurement_detail(
         &self,
-        element: MeasurementElementDetail,
+        mut element: MeasurementElementDetail,
     ) -> Result<(), tv::OcptvError> {
-        let element = spec::MeasurementSeriesElement {
-            index: self.incr_seqno(),
-            value: element.value,
-            timestamp: element
-                .timestamp
-                .unwrap_or(self.parent.emitter.timestamp_provider().now()),
-            series_id: self.parent.id.clone(),
-            metadata: element.metadata.option(),
-        };
+        let _emitting = self.emit_lock.lock().await;
+        if self.ended.load(Ordering::Acquire) {
+            return Err(self.already_ended());
+        }
 
-        self.parent
+        let timestamp = element
+            .timestamp
+            .unwrap_or(self.parent.emitter.timestamp_provider().now());
+        self.check_timestamps([&timestamp])?;
+        let failed = self.failed_validators(&element.value);
+        let violated = element.tag_failed(failed);
+
+        // note: the index is reserved right before the write, which can't race with other
+        // elements since they're all emitted while holding the emit lock; the element only
+        // counts in the series once written, and a failed write gives the index back
+        let number = element.number();
+        let index = self.next_index.load(Ordering::Acquire);
+        let element = element.to_spec(
+            index,
+            timestamp,
+            &self.parent.id,
+            self.parent.emitter.non_finite_policy(),
+        )?;
+        self.next_index.store(index + 1, Ordering::Release);
+
+        let result = self
+            .parent
             .emitter
-            .emit(&spec::TestStepArtifactImpl::MeasurementSeriesElement(
+            .emit(spec::TestStepArtifactImpl::MeasurementSeriesElement(
                 element,
             ))
-            .await?;
+            .await;
+        if let Err(e) = result {
+            self.next_index.store(index, Ordering::Release);
+            return Err(e);
+        }
 
+        self.record_written([(&timestamp, &number, violated)]);
         Ok(())
     }
+
+    /// Drains the stream into elements of the measurement series, in order, and returns
+    /// the number of elements added. Each item is only pulled from the stream once the
+    /// previous element was written, so a slow output writer applies backpressure to
+    /// the stream producer.
+    ///
+    /// If the returned future is dropped, eg. on a timeout, the elements written so far
+    /// stay in the series and count in the `totalCount` reported by
+    /// [`StartedMeasurementSeries::end`]. An element whose write was cancelled midway
+    /// isn't counted, but its index is never given to a later element. The same goes
+    /// for an error adding an element, which stops draining the stream.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurementserieselement>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("name").start().await?;
+    /// let readings = futures::stream::iter([60.5, 70.0, 80.25]);
+    /// assert_eq!(series.extend_from_stream(readings).await?, 3);
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn extend_from_stream<V, S>(&self, stream: S) -> Result<u64, tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        S: Stream<Item = V>,
+    {
+        self.extend_from_detail_stream(stream.map(|value| MeasurementElementDetail {
+            value: value.into(),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Drains the stream of timestamped values into elements of the measurement series,
+    /// eg. for samples buffered by a sensor driver. Each element is captured at the
+    /// timestamp of its item, as with [`StartedMeasurementSeries::add_measurement_at`].
+    /// See [`StartedMeasurementSeries::extend_from_stream`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use chrono::Utc;
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("name").start().await?;
+    /// let captured = Utc::now().with_timezone(&chrono_tz::UTC);
+    /// let readings = futures::stream::iter([(captured, 60), (captured, 70)]);
+    /// assert_eq!(series.extend_from_timestamped_stream(readings).await?, 2);
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn extend_from_timestamped_stream<V, S>(
+        &self,
+        stream: S,
+    ) -> Result<u64, tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        S: Stream<Item = (chrono::DateTime<chrono_tz::Tz>, V)>,
+    {
+        self.extend_from_detail_stream(stream.map(|(timestamp, value)| MeasurementElementDetail {
+            value: value.into(),
+            timestamp: Some(timestamp),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    async fn extend_from_detail_stream<S>(&self, stream: S) -> Result<u64, tv::OcptvError>
+    where
+        S: Stream<Item = MeasurementElementDetail>,
+    {
+        let mut stream = pin!(stream);
+        let mut count = 0;
+        while let Some(element) = stream.next().await {
+            self.add_measurement_detail(element).await?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Adds a batch of measurement elements to the measurement series, in order.
+    /// Each element is still a separate artifact, with consecutive indexes and sequence
+    /// numbers, but the batch is written at once, which is faster than adding the
+    /// elements one by one.
+    ///
+    /// If an element can't be written, this fails with [`tv::OcptvError::BatchInterrupted`],
+    /// giving the number of elements written; only these count in the series `totalCount`.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurementserieselement>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("name").start().await?;
+    /// series.add_measurements([60, 70, 80]).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_measurements<V, I>(&self, values: I) -> Result<(), tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        I: IntoIterator<Item = V>,
+    {
+        self.add_measurement_details(values.into_iter().map(|value| MeasurementElementDetail {
+            value: value.into(),
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Adds a batch of measurement elements to the measurement series, in order.
+    /// This method accepts a full set of details for each element, eg. with metadata.
+    /// See [`StartedMeasurementSeries::add_measurements`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let series = step.add_measurement_series("name").start().await?;
+    /// series
+    ///     .add_measurement_details((0..3).map(|i| {
+    ///         MeasurementElementDetail::builder(60 + i).add_metadata("sample", i).build()
+    ///     }))
+    ///     .await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_measurement_details<I>(&self, elements: I) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = MeasurementElementDetail>,
+    {
+        let _emitting = self.emit_lock.lock().await;
+        if self.ended.load(Ordering::Acquire) {
+            return Err(self.already_ended());
+        }
+
+        let now = self.parent.emitter.timestamp_provider().now();
+        let mut elements = elements
+            .into_iter()
+            .map(|element| (element.timestamp.unwrap_or(now), element))
+            .collect::<Vec<_>>();
+        self.check_timestamps(elements.iter().map(|(timestamp, _)| timestamp))?;
+
+        let mut violated = Vec::with_capacity(elements.len());
+        for (_, element) in &mut elements {
+            let failed = self.failed_validators(&element.value);
+            violated.push(element.tag_failed(failed));
+        }
+
+        let summaries = elements
+            .iter()
+            .zip(violated)
+            .map(|((timestamp, element), violated)| (*timestamp, element.number(), violated))
+            .collect::<Vec<_>>();
+        let count = summaries.len();
+        let first = self.next_index.load(Ordering::Acquire);
+        let policy = self.parent.emitter.non_finite_policy();
+        // note: convert the whole batch first, so that an invalid value rejects all of it
+        let artifacts = elements
+            .into_iter()
+            .zip(first..)
+            .map(|((timestamp, element), index)| {
+                element
+                    .to_spec(index, timestamp, &self.parent.id, policy)
+                    .map(spec::TestStepArtifactImpl::MeasurementSeriesElement)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.next_index
+            .store(first + count as u64, Ordering::Release);
+
+        // note: only the elements written before an error count in the series
+        let result = self.parent.emitter.emit_batch(artifacts).await;
+        let written = match &result {
+            Ok(()) => count,
+            Err(tv::OcptvError::BatchInterrupted(written, _)) => *written,
+            Err(_) => 0,
+        };
+        self.next_index
+            .store(first + written as u64, Ordering::Release);
+        self.record_written(summaries[..written].iter().map(|(t, n, v)| (t, n, *v)));
+        result
+    }
+}
+
+/// Running summary of the numeric elements of a series, see
+/// [`StartedMeasurementSeries::end_with_stats`].
+#[derive(Debug, Default, Clone)]
+struct SeriesStats {
+    count: u64,
+    skipped: u64,
+    min: Option<f64>,
+    max: Option<f64>,
+    // note: Welford's online algorithm, which is numerically stable for long series
+    mean: f64,
+    m2: f64,
+}
+
+impl SeriesStats {
+    fn record(&mut self, value: Option<f64>) {
+        let Some(value) = value else {
+            self.skipped += 1;
+            return;
+        };
+
+        self.count += 1;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    fn stddev(&self) -> f64 {
+        match self.count {
+            0 => 0.0,
+            n => (self.m2 / n as f64).sqrt(),
+        }
+    }
+}
+
+/// Guard over a [`StartedMeasurementSeries`] that ends the series when dropped.
+/// See [`StartedMeasurementSeries::into_guard`].
+pub struct MeasurementSeriesGuard {
+    // note: only taken when the series is explicitly ended
+    series: Option<StartedMeasurementSeries>,
+}
+
+impl MeasurementSeriesGuard {
+    fn series(&self) -> &StartedMeasurementSeries {
+        self.series.as_ref().expect("series is only taken by end")
+    }
+
+    /// Ends the measurement series and disarms the guard.
+    /// See [`StartedMeasurementSeries::end`] for details.
+    pub async fn end(mut self) -> Result<(), tv::OcptvError> {
+        let series = self.series.take().expect("series is only taken by end");
+        series.end().await
+    }
+
+    delegate! {
+        to self.series() {
+            pub fn violations(&self) -> u64;
+            pub async fn add_measurement<V: Into<tv::MeasurementValue>>(&self, value: V) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_at<V: Into<tv::MeasurementValue>>(
+                &self,
+                timestamp: chrono::DateTime<chrono_tz::Tz>,
+                value: V,
+            ) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_detail(
+                &self,
+                element: MeasurementElementDetail,
+            ) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurements<V: Into<tv::MeasurementValue>, I: IntoIterator<Item = V>>(&self, values: I) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_details<I: IntoIterator<Item = MeasurementElementDetail>>(&self, elements: I) -> Result<(), tv::OcptvError>;
+            pub async fn extend_from_stream<V: Into<tv::MeasurementValue>, S: Stream<Item = V>>(&self, stream: S) -> Result<u64, tv::OcptvError>;
+            pub async fn extend_from_timestamped_stream<V: Into<tv::MeasurementValue>, S: Stream<Item = (chrono::DateTime<chrono_tz::Tz>, V)>>(
+                &self,
+                stream: S,
+            ) -> Result<u64, tv::OcptvError>;
+        }
+    }
+}
+
+impl Drop for MeasurementSeriesGuard {
+    fn drop(&mut self) {
+        if let Some(series) = self.series.take() {
+            let _ = emitter::block_on_in_drop(series.end_impl());
+        }
+    }
 }
 
 /// TODO: docs
@@ -268,11 +1002,24 @@ pub struct ScopedMeasurementSeries {
 impl ScopedMeasurementSeries {
     delegate! {
         to self.series {
-            pub async fn add_measurement<V: Into<tv::Value>>(&self, value: V) -> Result<(), tv::OcptvError>;
+            pub fn violations(&self) -> u64;
+            pub async fn add_measurement<V: Into<tv::MeasurementValue>>(&self, value: V) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_at<V: Into<tv::MeasurementValue>>(
+                &self,
+                timestamp: chrono::DateTime<chrono_tz::Tz>,
+                value: V,
+            ) -> Result<(), tv::OcptvError>;
             pub async fn add_measurement_detail(
                 &self,
                 element: MeasurementElementDetail,
             ) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurements<V: Into<tv::MeasurementValue>, I: IntoIterator<Item = V>>(&self, values: I) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_details<I: IntoIterator<Item = MeasurementElementDetail>>(&self, elements: I) -> Result<(), tv::OcptvError>;
+            pub async fn extend_from_stream<V: Into<tv::MeasurementValue>, S: Stream<Item = V>>(&self, stream: S) -> Result<u64, tv::OcptvError>;
+            pub async fn extend_from_timestamped_stream<V: Into<tv::MeasurementValue>, S: Stream<Item = (chrono::DateTime<chrono_tz::Tz>, V)>>(
+                &self,
+                stream: S,
+            ) -> Result<u64, tv::OcptvError>;
         }
     }
 }
@@ -280,29 +1027,61 @@ impl ScopedMeasurementSeries {
 /// TODO: docs
 #[derive(Default)]
 pub struct MeasurementElementDetail {
-    value: tv::Value,
+    value: tv::MeasurementValue,
     timestamp: Option<chrono::DateTime<chrono_tz::Tz>>,
 
     metadata: BTreeMap<String, tv::Value>,
 }
 
 impl MeasurementElementDetail {
-    pub fn builder<V: Into<tv::Value>>(value: V) -> MeasurementElementDetailBuilder {
+    pub fn builder<V: Into<tv::MeasurementValue>>(value: V) -> MeasurementElementDetailBuilder {
         MeasurementElementDetailBuilder::new(value.into())
     }
+
+    /// Returns the value for the series statistics, if it's a finite number.
+    fn number(&self) -> Option<f64> {
+        self.value.as_f64().filter(|n| n.is_finite())
+    }
+
+    // note: returns whether the element failed any validator
+    fn tag_failed(&mut self, failed: Vec<String>) -> bool {
+        if failed.is_empty() {
+            return false;
+        }
+        self.metadata
+            .insert(FAILED_VALIDATORS_METADATA.to_owned(), failed.into());
+        true
+    }
+
+    fn to_spec(
+        &self,
+        index: u64,
+        timestamp: chrono::DateTime<chrono_tz::Tz>,
+        series_id: &str,
+        policy: tv::NonFinitePolicy,
+    ) -> Result<spec::MeasurementSeriesElement, tv::MeasurementValueError> {
+        let (value, encoded) = self.value.to_spec(policy)?;
+        Ok(spec::MeasurementSeriesElement {
+            index,
+            value,
+            timestamp,
+            series_id: series_id.to_owned(),
+            metadata: value::mark_non_finite(&self.metadata, encoded),
+        })
+    }
 }
 
 /// TODO: docs
 #[derive(Default)]
 pub struct MeasurementElementDetailBuilder {
-    value: tv::Value,
+    value: tv::MeasurementValue,
     timestamp: Option<chrono::DateTime<chrono_tz::Tz>>,
 
     metadata: BTreeMap<String, tv::Value>,
 }
 
 impl MeasurementElementDetailBuilder {
-    fn new(value: tv::Value) -> Self {
+    fn new(value: tv::MeasurementValue) -> Self {
         Self {
             value,
             ..Default::default()
@@ -333,25 +1112,374 @@ impl MeasurementElementDetailBuilder {
 pub struct Validator {
     name: Option<String>,
     validator_type: spec::ValidatorType,
-    value: tv::Value,
+    value: tv::ValidatorValue,
     metadata: BTreeMap<String, tv::Value>,
+
+    // compiled patterns of the regex validators, on the first evaluation; none if the
+    // validator value isn't a pattern or a list of patterns
+    regexes: OnceLock<Option<Result<Vec<regex::Regex>, ValidatorError>>>,
 }
 
 impl Validator {
-    pub fn builder<V: Into<tv::Value>>(
+    pub fn builder<V: Into<tv::ValidatorValue>>(
         validator_type: spec::ValidatorType,
         value: V,
     ) -> ValidatorBuilder {
         ValidatorBuilder::new(validator_type, value.into())
     }
 
+    /// Validator passing for values equal to `value`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ocptv::output::*;
+    /// let validator = Validator::equal(30).named("exact");
+    /// assert!(validator.validate(&30.into())?);
+    ///
+    /// # Ok::<(), ValidatorError>(())
+    /// ```
+    pub fn equal<V: Into<tv::MeasurementValue>>(value: V) -> Validator {
+        Self::scalar(spec::ValidatorType::Equal, value)
+    }
+
+    /// Validator passing for values not equal to `value`.
+    pub fn not_equal<V: Into<tv::MeasurementValue>>(value: V) -> Validator {
+        Self::scalar(spec::ValidatorType::NotEqual, value)
+    }
+
+    /// Validator passing for values strictly less than `value`.
+    pub fn less_than<V: Into<tv::MeasurementValue>>(value: V) -> Validator {
+        Self::scalar(spec::ValidatorType::LessThan, value)
+    }
+
+    /// Validator passing for values less than or equal to `value`.
+    pub fn less_than_or_equal<V: Into<tv::MeasurementValue>>(value: V) -> Validator {
+        Self::scalar(spec::ValidatorType::LessThanOrEqual, value)
+    }
+
+    /// Validator passing for values strictly greater than `value`.
+    pub fn greater_than<V: Into<tv::MeasurementValue>>(value: V) -> Validator {
+        Self::scalar(spec::ValidatorType::GreaterThan, value)
+    }
+
+    /// Validator passing for values greater than or equal to `value`.
+    pub fn greater_than_or_equal<V: Into<tv::MeasurementValue>>(value: V) -> Validator {
+        Self::scalar(spec::ValidatorType::GreaterThanOrEqual, value)
+    }
+
+    /// The pair of validators passing for values in the inclusive range `[low, high]`,
+    /// since the spec has no range validator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ocptv::output::*;
+    /// let [min, max] = Validator::in_range(20, 80);
+    /// let measurement = Measurement::builder("temp", 42)
+    ///     .add_validator(min.named("min"))
+    ///     .add_validator(max.named("max"))
+    ///     .build();
+    /// ```
+    pub fn in_range<V: Into<tv::MeasurementValue>>(low: V, high: V) -> [Validator; 2] {
+        [
+            Self::greater_than_or_equal(low),
+            Self::less_than_or_equal(high),
+        ]
+    }
+
+    /// Validator passing for string values matching the regex `pattern` somewhere.
+    /// Fails if the pattern doesn't compile, see [`ValidatorBuilder::try_build`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ocptv::output::*;
+    /// let validator = Validator::matches("^fan[0-9]+$")?;
+    /// assert!(validator.validate(&"fan3".into())?);
+    ///
+    /// assert!(Validator::matches("fan[").is_err());
+    ///
+    /// # Ok::<(), ValidatorError>(())
+    /// ```
+    pub fn matches(pattern: &str) -> Result<Validator, ValidatorError> {
+        Validator::builder(spec::ValidatorType::RegexMatch, pattern).try_build()
+    }
+
+    /// Validator passing for string values not matching the regex `pattern` anywhere.
+    /// Fails if the pattern doesn't compile, see [`ValidatorBuilder::try_build`].
+    pub fn not_matches(pattern: &str) -> Result<Validator, ValidatorError> {
+        Validator::builder(spec::ValidatorType::RegexNoMatch, pattern).try_build()
+    }
+
+    /// Validator passing for values equal to one of `values`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ocptv::output::*;
+    /// let validator = Validator::in_set(["ok", "degraded"]);
+    /// assert!(!validator.validate(&"failed".into())?);
+    ///
+    /// # Ok::<(), ValidatorError>(())
+    /// ```
+    pub fn in_set<V, I>(values: I) -> Validator
+    where
+        V: Into<tv::MeasurementValue>,
+        I: IntoIterator<Item = V>,
+    {
+        Self::list(spec::ValidatorType::InSet, values)
+    }
+
+    /// Validator passing for values equal to none of `values`.
+    pub fn not_in_set<V, I>(values: I) -> Validator
+    where
+        V: Into<tv::MeasurementValue>,
+        I: IntoIterator<Item = V>,
+    {
+        Self::list(spec::ValidatorType::NotInSet, values)
+    }
+
+    /// Sets the name of the validator.
+    pub fn named(mut self, name: &str) -> Validator {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    // name of the validator in diagnostics, defaulting to its type
+    fn label(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("{:?}", self.validator_type))
+    }
+
+    fn scalar<V: Into<tv::MeasurementValue>>(
+        validator_type: spec::ValidatorType,
+        value: V,
+    ) -> Validator {
+        ValidatorBuilder::new(validator_type, tv::ValidatorValue::Scalar(value.into())).build()
+    }
+
+    fn list<V, I>(validator_type: spec::ValidatorType, values: I) -> Validator
+    where
+        V: Into<tv::MeasurementValue>,
+        I: IntoIterator<Item = V>,
+    {
+        let values = values.into_iter().map(Into::into).collect();
+        ValidatorBuilder::new(validator_type, tv::ValidatorValue::List(values)).build()
+    }
+
+    /// Creates the spec object of the validator. NaN and infinite values are encoded as
+    /// strings, see [`tv::NonFinitePolicy::Encode`].
     pub fn to_spec(&self) -> spec::Validator {
-        spec::Validator {
+        self.to_spec_with(tv::NonFinitePolicy::Encode)
+            .expect("encoding non finite values never fails")
+    }
+
+    pub(crate) fn to_spec_with(
+        &self,
+        policy: tv::NonFinitePolicy,
+    ) -> Result<spec::Validator, tv::MeasurementValueError> {
+        let (value, encoded) = self.value.to_spec(policy)?;
+        Ok(spec::Validator {
             name: self.name.clone(),
             validator_type: self.validator_type.clone(),
-            value: self.value.clone(),
-            metadata: self.metadata.option(),
+            value,
+            metadata: value::mark_non_finite(&self.metadata, encoded),
+        })
+    }
+
+    /// Evaluates the validator against a measured value, returning whether it passed.
+    ///
+    /// Comparisons are only made between values of the same kind: numbers are compared
+    /// numerically, and strings and booleans only for equality. Arrays and objects are
+    /// compared deeply for equality, item by item and key by key. The regex validators
+    /// expect a string value and one pattern or a list of patterns, passing when any of
+    /// the patterns matches somewhere in the value; the patterns are compiled on the first
+    /// evaluation, then reused. The set validators expect a list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ocptv::output::*;
+    /// let validator = Validator::builder(ValidatorType::LessThan, 80).build();
+    /// assert!(validator.validate(&72.5.into())?);
+    ///
+    /// let validator = Validator::builder(ValidatorType::RegexMatch, "^fan[0-9]+$").build();
+    /// assert!(!validator.validate(&"psu0".into())?);
+    ///
+    /// # Ok::<(), ValidatorError>(())
+    /// ```
+    pub fn validate(&self, value: &tv::Value) -> Result<bool, ValidatorError> {
+        use spec::ValidatorType as T;
+
+        // note: non finite numbers become null here, so comparing them with anything is
+        // a type mismatch error
+        let expected = tv::Value::from(self.value.clone());
+        let mismatch = || {
+            ValidatorError::TypeMismatch(
+                self.validator_type.clone(),
+                value.clone(),
+                expected.clone(),
+            )
+        };
+        let ordered = |check: fn(CmpOrdering) -> bool| {
+            compare_values(value, &expected)
+                .map(check)
+                .ok_or_else(mismatch)
+        };
+
+        match self.validator_type {
+            T::Equal => equal_values(value, &expected).ok_or_else(mismatch),
+            T::NotEqual => equal_values(value, &expected)
+                .map(|eq| !eq)
+                .ok_or_else(mismatch),
+            T::LessThan => ordered(CmpOrdering::is_lt),
+            T::LessThanOrEqual => ordered(CmpOrdering::is_le),
+            T::GreaterThan => ordered(CmpOrdering::is_gt),
+            T::GreaterThanOrEqual => ordered(CmpOrdering::is_ge),
+            T::RegexMatch => self.matches_regex(value, &expected),
+            T::RegexNoMatch => self.matches_regex(value, &expected).map(|m| !m),
+            T::InSet => self.contains(value, &expected),
+            T::NotInSet => self.contains(value, &expected).map(|found| !found),
+        }
+    }
+
+    fn matches_regex(
+        &self,
+        value: &tv::Value,
+        expected: &tv::Value,
+    ) -> Result<bool, ValidatorError> {
+        let mismatch = || {
+            ValidatorError::TypeMismatch(
+                self.validator_type.clone(),
+                value.clone(),
+                expected.clone(),
+            )
+        };
+
+        let tv::Value::String(text) = value else {
+            return Err(mismatch());
+        };
+        let regexes = self
+            .regexes
+            .get_or_init(|| Self::compile_regexes(expected))
+            .as_ref()
+            .ok_or_else(mismatch)?
+            .as_ref()
+            .map_err(Clone::clone)?;
+
+        Ok(regexes.iter().any(|re| re.is_match(text)))
+    }
+
+    fn compile_regexes(expected: &tv::Value) -> Option<Result<Vec<regex::Regex>, ValidatorError>> {
+        let patterns = match expected {
+            tv::Value::String(pattern) => vec![pattern.as_str()],
+            tv::Value::Array(items) => items
+                .iter()
+                .map(|item| item.as_str())
+                .collect::<Option<Vec<_>>>()?,
+            _ => return None,
+        };
+
+        let regexes = patterns
+            .into_iter()
+            .map(|pattern| {
+                regex::Regex::new(pattern)
+                    .map_err(|err| ValidatorError::InvalidRegex(pattern.to_owned(), err))
+            })
+            .collect();
+        Some(regexes)
+    }
+
+    fn contains(&self, value: &tv::Value, expected: &tv::Value) -> Result<bool, ValidatorError> {
+        let tv::Value::Array(items) = expected else {
+            return Err(ValidatorError::NotASet(
+                self.validator_type.clone(),
+                expected.clone(),
+            ));
+        };
+
+        // items of another kind than the value can't match, but if none is comparable
+        // the validator was most likely built for another measurement
+        let comparable = items
+            .iter()
+            .filter_map(|item| equal_values(value, item))
+            .collect::<Vec<_>>();
+        if comparable.is_empty() && !items.is_empty() {
+            return Err(ValidatorError::TypeMismatch(
+                self.validator_type.clone(),
+                value.clone(),
+                expected.clone(),
+            ));
+        }
+        Ok(comparable.into_iter().any(|eq| eq))
+    }
+}
+
+fn validators_to_spec(
+    validators: &[Validator],
+    policy: tv::NonFinitePolicy,
+) -> Result<Option<Vec<spec::Validator>>, tv::MeasurementValueError> {
+    let validators = validators
+        .iter()
+        .map(|validator| validator.to_spec_with(policy))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((!validators.is_empty()).then_some(validators))
+}
+
+/// Reason why a [`Validator`] can't be evaluated against a value.
+/// See [`Validator::validate`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ValidatorError {
+    #[error("{0:?} validator can't compare {1} with {2}")]
+    TypeMismatch(spec::ValidatorType, tv::Value, tv::Value),
+
+    #[error("{0:?} validator expects a list of values, got {1}")]
+    NotASet(spec::ValidatorType, tv::Value),
+
+    #[error("invalid regex {0:?}: {1}")]
+    InvalidRegex(String, regex::Error),
+}
+
+/// Orders two numbers, without losing precision when both are integers.
+fn compare_numbers(a: &serde_json::Number, b: &serde_json::Number) -> Option<CmpOrdering> {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return Some(a.cmp(&b));
+    }
+    if let (Some(a), Some(b)) = (a.as_u64(), b.as_u64()) {
+        return Some(a.cmp(&b));
+    }
+    a.as_f64()?.partial_cmp(&b.as_f64()?)
+}
+
+/// Orders two values, which is only defined for numbers.
+fn compare_values(a: &tv::Value, b: &tv::Value) -> Option<CmpOrdering> {
+    match (a, b) {
+        (tv::Value::Number(a), tv::Value::Number(b)) => compare_numbers(a, b),
+        _ => None,
+    }
+}
+
+/// Compares two values of the same kind, or returns `None` for different kinds.
+/// Arrays and objects are compared deeply, where items of different kinds are unequal.
+fn equal_values(a: &tv::Value, b: &tv::Value) -> Option<bool> {
+    let deep_equal = |a, b| equal_values(a, b).unwrap_or(false);
+    match (a, b) {
+        (tv::Value::Number(x), tv::Value::Number(y)) => compare_numbers(x, y).map(|o| o.is_eq()),
+        (tv::Value::String(_), tv::Value::String(_)) | (tv::Value::Bool(_), tv::Value::Bool(_)) => {
+            Some(a == b)
+        }
+        (tv::Value::Array(x), tv::Value::Array(y)) => {
+            Some(x.len() == y.len() && x.iter().zip(y).all(|(x, y)| deep_equal(x, y)))
         }
+        (tv::Value::Object(x), tv::Value::Object(y)) => Some(
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(key, x)| y.get(key).is_some_and(|y| deep_equal(x, y))),
+        ),
+        _ => None,
     }
 }
 
@@ -360,13 +1488,13 @@ impl Validator {
 pub struct ValidatorBuilder {
     name: Option<String>,
     validator_type: spec::ValidatorType,
-    value: tv::Value,
+    value: tv::ValidatorValue,
 
     metadata: BTreeMap<String, tv::Value>,
 }
 
 impl ValidatorBuilder {
-    fn new(validator_type: spec::ValidatorType, value: tv::Value) -> Self {
+    fn new(validator_type: spec::ValidatorType, value: tv::ValidatorValue) -> Self {
         ValidatorBuilder {
             validator_type,
             value,
@@ -385,13 +1513,45 @@ impl ValidatorBuilder {
         self
     }
 
+    /// Builds the validator; the patterns of the regex validators are only compiled
+    /// on the first evaluation, so an invalid pattern goes unnoticed until then.
     pub fn build(self) -> Validator {
         Validator {
             name: self.name,
             validator_type: self.validator_type,
             value: self.value,
             metadata: self.metadata,
+            regexes: OnceLock::new(),
+        }
+    }
+
+    /// Builds the validator, compiling the patterns of the regex validators upfront
+    /// and failing on the first invalid one. The compiled patterns are then reused by
+    /// [`Validator::validate`], while the output still carries the pattern strings.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ocptv::output::*;
+    /// let result = Validator::builder(ValidatorType::RegexMatch, vec!["^fan", "psu["])
+    ///     .name("component")
+    ///     .try_build();
+    /// assert!(matches!(result, Err(ValidatorError::InvalidRegex(..))));
+    /// ```
+    pub fn try_build(self) -> Result<Validator, ValidatorError> {
+        use spec::ValidatorType as T;
+
+        let validator = self.build();
+        if matches!(validator.validator_type, T::RegexMatch | T::RegexNoMatch) {
+            let expected = tv::Value::from(validator.value.clone());
+            // values that aren't patterns are left for the evaluation to report,
+            // since the mismatch error needs the evaluated value
+            if let Some(regexes) = Validator::compile_regexes(&expected) {
+                let regexes = regexes?;
+                let _ = validator.regexes.set(Some(Ok(regexes)));
+            }
         }
+        Ok(validator)
     }
 }
 
@@ -425,7 +1585,7 @@ impl ValidatorBuilder {
 pub struct Measurement {
     name: String,
 
-    value: tv::Value,
+    value: tv::MeasurementValue,
     unit: Option<String>,
     validators: Vec<Validator>,
 
@@ -444,7 +1604,7 @@ impl Measurement {
     /// # use ocptv::output::*;
     /// let measurement = Measurement::new("name", 50);
     /// ```
-    pub fn new<V: Into<tv::Value>>(name: &str, value: V) -> Self {
+    pub fn new<V: Into<tv::MeasurementValue>>(name: &str, value: V) -> Self {
         Measurement {
             name: name.to_string(),
             value: value.into(),
@@ -469,7 +1629,7 @@ impl Measurement {
     ///     .subcomponent(Subcomponent::builder("name").build())
     ///     .build();
     /// ```
-    pub fn builder<V: Into<tv::Value>>(name: &str, value: V) -> MeasurementBuilder {
+    pub fn builder<V: Into<tv::MeasurementValue>>(name: &str, value: V) -> MeasurementBuilder {
         MeasurementBuilder::new(name, value.into())
     }
 
@@ -483,11 +1643,20 @@ impl Measurement {
     /// let _ = measurement.to_artifact();
     /// ```
     pub fn to_artifact(&self) -> spec::Measurement {
-        spec::Measurement {
+        self.to_artifact_with(tv::NonFinitePolicy::Encode)
+            .expect("encoding non finite values never fails")
+    }
+
+    pub(crate) fn to_artifact_with(
+        &self,
+        policy: tv::NonFinitePolicy,
+    ) -> Result<spec::Measurement, tv::MeasurementValueError> {
+        let (value, encoded) = self.value.to_spec(policy)?;
+        Ok(spec::Measurement {
             name: self.name.clone(),
             unit: self.unit.clone(),
-            value: self.value.clone(),
-            validators: self.validators.map_option(Validator::to_spec),
+            value,
+            validators: validators_to_spec(&self.validators, policy)?,
             hardware_info: self
                 .hardware_info
                 .as_ref()
@@ -496,8 +1665,8 @@ impl Measurement {
                 .subcomponent
                 .as_ref()
                 .map(|subcomponent| subcomponent.to_spec()),
-            metadata: self.metadata.option(),
-        }
+            metadata: value::mark_non_finite(&self.metadata, encoded),
+        })
     }
 }
 
@@ -521,7 +1690,7 @@ impl Measurement {
 pub struct MeasurementBuilder {
     name: String,
 
-    value: tv::Value,
+    value: tv::MeasurementValue,
     unit: Option<String>,
     validators: Vec<Validator>,
 
@@ -532,7 +1701,7 @@ pub struct MeasurementBuilder {
 }
 
 impl MeasurementBuilder {
-    fn new(name: &str, value: tv::Value) -> Self {
+    fn new(name: &str, value: tv::MeasurementValue) -> Self {
         MeasurementBuilder {
             name: name.to_string(),
             value,
@@ -607,8 +1776,8 @@ impl MeasurementBuilder {
     /// # use ocptv::output::*;
     /// let builder = Measurement::builder("name", 50000).unit("RPM");
     /// ```
-    pub fn unit(mut self, unit: &str) -> MeasurementBuilder {
-        self.unit = Some(unit.to_string());
+    pub fn unit<U: Into<tv::Unit>>(mut self, unit: U) -> MeasurementBuilder {
+        self.unit = Some(unit.into().into());
         self
     }
 
@@ -648,6 +1817,8 @@ pub struct MeasurementSeriesDetail {
     subcomponent: Option<dut::Subcomponent>,
 
     metadata: BTreeMap<String, tv::Value>,
+    strict_timestamps: bool,
+    enforce_validators: bool,
 }
 
 impl MeasurementSeriesDetail {
@@ -673,6 +1844,8 @@ pub struct MeasurementSeriesDetailBuilder {
     subcomponent: Option<dut::Subcomponent>,
 
     metadata: BTreeMap<String, tv::Value>,
+    strict_timestamps: bool,
+    enforce_validators: bool,
 }
 
 impl MeasurementSeriesDetailBuilder {
@@ -689,8 +1862,8 @@ impl MeasurementSeriesDetailBuilder {
         self
     }
 
-    pub fn unit(mut self, unit: &str) -> Self {
-        self.unit = Some(unit.to_string());
+    pub fn unit<U: Into<tv::Unit>>(mut self, unit: U) -> Self {
+        self.unit = Some(unit.into().into());
         self
     }
 
@@ -714,6 +1887,50 @@ impl MeasurementSeriesDetailBuilder {
         self
     }
 
+    /// Rejects the elements with a timestamp earlier than the previous element, with
+    /// [`tv::OcptvError::OutOfOrderElement`]. Out of order elements are allowed by default.
+    pub fn strict_timestamps(mut self, value: bool) -> Self {
+        self.strict_timestamps = value;
+        self
+    }
+
+    /// Evaluates each element against the series validators, see [`Validator::validate`].
+    /// The elements failing any of them are still emitted, with the names of the failed
+    /// validators (or their types, when unnamed) in the `ocptv_failed_validators` metadata
+    /// key, and counted by [`StartedMeasurementSeries::violations`]. If any element failed,
+    /// ending the series first emits a single `FAIL` diagnosis, with the series hardware
+    /// info and subcomponent, and the verdict `{name}_validation_failed`.
+    ///
+    /// Elements a validator can't be evaluated against, eg. a string element for a numeric
+    /// validator, fail that validator. Validators are not enforced by default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let detail = MeasurementSeriesDetail::builder("fan_speed")
+    ///     .add_validator(Validator::greater_than(1000).named("min_speed"))
+    ///     .enforce_validators(true)
+    ///     .build();
+    /// let series = step.add_measurement_series_detail(detail).start().await?;
+    /// series.add_measurements([1200, 800, 1100]).await?;
+    /// assert_eq!(series.violations(), 1);
+    /// // emits the fan_speed_validation_failed diagnosis
+    /// series.end().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn enforce_validators(mut self, value: bool) -> Self {
+        self.enforce_validators = value;
+        self
+    }
+
     pub fn build(self) -> MeasurementSeriesDetail {
         MeasurementSeriesDetail {
             id: self.id,
@@ -723,6 +1940,8 @@ impl MeasurementSeriesDetailBuilder {
             hardware_info: self.hardware_info,
             subcomponent: self.subcomponent,
             metadata: self.metadata,
+            strict_timestamps: self.strict_timestamps,
+            enforce_validators: self.enforce_validators,
         }
     }
 }
@@ -737,12 +1956,13 @@ mod tests {
     use tv::ValidatorType;
 
     use anyhow::{bail, Result};
+    use serde_json::json;
 
     #[test]
     fn test_measurement_as_test_step_descendant_to_artifact() -> Result<()> {
         let name = "name".to_owned();
         let value = tv::Value::from(50);
-        let measurement = Measurement::new(&name, value.clone());
+        let measurement = Measurement::new(&name, 50);
 
         let artifact = measurement.to_artifact();
         assert_eq!(
@@ -779,7 +1999,7 @@ mod tests {
         let subcomponent = Subcomponent::builder("name").build();
 
         let unit = "RPM";
-        let measurement = Measurement::builder(&name, value.clone())
+        let measurement = Measurement::builder(&name, 50000)
             .unit(unit)
             .add_validator(validator.clone())
             .add_validator(validator.clone())
@@ -829,4 +2049,324 @@ mod tests {
 
         Ok(())
     }
+
+    fn eval<V: Into<tv::Value>, W: Into<tv::Value>>(
+        validator_type: ValidatorType,
+        expected: V,
+        value: W,
+    ) -> Result<bool, ValidatorError> {
+        let expected =
+            tv::ValidatorValue::try_from(expected.into()).expect("valid validator value");
+        Validator::builder(validator_type, expected)
+            .build()
+            .validate(&value.into())
+    }
+
+    #[test]
+    fn test_validator_constructors() -> Result<()> {
+        let cases = [
+            (Validator::equal(30), "EQUAL", json!(30)),
+            (Validator::not_equal("x"), "NOT_EQUAL", json!("x")),
+            (Validator::less_than(80), "LESS_THAN", json!(80)),
+            (
+                Validator::less_than_or_equal(80),
+                "LESS_THAN_OR_EQUAL",
+                json!(80),
+            ),
+            (Validator::greater_than(1.5), "GREATER_THAN", json!(1.5)),
+            (
+                Validator::greater_than_or_equal(0),
+                "GREATER_THAN_OR_EQUAL",
+                json!(0),
+            ),
+            (Validator::matches("^fan")?, "REGEX_MATCH", json!("^fan")),
+            (
+                Validator::not_matches("err")?,
+                "REGEX_NO_MATCH",
+                json!("err"),
+            ),
+            (Validator::in_set([1, 2]), "IN_SET", json!([1, 2])),
+            (Validator::not_in_set(vec!["a"]), "NOT_IN_SET", json!(["a"])),
+        ];
+
+        for (validator, validator_type, value) in cases {
+            let spec = serde_json::to_value(validator.named("name").to_spec())?;
+            assert_eq!(
+                spec,
+                json!({"name": "name", "type": validator_type, "value": value})
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_constructors_validate() -> Result<()> {
+        assert!(Validator::equal(30).validate(&30.0.into())?);
+        assert!(Validator::not_equal(30).validate(&31.into())?);
+        assert!(Validator::less_than(80).validate(&72.5.into())?);
+        assert!(!Validator::greater_than(80).validate(&80.into())?);
+        assert!(Validator::matches("^fan[0-9]+$")?.validate(&"fan3".into())?);
+        assert!(Validator::not_matches("error")?.validate(&"ok".into())?);
+        assert!(Validator::in_set(["ok", "degraded"]).validate(&"ok".into())?);
+        assert!(!Validator::not_in_set([1, 2]).validate(&2.into())?);
+
+        let range = Validator::in_range(20, 80);
+        let within = |value: tv::Value| -> Result<bool, ValidatorError> {
+            range
+                .iter()
+                .try_fold(true, |ok, validator| Ok(ok && validator.validate(&value)?))
+        };
+        assert!(within(20.into())?);
+        assert!(within(80.into())?);
+        assert!(!within(19.9.into())?);
+        assert!(!within(81.into())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_equality() -> Result<()> {
+        assert!(eval(ValidatorType::Equal, 30, 30)?);
+        assert!(eval(ValidatorType::Equal, 30, 30.0)?);
+        assert!(!eval(ValidatorType::Equal, 30, 31)?);
+        assert!(eval(ValidatorType::Equal, "ok", "ok")?);
+        assert!(eval(ValidatorType::Equal, true, true)?);
+        assert!(eval(ValidatorType::NotEqual, 30, 31)?);
+        assert!(!eval(ValidatorType::NotEqual, "ok", "ok")?);
+        // integers beyond f64 precision still compare exactly
+        assert!(!eval(ValidatorType::Equal, u64::MAX, u64::MAX - 1)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_deep_equality() -> Result<()> {
+        let deep_eval = |validator_type, expected, value| {
+            Validator::builder(
+                validator_type,
+                tv::MeasurementValue::raw_unchecked(expected),
+            )
+            .build()
+            .validate(&value)
+        };
+
+        let expected = json!([[1, 2.5], {"fan": "ok", "rpm": [3000, 3100]}]);
+        assert!(deep_eval(
+            ValidatorType::Equal,
+            expected.clone(),
+            json!([[1.0, 2.5], {"rpm": [3000.0, 3100], "fan": "ok"}])
+        )?);
+
+        let unequal = [
+            // nested number differs
+            json!([[1, 2.5], {"fan": "ok", "rpm": [3000, 3101]}]),
+            // nested array length differs
+            json!([[1, 2.5, 4], {"fan": "ok", "rpm": [3000, 3100]}]),
+            // missing and extra keys
+            json!([[1, 2.5], {"fan": "ok"}]),
+            json!([[1, 2.5], {"fan": "ok", "rpm": [3000, 3100], "psu": 1}]),
+            // nested kinds differ
+            json!([[1, "2.5"], {"fan": "ok", "rpm": [3000, 3100]}]),
+        ];
+        for value in unequal {
+            assert!(!deep_eval(
+                ValidatorType::Equal,
+                expected.clone(),
+                value.clone()
+            )?);
+            assert!(deep_eval(ValidatorType::NotEqual, expected.clone(), value)?);
+        }
+
+        // only the top level kinds must match
+        assert!(matches!(
+            deep_eval(ValidatorType::Equal, expected, json!({"fan": "ok"})),
+            Err(ValidatorError::TypeMismatch(..))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_ordering() -> Result<()> {
+        assert!(eval(ValidatorType::LessThan, 80, 72.5)?);
+        assert!(!eval(ValidatorType::LessThan, 80, 80)?);
+        assert!(eval(ValidatorType::LessThanOrEqual, 80, 80)?);
+        assert!(!eval(ValidatorType::LessThanOrEqual, 80, 81)?);
+        assert!(eval(ValidatorType::GreaterThan, -5, 0)?);
+        assert!(!eval(ValidatorType::GreaterThan, 0, 0)?);
+        assert!(eval(ValidatorType::GreaterThanOrEqual, 0, 0)?);
+        assert!(!eval(ValidatorType::GreaterThanOrEqual, 1.5, 1.25)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_regex() -> Result<()> {
+        assert!(eval(ValidatorType::RegexMatch, "^fan[0-9]+$", "fan12")?);
+        assert!(!eval(ValidatorType::RegexMatch, "^fan[0-9]+$", "psu0")?);
+        assert!(eval(
+            ValidatorType::RegexMatch,
+            vec!["^fan", "^psu"],
+            "psu0"
+        )?);
+        assert!(eval(ValidatorType::RegexNoMatch, "error", "all good")?);
+        assert!(!eval(
+            ValidatorType::RegexNoMatch,
+            vec!["warn", "error"],
+            "error: x"
+        )?);
+
+        assert!(matches!(
+            eval(ValidatorType::RegexMatch, "fan[", "fan"),
+            Err(ValidatorError::InvalidRegex(pattern, _)) if pattern == "fan["
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_regex_compiled_once() -> Result<()> {
+        let validator = Validator::builder(ValidatorType::RegexMatch, vec!["^fan", "^psu"]).build();
+        assert!(validator.regexes.get().is_none());
+
+        assert!(validator.validate(&"fan0".into())?);
+        let compiled = validator
+            .regexes
+            .get()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap();
+        assert_eq!(compiled.len(), 2);
+        assert!(validator.validate(&"psu1".into())?);
+        assert!(!validator.validate(&"cpu0".into())?);
+
+        // the compiled patterns are carried over to clones
+        assert!(validator.clone().regexes.get().is_some());
+
+        // as are the compile errors
+        let validator = Validator::builder(ValidatorType::RegexNoMatch, "fan[").build();
+        for _ in 0..2 {
+            assert!(matches!(
+                validator.validate(&"fan".into()),
+                Err(ValidatorError::InvalidRegex(pattern, _)) if pattern == "fan["
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_regex_checked_at_construction() -> Result<()> {
+        let Err(err) = Validator::matches("fan[") else {
+            bail!("expected the pattern to be rejected");
+        };
+        let ValidatorError::InvalidRegex(pattern, regex_err) = &err else {
+            bail!("unexpected error: {err}");
+        };
+        assert_eq!(pattern, "fan[");
+        assert!(err.to_string().starts_with(r#"invalid regex "fan[": "#));
+        assert!(err.to_string().ends_with(&regex_err.to_string()));
+        assert!(err.to_string().contains("unclosed character class"));
+
+        let result =
+            Validator::builder(ValidatorType::RegexNoMatch, vec!["^fan", "(psu"]).try_build();
+        let Err(err) = result else {
+            bail!("expected the pattern to be rejected");
+        };
+        assert!(matches!(err, ValidatorError::InvalidRegex(pattern, _) if pattern == "(psu"));
+
+        // values that aren't patterns are only reported on evaluation
+        let validator = Validator::builder(ValidatorType::RegexMatch, 12).try_build()?;
+        assert!(validator.regexes.get().is_none());
+
+        // the other validators aren't checked
+        let validator = Validator::builder(ValidatorType::Equal, "fan[").try_build()?;
+        assert!(validator.validate(&"fan[".into())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_regex_round_trip() -> Result<()> {
+        let validator = Validator::not_matches(r"error\s+\d+")?.named("no_errors");
+        assert!(validator.regexes.get().is_some());
+
+        assert_eq!(
+            serde_json::to_value(validator.to_spec())?,
+            json!({"name": "no_errors", "type": "REGEX_NO_MATCH", "value": r"error\s+\d+"})
+        );
+        assert!(validator.validate(&"all good".into())?);
+        assert!(!validator.validate(&"error  42".into())?);
+
+        let validator =
+            Validator::builder(ValidatorType::RegexMatch, vec!["^fan", "^psu"]).try_build()?;
+        assert_eq!(
+            serde_json::to_value(validator.to_spec())?["value"],
+            json!(["^fan", "^psu"])
+        );
+        assert!(validator.validate(&"psu0".into())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_set() -> Result<()> {
+        assert!(eval(ValidatorType::InSet, vec![1, 2, 3], 2)?);
+        assert!(!eval(ValidatorType::InSet, vec![1, 2, 3], 4)?);
+        assert!(eval(ValidatorType::InSet, vec!["a", "b"], "b")?);
+        assert!(eval(ValidatorType::NotInSet, vec!["a", "b"], "c")?);
+        assert!(!eval(ValidatorType::NotInSet, vec![1, 2], 1.0)?);
+        assert!(!eval(ValidatorType::InSet, Vec::<i32>::new(), 1)?);
+
+        assert_eq!(
+            eval(ValidatorType::InSet, 1, 1),
+            Err(ValidatorError::NotASet(ValidatorType::InSet, 1.into()))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validator_evaluate_type_mismatch() -> Result<()> {
+        let cases = [
+            (
+                ValidatorType::Equal,
+                tv::Value::from(30),
+                tv::Value::from("30"),
+            ),
+            (ValidatorType::NotEqual, true.into(), 1.into()),
+            (ValidatorType::LessThan, 80.into(), "72".into()),
+            (ValidatorType::GreaterThanOrEqual, "a".into(), "b".into()),
+            (ValidatorType::RegexMatch, "^1".into(), 12.into()),
+            (ValidatorType::RegexNoMatch, vec![1, 2].into(), "x".into()),
+            (ValidatorType::InSet, vec!["a", "b"].into(), 1.into()),
+        ];
+
+        for (validator_type, expected, value) in cases {
+            let result = eval(validator_type.clone(), expected.clone(), value.clone());
+            assert_eq!(
+                result,
+                Err(ValidatorError::TypeMismatch(
+                    validator_type,
+                    value,
+                    expected
+                ))
+            );
+        }
+
+        let err = eval(ValidatorType::LessThan, 80, "72").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"LessThan validator can't compare "72" with 80"#
+        );
+
+        // non finite validator values can't be compared either
+        let result = Validator::equal(f64::NAN).validate(&1.into());
+        assert!(matches!(result, Err(ValidatorError::TypeMismatch(..))));
+
+        Ok(())
+    }
 }
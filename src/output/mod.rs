@@ -5,42 +5,81 @@
 // https://opensource.org/licenses/MIT.
 #![deny(warnings)]
 
+#[cfg(feature = "zstd")]
+mod compress;
 mod config;
 mod diagnosis;
 mod dut;
 mod emitter;
+mod environment;
 mod error;
 mod file;
+mod format;
+mod global;
+#[cfg(all(feature = "journald", target_os = "linux"))]
+mod journald;
 mod log;
 mod macros;
 mod measure;
+mod redact;
 mod run;
+#[cfg(feature = "schema-validation")]
+mod schema;
 mod step;
+#[cfg(feature = "sync")]
+pub mod sync;
 mod trait_ext;
+mod unit;
+mod value;
 mod writer;
 
 pub use crate::spec::{
     DiagnosisType, LogSeverity, SoftwareType, SubcomponentType, TestResult, TestStatus,
     ValidatorType, SPEC_VERSION,
 };
-pub use config::{Config, ConfigBuilder, TimestampProvider};
+#[cfg(feature = "zstd")]
+pub use compress::Compression;
+pub use config::{
+    Config, ConfigBuilder, ConfigError, ConfigTemplate, EmitHook, FileUriResolver, NonFinitePolicy,
+    OversizePolicy, TimestampProvider, Transform,
+};
 pub use diagnosis::{Diagnosis, DiagnosisBuilder};
 pub use dut::{
     DutHardwareInfo, DutInfo, DutInfoBuilder, DutSoftwareInfo, HardwareInfo, HardwareInfoBuilder,
     Ident, PlatformInfo, PlatformInfoBuilder, SoftwareInfo, SoftwareInfoBuilder, Subcomponent,
     SubcomponentBuilder,
 };
+pub use emitter::{Emitter, EmitterStats};
 pub use error::{Error, ErrorBuilder};
 pub use file::{File, FileBuilder};
+pub use format::OutputFormat;
+pub use global::{global, global_error, global_log, init_run, shutdown};
+#[cfg(all(feature = "journald", target_os = "linux"))]
+pub use journald::JournaldWriter;
 pub use log::{Log, LogBuilder};
 pub use measure::{
     Measurement, MeasurementBuilder, MeasurementElementDetail, MeasurementElementDetailBuilder,
     MeasurementSeries, MeasurementSeriesDetail, MeasurementSeriesDetailBuilder,
-    StartedMeasurementSeries, Validator, ValidatorBuilder,
+    MeasurementSeriesGuard, SeriesSampler, StartedMeasurementSeries, Validator, ValidatorBuilder,
+    ValidatorError,
+};
+pub use run::{
+    BuildError, BuildIssue, ResumeError, RunSummary, ScopedTestRun, StartedTestRun, TestRun,
+    TestRunBuilder, TestRunGuard, TestRunOutcome,
+};
+#[cfg(feature = "schema-validation")]
+pub use schema::ValidatingWriter;
+pub(crate) use step::STEP_GROUP_EXTENSION;
+pub use step::{ScopedTestStep, StartedTestStep, StepHandle, TestStep, TestStepGuard};
+pub use unit::Unit;
+pub use value::{MeasurementValue, MeasurementValueError, ValidatorValue};
+#[cfg(unix)]
+pub use writer::FifoWriter;
+pub use writer::{
+    ArtifactContext, AsyncWriter, BoundedBuffer, BufferWriter, BytesWriter, FileWriter,
+    FlushPolicy, OverflowPolicy, Separator, SplitWriter, StdoutWriter, SyncPolicy,
+    TypedBufferWriter, Writer, WriterError,
 };
-pub use run::{ScopedTestRun, StartedTestRun, TestRun, TestRunBuilder, TestRunOutcome};
-pub use step::{ScopedTestStep, StartedTestStep, TestStep};
-pub use writer::{BufferWriter, FileWriter, StdoutWriter, Writer};
 
 // re-export these as a public types we present
 pub use serde_json::Value;
@@ -53,6 +92,69 @@ pub enum OcptvError {
     #[error("failed to write to output stream")]
     IoError(#[from] std::io::Error),
 
+    #[error("failed to write artifact: {0}")]
+    Writer(#[from] WriterError),
+
+    #[error("invalid configuration: {0}")]
+    Config(#[from] ConfigError),
+
+    #[error("{0}")]
+    Build(#[from] BuildError),
+
+    #[error("cannot resume the test run: {0}")]
+    Resume(#[from] ResumeError),
+
+    #[error("parameter values must be scalars, invalid keys: {}", .0.join(", "))]
+    InvalidParameters(Vec<String>),
+
+    #[error("duplicate step id: {0}")]
+    DuplicateStepId(String),
+
+    #[error("cannot read the file artifact {}", .0.display())]
+    FileArtifact(std::path::PathBuf, #[source] std::io::Error),
+
+    #[error("cannot evaluate the validator: {0}")]
+    Validation(#[from] ValidatorError),
+
+    #[error("invalid measurement value: {0}")]
+    InvalidValue(#[from] MeasurementValueError),
+
+    #[error("unsupported file uri, expected a file or http(s) scheme: {0}")]
+    UnsupportedFileUri(String),
+
+    #[error("no planned step at index {0}")]
+    UnknownPlannedStep(usize),
+
+    #[error("batch interrupted after writing {0} artifacts: {1}")]
+    BatchInterrupted(usize, #[source] Box<OcptvError>),
+
+    #[error("software info {0} is registered on another DUT: {1}")]
+    ForeignSoftwareInfo(String, String),
+
+    #[error("failed after {0} attempts: {1}")]
+    RetriesExhausted(
+        u32,
+        #[source] Box<dyn std::error::Error + Send + Sync + 'static>,
+    ),
+
+    #[error("the test run already ended")]
+    RunAlreadyEnded,
+
+    #[error("the global test run is already initialized")]
+    GlobalRunAlreadyInitialized,
+
+    #[error("the global test run is not initialized")]
+    GlobalRunNotInitialized,
+
+    #[error("the test step {0} already ended")]
+    StepAlreadyEnded(String),
+
+    #[error("measurement series element out of timestamp order in series {0}")]
+    OutOfOrderElement(String),
+
+    #[error("the measurement series {0} already ended")]
+    SeriesAlreadyEnded(String),
+
     #[error("failed to format input object")]
     Format(Box<dyn std::error::Error + Send + Sync + 'static>), // opaque type so we don't leak impl
 
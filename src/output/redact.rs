@@ -0,0 +1,188 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use serde_json::{Map, Value};
+
+use crate::output::config::EmitHook;
+use crate::output::run;
+
+pub(crate) const REDACTED: &str = "<redacted>";
+
+// note: the spec objects carrying user defined key-value pairs
+const MAP_FIELDS: [&str; 2] = ["parameters", "metadata"];
+
+/// Returns an emit hook replacing the values of `keys` (case insensitive) in all the
+/// `parameters` and `metadata` maps, at any depth, and in the `commandLine` arguments.
+pub(crate) fn hook(keys: Vec<String>) -> EmitHook {
+    let keys = keys.iter().map(|k| k.to_lowercase()).collect::<Vec<_>>();
+
+    Box::new(move |artifact| {
+        walk(artifact, &keys);
+
+        if let Some(Value::String(cmd)) =
+            artifact.pointer_mut("/testRunArtifact/testRunStart/commandLine")
+        {
+            *cmd = scrub_command_line(cmd, &keys);
+        }
+    })
+}
+
+fn walk(value: &mut Value, keys: &[String]) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if MAP_FIELDS.contains(&k.as_str()) {
+                    if let Value::Object(inner) = v {
+                        redact_map(inner, keys);
+                    }
+                } else {
+                    walk(v, keys);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|v| walk(v, keys)),
+        _ => {}
+    }
+}
+
+fn redact_map(map: &mut Map<String, Value>, keys: &[String]) {
+    for (k, v) in map.iter_mut() {
+        if keys.contains(&k.to_lowercase()) {
+            *v = Value::String(REDACTED.to_owned());
+        } else if let Value::Object(inner) = v {
+            redact_map(inner, keys);
+        } else if let Value::Array(items) = v {
+            for item in items.iter_mut() {
+                if let Value::Object(inner) = item {
+                    redact_map(inner, keys);
+                }
+            }
+        }
+    }
+}
+
+/// Redacts the values of the `--key value`, `--key=value` and `key=value` arguments.
+/// The arguments are split and quoted back with the same rules as the default command
+/// line, which is returned unchanged if there is nothing to redact.
+fn scrub_command_line(cmd: &str, keys: &[String]) -> String {
+    let is_key = |s: &str| keys.contains(&s.trim_start_matches('-').to_lowercase());
+
+    let mut args = vec![];
+    let mut redacted = false;
+    let mut redact_next = false;
+    for arg in run::split_args(cmd) {
+        if redact_next {
+            args.push(REDACTED.to_owned());
+            redact_next = false;
+            redacted = true;
+        } else if let Some((key, _)) = arg.split_once('=').filter(|(k, _)| is_key(k)) {
+            args.push(format!("{}={}", key, REDACTED));
+            redacted = true;
+        } else {
+            redact_next = arg.starts_with('-') && is_key(&arg);
+            args.push(arg);
+        }
+    }
+
+    if !redacted {
+        return cmd.to_owned();
+    }
+    args.iter()
+        .map(|arg| run::quote_arg(arg))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_scrub_command_line() -> Result<()> {
+        let keys = vec!["password".to_owned()];
+
+        assert_eq!(
+            scrub_command_line("diag --password hunter2 -v", &keys),
+            "diag --password <redacted> -v"
+        );
+        assert_eq!(
+            scrub_command_line("diag --Password=hunter2 password=x", &keys),
+            "diag --Password=<redacted> password=<redacted>"
+        );
+        assert_eq!(scrub_command_line("diag password", &keys), "diag password");
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrub_command_line_only_touches_key_values() -> Result<()> {
+        let keys = vec!["password".to_owned()];
+
+        // short values of the redacted keys don't leak into other arguments
+        assert_eq!(
+            scrub_command_line("diag --password 1 --iterations 10 -a 1", &keys),
+            "diag --password <redacted> --iterations 10 -a 1"
+        );
+        // nor does anything else, including the original quoting
+        assert_eq!(
+            scrub_command_line(r#"diag --name "a  b" -p hunter2"#, &keys),
+            r#"diag --name "a  b" -p hunter2"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_scrub_quoted_command_line() -> Result<()> {
+        let keys = vec!["password".to_owned()];
+
+        assert_eq!(
+            scrub_command_line("diag --password 'a b' --label 'x y'", &keys),
+            "diag --password <redacted> --label 'x y'"
+        );
+        assert_eq!(
+            scrub_command_line(r"diag '--password=it'\''s secret' ''", &keys),
+            "diag --password=<redacted> ''"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_redaction_only_touches_user_maps() -> Result<()> {
+        let hook = hook(vec!["password".to_owned()]);
+        let mut artifact = json!({
+            "testStepArtifact": {
+                "password": "not user data",
+                "measurement": {
+                    "name": "password",
+                    "metadata": {
+                        "password": "hunter2",
+                        "nested": [{"PASSWORD": 1234}]
+                    }
+                }
+            }
+        });
+        hook(&mut artifact);
+
+        assert_eq!(
+            artifact,
+            json!({
+                "testStepArtifact": {
+                    "password": "not user data",
+                    "measurement": {
+                        "name": "password",
+                        "metadata": {
+                            "password": REDACTED,
+                            "nested": [{"PASSWORD": REDACTED}]
+                        }
+                    }
+                }
+            })
+        );
+        Ok(())
+    }
+}
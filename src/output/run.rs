@@ -4,20 +4,25 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::env;
+use std::ffi::OsString;
 use std::future::Future;
+use std::panic;
+use std::path::Path;
 use std::sync::{
     atomic::{self, Ordering},
-    Arc,
+    Arc, Mutex, Weak,
 };
 
 use delegate::delegate;
+use futures::FutureExt;
+use tokio::sync::broadcast;
 
 use crate::output as tv;
 use crate::spec;
-use tv::step::TestStep;
-use tv::{config, dut, emitter, error, log};
+use tv::step::{Outcome, StepEmitter, TestStep};
+use tv::{config, dut, emitter, environment, error, log};
 
 use super::trait_ext::MapExt;
 
@@ -30,6 +35,82 @@ pub struct TestRunOutcome {
     pub result: spec::TestResult,
 }
 
+/// Summary of the artifacts written by a test run, returned when the run ends.
+/// See [`StartedTestRun::end`].
+///
+/// Only artifacts that were successfully written are counted, including the ones
+/// of all the steps in the run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSummary {
+    /// Number of started steps.
+    pub steps: u64,
+    /// Number of errors, for both the run and its steps.
+    pub errors: u64,
+    /// Number of logs, for both the run and its steps.
+    pub logs: u64,
+    /// Number of measurements, including the measurement series elements.
+    pub measurements: u64,
+    /// Number of started measurement series.
+    pub series: u64,
+    /// Time between the run start and end.
+    pub duration: std::time::Duration,
+    /// Sequence number of the `testRunEnd` artifact.
+    pub last_seqno: u64,
+}
+
+/// A problem with a field of the test run description.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum BuildIssue {
+    #[error("{0} is empty")]
+    Empty(&'static str),
+
+    #[error("{0} contains control characters")]
+    ControlCharacters(&'static str),
+}
+
+/// Reason why an existing output can't be resumed. See [`TestRun::resume`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ResumeError {
+    #[error("the output has no testRunStart artifact")]
+    NotStarted,
+
+    #[error("the output already has a testRunEnd artifact")]
+    AlreadyEnded,
+
+    #[error("invalid artifact at line {0}")]
+    InvalidArtifact(usize),
+}
+
+/// Error for a test run description that downstream validators would reject, listing
+/// all the problems found. See [`TestRunBuilder::try_build`].
+#[derive(Debug, thiserror::Error)]
+#[error("invalid test run: {}", .issues.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", "))]
+pub struct BuildError {
+    pub issues: Vec<BuildIssue>,
+}
+
+fn check_fields(fields: &[(&'static str, &str)]) -> Result<(), BuildError> {
+    let issues = fields
+        .iter()
+        .filter_map(|(field, value)| {
+            if value.is_empty() {
+                Some(BuildIssue::Empty(field))
+            } else if value.chars().any(char::is_control) {
+                Some(BuildIssue::ControlCharacters(field))
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    match issues.is_empty() {
+        true => Ok(()),
+        false => Err(BuildError { issues }),
+    }
+}
+
 /// The main diag test run.
 ///
 /// This object describes a single run instance of the diag, and therefore drives the test session.
@@ -39,6 +120,12 @@ pub struct TestRun {
     parameters: BTreeMap<String, tv::Value>,
     command_line: String,
     metadata: BTreeMap<String, tv::Value>,
+    probe_output: bool,
+    allow_complex_parameters: bool,
+    planned_steps: Vec<String>,
+    record_duration: bool,
+    environment_metadata: bool,
+    end_open_steps: bool,
 
     emitter: Arc<emitter::JsonEmitter>,
 }
@@ -46,6 +133,8 @@ pub struct TestRun {
 impl TestRun {
     /// Creates a new [`TestRun`] object.
     ///
+    /// The DUT is given when starting the run, see [`TestRun::start`].
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -68,8 +157,37 @@ impl TestRun {
         TestRunBuilder::new(name, version)
     }
 
+    /// Returns the name of the diagnostic.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the version of the diagnostic.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Returns the user defined parameters of the run.
+    pub fn parameters(&self) -> &BTreeMap<String, tv::Value> {
+        &self.parameters
+    }
+
+    /// Returns the user defined metadata of the run.
+    pub fn metadata(&self) -> &BTreeMap<String, tv::Value> {
+        &self.metadata
+    }
+
     /// Starts the test run.
     ///
+    /// The `testRunStart` artifact carries the full `dut` description, including its
+    /// hardware and software infos. The run keeps the `dut`, so pass it by value to avoid
+    /// cloning a large description; a `&DutInfo` is cloned.
+    ///
+    /// Fails with [`tv::OcptvError::Build`] if the run name or version, or the `dut` id
+    /// are empty or contain control characters, and with
+    /// [`tv::OcptvError::InvalidParameters`] if any parameter value is an object or an
+    /// array, unless allowed by [`TestRunBuilder::allow_complex_parameters`].
+    ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#testrunstart>
     ///
     /// # Examples
@@ -78,13 +196,47 @@ impl TestRun {
     /// # tokio_test::block_on(async {
     /// # use ocptv::output::*;
     /// let run = TestRun::new("diagnostic_name", "1.0");
-    /// let dut = DutInfo::builder("my_dut").build();
+    /// let mut dut = DutInfo::builder("my_dut").build();
+    /// dut.add_hardware_info(HardwareInfo::builder("fan").location("board0/fan").build());
     /// run.start(dut).await?;
     ///
     /// # Ok::<(), OcptvError>(())
     /// # });
     /// ```
-    pub async fn start(self, dut: dut::DutInfo) -> Result<StartedTestRun, tv::OcptvError> {
+    pub async fn start<D: Into<dut::DutInfo>>(
+        mut self,
+        dut: D,
+    ) -> Result<StartedTestRun, tv::OcptvError> {
+        let dut = dut.into();
+        let dut_info = dut.to_spec();
+        check_fields(&[
+            ("name", &self.name),
+            ("version", &self.version),
+            ("dut id", &dut_info.id),
+        ])?;
+
+        if !self.allow_complex_parameters {
+            let keys = self
+                .parameters
+                .iter()
+                .filter(|(_, v)| v.is_object() || v.is_array())
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<_>>();
+            if !keys.is_empty() {
+                return Err(tv::OcptvError::InvalidParameters(keys));
+            }
+        }
+
+        if self.environment_metadata {
+            for (key, value) in environment::gather() {
+                self.metadata.entry(key).or_insert(value);
+            }
+        }
+
+        self.emitter.open(&self.name, &dut_info.id).await?;
+        let baseline = self.emitter.stats();
+        let started_at = self.emitter.timestamp_provider().now();
+
         let start = spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
             artifact: spec::TestRunArtifactImpl::TestRunStart(spec::TestRunStart {
                 name: self.name.clone(),
@@ -92,13 +244,144 @@ impl TestRun {
                 command_line: self.command_line.clone(),
                 parameters: self.parameters.clone(),
                 metadata: self.metadata.option(),
-                dut_info: dut.to_spec(),
+                dut_info,
             }),
         });
 
-        self.emitter.emit(&start).await?;
+        if self.probe_output {
+            self.emitter.probe().await?;
+        }
+        self.emitter.emit(start).await?;
+
+        Ok(StartedTestRun::new(self, dut, started_at, baseline))
+    }
+
+    /// Resumes an interrupted run from its json lines output file at `path`, eg. after
+    /// the DUT was power cycled mid-diagnostic. The returned run appends to the file,
+    /// continuing the sequence numbers, without emitting the `schemaVersion` and
+    /// `testRunStart` artifacts again. The run name, version, parameters, metadata and
+    /// DUT are restored from the existing `testRunStart`, and new steps don't reuse the
+    /// ids already in the file.
+    ///
+    /// The output of `config` is replaced by the file. A last line cut short by the
+    /// interruption is ignored. The statistics and the summary returned at the end only
+    /// count the artifacts written after resuming.
+    ///
+    /// Fails with [`tv::OcptvError::Resume`] if the file has no `testRunStart`, already
+    /// has a `testRunEnd`, or has invalid artifacts.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let run = TestRun::resume(Config::builder(), "output.jsonl").await?;
+    /// let step = run.add_step("after reboot").start().await?;
+    /// step.end(TestStatus::Complete).await?;
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn resume<P: AsRef<Path>>(
+        config: config::ConfigBuilder,
+        path: P,
+    ) -> Result<StartedTestRun, tv::OcptvError> {
+        let content = tokio::fs::read_to_string(&path).await?;
+        let lines = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+
+        let mut artifacts = Vec::with_capacity(lines.len());
+        for (i, (index, line)) in lines.iter().enumerate() {
+            match serde_json::from_str::<tv::Value>(line) {
+                Ok(artifact) => artifacts.push(artifact),
+                // note: the write of the last artifact may have been interrupted
+                Err(_) if i == lines.len() - 1 => break,
+                Err(_) => return Err(ResumeError::InvalidArtifact(index + 1).into()),
+            }
+        }
+
+        let run_artifact = |artifact: &tv::Value, kind: &str| {
+            artifact["testRunArtifact"]
+                .get(kind)
+                .filter(|value| value.is_object())
+                .cloned()
+        };
+        if artifacts
+            .iter()
+            .any(|a| run_artifact(a, "testRunEnd").is_some())
+        {
+            return Err(ResumeError::AlreadyEnded.into());
+        }
+        let (start, start_artifact) = artifacts
+            .iter()
+            .find_map(|a| run_artifact(a, "testRunStart").map(|start| (start, a)))
+            .ok_or(ResumeError::NotStarted)?;
+        let last_seqno = artifacts
+            .iter()
+            .filter_map(|a| a["sequenceNumber"].as_u64())
+            .max()
+            .ok_or(ResumeError::NotStarted)?;
+
+        let text = |key: &str| start[key].as_str().unwrap_or_default().to_owned();
+        let object = |key: &str| {
+            start[key]
+                .as_object()
+                .map(|map| map.clone().into_iter().collect::<BTreeMap<_, _>>())
+                .unwrap_or_default()
+        };
+        let planned_steps = start["metadata"][PLANNED_STEPS_KEY]
+            .as_array()
+            .map(|plan| {
+                plan.iter()
+                    .map(|step| step["name"].as_str().unwrap_or_default().to_owned())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+
+        let config = config
+            .with_sequence_start(last_seqno + 1)
+            .emit_schema_version(false)
+            .with_file_output_append(&path)
+            .await?
+            .build();
+        let mut builder = TestRun::builder(&text("name"), &text("version"))
+            .config(config)
+            .command_line(&text("commandLine"))
+            .add_parameters(object("parameters"))
+            .add_metadata_map(object("metadata"))
+            .allow_complex_parameters();
+        builder.planned_steps = planned_steps;
+        let run = builder.build();
+
+        let now = run.emitter.timestamp_provider().now();
+        let started_at = start_artifact["timestamp"]
+            .as_str()
+            .and_then(|ts| chrono::DateTime::parse_from_rfc3339(ts).ok())
+            .map_or(now, |ts| ts.with_timezone(&now.timezone()));
+        let dut = dut::DutInfo::from_value(&start["dutInfo"]);
+        let baseline = run.emitter.stats();
 
-        Ok(StartedTestRun::new(self))
+        let step_ids = artifacts
+            .iter()
+            .filter_map(|a| a["testStepArtifact"]["testStepId"].as_str())
+            .map(str::to_owned)
+            .collect::<BTreeSet<_>>();
+        let step_seqno = step_ids
+            .iter()
+            .filter_map(|id| id.strip_prefix("step")?.parse::<u64>().ok())
+            .max()
+            .map_or(0, |n| n + 1);
+
+        let run = StartedTestRun::new(run, dut, started_at, baseline);
+        run.step_seqno.store(step_seqno, Ordering::Release);
+        *run.step_ids
+            .lock()
+            .expect("step ids lock is never poisoned") = step_ids;
+        Ok(run)
     }
 
     /// Builds a scope in the [`TestRun`] object, taking care of starting and
@@ -108,6 +391,11 @@ impl TestRun {
     /// all the messages are emitted between the start and end messages, the order
     /// is respected and no messages is lost.
     ///
+    /// If the closure returns an error, the run is still ended with the `Error` status
+    /// and `NotApplicable` result, then the closure error is returned. If the closure
+    /// panics, an error artifact with the `panic` symptom is emitted before ending the
+    /// run the same way, then the panic is resumed.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -129,18 +417,65 @@ impl TestRun {
     /// # Ok::<(), OcptvError>(())
     /// # });
     /// ```
-    pub async fn scope<F, R>(self, dut: dut::DutInfo, func: F) -> Result<(), tv::OcptvError>
+    pub async fn scope<D, F, R>(self, dut: D, func: F) -> Result<(), tv::OcptvError>
     where
+        D: Into<dut::DutInfo>,
         R: Future<Output = Result<TestRunOutcome, tv::OcptvError>> + Send + 'static,
         F: FnOnce(ScopedTestRun) -> R,
     {
         let run = Arc::new(self.start(dut).await?);
-        let outcome = func(ScopedTestRun {
+        let outcome = panic::AssertUnwindSafe(func(ScopedTestRun {
             run: Arc::clone(&run),
-        })
-        .await?;
-        run.end_impl(outcome.status, outcome.result).await?;
+        }))
+        .catch_unwind()
+        .await;
+
+        match outcome {
+            Ok(Ok(outcome)) => {
+                run.end_impl(outcome.status, outcome.result).await?;
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                // note: the closure error is more relevant than a failure to emit the end
+                let _ = run
+                    .end_impl(spec::TestStatus::Error, spec::TestResult::NotApplicable)
+                    .await;
+                Err(e)
+            }
+            Err(payload) => {
+                let _ = run
+                    .add_error_detail(error::Error::from_panic(&*payload))
+                    .await;
+                let _ = run
+                    .end_impl(spec::TestStatus::Error, spec::TestResult::NotApplicable)
+                    .await;
+                panic::resume_unwind(payload)
+            }
+        }
+    }
 
+    /// Emits the schemaVersion artifact.
+    ///
+    /// This is only needed when the automatic emission is disabled by
+    /// [`tv::ConfigBuilder::emit_schema_version`], to control where the header is placed.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#schemaversion>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("diagnostic_name", "1.0")
+    ///     .config(Config::builder().emit_schema_version(false).build())
+    ///     .build();
+    /// run.emit_schema_version().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn emit_schema_version(&self) -> Result<(), tv::OcptvError> {
+        self.emitter.emit_version().await?;
         Ok(())
     }
 
@@ -181,7 +516,7 @@ impl TestRun {
             artifact: spec::TestRunArtifactImpl::Error(error.to_artifact()),
         };
         self.emitter
-            .emit(&spec::RootImpl::TestRunArtifact(artifact))
+            .emit(spec::RootImpl::TestRunArtifact(artifact))
             .await?;
 
         Ok(())
@@ -197,7 +532,14 @@ pub struct TestRunBuilder {
     command_line: String,
 
     config: Option<config::Config>,
+    emitter: Option<emitter::Emitter>,
     metadata: BTreeMap<String, tv::Value>,
+    probe_output: bool,
+    allow_complex_parameters: bool,
+    planned_steps: Vec<String>,
+    record_duration: bool,
+    environment_metadata: bool,
+    end_open_steps: bool,
 }
 
 impl TestRunBuilder {
@@ -206,7 +548,7 @@ impl TestRunBuilder {
             name: name.to_string(),
             version: version.to_string(),
             parameters: BTreeMap::new(),
-            command_line: env::args().collect::<Vec<_>>()[1..].join(" "),
+            command_line: quote_args(env::args_os().skip(1)),
             ..Default::default()
         }
     }
@@ -226,6 +568,83 @@ impl TestRunBuilder {
         self
     }
 
+    /// Adds all the user defined parameters from `params` to the future [`TestRun`] object,
+    /// eg. from a map loaded from a config file. Duplicate keys overwrite the previous
+    /// value, the last one wins.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::collections::HashMap;
+    /// # use ocptv::output::*;
+    /// let params = HashMap::from([("param1", 1), ("param2", 2)]);
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .add_parameters(params)
+    ///     .build();
+    /// ```
+    pub fn add_parameters<I, K, V>(mut self, params: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<tv::Value>,
+    {
+        self.parameters
+            .extend(params.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Adds the top level fields of a serializable struct as user defined parameters
+    /// to the future [`TestRun`] object, eg. the parsed command line arguments.
+    ///
+    /// The struct must serialize to a json object with scalar values, since nested
+    /// objects and arrays are not valid parameter values; otherwise this returns
+    /// [`tv::OcptvError::Format`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// #[derive(serde::Serialize)]
+    /// struct Args {
+    ///     iterations: u32,
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let args = Args { iterations: 10, verbose: true };
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .parameters_from(&args)?
+    ///     .build();
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// ```
+    pub fn parameters_from<T: serde::Serialize>(
+        mut self,
+        params: &T,
+    ) -> Result<Self, tv::OcptvError> {
+        let invalid = |msg: String| {
+            tv::OcptvError::Format(Box::new(<serde_json::Error as serde::ser::Error>::custom(
+                msg,
+            )))
+        };
+
+        let value =
+            serde_json::to_value(params).map_err(|e| tv::OcptvError::Format(Box::new(e)))?;
+        let tv::Value::Object(fields) = value else {
+            return Err(invalid(format!(
+                "parameters must serialize to an object, got: {}",
+                value
+            )));
+        };
+
+        for (key, value) in fields {
+            if value.is_object() || value.is_array() {
+                return Err(invalid(format!("parameter {} is not a scalar value", key)));
+            }
+            self.parameters.insert(key, value);
+        }
+        Ok(self)
+    }
+
     /// Adds the command line used to run the test session to the future
     /// [`TestRun`] object.
     ///
@@ -242,6 +661,25 @@ impl TestRunBuilder {
         self
     }
 
+    /// Captures the full command line used to run the test session, including the
+    /// program path, for the future [`TestRun`] object. By default, only the arguments
+    /// are captured.
+    ///
+    /// Arguments with whitespace or quotes are single quoted, as a shell would expect them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .command_line_full()
+    ///     .build();
+    /// ```
+    pub fn command_line_full(mut self) -> Self {
+        self.command_line = quote_args(env::args_os());
+        self
+    }
+
     /// Adds the configuration for the test session to the future [`TestRun`] object
     ///
     /// # Examples
@@ -257,6 +695,24 @@ impl TestRunBuilder {
         self
     }
 
+    /// Makes the future [`TestRun`] write to a shared output, instead of creating a new
+    /// one from its [`config::Config`]. When set, the config is ignored.
+    /// See [`emitter::Emitter`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let emitter = Emitter::new(Config::builder().build());
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .emitter(&emitter)
+    ///     .build();
+    /// ```
+    pub fn emitter(mut self, emitter: &emitter::Emitter) -> Self {
+        self.emitter = Some(emitter.clone());
+        self
+    }
+
     /// Adds user defined metadata to the future [`TestRun`] object
     ///
     /// # Examples
@@ -273,9 +729,182 @@ impl TestRunBuilder {
         self
     }
 
-    pub fn build(self) -> TestRun {
-        let config = self.config.unwrap_or(config::Config::builder().build());
-        let emitter = emitter::JsonEmitter::new(config.timestamp_provider, config.writer);
+    /// Adds all the user defined metadata from `metadata` to the future [`TestRun`] object.
+    /// Duplicate keys overwrite the previous value, the last one wins.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::collections::HashMap;
+    /// # use ocptv::output::*;
+    /// let metadata = HashMap::from([("meta1", "value1"), ("meta2", "value2")]);
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .add_metadata_map(metadata)
+    ///     .build();
+    /// ```
+    pub fn add_metadata_map<I, K, V>(mut self, metadata: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<tv::Value>,
+    {
+        self.metadata
+            .extend(metadata.into_iter().map(|(k, v)| (k.into(), v.into())));
+        self
+    }
+
+    /// Makes [`TestRun::start`] check that the output is writable before emitting
+    /// anything, so that an unusable output fails the start cleanly instead of
+    /// midway through the first artifacts. See [`emitter::Emitter::probe`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .probe_output(true)
+    ///     .build();
+    /// ```
+    pub fn probe_output(mut self, value: bool) -> Self {
+        self.probe_output = value;
+        self
+    }
+
+    /// Allows parameter values that are objects or arrays. The spec expects scalar
+    /// parameter values, so by default [`TestRun::start`] rejects these.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .add_parameter("limits", serde_json::json!({"min": 0, "max": 100}))
+    ///     .allow_complex_parameters()
+    ///     .build();
+    /// ```
+    pub fn allow_complex_parameters(mut self) -> Self {
+        self.allow_complex_parameters = true;
+        self
+    }
+
+    /// Makes the future [`TestRun`] report its duration when it ends, as a run log
+    /// artifact emitted just before `testRunEnd`, since the end artifact has no room
+    /// for it. The log message is a json object like `{"durationMs":1500}`, measured
+    /// with the configured [`config::TimestampProvider`] from the run start.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .record_duration(true)
+    ///     .build();
+    /// ```
+    pub fn record_duration(mut self, value: bool) -> Self {
+        self.record_duration = value;
+        self
+    }
+
+    /// Makes the future [`TestRun`] add metadata about the environment it runs in to the
+    /// `testRunStart` artifact, gathered when the run starts. The keys are:
+    /// - `ocptv_hostname`: the host name;
+    /// - `ocptv_os_release`: the kernel release, eg. `6.1.0-13-amd64`;
+    /// - `ocptv_arch`: the cpu architecture, eg. `x86_64`;
+    /// - `ocptv_pid`: the process id;
+    /// - `ocptv_user`: the name of the user running the diagnostic.
+    ///
+    /// All the values are strings. The ones that can't be determined are left out, and
+    /// user defined metadata with the same keys is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .with_environment_metadata()
+    ///     .build();
+    /// ```
+    pub fn with_environment_metadata(mut self) -> Self {
+        self.environment_metadata = true;
+        self
+    }
+
+    /// Makes the future [`TestRun`] end the steps that are still open when it ends with
+    /// the `Error` status. By default, a `Warning` log listing the open step ids is
+    /// emitted instead, before the `testRunEnd` artifact.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .end_open_steps(true)
+    ///     .build();
+    /// ```
+    pub fn end_open_steps(mut self, value: bool) -> Self {
+        self.end_open_steps = value;
+        self
+    }
+
+    /// Declares the ordered list of steps the future [`TestRun`] plans to execute, eg. to
+    /// display the progress before the steps are started. The plan is emitted in the
+    /// `testRunStart` metadata, under the `ocptv_planned_steps` key, as a list of
+    /// `{"testStepId": ..., "name": ...}` objects; the planned steps have the
+    /// `plan<index>` ids.
+    ///
+    /// The planned steps are created with [`StartedTestRun::add_step_from_plan`]. They
+    /// may be started in any order or skipped; [`tv::EmitterStats::started_steps`] shows
+    /// which were actually started.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("run_name", "1.0")
+    ///     .plan_steps(&["check fans", "check voltages"])
+    ///     .build();
+    /// ```
+    pub fn plan_steps(mut self, names: &[&str]) -> Self {
+        self.planned_steps = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    /// Same as [`TestRunBuilder::build`], but checks that the run name and version are
+    /// not empty and have no control characters, which would otherwise only fail at
+    /// [`TestRun::start`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let result = TestRun::builder("", "1.0").try_build();
+    /// assert!(matches!(result, Err(BuildError { .. })));
+    /// ```
+    pub fn try_build(self) -> Result<TestRun, BuildError> {
+        check_fields(&[("name", &self.name), ("version", &self.version)])?;
+        Ok(self.build())
+    }
+
+    pub fn build(mut self) -> TestRun {
+        let emitter = self.emitter.unwrap_or_else(|| {
+            emitter::Emitter::new(self.config.unwrap_or(config::Config::builder().build()))
+        });
+
+        if !self.planned_steps.is_empty() {
+            let plan = self
+                .planned_steps
+                .iter()
+                .enumerate()
+                .map(|(index, name)| {
+                    serde_json::json!({
+                        "testStepId": planned_step_id(index),
+                        "name": name,
+                    })
+                })
+                .collect::<Vec<_>>();
+            self.metadata
+                .insert(PLANNED_STEPS_KEY.to_owned(), tv::Value::Array(plan));
+        }
 
         TestRun {
             name: self.name,
@@ -283,10 +912,64 @@ impl TestRunBuilder {
             parameters: self.parameters,
             command_line: self.command_line,
             metadata: self.metadata,
+            probe_output: self.probe_output,
+            allow_complex_parameters: self.allow_complex_parameters,
+            planned_steps: self.planned_steps,
+            record_duration: self.record_duration,
+            environment_metadata: self.environment_metadata,
+            end_open_steps: self.end_open_steps,
+
+            emitter: emitter.inner,
+        }
+    }
+}
+
+const PLANNED_STEPS_KEY: &str = "ocptv_planned_steps";
+
+fn planned_step_id(index: usize) -> String {
+    format!("plan{}", index)
+}
+
+fn quote_args<I: IntoIterator<Item = OsString>>(args: I) -> String {
+    args.into_iter()
+        .map(|arg| quote_arg(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
-            emitter: Arc::new(emitter),
+/// Splits a command line quoted as by [`quote_args`] back into its arguments: whitespace
+/// separates the arguments, single quotes keep their content verbatim and a backslash
+/// escapes the next character outside of quotes.
+pub(crate) fn split_args(cmd: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut arg: Option<String> = None;
+    let mut chars = cmd.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => args.extend(arg.take()),
+            '\'' => {
+                let arg = arg.get_or_insert_with(String::new);
+                arg.extend(chars.by_ref().take_while(|&c| c != '\''));
+            }
+            '\\' => arg.get_or_insert_with(String::new).extend(chars.next()),
+            c => arg.get_or_insert_with(String::new).push(c),
         }
     }
+    args.extend(arg);
+    args
+}
+
+pub(crate) fn quote_arg(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && !arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '\'' | '"' | '\\'));
+    if plain {
+        return arg.to_owned();
+    }
+    // note: a single quote can't be escaped inside single quotes, so close the quoted
+    // string, add an escaped quote and reopen it
+    format!("'{}'", arg.replace('\'', r"'\''"))
 }
 
 /// A test run that was started.
@@ -294,34 +977,181 @@ impl TestRunBuilder {
 /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#testrunstart>
 pub struct StartedTestRun {
     run: TestRun,
+    dut: dut::DutInfo,
+    started_at: chrono::DateTime<chrono_tz::Tz>,
+    // emitter stats before the run start, since the emitter may be shared with other runs
+    baseline: tv::EmitterStats,
 
     step_seqno: atomic::AtomicU64,
+    step_ids: Mutex<BTreeSet<String>>,
+    // steps that may still be open, in creation order; dropped and ended steps are pruned
+    // when adding new ones. note: these are weak handles, so the run never keeps a step
+    // alive, and a step dropped without ending (or before starting) is forgotten
+    steps: Mutex<Vec<Weak<StepEmitter>>>,
+    // note: shared with the steps, so they can't emit after the run end either
+    ended: Arc<atomic::AtomicBool>,
+    // errors and failed diagnoses of the run and all its steps, for `end_auto`
+    outcome: Arc<Outcome>,
 }
 
 impl StartedTestRun {
-    fn new(run: TestRun) -> StartedTestRun {
+    fn new(
+        run: TestRun,
+        dut: dut::DutInfo,
+        started_at: chrono::DateTime<chrono_tz::Tz>,
+        baseline: tv::EmitterStats,
+    ) -> StartedTestRun {
         StartedTestRun {
             run,
+            dut,
+            started_at,
+            baseline,
             step_seqno: atomic::AtomicU64::new(0),
+            step_ids: Mutex::new(BTreeSet::new()),
+            steps: Mutex::new(Vec::new()),
+            ended: Arc::new(atomic::AtomicBool::new(false)),
+            outcome: Arc::new(Outcome::default()),
+        }
+    }
+
+    // note: called while holding the emit lock, so that no artifact of this run, or of
+    // its steps, can be written after the run end
+    fn check_open(&self) -> Result<(), tv::OcptvError> {
+        if self.ended.load(Ordering::Acquire) {
+            return Err(tv::OcptvError::RunAlreadyEnded);
+        }
+        Ok(())
+    }
+
+    async fn emit(&self, artifact: spec::TestRunArtifact) -> Result<(), tv::OcptvError> {
+        let is_error = matches!(artifact.artifact, spec::TestRunArtifactImpl::Error(_));
+        self.run
+            .emitter
+            .emit_if(
+                [spec::RootImpl::TestRunArtifact(artifact)],
+                || self.check_open(),
+                || {},
+            )
+            .await?;
+
+        if is_error {
+            self.outcome.add_error();
         }
+        Ok(())
     }
 
     // note: keep the self-consuming method for crate api, but use this one internally,
     // since `StartedTestRun::end` only needs to take ownership for syntactic reasons
-    async fn end_impl(
+    pub(crate) async fn end_impl(
         &self,
         status: spec::TestStatus,
         result: spec::TestResult,
-    ) -> Result<(), tv::OcptvError> {
-        let end = spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
-            artifact: spec::TestRunArtifactImpl::TestRunEnd(spec::TestRunEnd { status, result }),
-        });
+    ) -> Result<RunSummary, tv::OcptvError> {
+        if self.ended.load(Ordering::Acquire) {
+            return Err(tv::OcptvError::RunAlreadyEnded);
+        }
 
-        self.run.emitter.emit(&end).await?;
-        Ok(())
-    }
+        // note: the open steps must be handled before the run is marked as ended, since
+        // that rejects any further step artifact
+        let open_steps = self.open_steps();
+        if !open_steps.is_empty() {
+            if self.run.end_open_steps {
+                Self::end_steps(&open_steps).await?;
+            } else {
+                let ids = open_steps
+                    .iter()
+                    .map(|step| step.step_id())
+                    .collect::<Vec<_>>();
+                let msg = format!("steps not ended before the run end: {}", ids.join(", "));
+                self.add_log(spec::LogSeverity::Warning, &msg).await?;
+            }
+        }
 
-    /// Ends the test run.
+        let duration = self.run.emitter.timestamp_provider().now() - self.started_at;
+        let mut artifacts = vec![];
+        if self.run.record_duration {
+            let message = serde_json::json!({ "durationMs": duration.num_milliseconds() });
+            let log = log::Log::builder(&message.to_string()).build();
+            artifacts.push(spec::TestRunArtifactImpl::Log(log.to_artifact()));
+        }
+        artifacts.push(spec::TestRunArtifactImpl::TestRunEnd(spec::TestRunEnd {
+            status,
+            result,
+        }));
+
+        // note: the run is only marked as ended once its end was written, so that a
+        // failed end can be retried
+        let roots = artifacts
+            .into_iter()
+            .map(|artifact| spec::RootImpl::TestRunArtifact(spec::TestRunArtifact { artifact }));
+        let mut last_seqno = 0;
+        self.run
+            .emitter
+            .emit_if(
+                roots,
+                || self.check_open(),
+                || {
+                    self.ended.store(true, Ordering::Release);
+                    // note: read under the emit lock, since the end artifact was just
+                    // written and another run on a shared emitter can't emit before it's
+                    // released
+                    last_seqno = self.run.emitter.seqno() - 1;
+                },
+            )
+            .await?;
+        self.run.emitter.flush().await?;
+
+        Ok(self.summary(duration.to_std().unwrap_or_default(), last_seqno))
+    }
+
+    fn open_steps(&self) -> Vec<Arc<StepEmitter>> {
+        self.steps
+            .lock()
+            .expect("steps lock is never poisoned")
+            .iter()
+            .filter_map(Weak::upgrade)
+            .filter(|step| step.is_open())
+            .collect()
+    }
+
+    async fn end_steps(steps: &[Arc<StepEmitter>]) -> Result<(), tv::OcptvError> {
+        for step in steps {
+            let end = spec::TestStepArtifactImpl::TestStepEnd(spec::TestStepEnd {
+                status: spec::TestStatus::Error,
+            });
+            match step.emit(end).await {
+                // note: the step may have been ended concurrently
+                Ok(()) | Err(tv::OcptvError::StepAlreadyEnded(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn summary(&self, duration: std::time::Duration, last_seqno: u64) -> RunSummary {
+        let stats = self.run.emitter.stats();
+        let count = |kinds: &[&str]| {
+            kinds
+                .iter()
+                .map(|kind| stats.count(kind) - self.baseline.count(kind))
+                .sum()
+        };
+
+        RunSummary {
+            steps: count(&["testStepArtifact.testStepStart"]),
+            errors: count(&["testRunArtifact.error", "testStepArtifact.error"]),
+            logs: count(&["testRunArtifact.log", "testStepArtifact.log"]),
+            measurements: count(&[
+                "testStepArtifact.measurement",
+                "testStepArtifact.measurementSeriesElement",
+            ]),
+            series: count(&["testStepArtifact.measurementSeriesStart"]),
+            duration,
+            last_seqno,
+        }
+    }
+
+    /// Ends the test run, returning a summary of the artifacts written by it.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#testrunend>
     ///
@@ -332,7 +1162,10 @@ impl StartedTestRun {
     /// # use ocptv::output::*;
     /// let dut = DutInfo::builder("my_dut").build();
     /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
-    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    /// run.add_log(LogSeverity::Info, "message").await?;
+    ///
+    /// let summary = run.end(TestStatus::Complete, TestResult::Pass).await?;
+    /// assert_eq!(summary.logs, 1);
     ///
     /// # Ok::<(), OcptvError>(())
     /// # });
@@ -341,10 +1174,320 @@ impl StartedTestRun {
         self,
         status: spec::TestStatus,
         result: spec::TestResult,
-    ) -> Result<(), tv::OcptvError> {
+    ) -> Result<RunSummary, tv::OcptvError> {
         self.end_impl(status, result).await
     }
 
+    /// Ends the test run with the `Complete` status and a result derived from the
+    /// artifacts emitted by the run and its steps: `Fail` if any of them emitted an error
+    /// or a diagnosis of the `Fail` type, `Pass` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.add_diagnosis("fan-stalled", DiagnosisType::Fail).await?;
+    /// step.end_auto().await?;
+    ///
+    /// // ends with the Fail result
+    /// run.end_auto().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn end_auto(self) -> Result<RunSummary, tv::OcptvError> {
+        let result = match self.outcome.errors() + self.outcome.failed_diagnoses() {
+            0 => spec::TestResult::Pass,
+            _ => spec::TestResult::Fail,
+        };
+        self.end_impl(spec::TestStatus::Complete, result).await
+    }
+
+    /// Aborts the test run: emits an error artifact with the given symptom and message,
+    /// ends the steps that are still open with the `Error` status, then ends the run with
+    /// the `Error` status and `Fail` result. The run is ended even if emitting the error
+    /// or ending the steps fails, and the first failure is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// run.abort("dut-unreachable", "lost connection to the bmc").await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn abort(self, symptom: &str, msg: &str) -> Result<RunSummary, tv::OcptvError> {
+        let error = error::Error::builder(symptom).message(msg).build();
+        self.abort_with(&error, spec::TestResult::Fail).await
+    }
+
+    /// Same as [`StartedTestRun::abort`], but with a detailed error and the given result.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let error = Error::builder("power-cycle")
+    ///     .message("the dut rebooted unexpectedly")
+    ///     .build();
+    /// run.abort_with(&error, TestResult::NotApplicable).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn abort_with(
+        self,
+        error: &error::Error,
+        result: spec::TestResult,
+    ) -> Result<RunSummary, tv::OcptvError> {
+        // note: aborting is best effort, the run is always ended since it was consumed;
+        // the first failure is returned once it is
+        let mut failure = self.add_error_detail(error.clone()).await.err();
+        if let Err(e) = Self::end_steps(&self.open_steps()).await {
+            failure.get_or_insert(e);
+        }
+
+        let summary = self.end_impl(spec::TestStatus::Error, result).await;
+        match failure {
+            Some(e) => Err(e),
+            None => summary,
+        }
+    }
+
+    /// Skips the test run, eg. when its preconditions aren't met: emits an `Info` log with
+    /// the reason, then ends the run with the `Skip` status and `NotApplicable` result.
+    /// The run may already have emitted steps.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// run.skip("unsupported platform").await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn skip(self, reason: &str) -> Result<RunSummary, tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Info, reason).await?;
+        self.end_impl(spec::TestStatus::Skip, spec::TestResult::NotApplicable)
+            .await
+    }
+
+    /// Wraps the run in a guard that emits the `testRunEnd` artifact with the given
+    /// defaults if it is dropped without being ended, eg. on an early return or a panic
+    /// in the test body. Ending the run through [`TestRunGuard::end`] disarms the guard.
+    ///
+    /// The end artifact is emitted on a best effort basis, since there's no async drop;
+    /// any error is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let run = run.into_guard(TestStatus::Error, TestResult::NotApplicable);
+    ///
+    /// run.add_log(LogSeverity::Info, "First message").await?;
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn into_guard(self, status: spec::TestStatus, result: spec::TestResult) -> TestRunGuard {
+        TestRunGuard {
+            run: Some(self),
+            status,
+            result,
+        }
+    }
+
+    /// Flushes and closes the output without emitting the `testRunEnd` artifact, eg. when
+    /// bailing out of the run early. Dropping the run also does this on a best effort
+    /// basis, but any error is then lost. See [`tv::Emitter::close`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// run.close().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn close(self) -> Result<(), tv::OcptvError> {
+        self.close_impl().await
+    }
+
+    // note: same as `end_impl`, for the global run which can't be moved out of its static
+    pub(crate) async fn close_impl(&self) -> Result<(), tv::OcptvError> {
+        self.run.emitter.close().await
+    }
+
+    /// Returns the sequence number that will be assigned to the next emitted artifact.
+    /// When resuming a run into the same output, this can be passed to
+    /// [`tv::ConfigBuilder::with_sequence_start`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// // schemaVersion and testRunStart were emitted
+    /// assert_eq!(run.sequence_number(), 2);
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn sequence_number(&self) -> u64 {
+        self.run.emitter.seqno()
+    }
+
+    /// Returns the sequence number of the last emitted artifact. If the output is shared
+    /// with other runs, the artifact may belong to another run.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// // testRunStart was the last one
+    /// assert_eq!(run.last_seqno(), 1);
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn last_seqno(&self) -> u64 {
+        // note: a started run always emitted at least the testRunStart artifact
+        self.run.emitter.seqno() - 1
+    }
+
+    /// Returns the ids of the steps that were started but not ended yet, in the order
+    /// they were created; steps dropped without being ended aren't tracked anymore.
+    /// See [`TestRunBuilder::end_open_steps`] for how they are handled when the run ends.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// assert_eq!(run.active_steps(), ["step0"]);
+    /// step.end(TestStatus::Complete).await?;
+    /// assert!(run.active_steps().is_empty());
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn active_steps(&self) -> Vec<String> {
+        self.open_steps()
+            .iter()
+            .map(|step| step.step_id().to_owned())
+            .collect()
+    }
+
+    /// Returns the DUT the run was started with.
+    pub fn dut(&self) -> &dut::DutInfo {
+        &self.dut
+    }
+
+    delegate! {
+        to self.run {
+            pub fn name(&self) -> &str;
+            pub fn version(&self) -> &str;
+            pub fn parameters(&self) -> &BTreeMap<String, tv::Value>;
+            pub fn metadata(&self) -> &BTreeMap<String, tv::Value>;
+        }
+    }
+
+    /// Returns statistics about the artifacts written so far by this run, like the number
+    /// of artifacts of each type and the total number of bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// run.add_log(LogSeverity::Info, "message").await?;
+    ///
+    /// let stats = run.stats();
+    /// assert_eq!(stats.count("testRunArtifact.log"), 1);
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn stats(&self) -> tv::EmitterStats {
+        self.run.emitter.stats()
+    }
+
+    /// Subscribes to the live stream of artifacts, eg. to display the measurements as
+    /// they happen. Each artifact is received after it was successfully written, as the
+    /// typed [`spec::Root`] it was emitted as, ie. before the emit hooks, redaction and
+    /// truncation apply. The subscription ends after the `testRunEnd` artifact.
+    ///
+    /// Subscribers never slow down the output: a subscriber that falls too far behind
+    /// misses the oldest artifacts and gets a [`broadcast::error::RecvError::Lagged`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// use ocptv::spec::{RootImpl, TestRunArtifact, TestRunArtifactImpl};
+    ///
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let mut artifacts = run.subscribe();
+    ///
+    /// run.add_log(LogSeverity::Info, "message").await?;
+    /// let artifact = artifacts.recv().await.unwrap();
+    /// if let RootImpl::TestRunArtifact(TestRunArtifact {
+    ///     artifact: TestRunArtifactImpl::Log(log),
+    /// }) = &artifact.artifact
+    /// {
+    ///     assert_eq!(log.message, "message");
+    /// }
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<spec::Root>> {
+        self.run.emitter.subscribe()
+    }
+
     /// Emits a Log message.
     /// This method accepts a [`tv::LogSeverity`] to define the severity
     /// and a [`String`] for the message.
@@ -377,14 +1520,54 @@ impl StartedTestRun {
         let artifact = spec::TestRunArtifact {
             artifact: spec::TestRunArtifactImpl::Log(log.to_artifact()),
         };
-        self.run
-            .emitter
-            .emit(&spec::RootImpl::TestRunArtifact(artifact))
-            .await?;
+        self.emit(artifact).await?;
 
         Ok(())
     }
 
+    /// Emits a Log message with DEBUG severity.
+    /// See [`StartedTestRun::add_log`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// run.log_debug("This is a log message with DEBUG severity").await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn log_debug(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Debug, msg).await
+    }
+
+    /// Emits a Log message with INFO severity.
+    /// See [`StartedTestRun::add_log`] for details.
+    pub async fn log_info(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Info, msg).await
+    }
+
+    /// Emits a Log message with WARNING severity.
+    /// See [`StartedTestRun::add_log`] for details.
+    pub async fn log_warning(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Warning, msg).await
+    }
+
+    /// Emits a Log message with ERROR severity.
+    /// See [`StartedTestRun::add_log`] for details.
+    pub async fn log_error(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Error, msg).await
+    }
+
+    /// Emits a Log message with FATAL severity.
+    /// See [`StartedTestRun::add_log`] for details.
+    pub async fn log_fatal(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Fatal, msg).await
+    }
+
     /// Emits a Log message.
     /// This method accepts a [`tv::Log`] object.
     ///
@@ -412,10 +1595,7 @@ impl StartedTestRun {
         let artifact = spec::TestRunArtifact {
             artifact: spec::TestRunArtifactImpl::Log(log.to_artifact()),
         };
-        self.run
-            .emitter
-            .emit(&spec::RootImpl::TestRunArtifact(artifact))
-            .await?;
+        self.emit(artifact).await?;
 
         Ok(())
     }
@@ -473,6 +1653,8 @@ impl StartedTestRun {
 
     /// Emits a Error message.
     /// This method accepts an [`tv::Error`] object.
+    /// The software infos it references must be registered on the DUT of this run,
+    /// otherwise [`tv::OcptvError::ForeignSoftwareInfo`] is returned.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#error>
     ///
@@ -499,22 +1681,144 @@ impl StartedTestRun {
     /// # });
     /// ```
     pub async fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError> {
+        error.check_dut(self.dut.id())?;
         let artifact = spec::TestRunArtifact {
             artifact: spec::TestRunArtifactImpl::Error(error.to_artifact()),
         };
-        self.run
-            .emitter
-            .emit(&spec::RootImpl::TestRunArtifact(artifact))
-            .await?;
+        self.emit(artifact).await?;
 
         Ok(())
     }
 
     /// Create a new step for this test run.
+    /// Steps can't emit any artifacts after the run ended, see [`tv::OcptvError::RunAlreadyEnded`].
     /// TODO: docs + example
     pub fn add_step(&self, name: &str) -> TestStep {
-        let step_id = format!("step{}", self.step_seqno.fetch_add(1, Ordering::AcqRel));
-        TestStep::new(&step_id, name, Arc::clone(&self.run.emitter))
+        let mut step_ids = self
+            .step_ids
+            .lock()
+            .expect("step ids lock is never poisoned");
+        // note: skip over the ids already taken by custom ids
+        let step_id = loop {
+            let id = format!("step{}", self.step_seqno.fetch_add(1, Ordering::AcqRel));
+            if step_ids.insert(id.clone()) {
+                break id;
+            }
+        };
+        drop(step_ids);
+
+        self.new_step(&step_id, name)
+    }
+
+    /// Create a new step for this test run, with a custom step id instead of the
+    /// generated one, eg. to match the steps across runs.
+    /// Fails with [`tv::OcptvError::DuplicateStepId`] if the id is already used in this run.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step_with_id("fan_check", "check the fans")?.start().await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn add_step_with_id(&self, id: &str, name: &str) -> Result<TestStep, tv::OcptvError> {
+        let inserted = self
+            .step_ids
+            .lock()
+            .expect("step ids lock is never poisoned")
+            .insert(id.to_owned());
+        if !inserted {
+            return Err(tv::OcptvError::DuplicateStepId(id.to_owned()));
+        }
+
+        Ok(self.new_step(id, name))
+    }
+
+    /// Create the step planned at `index` with [`TestRunBuilder::plan_steps`], with the
+    /// planned name and id.
+    /// Fails with [`tv::OcptvError::UnknownPlannedStep`] if there's no such planned step,
+    /// or [`tv::OcptvError::DuplicateStepId`] if it was already created.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::builder("diagnostic_name", "1.0")
+    ///     .plan_steps(&["check fans", "check voltages"])
+    ///     .build()
+    ///     .start(dut)
+    ///     .await?;
+    ///
+    /// let step = run.add_step_from_plan(0)?.start().await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn add_step_from_plan(&self, index: usize) -> Result<TestStep, tv::OcptvError> {
+        let name = self
+            .run
+            .planned_steps
+            .get(index)
+            .ok_or(tv::OcptvError::UnknownPlannedStep(index))?;
+
+        self.add_step_with_id(&planned_step_id(index), name)
+    }
+
+    /// Create a new step for this test run, in a logical group of steps (eg. "thermal").
+    ///
+    /// Since the testStepStart artifact has no metadata, the group is emitted right after
+    /// it, as a step extension artifact named `ocptv_step_group`, with the content
+    /// `{"group": "<group>"}`. See [`crate::reader::step_groups`] for reading it back.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.step_in_group("thermal", "check fans").start().await?;
+    /// assert_eq!(step.group(), Some("thermal"));
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// run.end(TestStatus::Complete, TestResult::Pass).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn step_in_group(&self, group: &str, name: &str) -> TestStep {
+        self.add_step(name).with_group(group)
+    }
+
+    fn new_step(&self, id: &str, name: &str) -> TestStep {
+        let step = TestStep::new(
+            id,
+            name,
+            self.dut.id(),
+            Arc::clone(&self.run.emitter),
+            Arc::clone(&self.ended),
+            Arc::clone(&self.outcome),
+        );
+
+        let mut steps = self.steps.lock().expect("steps lock is never poisoned");
+        steps.retain(|step| step.upgrade().is_some_and(|step| !step.is_ended()));
+        steps.push(Arc::downgrade(step.emitter()));
+        step
     }
 }
 
@@ -528,12 +1832,165 @@ impl ScopedTestRun {
         to self.run {
             pub async fn add_log(&self, severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError>;
             pub async fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError>;
+            pub async fn log_debug(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_info(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_warning(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_error(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_fatal(&self, msg: &str) -> Result<(), tv::OcptvError>;
 
             pub async fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError>;
             pub async fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError>;
             pub async fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError>;
 
             pub fn add_step(&self, name: &str) -> TestStep;
+            pub fn add_step_with_id(&self, id: &str, name: &str) -> Result<TestStep, tv::OcptvError>;
+            pub fn add_step_from_plan(&self, index: usize) -> Result<TestStep, tv::OcptvError>;
+            pub fn step_in_group(&self, group: &str, name: &str) -> TestStep;
+
+            pub fn sequence_number(&self) -> u64;
+            pub fn last_seqno(&self) -> u64;
+            pub fn active_steps(&self) -> Vec<String>;
+            pub fn stats(&self) -> tv::EmitterStats;
+            pub fn subscribe(&self) -> broadcast::Receiver<Arc<spec::Root>>;
+
+            pub fn name(&self) -> &str;
+            pub fn version(&self) -> &str;
+            pub fn parameters(&self) -> &BTreeMap<String, tv::Value>;
+            pub fn metadata(&self) -> &BTreeMap<String, tv::Value>;
+            pub fn dut(&self) -> &dut::DutInfo;
         }
     }
 }
+
+/// Guard over a [`StartedTestRun`] that ends the run with default status and result
+/// when dropped. See [`StartedTestRun::into_guard`].
+pub struct TestRunGuard {
+    // note: only taken when the run is explicitly ended
+    run: Option<StartedTestRun>,
+    status: spec::TestStatus,
+    result: spec::TestResult,
+}
+
+impl TestRunGuard {
+    fn run(&self) -> &StartedTestRun {
+        self.run.as_ref().expect("run is only taken by end")
+    }
+
+    /// Ends the test run and disarms the guard.
+    /// See [`StartedTestRun::end`] for details.
+    pub async fn end(
+        mut self,
+        status: spec::TestStatus,
+        result: spec::TestResult,
+    ) -> Result<RunSummary, tv::OcptvError> {
+        let run = self.run.take().expect("run is only taken by end");
+        run.end(status, result).await
+    }
+
+    delegate! {
+        to self.run() {
+            pub async fn add_log(&self, severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError>;
+            pub async fn log_debug(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_info(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_warning(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_error(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_fatal(&self, msg: &str) -> Result<(), tv::OcptvError>;
+
+            pub async fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError>;
+            pub async fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError>;
+
+            pub fn add_step(&self, name: &str) -> TestStep;
+            pub fn add_step_with_id(&self, id: &str, name: &str) -> Result<TestStep, tv::OcptvError>;
+            pub fn add_step_from_plan(&self, index: usize) -> Result<TestStep, tv::OcptvError>;
+            pub fn step_in_group(&self, group: &str, name: &str) -> TestStep;
+
+            pub fn sequence_number(&self) -> u64;
+            pub fn last_seqno(&self) -> u64;
+            pub fn active_steps(&self) -> Vec<String>;
+            pub fn stats(&self) -> tv::EmitterStats;
+            pub fn subscribe(&self) -> broadcast::Receiver<Arc<spec::Root>>;
+
+            pub fn name(&self) -> &str;
+            pub fn version(&self) -> &str;
+            pub fn parameters(&self) -> &BTreeMap<String, tv::Value>;
+            pub fn metadata(&self) -> &BTreeMap<String, tv::Value>;
+            pub fn dut(&self) -> &dut::DutInfo;
+        }
+    }
+}
+
+impl Drop for TestRunGuard {
+    fn drop(&mut self) {
+        if let Some(run) = self.run.take() {
+            let _ =
+                emitter::block_on_in_drop(run.end_impl(self.status.clone(), self.result.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use tokio::sync::Mutex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run_accessors() -> Result<()> {
+        let run = TestRun::builder("run_name", "1.0")
+            .config(
+                config::Config::builder()
+                    .with_buffer_output(Arc::new(Mutex::new(vec![])))
+                    .build(),
+            )
+            .add_parameter("param", 1)
+            .add_metadata("meta", "value")
+            .build();
+
+        let params = BTreeMap::from([("param".to_owned(), tv::Value::from(1))]);
+        let metadata = BTreeMap::from([("meta".to_owned(), tv::Value::from("value"))]);
+        assert_eq!(run.name(), "run_name");
+        assert_eq!(run.version(), "1.0");
+        assert_eq!(run.parameters(), &params);
+        assert_eq!(run.metadata(), &metadata);
+
+        let dut = dut::DutInfo::builder("dut_id").name("dut").build();
+        let run = run.start(dut.clone()).await?;
+        assert_eq!(run.name(), "run_name");
+        assert_eq!(run.version(), "1.0");
+        assert_eq!(run.parameters(), &params);
+        assert_eq!(run.metadata(), &metadata);
+        assert_eq!(run.dut(), &dut);
+        // schemaVersion, testRunStart
+        assert_eq!(run.last_seqno(), 1);
+
+        run.add_log(spec::LogSeverity::Info, "log").await?;
+        assert_eq!(run.last_seqno(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_quote_args() -> Result<()> {
+        let args = ["diag", "--name", "my fan", "it's", "", "a\"b", "--x=1"].map(OsString::from);
+        assert_eq!(
+            quote_args(args),
+            r#"diag --name 'my fan' 'it'\''s' '' 'a"b' --x=1"#
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_args() -> Result<()> {
+        let args = [
+            "diag", "--name", "my  fan", "it's", "", "a\"b", r"c:\x", "--x=1",
+        ];
+        assert_eq!(
+            split_args(&quote_args(args.map(OsString::from))),
+            args.map(str::to_owned)
+        );
+        assert_eq!(split_args("  a\tb\\ c  "), ["a", "b c"]);
+        Ok(())
+    }
+}
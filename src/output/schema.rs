@@ -0,0 +1,145 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::io;
+
+use async_trait::async_trait;
+
+use crate::output::writer::{ArtifactContext, Writer, WriterError};
+
+// note: hand written approximation of the OCPTV output spec v2.0, not a copy of the
+// upstream schemas, see the comment in the schema itself
+const OUTPUT_SCHEMA: &str = include_str!("../../schemas/output.json");
+
+/// Writer that validates every artifact against the OCPTV output json schema before
+/// handing it over to the wrapped writer.
+///
+/// The schema is a local approximation of the v2.0 output spec, bundled with this crate,
+/// and not the upstream schema; it may accept or reject artifacts the upstream one doesn't.
+///
+/// Artifacts that fail validation are not written, and the emitting call returns
+/// [`crate::output::OcptvError::Writer`] with a [`WriterError::SchemaViolation`], or
+/// [`WriterError::InvalidJson`] if what the writer was given is not json at all.
+/// Validation has a noticeable runtime cost, so this is meant for development and
+/// testing of diagnostics.
+///
+/// # Examples
+///
+/// ```rust
+/// # use ocptv::output::*;
+/// let writer = ValidatingWriter::new(Box::new(StdoutWriter::new()));
+/// let config = Config::builder()
+///     .with_custom_output(Box::new(writer))
+///     .build();
+/// ```
+pub struct ValidatingWriter {
+    inner: Box<dyn Writer + Send + Sync + 'static>,
+    validator: jsonschema::Validator,
+}
+
+impl ValidatingWriter {
+    pub fn new(inner: Box<dyn Writer + Send + Sync + 'static>) -> Self {
+        let schema = serde_json::from_str(OUTPUT_SCHEMA).expect("bundled schema is valid json");
+        let validator = jsonschema::options()
+            .should_validate_formats(true)
+            .build(&schema)
+            .expect("bundled schema is a valid json schema");
+
+        ValidatingWriter { inner, validator }
+    }
+
+    fn validate(&self, s: &str) -> Result<(), WriterError> {
+        let value =
+            serde_json::from_str::<serde_json::Value>(s).map_err(|e| WriterError::InvalidJson {
+                artifact: ArtifactContext::default(),
+                message: e.to_string(),
+            })?;
+
+        self.validator
+            .validate(&value)
+            .map_err(|e| WriterError::SchemaViolation {
+                // note: filled in by the emitter
+                artifact: ArtifactContext::default(),
+                pointer: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+    }
+}
+
+#[async_trait]
+impl Writer for ValidatingWriter {
+    async fn write(&self, s: &str) -> Result<(), io::Error> {
+        self.validate(s)?;
+        self.inner.write(s).await
+    }
+
+    async fn probe(&self) -> Result<(), io::Error> {
+        self.inner.probe().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use serde_json::json;
+
+    use super::*;
+    use crate::output::StdoutWriter;
+
+    #[test]
+    fn test_schema_accepts_valid_artifact() -> Result<()> {
+        let writer = ValidatingWriter::new(Box::new(StdoutWriter::new()));
+        let artifact = json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan_speed",
+                    "value": 1000,
+                    "unit": "rpm"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": "1970-01-01T00:00:00.000Z"
+        });
+
+        writer.validate(&artifact.to_string())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_rejects_invalid_artifact() -> Result<()> {
+        let writer = ValidatingWriter::new(Box::new(StdoutWriter::new()));
+        let artifact = json!({
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "status": "COMPLETE",
+                    "result": "MAYBE"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": "1970-01-01T00:00:00.000Z"
+        });
+
+        match writer.validate(&artifact.to_string()) {
+            Err(WriterError::SchemaViolation { pointer, .. }) => {
+                assert_eq!(pointer, "/testRunArtifact/testRunEnd/result");
+            }
+            _ => panic!("expected schema violation"),
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_schema_rejects_invalid_json() -> Result<()> {
+        let writer = ValidatingWriter::new(Box::new(StdoutWriter::new()));
+
+        assert!(matches!(
+            writer.validate("{\"sequenceNumber\": 3"),
+            Err(WriterError::InvalidJson { .. })
+        ));
+        Ok(())
+    }
+}
@@ -5,22 +5,32 @@
 // https://opensource.org/licenses/MIT.
 
 use std::future::Future;
-use std::io;
+use std::panic;
+use std::path::Path;
 use std::sync::atomic::{self, Ordering};
 use std::sync::Arc;
 
 use delegate::delegate;
+use futures::FutureExt;
 
 use crate::output as tv;
 use crate::spec::{self, TestStepArtifactImpl};
 use tv::OcptvError;
 use tv::{config, diagnosis, emitter, error, file, log, measure, Ident};
 
+const STEP_DURATION_MEASUREMENT: &str = "step_duration";
+
+/// Name of the extension artifact carrying the step group, emitted right after the
+/// testStepStart of the steps created with [`tv::StartedTestRun::step_in_group`].
+pub(crate) const STEP_GROUP_EXTENSION: &str = "ocptv_step_group";
+
 /// A single test step in the scope of a [`tv::TestRun`].
 ///
 /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#test-step-artifacts>
 pub struct TestStep {
     name: String,
+    dut_id: String,
+    group: Option<String>,
 
     emitter: Arc<StepEmitter>,
 }
@@ -28,16 +38,60 @@ pub struct TestStep {
 impl TestStep {
     // note: this object is crate public but users should only construct
     // instances through the `StartedTestRun.add_step` api
-    pub(crate) fn new(id: &str, name: &str, run_emitter: Arc<emitter::JsonEmitter>) -> Self {
+    pub(crate) fn new(
+        id: &str,
+        name: &str,
+        dut_id: &str,
+        run_emitter: Arc<emitter::JsonEmitter>,
+        run_ended: Arc<atomic::AtomicBool>,
+        run_outcome: Arc<Outcome>,
+    ) -> Self {
         TestStep {
             name: name.to_owned(),
+            dut_id: dut_id.to_owned(),
+            group: None,
             emitter: Arc::new(StepEmitter {
                 step_id: id.to_owned(),
                 emitter: run_emitter,
+                run_ended,
+                outcome: Outcome::default(),
+                run_outcome,
+                started: atomic::AtomicBool::new(false),
+                ended: atomic::AtomicBool::new(false),
             }),
         }
     }
 
+    pub(crate) fn emitter(&self) -> &Arc<StepEmitter> {
+        &self.emitter
+    }
+
+    pub(crate) fn with_group(mut self, group: &str) -> Self {
+        self.group = Some(group.to_owned());
+        self
+    }
+
+    /// Returns the step identifier, as in the `testStepId` of the step artifacts.
+    pub fn id(&self) -> &str {
+        self.emitter.step_id()
+    }
+
+    /// Returns the step name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the identifier of the DUT the parent run was started with.
+    pub fn dut_id(&self) -> &str {
+        &self.dut_id
+    }
+
+    /// Returns the group of the step, for steps created with
+    /// [`tv::StartedTestRun::step_in_group`].
+    pub fn group(&self) -> Option<&str> {
+        self.group.as_deref()
+    }
+
     /// Starts the test step.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#teststepstart>
@@ -55,15 +109,75 @@ impl TestStep {
     /// # });
     /// ```
     pub async fn start(self) -> Result<StartedTestStep, tv::OcptvError> {
-        self.emitter
-            .emit(&TestStepArtifactImpl::TestStepStart(spec::TestStepStart {
-                name: self.name.clone(),
-            }))
-            .await?;
+        self.start_impl(false).await
+    }
+
+    /// Starts the test step, and records its duration when it ends: a `step_duration`
+    /// measurement, in milliseconds according to the configured
+    /// [`tv::TimestampProvider`], is emitted just before the testStepEnd artifact.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#teststepstart>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start_timed().await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn start_timed(self) -> Result<StartedTestStep, tv::OcptvError> {
+        self.start_impl(true).await
+    }
+
+    /// Skips the test step without running it: starts the step, so that it's still
+    /// visible in the output, then skips it as [`StartedTestStep::skip`] does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("nvme endurance");
+    /// step.skip_before_start("NVMe not present").await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn skip_before_start(self, reason: &str) -> Result<(), tv::OcptvError> {
+        self.start().await?.skip(reason).await
+    }
+
+    async fn start_impl(self, timed: bool) -> Result<StartedTestStep, tv::OcptvError> {
+        let started_at = timed.then(|| self.emitter.emitter.timestamp_provider().now());
+
+        let start = TestStepArtifactImpl::TestStepStart(spec::TestStepStart {
+            name: self.name.clone(),
+        });
+        match &self.group {
+            None => self.emitter.emit(start).await?,
+            Some(group) => {
+                // note: batched, so that no other artifact comes between the two
+                let ext = TestStepArtifactImpl::Extension(spec::Extension {
+                    name: STEP_GROUP_EXTENSION.to_owned(),
+                    content: serde_json::json!({ "group": group }),
+                });
+                self.emitter.emit_batch([start, ext]).await?;
+            }
+        }
 
         Ok(StartedTestStep {
             step: self,
             measurement_seqno: Arc::new(atomic::AtomicU64::new(0)),
+            started_at,
         })
     }
 
@@ -74,6 +188,11 @@ impl TestStep {
     /// all the messages are emitted between the start and end messages, the order
     /// is respected and no messages is lost.
     ///
+    /// If the closure returns an error, the step is still ended with the `Error` status,
+    /// then the closure error is returned. If the closure panics, an error artifact with
+    /// the `panic` symptom is emitted and the step is ended the same way, then the panic
+    /// is resumed. See [`StartedTestStep::scope`] for an already started step.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -102,21 +221,51 @@ impl TestStep {
         R: Future<Output = Result<tv::TestStatus, tv::OcptvError>> + Send + 'static,
         F: FnOnce(ScopedTestStep) -> R + Send + 'static,
     {
-        let step = Arc::new(self.start().await?);
-        let status = func(ScopedTestStep {
-            step: Arc::clone(&step),
-        })
-        .await?;
-        step.end_impl(status).await?;
-
-        Ok(())
+        self.start().await?.scope(func).await
     }
 }
 
-/// TODO: docs
+/// A test step that was started.
+///
+/// Steps of the same run may execute concurrently, eg. one per CPU socket: the started
+/// run and steps are `Send + Sync`, so a run shared through an [`Arc`] can start a step
+/// in each spawned task. The output stays ordered by sequence number, and the step ids
+/// are unique within the run.
+///
+/// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#teststepstart>
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// # use std::sync::Arc;
+/// # use ocptv::output::*;
+/// let dut = DutInfo::builder("my_dut").build();
+/// let run = Arc::new(TestRun::new("diagnostic_name", "1.0").start(dut).await?);
+///
+/// let mut tasks = vec![];
+/// for socket in 0..2 {
+///     let run = Arc::clone(&run);
+///     tasks.push(tokio::spawn(async move {
+///         let step = run.add_step(&format!("socket{}", socket)).start().await?;
+///         step.add_measurement("temperature", 40 + socket).await?;
+///         step.end(TestStatus::Complete).await
+///     }));
+/// }
+/// for task in tasks {
+///     task.await.unwrap()?;
+/// }
+///
+/// Arc::into_inner(run).unwrap().end(TestStatus::Complete, TestResult::Pass).await?;
+///
+/// # Ok::<(), OcptvError>(())
+/// # });
+/// ```
 pub struct StartedTestStep {
     step: TestStep,
     measurement_seqno: Arc<atomic::AtomicU64>,
+    // only set for steps started with `TestStep::start_timed`
+    started_at: Option<chrono::DateTime<chrono_tz::Tz>>,
 }
 
 impl StartedTestStep {
@@ -125,8 +274,20 @@ impl StartedTestStep {
     async fn end_impl(&self, status: tv::TestStatus) -> Result<(), tv::OcptvError> {
         let end = TestStepArtifactImpl::TestStepEnd(spec::TestStepEnd { status });
 
-        self.step.emitter.emit(&end).await?;
-        Ok(())
+        let Some(started_at) = self.started_at else {
+            return self.step.emitter.emit(end).await;
+        };
+
+        // note: batched, so that the duration is only emitted along with the step end
+        let duration = self.step.emitter.emitter.timestamp_provider().now() - started_at;
+        let measurement =
+            measure::Measurement::builder(STEP_DURATION_MEASUREMENT, duration.num_milliseconds())
+                .unit(tv::Unit::MILLISECOND)
+                .build();
+        let measurement = TestStepArtifactImpl::Measurement(
+            measurement.to_artifact_with(self.step.emitter.non_finite_policy())?,
+        );
+        self.step.emitter.emit_batch([measurement, end]).await
     }
 
     /// Ends the test step.
@@ -147,10 +308,361 @@ impl StartedTestStep {
     /// # Ok::<(), OcptvError>(())
     /// # });
     /// ```
+    ///
+    /// Ending consumes the step, so it can't be used afterwards:
+    ///
+    /// ```compile_fail
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// # let dut = DutInfo::new("my_dut");
+    /// # let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.end(TestStatus::Complete).await?;
+    /// step.add_log(LogSeverity::Info, "too late").await?;
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    ///
+    /// Handles that can't be consumed, like the [`ScopedTestStep`] shared with a scope,
+    /// are checked at runtime instead: using them after the step ended fails with
+    /// [`tv::OcptvError::StepAlreadyEnded`].
     pub async fn end(self, status: tv::TestStatus) -> Result<(), tv::OcptvError> {
         self.end_impl(status).await
     }
 
+    /// Ends the test step with a status derived from the artifacts it emitted: `Error`
+    /// if it emitted any error artifact, `Complete` otherwise. Failed diagnoses don't
+    /// change the step status, they only fail the run, see [`tv::StartedTestRun::end_auto`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.add_error("fan-stalled").await?;
+    /// // ends with the Error status
+    /// step.end_auto().await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn end_auto(self) -> Result<(), tv::OcptvError> {
+        let status = match self.step.emitter.outcome.errors() {
+            0 => tv::TestStatus::Complete,
+            _ => tv::TestStatus::Error,
+        };
+        self.end_impl(status).await
+    }
+
+    delegate! {
+        to self.step {
+            pub fn id(&self) -> &str;
+            pub fn name(&self) -> &str;
+            pub fn dut_id(&self) -> &str;
+            pub fn group(&self) -> Option<&str>;
+        }
+    }
+
+    /// Builds a scope over the started step, ending it when the closure returns, with
+    /// the returned status. All the artifacts emitted through the scope come between the
+    /// `testStepStart` and `testStepEnd` artifacts, even on an early `?` return.
+    ///
+    /// If the closure returns an error, the step is ended with the `Error` status, then
+    /// the closure error is returned. If the closure panics, an error artifact with the
+    /// `panic` symptom is emitted and the step is ended the same way, then the panic is
+    /// resumed.
+    ///
+    /// Inside a run scope (see [`tv::TestRun::scope`]), the step is always ended before
+    /// the run scope returns; propagating the step scope error out of the run scope
+    /// closure ends the run with the `Error` status as well.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use futures::FutureExt;
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("first step").start().await?;
+    /// step.scope(|s| {
+    ///     async move {
+    ///         s.add_measurement("fan", 1000).await?;
+    ///         Ok(TestStatus::Complete)
+    ///     }.boxed()
+    /// }).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn scope<F, R>(self, func: F) -> Result<(), tv::OcptvError>
+    where
+        R: Future<Output = Result<tv::TestStatus, tv::OcptvError>> + Send + 'static,
+        F: FnOnce(ScopedTestStep) -> R + Send + 'static,
+    {
+        let step = Arc::new(self);
+        let status = panic::AssertUnwindSafe(func(ScopedTestStep {
+            step: Arc::clone(&step),
+        }))
+        .catch_unwind()
+        .await;
+
+        match status {
+            Ok(Ok(status)) => step.end_impl(status).await,
+            Ok(Err(e)) => {
+                // note: the closure error is more relevant than a failure to emit the end
+                let _ = step.end_impl(tv::TestStatus::Error).await;
+                Err(e)
+            }
+            Err(payload) => {
+                let _ = step
+                    .add_error_detail(error::Error::from_panic(&*payload))
+                    .await;
+                let _ = step.end_impl(tv::TestStatus::Error).await;
+                panic::resume_unwind(payload)
+            }
+        }
+    }
+
+    /// Wraps the step in a guard that emits the `testStepEnd` artifact with the given
+    /// default status if it is dropped without being ended, eg. on an early `?` return.
+    /// Ending the step through [`TestStepGuard::end`] disarms the guard.
+    ///
+    /// The end artifact is emitted on a best effort basis, since there's no async drop;
+    /// any error is lost. Measurement series started after the guard was created are
+    /// dropped before it, so the step end still comes after them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// let step = step.into_guard(TestStatus::Error);
+    /// step.add_measurement("fan", 1000).await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn into_guard(self, status: tv::TestStatus) -> TestStepGuard {
+        TestStepGuard {
+            step: Some(self),
+            status,
+        }
+    }
+
+    /// Returns a cheap, cloneable handle that emits artifacts in this step, eg. from a
+    /// task spawned per fan. The handle can't end the step; artifacts emitted through it
+    /// after the step ended fail with [`tv::OcptvError::StepAlreadyEnded`].
+    ///
+    /// Each artifact is written whole, as a single line, and gets its own sequence number.
+    /// There is no ordering guarantee across tasks beyond the sequence numbers, which are
+    /// strictly increasing in the output.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("fans").start().await?;
+    /// let mut tasks = vec![];
+    /// for fan in 0..4 {
+    ///     let handle = step.handle();
+    ///     tasks.push(tokio::spawn(async move {
+    ///         handle.add_measurement(&format!("fan{}", fan), 1000).await
+    ///     }));
+    /// }
+    /// for task in tasks {
+    ///     task.await.unwrap()?;
+    /// }
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn handle(&self) -> StepHandle {
+        ScopedTestStep {
+            step: Arc::new(self.share()),
+        }
+    }
+
+    // note: the copy shares the step emitter and the measurement series ids, but not the
+    // duration tracking, which only the original reports when it ends
+    fn share(&self) -> StartedTestStep {
+        StartedTestStep {
+            step: TestStep {
+                name: self.step.name.clone(),
+                dut_id: self.step.dut_id.clone(),
+                group: self.step.group.clone(),
+                emitter: Arc::clone(&self.step.emitter),
+            },
+            measurement_seqno: Arc::clone(&self.measurement_seqno),
+            started_at: None,
+        }
+    }
+
+    /// Skips the test step: emits an `Info` log with the reason, then ends the step with
+    /// the `Skip` status. The step is ended even if the log can't be emitted, and the log
+    /// failure is returned then.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.skip("missing firmware").await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn skip(self, reason: &str) -> Result<(), tv::OcptvError> {
+        // note: the step was consumed, so it's ended even if the log can't be emitted
+        let logged = self.add_log(spec::LogSeverity::Info, reason).await;
+        let ended = self.end_impl(tv::TestStatus::Skip).await;
+        logged.and(ended)
+    }
+
+    /// Fails the test step: emits an error with the given symptom and optional message,
+    /// then ends the step with the `Error` status.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.end_with_error("fan-missing", Some("no tach signal")).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn end_with_error(
+        self,
+        symptom: &str,
+        msg: Option<&str>,
+    ) -> Result<(), tv::OcptvError> {
+        let mut error = error::Error::builder(symptom);
+        if let Some(msg) = msg {
+            error = error.message(msg);
+        }
+        self.end_with_error_detail(error.build()).await
+    }
+
+    /// Fails the test step: emits the given [`tv::Error`], then ends the step with the
+    /// `Error` status. The step is ended even if the error can't be emitted, eg. when it
+    /// references software infos of another DUT, and the first failure is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// let error = Error::builder("fan-missing")
+    ///     .message("no tach signal")
+    ///     .source("fans.rs", 12)
+    ///     .build();
+    /// step.end_with_error_detail(error).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn end_with_error_detail(self, error: error::Error) -> Result<(), tv::OcptvError> {
+        // note: the step was consumed, so it's ended even if the error can't be emitted
+        let emitted = self.add_error_detail(error).await;
+        let ended = self.end_impl(tv::TestStatus::Error).await;
+        emitted.and(ended)
+    }
+
+    /// Runs the closure up to `attempts` times, at least once, until it succeeds, waiting
+    /// `backoff` between the attempts. Every failed attempt is recorded in a `Warning`
+    /// log with the attempt number and the error; if all of them fail, an error with the
+    /// `retries-exhausted` symptom is emitted and this fails with
+    /// [`tv::OcptvError::RetriesExhausted`], whose source is the last error.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use std::time::Duration;
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let rpm = step
+    ///     .with_retries(3, Duration::from_millis(10), || async {
+    ///         // poke the hardware here
+    ///         Ok::<_, std::io::Error>(8000)
+    ///     })
+    ///     .await?;
+    /// step.add_measurement("fan_speed", rpm).await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn with_retries<F, R, T, E>(
+        &self,
+        attempts: u32,
+        backoff: std::time::Duration,
+        mut func: F,
+    ) -> Result<T, tv::OcptvError>
+    where
+        F: FnMut() -> R,
+        R: Future<Output = Result<T, E>>,
+        E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>,
+    {
+        let attempts = attempts.max(1);
+        let mut attempt = 1;
+        loop {
+            let e = match func().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e.into(),
+            };
+
+            self.add_log(
+                spec::LogSeverity::Warning,
+                &format!("attempt {} of {} failed: {}", attempt, attempts, e),
+            )
+            .await?;
+
+            if attempt == attempts {
+                self.add_error_msg(
+                    "retries-exhausted",
+                    &format!("failed after {} attempts: {}", attempts, e),
+                )
+                .await?;
+                return Err(tv::OcptvError::RetriesExhausted(attempts, e));
+            }
+
+            attempt += 1;
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
     /// Emits Log message.
     /// This method accepts a [`tv::LogSeverity`] to define the severity
     /// and a [`String`] for the message.
@@ -201,12 +713,56 @@ impl StartedTestStep {
 
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Log(log.to_artifact()))
+            .emit(TestStepArtifactImpl::Log(log.to_artifact()))
             .await?;
 
         Ok(())
     }
 
+    /// Emits a Log message with DEBUG severity.
+    /// See [`StartedTestStep::add_log`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::builder("my_dut").build();
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.log_debug("This is a log message with DEBUG severity").await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn log_debug(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Debug, msg).await
+    }
+
+    /// Emits a Log message with INFO severity.
+    /// See [`StartedTestStep::add_log`] for details.
+    pub async fn log_info(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Info, msg).await
+    }
+
+    /// Emits a Log message with WARNING severity.
+    /// See [`StartedTestStep::add_log`] for details.
+    pub async fn log_warning(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Warning, msg).await
+    }
+
+    /// Emits a Log message with ERROR severity.
+    /// See [`StartedTestStep::add_log`] for details.
+    pub async fn log_error(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Error, msg).await
+    }
+
+    /// Emits a Log message with FATAL severity.
+    /// See [`StartedTestStep::add_log`] for details.
+    pub async fn log_fatal(&self, msg: &str) -> Result<(), tv::OcptvError> {
+        self.add_log(spec::LogSeverity::Fatal, msg).await
+    }
+
     /// Emits Log message.
     /// This method accepts a [`tv::Log`] object.
     ///
@@ -235,7 +791,7 @@ impl StartedTestStep {
     pub async fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError> {
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Log(log.to_artifact()))
+            .emit(TestStepArtifactImpl::Log(log.to_artifact()))
             .await?;
 
         Ok(())
@@ -284,7 +840,7 @@ impl StartedTestStep {
 
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Error(error.to_artifact()))
+            .emit(TestStepArtifactImpl::Error(error.to_artifact()))
             .await?;
 
         Ok(())
@@ -334,7 +890,7 @@ impl StartedTestStep {
 
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Error(error.to_artifact()))
+            .emit(TestStepArtifactImpl::Error(error.to_artifact()))
             .await?;
 
         Ok(())
@@ -342,6 +898,8 @@ impl StartedTestStep {
 
     /// Emits a Error message.
     /// This method accepts a [`tv::Error`] object.
+    /// The software infos it references must be registered on the DUT of this step,
+    /// otherwise [`tv::OcptvError::ForeignSoftwareInfo`] is returned.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#error>
     ///
@@ -368,9 +926,10 @@ impl StartedTestStep {
     /// # });
     /// ```
     pub async fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError> {
+        error.check_dut(&self.step.dut_id)?;
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Error(error.to_artifact()))
+            .emit(TestStepArtifactImpl::Error(error.to_artifact()))
             .await?;
 
         Ok(())
@@ -395,23 +954,97 @@ impl StartedTestStep {
     /// # Ok::<(), OcptvError>(())
     /// # });
     /// ```
-    pub async fn add_measurement<V: Into<tv::Value>>(
+    pub async fn add_measurement<V: Into<tv::MeasurementValue>>(
         &self,
         name: &str,
         value: V,
     ) -> Result<(), tv::OcptvError> {
-        let measurement = measure::Measurement::new(name, value);
+        let measurement = measure::Measurement::new(name, value)
+            .to_artifact_with(self.step.emitter.non_finite_policy())?;
 
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Measurement(
-                measurement.to_artifact(),
-            ))
+            .emit(TestStepArtifactImpl::Measurement(measurement))
             .await?;
 
         Ok(())
     }
 
+    /// Emits a Measurement message with a unit.
+    /// For the other measurement fields, see [`StartedTestStep::add_measurement_detail`].
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurement>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// step.add_measurement_with_unit("fan_speed", 8000, "rpm").await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_measurement_with_unit<V: Into<tv::MeasurementValue>, U: Into<tv::Unit>>(
+        &self,
+        name: &str,
+        value: V,
+        unit: U,
+    ) -> Result<(), tv::OcptvError> {
+        let measurement = measure::Measurement::builder(name, value)
+            .unit(unit)
+            .build();
+        self.add_measurement_detail(measurement).await
+    }
+
+    /// Emits a Measurement message with a validator, and returns whether the value
+    /// passed the validator, see [`tv::Validator::validate`].
+    ///
+    /// The validator is evaluated before emitting, so nothing is emitted if it can't be
+    /// evaluated against the value, eg. for a string value with a numeric validator.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurement>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// let validator = Validator::builder(ValidatorType::GreaterThanOrEqual, 5000).build();
+    /// if !step.add_measurement_checked("fan_speed", 4200, &validator).await? {
+    ///     step.add_error("fan-speed-low").await?;
+    /// }
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_measurement_checked<V: Into<tv::MeasurementValue>>(
+        &self,
+        name: &str,
+        value: V,
+        validator: &measure::Validator,
+    ) -> Result<bool, tv::OcptvError> {
+        let value = value.into();
+        let passed = validator.validate(&tv::Value::from(value.clone()))?;
+
+        let measurement = measure::Measurement::builder(name, value)
+            .add_validator(validator.clone())
+            .build();
+        self.add_measurement_detail(measurement).await?;
+
+        Ok(passed)
+    }
+
     /// Emits a Measurement message.
     /// This method accepts a [`tv::Error`] object.
     ///
@@ -446,14 +1079,61 @@ impl StartedTestStep {
     ) -> Result<(), tv::OcptvError> {
         self.step
             .emitter
-            .emit(&spec::TestStepArtifactImpl::Measurement(
-                detail.to_artifact(),
+            .emit(spec::TestStepArtifactImpl::Measurement(
+                detail.to_artifact_with(self.step.emitter.non_finite_policy())?,
             ))
             .await?;
 
         Ok(())
     }
 
+    /// Emits a batch of Measurement messages, in order.
+    /// Each measurement is still a separate artifact, with consecutive sequence numbers,
+    /// but the batch is written at once, which is faster than emitting the measurements
+    /// one by one. If an artifact can't be written, this fails with
+    /// [`tv::OcptvError::BatchInterrupted`], giving the number of measurements written.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurement>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    ///
+    /// let dimms = ["dimm0", "dimm1", "dimm2"];
+    /// step.add_measurements(
+    ///     dimms
+    ///         .iter()
+    ///         .map(|dimm| Measurement::builder(&format!("{}_size", dimm), 32).unit("GiB").build()),
+    /// )
+    /// .await?;
+    ///
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_measurements<I>(&self, measurements: I) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = measure::Measurement>,
+    {
+        // note: convert the whole batch first, so that an invalid value rejects all of it
+        let policy = self.step.emitter.non_finite_policy();
+        let artifacts = measurements
+            .into_iter()
+            .map(|m| {
+                m.to_artifact_with(policy)
+                    .map(TestStepArtifactImpl::Measurement)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.step.emitter.emit_batch(artifacts).await
+    }
+
     /// Create a Measurement Series (a time-series list of measurements).
     /// This method accepts a [`String`] as series ID and a [`String`] as series name.
     ///
@@ -476,6 +1156,36 @@ impl StartedTestStep {
         self.add_measurement_series_detail(tv::MeasurementSeriesDetail::new(name))
     }
 
+    /// Create a Measurement Series (a time-series list of measurements) with a unit.
+    /// For the other series fields, see [`StartedTestStep::add_measurement_series_detail`].
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurementseriesstart>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    /// let step = run.add_step("step_name").start().await?;
+    /// let series = step.add_measurement_series_with_unit("temperature", "C");
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub fn add_measurement_series_with_unit<U: Into<tv::Unit>>(
+        &self,
+        name: &str,
+        unit: U,
+    ) -> tv::MeasurementSeries {
+        self.add_measurement_series_detail(
+            tv::MeasurementSeriesDetail::builder(name)
+                .unit(unit)
+                .build(),
+        )
+    }
+
     /// Create a Measurement Series (a time-series list of measurements).
     /// This method accepts a [`tv::MeasurementSeriesDetail`] object.
     ///
@@ -514,7 +1224,9 @@ impl StartedTestStep {
         tv::MeasurementSeries::new(&series_id, detail, Arc::clone(&self.step.emitter))
     }
 
-    /// Emits a Diagnosis message.
+    /// Emits a Diagnosis message, with only the verdict and type. See
+    /// [`StartedTestStep::add_diagnosis_detail`] to add the hardware info, subcomponent,
+    /// message or source location.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#diagnosis>
     ///
@@ -542,7 +1254,7 @@ impl StartedTestStep {
 
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::Diagnosis(diagnosis.to_artifact()))
+            .emit(TestStepArtifactImpl::Diagnosis(diagnosis.to_artifact()))
             .await?;
 
         Ok(())
@@ -582,7 +1294,7 @@ impl StartedTestStep {
     ) -> Result<(), tv::OcptvError> {
         self.step
             .emitter
-            .emit(&spec::TestStepArtifactImpl::Diagnosis(
+            .emit(spec::TestStepArtifactImpl::Diagnosis(
                 diagnosis.to_artifact(),
             ))
             .await?;
@@ -591,6 +1303,8 @@ impl StartedTestStep {
     }
 
     /// Emits a File message.
+    /// The uri must have a `file`, `http` or `https` scheme, otherwise this fails with
+    /// [`tv::OcptvError::UnsupportedFileUri`].
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#file>
     ///
@@ -612,17 +1326,19 @@ impl StartedTestStep {
     /// ```
     pub async fn add_file(&self, name: &str, uri: tv::Uri) -> Result<(), tv::OcptvError> {
         let file = file::File::new(name, uri);
+        file.check_uri()?;
 
         self.step
             .emitter
-            .emit(&TestStepArtifactImpl::File(file.to_artifact()))
+            .emit(TestStepArtifactImpl::File(file.to_artifact()))
             .await?;
 
         Ok(())
     }
 
     /// Emits a File message.
-    /// This method accepts a [`tv::Error`] object.
+    /// This method accepts a [`tv::File`] object, whose uri must have a `file`, `http`
+    /// or `https` scheme.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#file>
     ///
@@ -651,15 +1367,87 @@ impl StartedTestStep {
     /// # });
     /// ```
     pub async fn add_file_detail(&self, file: file::File) -> Result<(), tv::OcptvError> {
+        file.check_uri()?;
+
         self.step
             .emitter
-            .emit(&spec::TestStepArtifactImpl::File(file.to_artifact()))
+            .emit(spec::TestStepArtifactImpl::File(file.to_artifact()))
             .await?;
 
         Ok(())
     }
 
+    /// Emits a File message for a local file, eg. one the diagnostic just wrote.
+    /// The uri is the `file://` uri of the canonical path, the name is the file name and
+    /// the content type is inferred from the extension. The file is marked as a snapshot,
+    /// since its content may change after the run.
+    ///
+    /// With a resolver set by [`tv::ConfigBuilder::with_file_uri_resolver`], the uri is
+    /// the one returned by the resolver instead.
+    ///
+    /// Fails with [`tv::OcptvError::FileArtifact`] without emitting anything if the file
+    /// can't be read.
+    ///
+    /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#file>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// # let dir = assert_fs::TempDir::new().unwrap();
+    /// # let path = dir.path().join("dmesg.txt");
+    /// # std::fs::write(&path, "dmesg")?;
+    /// step.add_file_from_path(&path).await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_file_from_path<P: AsRef<Path>>(&self, path: P) -> Result<(), tv::OcptvError> {
+        let resolver = self.step.emitter.emitter.file_uri_resolver();
+        let file = file::File::from_path(path.as_ref(), false, resolver).await?;
+        self.add_file_detail(file).await
+    }
+
+    /// Emits a File message for a local file, like [`StartedTestStep::add_file_from_path`],
+    /// and also records the file size and sha256 digest in the `ocptv_file_size` and
+    /// `ocptv_sha256` metadata keys. This reads the whole file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # tokio_test::block_on(async {
+    /// # use ocptv::output::*;
+    /// let dut = DutInfo::new("my_dut");
+    /// let run = TestRun::new("diagnostic_name", "1.0").start(dut).await?;
+    ///
+    /// let step = run.add_step("step_name").start().await?;
+    /// # let dir = assert_fs::TempDir::new().unwrap();
+    /// # let path = dir.path().join("flamegraph.svg");
+    /// # std::fs::write(&path, "<svg/>")?;
+    /// step.add_file_from_path_with_digest(&path).await?;
+    /// step.end(TestStatus::Complete).await?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// # });
+    /// ```
+    pub async fn add_file_from_path_with_digest<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), tv::OcptvError> {
+        let resolver = self.step.emitter.emitter.file_uri_resolver();
+        let file = file::File::from_path(path.as_ref(), true, resolver).await?;
+        self.add_file_detail(file).await
+    }
+
     /// Emits an extension message;
+    /// The content can be any serializable value, from scalars to nested objects and arrays,
+    /// and fails with [`tv::OcptvError::Format`] if it can't be serialized.
     ///
     /// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#extension>
     ///
@@ -690,12 +1478,15 @@ impl StartedTestStep {
             content: serde_json::to_value(&any).map_err(|e| OcptvError::Format(Box::new(e)))?,
         });
 
-        self.step.emitter.emit(&ext).await?;
+        self.step.emitter.emit(ext).await?;
         Ok(())
     }
 }
 
-/// TODO: docs
+/// Handle emitting artifacts in a started step, which can't end the step. It is given to
+/// the closure of [`TestStep::scope`], and returned by [`StartedTestStep::handle`].
+/// Clones emit in the same step.
+#[derive(Clone)]
 pub struct ScopedTestStep {
     step: Arc<StartedTestStep>,
 }
@@ -703,17 +1494,116 @@ pub struct ScopedTestStep {
 impl ScopedTestStep {
     delegate! {
         to self.step {
+            pub fn id(&self) -> &str;
+            pub fn name(&self) -> &str;
+            pub fn dut_id(&self) -> &str;
+            pub fn group(&self) -> Option<&str>;
+
+            pub async fn with_retries<F, R, T, E>(&self, attempts: u32, backoff: std::time::Duration, func: F) -> Result<T, tv::OcptvError>
+            where
+                F: FnMut() -> R,
+                R: Future<Output = Result<T, E>>,
+                E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+            pub async fn add_log(&self, severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError>;
+            pub async fn log_debug(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_info(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_warning(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_error(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_fatal(&self, msg: &str) -> Result<(), tv::OcptvError>;
+
+            pub async fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError>;
+            pub async fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError>;
+
+            pub async fn add_measurement<V: Into<tv::MeasurementValue>>(&self, name: &str, value: V) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_with_unit<V: Into<tv::MeasurementValue>, U: Into<tv::Unit>>(&self, name: &str, value: V, unit: U) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_checked<V: Into<tv::MeasurementValue>>(&self, name: &str, value: V, validator: &measure::Validator) -> Result<bool, tv::OcptvError>;
+            pub async fn add_measurement_detail(&self, detail: measure::Measurement) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurements<I: IntoIterator<Item = measure::Measurement>>(&self, measurements: I) -> Result<(), tv::OcptvError>;
+
+            pub fn add_measurement_series(&self, name: &str) -> tv::MeasurementSeries;
+            pub fn add_measurement_series_with_unit<U: Into<tv::Unit>>(&self, name: &str, unit: U) -> tv::MeasurementSeries;
+            pub fn add_measurement_series_detail(
+                &self,
+                detail: measure::MeasurementSeriesDetail,
+            ) -> tv::MeasurementSeries;
+
+            pub async fn add_diagnosis(
+                &self,
+                verdict: &str,
+                diagnosis_type: spec::DiagnosisType,
+            ) -> Result<(), tv::OcptvError>;
+            pub async fn add_diagnosis_detail(&self, diagnosis: diagnosis::Diagnosis) -> Result<(), tv::OcptvError>;
+
+            pub async fn add_file(&self, name: &str, uri: tv::Uri) -> Result<(), tv::OcptvError>;
+            pub async fn add_file_detail(&self, file: file::File) -> Result<(), tv::OcptvError>;
+            pub async fn add_file_from_path<P: AsRef<Path>>(&self, path: P) -> Result<(), tv::OcptvError>;
+            pub async fn add_file_from_path_with_digest<P: AsRef<Path>>(&self, path: P) -> Result<(), tv::OcptvError>;
+
+            pub async fn add_extension<S: serde::Serialize>(&self, name: &str, any: S) -> Result<(), tv::OcptvError>;
+        }
+    }
+}
+
+/// Cloneable handle emitting artifacts in a started step, from any task.
+/// See [`StartedTestStep::handle`].
+pub type StepHandle = ScopedTestStep;
+
+/// Guard over a [`StartedTestStep`] that ends the step with a default status when
+/// dropped. See [`StartedTestStep::into_guard`].
+pub struct TestStepGuard {
+    // note: only taken when the step is explicitly ended
+    step: Option<StartedTestStep>,
+    status: tv::TestStatus,
+}
+
+impl TestStepGuard {
+    fn step(&self) -> &StartedTestStep {
+        self.step.as_ref().expect("step is only taken by end")
+    }
+
+    /// Ends the test step and disarms the guard.
+    /// See [`StartedTestStep::end`] for details.
+    pub async fn end(mut self, status: tv::TestStatus) -> Result<(), tv::OcptvError> {
+        let step = self.step.take().expect("step is only taken by end");
+        step.end(status).await
+    }
+
+    delegate! {
+        to self.step() {
+            pub fn id(&self) -> &str;
+            pub fn name(&self) -> &str;
+            pub fn dut_id(&self) -> &str;
+            pub fn group(&self) -> Option<&str>;
+
+            pub async fn with_retries<F, R, T, E>(&self, attempts: u32, backoff: std::time::Duration, func: F) -> Result<T, tv::OcptvError>
+            where
+                F: FnMut() -> R,
+                R: Future<Output = Result<T, E>>,
+                E: Into<Box<dyn std::error::Error + Send + Sync + 'static>>;
+
             pub async fn add_log(&self, severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError>;
             pub async fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError>;
+            pub async fn log_debug(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_info(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_warning(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_error(&self, msg: &str) -> Result<(), tv::OcptvError>;
+            pub async fn log_fatal(&self, msg: &str) -> Result<(), tv::OcptvError>;
 
             pub async fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError>;
             pub async fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError>;
             pub async fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError>;
 
-            pub async fn add_measurement<V: Into<tv::Value>>(&self, name: &str, value: V) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement<V: Into<tv::MeasurementValue>>(&self, name: &str, value: V) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_with_unit<V: Into<tv::MeasurementValue>, U: Into<tv::Unit>>(&self, name: &str, value: V, unit: U) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurement_checked<V: Into<tv::MeasurementValue>>(&self, name: &str, value: V, validator: &measure::Validator) -> Result<bool, tv::OcptvError>;
             pub async fn add_measurement_detail(&self, detail: measure::Measurement) -> Result<(), tv::OcptvError>;
+            pub async fn add_measurements<I: IntoIterator<Item = measure::Measurement>>(&self, measurements: I) -> Result<(), tv::OcptvError>;
 
             pub fn add_measurement_series(&self, name: &str) -> tv::MeasurementSeries;
+            pub fn add_measurement_series_with_unit<U: Into<tv::Unit>>(&self, name: &str, unit: U) -> tv::MeasurementSeries;
             pub fn add_measurement_series_detail(
                 &self,
                 detail: measure::MeasurementSeriesDetail,
@@ -728,31 +1618,171 @@ impl ScopedTestStep {
 
             pub async fn add_file(&self, name: &str, uri: tv::Uri) -> Result<(), tv::OcptvError>;
             pub async fn add_file_detail(&self, file: file::File) -> Result<(), tv::OcptvError>;
+            pub async fn add_file_from_path<P: AsRef<Path>>(&self, path: P) -> Result<(), tv::OcptvError>;
+            pub async fn add_file_from_path_with_digest<P: AsRef<Path>>(&self, path: P) -> Result<(), tv::OcptvError>;
 
             pub async fn add_extension<S: serde::Serialize>(&self, name: &str, any: S) -> Result<(), tv::OcptvError>;
         }
     }
 }
 
+impl Drop for TestStepGuard {
+    fn drop(&mut self) {
+        if let Some(step) = self.step.take() {
+            let _ = emitter::block_on_in_drop(step.end_impl(self.status.clone()));
+        }
+    }
+}
+
+/// Counts of the emitted artifacts that decide the automatic end status of a step
+/// and the automatic result of a run.
+#[derive(Debug, Default)]
+pub(crate) struct Outcome {
+    errors: atomic::AtomicU64,
+    failed_diagnoses: atomic::AtomicU64,
+}
+
+impl Outcome {
+    pub(crate) fn add_error(&self) {
+        self.errors.fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn add_failed_diagnosis(&self) {
+        self.failed_diagnoses.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub(crate) fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn failed_diagnoses(&self) -> u64 {
+        self.failed_diagnoses.load(Ordering::Acquire)
+    }
+}
+
 pub struct StepEmitter {
     step_id: String,
     // root emitter
     emitter: Arc<emitter::JsonEmitter>,
+
+    run_ended: Arc<atomic::AtomicBool>,
+    // artifacts deciding the automatic end status of this step, and of the run
+    outcome: Outcome,
+    run_outcome: Arc<Outcome>,
+    started: atomic::AtomicBool,
+    ended: atomic::AtomicBool,
 }
 
 impl StepEmitter {
-    pub async fn emit(&self, object: &spec::TestStepArtifactImpl) -> Result<(), io::Error> {
-        let root = spec::RootImpl::TestStepArtifact(spec::TestStepArtifact {
+    pub async fn emit(&self, object: spec::TestStepArtifactImpl) -> Result<(), tv::OcptvError> {
+        let is_error = matches!(object, spec::TestStepArtifactImpl::Error(_));
+        let is_failure = matches!(
+            &object,
+            spec::TestStepArtifactImpl::Diagnosis(d) if d.diagnosis_type == spec::DiagnosisType::Fail
+        );
+        let (is_start, is_end) = Self::lifecycle([&object]);
+        let root = self.root(object);
+        self.emitter
+            .emit_if(
+                [root],
+                || self.check_open(),
+                || self.commit(is_start, is_end),
+            )
+            .await?;
+
+        for outcome in [&self.outcome, &*self.run_outcome] {
+            if is_error {
+                outcome.add_error();
+            }
+            if is_failure {
+                outcome.add_failed_diagnosis();
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the artifacts as one batch, see [`emitter::JsonEmitter::emit_batch_if`].
+    /// Only meant for the artifacts emitted while the step is open, possibly ending
+    /// with its `testStepEnd`.
+    pub async fn emit_batch<I>(&self, objects: I) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = spec::TestStepArtifactImpl>,
+    {
+        let objects = objects.into_iter().collect::<Vec<_>>();
+        let (is_start, is_end) = Self::lifecycle(&objects);
+        let roots = objects.into_iter().map(|object| self.root(object));
+        self.emitter
+            .emit_batch_if(
+                roots,
+                || self.check_open(),
+                || self.commit(is_start, is_end),
+            )
+            .await
+    }
+
+    fn root(&self, artifact: spec::TestStepArtifactImpl) -> spec::RootImpl {
+        spec::RootImpl::TestStepArtifact(spec::TestStepArtifact {
             id: self.step_id.clone(),
-            // TODO: can these copies be avoided?
-            artifact: object.clone(),
-        });
-        self.emitter.emit(&root).await?;
+            artifact,
+        })
+    }
 
+    // returns whether the artifacts contain the step start and end
+    fn lifecycle<'a, I>(objects: I) -> (bool, bool)
+    where
+        I: IntoIterator<Item = &'a spec::TestStepArtifactImpl>,
+    {
+        objects
+            .into_iter()
+            .fold((false, false), |(start, end), object| match object {
+                spec::TestStepArtifactImpl::TestStepStart(_) => (true, end),
+                spec::TestStepArtifactImpl::TestStepEnd(_) => (start, true),
+                _ => (start, end),
+            })
+    }
+
+    // note: called while holding the emit lock, so that no artifact of this step can be
+    // written after its end or the run end; this also covers the measurement series,
+    // which emit through the step
+    fn check_open(&self) -> Result<(), tv::OcptvError> {
+        if self.run_ended.load(Ordering::Acquire) {
+            return Err(OcptvError::RunAlreadyEnded);
+        }
+        if self.ended.load(Ordering::Acquire) {
+            return Err(OcptvError::StepAlreadyEnded(self.step_id.clone()));
+        }
         Ok(())
     }
 
+    // note: called while holding the emit lock, only once the artifacts were written, so
+    // that an end failing to write can be retried
+    fn commit(&self, is_start: bool, is_end: bool) {
+        if is_start {
+            self.started.store(true, Ordering::Release);
+        }
+        if is_end {
+            self.ended.store(true, Ordering::Release);
+        }
+    }
+
+    pub(crate) fn step_id(&self) -> &str {
+        &self.step_id
+    }
+
+    /// Returns whether the step was started but not ended yet.
+    pub(crate) fn is_open(&self) -> bool {
+        self.started.load(Ordering::Acquire) && !self.ended.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn is_ended(&self) -> bool {
+        self.ended.load(Ordering::Acquire)
+    }
+
     pub fn timestamp_provider(&self) -> &(dyn config::TimestampProvider + Send + Sync + 'static) {
         self.emitter.timestamp_provider()
     }
+
+    pub(crate) fn non_finite_policy(&self) -> config::NonFinitePolicy {
+        self.emitter.non_finite_policy()
+    }
 }
@@ -0,0 +1,617 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Blocking facade over the async output api.
+//!
+//! Each object in this module wraps its async counterpart and drives it to completion
+//! on a lightweight current-thread tokio runtime, owned by the [`TestRun`] and shared
+//! with all of its descendants. Since the same emitter is used underneath, the output
+//! is byte-identical to the async api for the same sequence of calls.
+//!
+//! Note: these methods must not be called from inside an async context, as blocking
+//! on a nested runtime panics.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use ocptv::output::*;
+//! use ocptv::output::sync;
+//!
+//! let dut = DutInfo::new("my_dut");
+//! let run = sync::TestRun::new("diagnostic_name", "1.0")?.start(dut)?;
+//!
+//! let step = run.add_step("step_name").start()?;
+//! step.add_measurement("fan_speed", 1200)?;
+//! step.end(TestStatus::Complete)?;
+//!
+//! run.end(TestStatus::Complete, TestResult::Pass)?;
+//!
+//! # Ok::<(), OcptvError>(())
+//! ```
+
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::runtime;
+
+use crate::output as tv;
+use crate::spec;
+use tv::{diagnosis, dut, error, file, log, measure};
+
+/// Blocking version of [`tv::TestRun`].
+pub struct TestRun {
+    run: tv::TestRun,
+    rt: Arc<runtime::Runtime>,
+}
+
+impl TestRun {
+    /// Creates a new blocking [`TestRun`] object with the default configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = sync::TestRun::new("diagnostic_name", "1.0")?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// ```
+    pub fn new(name: &str, version: &str) -> Result<TestRun, tv::OcptvError> {
+        Self::from_async(tv::TestRun::new(name, version))
+    }
+
+    /// Wraps an already built [`tv::TestRun`], eg. one made with [`tv::TestRunBuilder`].
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use ocptv::output::*;
+    /// let run = TestRun::builder("diagnostic_name", "1.0")
+    ///     .add_parameter("param1", "value1")
+    ///     .build();
+    /// let run = sync::TestRun::from_async(run)?;
+    ///
+    /// # Ok::<(), OcptvError>(())
+    /// ```
+    pub fn from_async(run: tv::TestRun) -> Result<TestRun, tv::OcptvError> {
+        let rt = runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+
+        Ok(TestRun {
+            run,
+            rt: Arc::new(rt),
+        })
+    }
+
+    /// Starts the test run.
+    ///
+    /// See: [`tv::TestRun::start`]
+    pub fn start<D: Into<dut::DutInfo>>(self, dut: D) -> Result<StartedTestRun, tv::OcptvError> {
+        let run = self.rt.block_on(self.run.start(dut))?;
+
+        Ok(StartedTestRun { run, rt: self.rt })
+    }
+
+    /// Emits the schemaVersion artifact.
+    ///
+    /// See: [`tv::TestRun::emit_schema_version`]
+    pub fn emit_schema_version(&self) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.emit_schema_version())
+    }
+
+    /// Emits a Error message before starting the test run.
+    ///
+    /// See: [`tv::TestRun::add_error`]
+    pub fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_error(symptom))
+    }
+
+    /// Emits a Error message before starting the test run.
+    ///
+    /// See: [`tv::TestRun::add_error_msg`]
+    pub fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_error_msg(symptom, msg))
+    }
+
+    /// Emits a Error message before starting the test run.
+    ///
+    /// See: [`tv::TestRun::add_error_detail`]
+    pub fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_error_detail(error))
+    }
+}
+
+/// Blocking version of [`tv::StartedTestRun`].
+pub struct StartedTestRun {
+    run: tv::StartedTestRun,
+    rt: Arc<runtime::Runtime>,
+}
+
+impl StartedTestRun {
+    /// Ends the test run.
+    ///
+    /// See: [`tv::StartedTestRun::end`]
+    pub fn end(
+        self,
+        status: spec::TestStatus,
+        result: spec::TestResult,
+    ) -> Result<tv::RunSummary, tv::OcptvError> {
+        self.rt.block_on(self.run.end(status, result))
+    }
+
+    /// Ends the test run, with a result derived from the emitted artifacts.
+    ///
+    /// See: [`tv::StartedTestRun::end_auto`]
+    pub fn end_auto(self) -> Result<tv::RunSummary, tv::OcptvError> {
+        self.rt.block_on(self.run.end_auto())
+    }
+
+    /// Returns the sequence number that will be assigned to the next emitted artifact.
+    ///
+    /// See: [`tv::StartedTestRun::sequence_number`]
+    pub fn sequence_number(&self) -> u64 {
+        self.run.sequence_number()
+    }
+
+    /// Returns statistics about the artifacts written so far by this run.
+    ///
+    /// See: [`tv::StartedTestRun::stats`]
+    pub fn stats(&self) -> tv::EmitterStats {
+        self.run.stats()
+    }
+
+    /// Emits a Log message.
+    ///
+    /// See: [`tv::StartedTestRun::add_log`]
+    pub fn add_log(&self, severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_log(severity, msg))
+    }
+
+    /// Emits a Log message.
+    ///
+    /// See: [`tv::StartedTestRun::add_log_detail`]
+    pub fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_log_detail(log))
+    }
+
+    /// Emits a Error message.
+    ///
+    /// See: [`tv::StartedTestRun::add_error`]
+    pub fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_error(symptom))
+    }
+
+    /// Emits a Error message.
+    ///
+    /// See: [`tv::StartedTestRun::add_error_msg`]
+    pub fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_error_msg(symptom, msg))
+    }
+
+    /// Emits a Error message.
+    ///
+    /// See: [`tv::StartedTestRun::add_error_detail`]
+    pub fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.run.add_error_detail(error))
+    }
+
+    /// Create a new step for this test run.
+    ///
+    /// See: [`tv::StartedTestRun::add_step`]
+    pub fn add_step(&self, name: &str) -> TestStep {
+        TestStep {
+            step: self.run.add_step(name),
+            rt: Arc::clone(&self.rt),
+        }
+    }
+
+    /// Create a new step for this test run, in a logical group of steps.
+    ///
+    /// See: [`tv::StartedTestRun::step_in_group`]
+    pub fn step_in_group(&self, group: &str, name: &str) -> TestStep {
+        TestStep {
+            step: self.run.step_in_group(group, name),
+            rt: Arc::clone(&self.rt),
+        }
+    }
+}
+
+/// Blocking version of [`tv::TestStep`].
+pub struct TestStep {
+    step: tv::TestStep,
+    rt: Arc<runtime::Runtime>,
+}
+
+impl TestStep {
+    /// Starts the test step.
+    ///
+    /// See: [`tv::TestStep::start`]
+    pub fn start(self) -> Result<StartedTestStep, tv::OcptvError> {
+        let step = self.rt.block_on(self.step.start())?;
+
+        Ok(StartedTestStep { step, rt: self.rt })
+    }
+
+    /// Starts the test step, and records its duration when it ends.
+    ///
+    /// See: [`tv::TestStep::start_timed`]
+    pub fn start_timed(self) -> Result<StartedTestStep, tv::OcptvError> {
+        let step = self.rt.block_on(self.step.start_timed())?;
+
+        Ok(StartedTestStep { step, rt: self.rt })
+    }
+}
+
+/// Blocking version of [`tv::StartedTestStep`].
+pub struct StartedTestStep {
+    step: tv::StartedTestStep,
+    rt: Arc<runtime::Runtime>,
+}
+
+impl StartedTestStep {
+    /// Ends the test step.
+    ///
+    /// See: [`tv::StartedTestStep::end`]
+    pub fn end(self, status: spec::TestStatus) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.end(status))
+    }
+
+    /// Ends the test step, with a status derived from the emitted artifacts.
+    ///
+    /// See: [`tv::StartedTestStep::end_auto`]
+    pub fn end_auto(self) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.end_auto())
+    }
+
+    /// Returns the step identifier.
+    ///
+    /// See: [`tv::StartedTestStep::id`]
+    pub fn id(&self) -> &str {
+        self.step.id()
+    }
+
+    /// Returns the step name.
+    ///
+    /// See: [`tv::StartedTestStep::name`]
+    pub fn name(&self) -> &str {
+        self.step.name()
+    }
+
+    /// Returns the identifier of the DUT the parent run was started with.
+    ///
+    /// See: [`tv::StartedTestStep::dut_id`]
+    pub fn dut_id(&self) -> &str {
+        self.step.dut_id()
+    }
+
+    /// Returns the group of the step, if it was created in one.
+    ///
+    /// See: [`tv::StartedTestStep::group`]
+    pub fn group(&self) -> Option<&str> {
+        self.step.group()
+    }
+
+    /// Emits a Log message.
+    ///
+    /// See: [`tv::StartedTestStep::add_log`]
+    pub fn add_log(&self, severity: spec::LogSeverity, msg: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_log(severity, msg))
+    }
+
+    /// Emits a Log message.
+    ///
+    /// See: [`tv::StartedTestStep::add_log_detail`]
+    pub fn add_log_detail(&self, log: log::Log) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_log_detail(log))
+    }
+
+    /// Emits an Error message.
+    ///
+    /// See: [`tv::StartedTestStep::add_error`]
+    pub fn add_error(&self, symptom: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_error(symptom))
+    }
+
+    /// Emits an Error message.
+    ///
+    /// See: [`tv::StartedTestStep::add_error_msg`]
+    pub fn add_error_msg(&self, symptom: &str, msg: &str) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_error_msg(symptom, msg))
+    }
+
+    /// Emits an Error message.
+    ///
+    /// See: [`tv::StartedTestStep::add_error_detail`]
+    pub fn add_error_detail(&self, error: error::Error) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_error_detail(error))
+    }
+
+    /// Emits a Measurement message.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement`]
+    pub fn add_measurement<V: Into<tv::MeasurementValue>>(
+        &self,
+        name: &str,
+        value: V,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_measurement(name, value))
+    }
+
+    /// Emits a Measurement message with a unit.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement_with_unit`]
+    pub fn add_measurement_with_unit<V: Into<tv::MeasurementValue>, U: Into<tv::Unit>>(
+        &self,
+        name: &str,
+        value: V,
+        unit: U,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt
+            .block_on(self.step.add_measurement_with_unit(name, value, unit))
+    }
+
+    /// Emits a Measurement message with a validator, and returns whether it passed.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement_checked`]
+    pub fn add_measurement_checked<V: Into<tv::MeasurementValue>>(
+        &self,
+        name: &str,
+        value: V,
+        validator: &measure::Validator,
+    ) -> Result<bool, tv::OcptvError> {
+        self.rt
+            .block_on(self.step.add_measurement_checked(name, value, validator))
+    }
+
+    /// Emits a Measurement message.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement_detail`]
+    pub fn add_measurement_detail(
+        &self,
+        detail: measure::Measurement,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_measurement_detail(detail))
+    }
+
+    /// Emits a batch of Measurement messages.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurements`]
+    pub fn add_measurements<I>(&self, measurements: I) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = measure::Measurement>,
+    {
+        self.rt.block_on(self.step.add_measurements(measurements))
+    }
+
+    /// Create a Measurement Series.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement_series`]
+    pub fn add_measurement_series(&self, name: &str) -> MeasurementSeries {
+        MeasurementSeries {
+            series: self.step.add_measurement_series(name),
+            rt: Arc::clone(&self.rt),
+        }
+    }
+
+    /// Create a Measurement Series with a unit.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement_series_with_unit`]
+    pub fn add_measurement_series_with_unit<U: Into<tv::Unit>>(
+        &self,
+        name: &str,
+        unit: U,
+    ) -> MeasurementSeries {
+        MeasurementSeries {
+            series: self.step.add_measurement_series_with_unit(name, unit),
+            rt: Arc::clone(&self.rt),
+        }
+    }
+
+    /// Create a Measurement Series.
+    ///
+    /// See: [`tv::StartedTestStep::add_measurement_series_detail`]
+    pub fn add_measurement_series_detail(
+        &self,
+        detail: measure::MeasurementSeriesDetail,
+    ) -> MeasurementSeries {
+        MeasurementSeries {
+            series: self.step.add_measurement_series_detail(detail),
+            rt: Arc::clone(&self.rt),
+        }
+    }
+
+    /// Emits a Diagnosis message.
+    ///
+    /// See: [`tv::StartedTestStep::add_diagnosis`]
+    pub fn add_diagnosis(
+        &self,
+        verdict: &str,
+        diagnosis_type: spec::DiagnosisType,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt
+            .block_on(self.step.add_diagnosis(verdict, diagnosis_type))
+    }
+
+    /// Emits a Diagnosis message.
+    ///
+    /// See: [`tv::StartedTestStep::add_diagnosis_detail`]
+    pub fn add_diagnosis_detail(
+        &self,
+        diagnosis: diagnosis::Diagnosis,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_diagnosis_detail(diagnosis))
+    }
+
+    /// Emits a File message.
+    ///
+    /// See: [`tv::StartedTestStep::add_file`]
+    pub fn add_file(&self, name: &str, uri: tv::Uri) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_file(name, uri))
+    }
+
+    /// Emits a File message.
+    ///
+    /// See: [`tv::StartedTestStep::add_file_detail`]
+    pub fn add_file_detail(&self, file: file::File) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_file_detail(file))
+    }
+
+    /// Emits a File message for a local file.
+    ///
+    /// See: [`tv::StartedTestStep::add_file_from_path`]
+    pub fn add_file_from_path<P: AsRef<Path>>(&self, path: P) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_file_from_path(path))
+    }
+
+    /// Emits a File message for a local file, with its size and sha256 digest.
+    ///
+    /// See: [`tv::StartedTestStep::add_file_from_path_with_digest`]
+    pub fn add_file_from_path_with_digest<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt
+            .block_on(self.step.add_file_from_path_with_digest(path))
+    }
+
+    /// Emits an extension message.
+    ///
+    /// See: [`tv::StartedTestStep::add_extension`]
+    pub fn add_extension<S: serde::Serialize>(
+        &self,
+        name: &str,
+        any: S,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.step.add_extension(name, any))
+    }
+}
+
+/// Blocking version of [`tv::MeasurementSeries`].
+pub struct MeasurementSeries {
+    series: tv::MeasurementSeries,
+    rt: Arc<runtime::Runtime>,
+}
+
+impl MeasurementSeries {
+    /// Starts the measurement series.
+    ///
+    /// See: [`tv::MeasurementSeries::start`]
+    pub fn start(self) -> Result<StartedMeasurementSeries, tv::OcptvError> {
+        let series = self.rt.block_on(self.series.start())?;
+
+        Ok(StartedMeasurementSeries {
+            series,
+            rt: self.rt,
+        })
+    }
+}
+
+/// Blocking version of [`tv::StartedMeasurementSeries`].
+pub struct StartedMeasurementSeries {
+    series: tv::StartedMeasurementSeries,
+    rt: Arc<runtime::Runtime>,
+}
+
+impl StartedMeasurementSeries {
+    /// Ends the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::end`]
+    pub fn end(self) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.series.end())
+    }
+
+    /// Ends the measurement series, after emitting summary statistics of its elements.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::end_with_stats`]
+    pub fn end_with_stats(self) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.series.end_with_stats())
+    }
+
+    /// Returns the number of elements that failed the enforced series validators.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::violations`]
+    pub fn violations(&self) -> u64 {
+        self.series.violations()
+    }
+
+    /// Adds a measurement element to the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::add_measurement`]
+    pub fn add_measurement<V: Into<tv::MeasurementValue>>(
+        &self,
+        value: V,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt.block_on(self.series.add_measurement(value))
+    }
+
+    /// Adds a measurement element captured at the given time to the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::add_measurement_at`]
+    pub fn add_measurement_at<V: Into<tv::MeasurementValue>>(
+        &self,
+        timestamp: chrono::DateTime<chrono_tz::Tz>,
+        value: V,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt
+            .block_on(self.series.add_measurement_at(timestamp, value))
+    }
+
+    /// Adds a measurement element to the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::add_measurement_detail`]
+    pub fn add_measurement_detail(
+        &self,
+        element: measure::MeasurementElementDetail,
+    ) -> Result<(), tv::OcptvError> {
+        self.rt
+            .block_on(self.series.add_measurement_detail(element))
+    }
+
+    /// Adds a batch of measurement elements to the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::add_measurements`]
+    pub fn add_measurements<V, I>(&self, values: I) -> Result<(), tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        I: IntoIterator<Item = V>,
+    {
+        self.rt.block_on(self.series.add_measurements(values))
+    }
+
+    /// Adds a batch of measurement elements to the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::add_measurement_details`]
+    pub fn add_measurement_details<I>(&self, elements: I) -> Result<(), tv::OcptvError>
+    where
+        I: IntoIterator<Item = measure::MeasurementElementDetail>,
+    {
+        self.rt
+            .block_on(self.series.add_measurement_details(elements))
+    }
+
+    /// Drains the stream into elements of the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::extend_from_stream`]
+    pub fn extend_from_stream<V, S>(&self, stream: S) -> Result<u64, tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        S: futures::Stream<Item = V>,
+    {
+        self.rt.block_on(self.series.extend_from_stream(stream))
+    }
+
+    /// Drains the stream of timestamped values into elements of the measurement series.
+    ///
+    /// See: [`tv::StartedMeasurementSeries::extend_from_timestamped_stream`]
+    pub fn extend_from_timestamped_stream<V, S>(&self, stream: S) -> Result<u64, tv::OcptvError>
+    where
+        V: Into<tv::MeasurementValue>,
+        S: futures::Stream<Item = (chrono::DateTime<chrono_tz::Tz>, V)>,
+    {
+        self.rt
+            .block_on(self.series.extend_from_timestamped_stream(stream))
+    }
+}
@@ -0,0 +1,139 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// The unit of a measurement or measurement series.
+///
+/// Units are free-form strings in the spec; the associated constants spell the common
+/// ones the same way everywhere, so that eg. temperatures aren't reported as "C",
+/// "celsius" and "degC" by different diagnostics. Custom units are built from strings.
+///
+/// # Examples
+///
+/// ```rust
+/// # use ocptv::output::*;
+/// let measurement = Measurement::builder("fan_speed", 8000).unit(Unit::RPM).build();
+/// let measurement = Measurement::builder("airflow", 35).unit("CFM").build();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Unit(Cow<'static, str>);
+
+impl Unit {
+    pub const CELSIUS: Unit = Unit::from_static("C");
+    pub const VOLT: Unit = Unit::from_static("V");
+    pub const MILLIVOLT: Unit = Unit::from_static("mV");
+    pub const AMPERE: Unit = Unit::from_static("A");
+    pub const MILLIAMPERE: Unit = Unit::from_static("mA");
+    pub const WATT: Unit = Unit::from_static("W");
+    pub const JOULE: Unit = Unit::from_static("J");
+    pub const HERTZ: Unit = Unit::from_static("Hz");
+    pub const MEGAHERTZ: Unit = Unit::from_static("MHz");
+    pub const RPM: Unit = Unit::from_static("RPM");
+    pub const SECOND: Unit = Unit::from_static("s");
+    pub const MILLISECOND: Unit = Unit::from_static("ms");
+    pub const MICROSECOND: Unit = Unit::from_static("us");
+    pub const BYTE: Unit = Unit::from_static("B");
+    pub const KIBIBYTE: Unit = Unit::from_static("KiB");
+    pub const MEBIBYTE: Unit = Unit::from_static("MiB");
+    pub const GIBIBYTE: Unit = Unit::from_static("GiB");
+    pub const BYTES_PER_SECOND: Unit = Unit::from_static("B/s");
+    pub const PERCENT: Unit = Unit::from_static("%");
+
+    const fn from_static(unit: &'static str) -> Self {
+        Unit(Cow::Borrowed(unit))
+    }
+
+    /// Returns the unit as written in the output.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<&str> for Unit {
+    fn from(unit: &str) -> Self {
+        Unit(Cow::Owned(unit.to_owned()))
+    }
+}
+
+impl From<String> for Unit {
+    fn from(unit: String) -> Self {
+        Unit(Cow::Owned(unit))
+    }
+}
+
+impl From<&Unit> for Unit {
+    fn from(unit: &Unit) -> Self {
+        unit.clone()
+    }
+}
+
+impl From<Unit> for String {
+    fn from(unit: Unit) -> Self {
+        unit.0.into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output as tv;
+
+    use anyhow::Result;
+
+    #[test]
+    fn test_unit_constants_serialized_form() -> Result<()> {
+        let cases = [
+            (Unit::CELSIUS, "C"),
+            (Unit::VOLT, "V"),
+            (Unit::MILLIVOLT, "mV"),
+            (Unit::AMPERE, "A"),
+            (Unit::MILLIAMPERE, "mA"),
+            (Unit::WATT, "W"),
+            (Unit::JOULE, "J"),
+            (Unit::HERTZ, "Hz"),
+            (Unit::MEGAHERTZ, "MHz"),
+            (Unit::RPM, "RPM"),
+            (Unit::SECOND, "s"),
+            (Unit::MILLISECOND, "ms"),
+            (Unit::MICROSECOND, "us"),
+            (Unit::BYTE, "B"),
+            (Unit::KIBIBYTE, "KiB"),
+            (Unit::MEBIBYTE, "MiB"),
+            (Unit::GIBIBYTE, "GiB"),
+            (Unit::BYTES_PER_SECOND, "B/s"),
+            (Unit::PERCENT, "%"),
+        ];
+
+        for (unit, expected) in cases {
+            let artifact = tv::Measurement::builder("name", 1)
+                .unit(unit)
+                .build()
+                .to_artifact();
+            assert_eq!(
+                serde_json::to_value(&artifact)?["unit"],
+                serde_json::json!(expected)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_custom_unit() {
+        assert_eq!(Unit::from("CFM").as_str(), "CFM");
+        assert_eq!(Unit::from("C".to_owned()), Unit::CELSIUS);
+        assert_eq!(String::from(Unit::WATT), "W");
+        assert_eq!(Unit::PERCENT.to_string(), "%");
+    }
+}
@@ -0,0 +1,402 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::collections::BTreeMap;
+
+use crate::output as tv;
+use tv::NonFinitePolicy;
+
+/// Metadata key marking the artifacts with a NaN or infinite value encoded as a string,
+/// see [`NonFinitePolicy::Encode`].
+pub(crate) const NON_FINITE_METADATA: &str = "ocptv_non_finite";
+
+/// The value of a measurement or of a measurement series element.
+///
+/// The spec only allows scalar values there, so unlike [`tv::Value`] this can't hold
+/// objects or arrays. Values are usually built from the common Rust types, through the
+/// `From` impls, and serialize to the same json as the equivalent [`tv::Value`].
+///
+/// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#measurement>
+///
+/// # Examples
+///
+/// ```rust
+/// # use ocptv::output::*;
+/// let value = MeasurementValue::from(8000);
+/// assert_eq!(value, MeasurementValue::Int(8000));
+///
+/// // json values are checked to be scalars
+/// let value = MeasurementValue::try_from(serde_json::json!({"rpm": 8000}));
+/// assert!(value.is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeasurementValue {
+    Number(f64),
+    Int(i64),
+    Bool(bool),
+    Str(String),
+    /// Unchecked json value, see [`MeasurementValue::raw_unchecked`].
+    Raw(tv::Value),
+}
+
+impl MeasurementValue {
+    /// Wraps a json value as is, without checking that it's a scalar. Emitting an object
+    /// or array this way produces output that doesn't validate against the spec schema.
+    /// Prefer the checked `TryFrom<Value>` conversion.
+    pub fn raw_unchecked(value: tv::Value) -> Self {
+        MeasurementValue::Raw(value)
+    }
+
+    /// Converts to the json value to emit, following the policy for NaN and infinite
+    /// numbers. Returns whether such a number was encoded as a string.
+    pub(crate) fn to_spec(
+        &self,
+        policy: NonFinitePolicy,
+    ) -> Result<(tv::Value, bool), MeasurementValueError> {
+        let MeasurementValue::Number(n) = self else {
+            return Ok((self.clone().into(), false));
+        };
+        if n.is_finite() {
+            return Ok((tv::Value::from(*n), false));
+        }
+
+        match policy {
+            NonFinitePolicy::Reject => Err(MeasurementValueError::NonFinite(*n)),
+            NonFinitePolicy::Encode => {
+                let encoded = match n {
+                    n if n.is_nan() => "NaN",
+                    n if n.is_sign_positive() => "Inf",
+                    _ => "-Inf",
+                };
+                Ok((tv::Value::from(encoded), true))
+            }
+        }
+    }
+
+    /// Returns the value as a float, if it's a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            MeasurementValue::Number(n) => Some(*n),
+            MeasurementValue::Int(n) => Some(*n as f64),
+            MeasurementValue::Raw(value) => value.as_f64(),
+            _ => None,
+        }
+    }
+}
+
+impl Default for MeasurementValue {
+    // note: null, like the default json value that used to hold measurement values
+    fn default() -> Self {
+        MeasurementValue::Raw(tv::Value::Null)
+    }
+}
+
+impl From<MeasurementValue> for tv::Value {
+    fn from(value: MeasurementValue) -> Self {
+        match value {
+            // note: non finite floats become null, as with `tv::Value::from(f64)`
+            MeasurementValue::Number(n) => tv::Value::from(n),
+            MeasurementValue::Int(n) => tv::Value::from(n),
+            MeasurementValue::Bool(b) => tv::Value::from(b),
+            MeasurementValue::Str(s) => tv::Value::from(s),
+            MeasurementValue::Raw(value) => value,
+        }
+    }
+}
+
+macro_rules! impl_from {
+    ($variant:ident, $inner:ty, $($ty:ty),+) => {
+        $(
+            impl From<$ty> for MeasurementValue {
+                fn from(value: $ty) -> Self {
+                    MeasurementValue::$variant(<$inner>::from(value))
+                }
+            }
+        )+
+    };
+}
+
+impl_from!(Int, i64, i8, i16, i32, i64, u8, u16, u32);
+impl_from!(Number, f64, f32, f64);
+impl_from!(Bool, bool, bool);
+impl_from!(Str, String, &str, String, &String, char);
+
+impl From<isize> for MeasurementValue {
+    fn from(value: isize) -> Self {
+        // note: isize is at most 64 bits on all the supported targets
+        MeasurementValue::Int(value as i64)
+    }
+}
+
+impl From<u64> for MeasurementValue {
+    fn from(value: u64) -> Self {
+        // note: keep the values over i64::MAX exact, as json numbers
+        i64::try_from(value)
+            .map(MeasurementValue::Int)
+            .unwrap_or_else(|_| MeasurementValue::Raw(tv::Value::from(value)))
+    }
+}
+
+impl From<usize> for MeasurementValue {
+    fn from(value: usize) -> Self {
+        MeasurementValue::from(value as u64)
+    }
+}
+
+impl TryFrom<tv::Value> for MeasurementValue {
+    type Error = MeasurementValueError;
+
+    fn try_from(value: tv::Value) -> Result<Self, Self::Error> {
+        match value {
+            tv::Value::Bool(b) => Ok(MeasurementValue::Bool(b)),
+            tv::Value::String(s) => Ok(MeasurementValue::Str(s)),
+            tv::Value::Number(n) => Ok(match n.as_i64() {
+                Some(n) => MeasurementValue::Int(n),
+                // note: keep u64 and floats exactly as they were parsed
+                None => MeasurementValue::Raw(tv::Value::Number(n)),
+            }),
+            _ => Err(MeasurementValueError::NotAScalar(value)),
+        }
+    }
+}
+
+/// The value of a [`tv::Validator`]: a scalar, or a list of scalars for the set and
+/// regex validators.
+///
+/// ref: <https://github.com/opencomputeproject/ocp-diag-core/tree/main/json_spec#validator>
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidatorValue {
+    Scalar(MeasurementValue),
+    List(Vec<MeasurementValue>),
+}
+
+impl ValidatorValue {
+    /// Converts to the json value to emit, see [`MeasurementValue::to_spec`].
+    pub(crate) fn to_spec(
+        &self,
+        policy: NonFinitePolicy,
+    ) -> Result<(tv::Value, bool), MeasurementValueError> {
+        match self {
+            ValidatorValue::Scalar(value) => value.to_spec(policy),
+            ValidatorValue::List(values) => {
+                let mut encoded = false;
+                let items = values
+                    .iter()
+                    .map(|value| {
+                        let (item, item_encoded) = value.to_spec(policy)?;
+                        encoded |= item_encoded;
+                        Ok(item)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok((tv::Value::Array(items), encoded))
+            }
+        }
+    }
+}
+
+/// Returns the metadata of an artifact, with the non finite marker if its value was encoded.
+pub(crate) fn mark_non_finite(
+    metadata: &BTreeMap<String, tv::Value>,
+    encoded: bool,
+) -> Option<BTreeMap<String, tv::Value>> {
+    let mut metadata = metadata.clone();
+    if encoded {
+        metadata.insert(NON_FINITE_METADATA.to_owned(), true.into());
+    }
+    (!metadata.is_empty()).then_some(metadata)
+}
+
+impl From<ValidatorValue> for tv::Value {
+    fn from(value: ValidatorValue) -> Self {
+        match value {
+            ValidatorValue::Scalar(value) => value.into(),
+            ValidatorValue::List(values) => {
+                tv::Value::Array(values.into_iter().map(tv::Value::from).collect())
+            }
+        }
+    }
+}
+
+impl From<MeasurementValue> for ValidatorValue {
+    fn from(value: MeasurementValue) -> Self {
+        ValidatorValue::Scalar(value)
+    }
+}
+
+macro_rules! impl_validator_from {
+    ($($ty:ty),+) => {
+        $(
+            impl From<$ty> for ValidatorValue {
+                fn from(value: $ty) -> Self {
+                    ValidatorValue::Scalar(value.into())
+                }
+            }
+        )+
+    };
+}
+
+impl_validator_from!(
+    i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64, bool, &str, String, &String, char
+);
+
+impl<T: Into<MeasurementValue>> From<Vec<T>> for ValidatorValue {
+    fn from(values: Vec<T>) -> Self {
+        ValidatorValue::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<MeasurementValue>, const N: usize> From<[T; N]> for ValidatorValue {
+    fn from(values: [T; N]) -> Self {
+        ValidatorValue::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl TryFrom<tv::Value> for ValidatorValue {
+    type Error = MeasurementValueError;
+
+    fn try_from(value: tv::Value) -> Result<Self, Self::Error> {
+        match value {
+            tv::Value::Array(items) => items
+                .into_iter()
+                .map(MeasurementValue::try_from)
+                .collect::<Result<_, _>>()
+                .map(ValidatorValue::List),
+            value => MeasurementValue::try_from(value).map(ValidatorValue::Scalar),
+        }
+    }
+}
+
+/// Reason why a json value can't be used as a [`MeasurementValue`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum MeasurementValueError {
+    #[error("measurement values must be numbers, strings or booleans, got {0}")]
+    NotAScalar(tv::Value),
+
+    #[error("measurement values must be finite numbers, got {0}")]
+    NonFinite(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn test_from_integers() {
+        assert_eq!(MeasurementValue::from(-8i8), MeasurementValue::Int(-8));
+        assert_eq!(MeasurementValue::from(-16i16), MeasurementValue::Int(-16));
+        assert_eq!(MeasurementValue::from(-32i32), MeasurementValue::Int(-32));
+        assert_eq!(MeasurementValue::from(-64i64), MeasurementValue::Int(-64));
+        assert_eq!(MeasurementValue::from(-1isize), MeasurementValue::Int(-1));
+        assert_eq!(MeasurementValue::from(8u8), MeasurementValue::Int(8));
+        assert_eq!(MeasurementValue::from(16u16), MeasurementValue::Int(16));
+        assert_eq!(MeasurementValue::from(32u32), MeasurementValue::Int(32));
+        assert_eq!(MeasurementValue::from(64u64), MeasurementValue::Int(64));
+        assert_eq!(MeasurementValue::from(1usize), MeasurementValue::Int(1));
+    }
+
+    #[test]
+    fn test_from_large_unsigned_stays_exact() {
+        let value = tv::Value::from(MeasurementValue::from(u64::MAX));
+        assert_eq!(value, json!(u64::MAX));
+    }
+
+    #[test]
+    fn test_from_floats() {
+        assert_eq!(
+            MeasurementValue::from(0.5f32),
+            MeasurementValue::Number(0.5)
+        );
+        assert_eq!(
+            MeasurementValue::from(72.5f64),
+            MeasurementValue::Number(72.5)
+        );
+    }
+
+    #[test]
+    fn test_from_bool_and_strings() {
+        assert_eq!(MeasurementValue::from(true), MeasurementValue::Bool(true));
+        assert_eq!(
+            MeasurementValue::from("ok"),
+            MeasurementValue::Str("ok".to_owned())
+        );
+        assert_eq!(
+            MeasurementValue::from("ok".to_owned()),
+            MeasurementValue::Str("ok".to_owned())
+        );
+        assert_eq!(
+            MeasurementValue::from(&"ok".to_owned()),
+            MeasurementValue::Str("ok".to_owned())
+        );
+        assert_eq!(
+            MeasurementValue::from('x'),
+            MeasurementValue::Str("x".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_serializes_as_json_value() {
+        assert_eq!(tv::Value::from(MeasurementValue::from(50)), json!(50));
+        assert_eq!(tv::Value::from(MeasurementValue::from(50.5)), json!(50.5));
+        assert_eq!(tv::Value::from(MeasurementValue::from(false)), json!(false));
+        assert_eq!(tv::Value::from(MeasurementValue::from("on")), json!("on"));
+        assert_eq!(
+            tv::Value::from(MeasurementValue::from(f64::NAN)),
+            json!(null)
+        );
+    }
+
+    #[test]
+    fn test_try_from_json_scalars() {
+        assert_eq!(
+            MeasurementValue::try_from(json!(50)),
+            Ok(MeasurementValue::Int(50))
+        );
+        assert_eq!(
+            MeasurementValue::try_from(json!("on")),
+            Ok(MeasurementValue::Str("on".to_owned()))
+        );
+        assert_eq!(
+            tv::Value::from(MeasurementValue::try_from(json!(0.1)).unwrap()),
+            json!(0.1)
+        );
+    }
+
+    #[test]
+    fn test_try_from_rejects_non_scalars() {
+        for value in [json!({"rpm": 8000}), json!([1, 2]), json!(null)] {
+            assert_eq!(
+                MeasurementValue::try_from(value.clone()),
+                Err(MeasurementValueError::NotAScalar(value))
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_unchecked_is_kept_as_is() {
+        let value = json!({"rpm": 8000});
+        assert_eq!(
+            tv::Value::from(MeasurementValue::raw_unchecked(value.clone())),
+            value
+        );
+    }
+
+    #[test]
+    fn test_validator_value() {
+        assert_eq!(tv::Value::from(ValidatorValue::from(30)), json!(30));
+        assert_eq!(
+            tv::Value::from(ValidatorValue::from(vec![1000, 2000])),
+            json!([1000, 2000])
+        );
+        assert_eq!(
+            tv::Value::from(ValidatorValue::from(["a", "b"])),
+            json!(["a", "b"])
+        );
+        assert!(ValidatorValue::try_from(json!([1, {"a": 1}])).is_err());
+    }
+}
@@ -4,34 +4,271 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+use std::collections::VecDeque;
 use std::convert::Infallible;
+use std::fmt;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::sync::Mutex;
+use unwrap_infallible::UnwrapInfallible;
+
+use crate::spec;
+
+/// Framing used between the artifacts of the output stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Separator {
+    /// Each artifact is terminated by a newline (json lines). This is the default.
+    #[default]
+    Newline,
+    /// Each artifact is prefixed by the 0x1E (RS) byte and terminated by a newline,
+    /// as described in RFC 7464.
+    Rs,
+    /// Each artifact is terminated by a NUL byte.
+    NulByte,
+}
+
+impl Separator {
+    // note: the serialized json never contains raw control characters, since these
+    // are always escaped in strings, so the framing bytes cannot appear inside a record
+    pub(crate) fn frame(&self, s: &str) -> String {
+        match self {
+            Separator::Newline => format!("{}\n", s),
+            Separator::Rs => format!("\x1e{}\n", s),
+            Separator::NulByte => format!("{}\0", s),
+        }
+    }
+
+    /// Last byte of every framed record.
+    pub(crate) fn terminator(&self) -> u8 {
+        match self {
+            Separator::Newline | Separator::Rs => b'\n',
+            Separator::NulByte => b'\0',
+        }
+    }
+}
+
+/// Identifies the artifact that could not be written. Errors from
+/// [`crate::output::Emitter::probe`] have the `probe` kind.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArtifactContext {
+    /// Artifact type, qualified by its container (eg. `testStepArtifact.measurement`).
+    pub kind: &'static str,
+    pub seqno: u64,
+}
+
+impl fmt::Display for ArtifactContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} seqno={}", self.kind, self.seqno)
+    }
+}
+
+/// Errors reported by the output writers.
+///
+/// Writers implementing the [`Writer`] trait report these wrapped in an [`io::Error`];
+/// they are surfaced to the caller as [`crate::output::OcptvError::Writer`], with the
+/// `artifact` context filled in by the emitter.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum WriterError {
+    /// The underlying output failed to write the artifact.
+    #[error("failed to write {artifact}")]
+    Io {
+        artifact: ArtifactContext,
+        #[source]
+        source: io::Error,
+    },
+
+    /// The artifact does not conform to the OCPTV output schema.
+    #[error("{artifact} violates the output schema at \"{pointer}\": {message}")]
+    SchemaViolation {
+        artifact: ArtifactContext,
+        /// JSON pointer to the offending value inside the artifact.
+        pointer: String,
+        message: String,
+    },
+
+    /// The artifact is not valid json, so it could not be checked against the output schema.
+    #[error("{artifact} is not valid json: {message}")]
+    InvalidJson {
+        artifact: ArtifactContext,
+        message: String,
+    },
+
+    /// The underlying write did not complete within the configured timeout.
+    #[error("write of {artifact} did not complete within {timeout:?}")]
+    Timeout {
+        artifact: ArtifactContext,
+        timeout: Duration,
+    },
+
+    /// The serialized artifact is larger than the configured limit.
+    #[error("{artifact} is {size} bytes, over the limit of {limit} bytes")]
+    ArtifactTooLarge {
+        artifact: ArtifactContext,
+        size: usize,
+        limit: usize,
+    },
+
+    /// A transform set with [`crate::output::ConfigBuilder::with_transform`] failed.
+    #[error("failed to transform {artifact}: {message}")]
+    Transform {
+        artifact: ArtifactContext,
+        message: String,
+    },
+
+    /// No process has the named pipe output open for reading.
+    #[error("cannot write {artifact}, no reader attached to {path:?}")]
+    NoReader {
+        artifact: ArtifactContext,
+        path: PathBuf,
+    },
+
+    /// The reader of the named pipe output went away.
+    #[error("cannot write {artifact}, the reader of {path:?} closed the pipe")]
+    BrokenPipe {
+        artifact: ArtifactContext,
+        path: PathBuf,
+    },
+
+    /// The bounded buffer output is full and its overflow policy is [`OverflowPolicy::Error`].
+    #[error("cannot write {artifact}, buffer is full (capacity {capacity})")]
+    BufferFull {
+        artifact: ArtifactContext,
+        capacity: usize,
+    },
+}
+
+impl WriterError {
+    pub(crate) fn with_artifact(mut self, context: ArtifactContext) -> Self {
+        match &mut self {
+            WriterError::Io { artifact, .. }
+            | WriterError::SchemaViolation { artifact, .. }
+            | WriterError::InvalidJson { artifact, .. }
+            | WriterError::Timeout { artifact, .. }
+            | WriterError::BufferFull { artifact, .. }
+            | WriterError::Transform { artifact, .. }
+            | WriterError::NoReader { artifact, .. }
+            | WriterError::BrokenPipe { artifact, .. }
+            | WriterError::ArtifactTooLarge { artifact, .. } => *artifact = context,
+        }
+        self
+    }
+}
+
+impl From<WriterError> for io::Error {
+    fn from(value: WriterError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, value)
+    }
+}
 
 /// TODO: docs
+///
+/// Custom writers receive a single serialized artifact per call, without any framing.
 #[async_trait]
 pub trait Writer {
     async fn write(&self, s: &str) -> Result<(), io::Error>;
+
+    /// Checks that the output is currently writable, without writing any artifact.
+    /// See [`crate::output::Emitter::probe`]. The default implementation does nothing.
+    async fn probe(&self) -> Result<(), io::Error> {
+        Ok(())
+    }
 }
 
 pub enum WriterType {
     // optimization: static dispatch for these known types
     Stdout(StdoutWriter),
     File(FileWriter),
+    FileTemplate(FileTemplateWriter),
     Buffer(BufferWriter),
+    TypedBuffer(TypedBufferWriter),
+    BoundedBuffer(BoundedBuffer),
+    Bytes(BytesWriter),
+    Async(AsyncWriter),
+    #[cfg(unix)]
+    Fifo(FifoWriter),
+    #[cfg(all(feature = "journald", target_os = "linux"))]
+    Journald(crate::output::JournaldWriter),
+    Split(SplitWriter),
 
     Custom(Box<dyn Writer + Send + Sync + 'static>),
 }
 
+impl WriterType {
+    /// Returns whether the output handles raw bytes, as needed by the binary formats.
+    pub(crate) fn is_byte_oriented(&self) -> bool {
+        match self {
+            WriterType::File(_)
+            | WriterType::FileTemplate(_)
+            | WriterType::Bytes(_)
+            | WriterType::Async(_) => true,
+            #[cfg(unix)]
+            WriterType::Fifo(_) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) async fn probe(&self) -> Result<(), io::Error> {
+        match self {
+            WriterType::File(file) => file.probe().await,
+            // note: the file is only created when the run starts, and probed then
+            WriterType::FileTemplate(template) => match template.get() {
+                Ok(file) => file.probe().await,
+                Err(_) => Ok(()),
+            },
+            WriterType::Async(sink) => sink.probe().await,
+            #[cfg(unix)]
+            WriterType::Fifo(fifo) => fifo.probe().await,
+            WriterType::Split(split) => split.probe().await,
+            WriterType::Custom(custom) => custom.probe().await,
+            #[cfg(all(feature = "journald", target_os = "linux"))]
+            WriterType::Journald(_) => Ok(()),
+            WriterType::Stdout(_)
+            | WriterType::Buffer(_)
+            | WriterType::TypedBuffer(_)
+            | WriterType::BoundedBuffer(_)
+            | WriterType::Bytes(_) => Ok(()),
+        }
+    }
+}
+
+/// When the file output is synced to the storage device, using `fsync(2)`-like semantics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// Never explicitly sync, rely on the OS. This is the default.
+    #[default]
+    Never,
+    /// Sync once, when the test run ends.
+    OnEnd,
+    /// Sync after every artifact.
+    EveryWrite,
+    /// Sync after every N artifacts, and when the test run ends.
+    EveryN(u64),
+}
+
+impl SyncPolicy {
+    /// Returns whether to sync after the `count`-th write (1-based).
+    pub(crate) fn sync_after(&self, count: u64) -> bool {
+        match self {
+            SyncPolicy::Never | SyncPolicy::OnEnd => false,
+            SyncPolicy::EveryWrite => true,
+            SyncPolicy::EveryN(n) => count % (*n).max(1) == 0,
+        }
+    }
+}
+
 /// TODO: docs
 pub struct FileWriter {
     file: Arc<Mutex<fs::File>>,
+    // last byte of the existing content when appending, taken by the first write
+    last_byte: std::sync::Mutex<Option<u8>>,
 }
 
 impl FileWriter {
@@ -39,20 +276,314 @@ impl FileWriter {
         let file = fs::File::create(path).await?;
         Ok(FileWriter {
             file: Arc::new(Mutex::new(file)),
+            last_byte: std::sync::Mutex::new(None),
         })
     }
 
-    pub async fn write(&self, s: &str) -> Result<(), io::Error> {
-        let mut handle = self.file.lock().await;
+    /// Same as [`FileWriter::new`], but creates the file synchronously, for use outside
+    /// of an async context.
+    pub(crate) fn create_blocking<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let file = std::fs::File::create(path)?;
+        Ok(Self::from_std(file))
+    }
+
+    fn from_std(file: std::fs::File) -> Self {
+        FileWriter {
+            file: Arc::new(Mutex::new(fs::File::from_std(file))),
+            last_byte: std::sync::Mutex::new(None),
+        }
+    }
+
+    /// Same as [`FileWriter::append`], but opens the file synchronously, for use outside
+    /// of an async context.
+    pub(crate) fn append_blocking<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        use std::io::{Read, Seek};
+
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut last_byte = None;
+        if file.metadata()?.len() > 0 {
+            let mut last = [0u8; 1];
+            file.seek(io::SeekFrom::End(-1))?;
+            file.read_exact(&mut last)?;
+            last_byte = Some(last[0]);
+        }
+
+        Ok(FileWriter {
+            file: Arc::new(Mutex::new(fs::File::from_std(file))),
+            last_byte: std::sync::Mutex::new(last_byte),
+        })
+    }
 
+    /// Opens the file at `path` for appending, creating it if it doesn't exist.
+    /// For json lines output, if the existing content doesn't end with the record
+    /// separator, one is inserted before the first new artifact. Binary formats and
+    /// compressed output are appended as is.
+    pub async fn append<P: AsRef<Path>>(path: P) -> Result<Self, io::Error> {
+        let path = path.as_ref().to_owned();
+        tokio::task::spawn_blocking(move || Self::append_blocking(path)).await?
+    }
+
+    pub async fn write(&self, s: &str) -> Result<(), io::Error> {
         let mut buf = Vec::<u8>::new();
         writeln!(buf, "{}", s)?;
 
-        handle.write_all(&buf).await?;
+        self.write_raw(&buf, Some(b'\n')).await
+    }
+
+    /// Writes the bytes as is. For records ending with `terminator`, if the file was opened
+    /// for appending and the existing content doesn't end with it, it is inserted first,
+    /// so that the record doesn't run into the existing content.
+    pub(crate) async fn write_raw(
+        &self,
+        s: &[u8],
+        terminator: Option<u8>,
+    ) -> Result<(), io::Error> {
+        let mut handle = self.file.lock().await;
+
+        // note: only the first write after opening can need the terminator
+        let last_byte = self
+            .last_byte
+            .lock()
+            .expect("last byte lock is never poisoned")
+            .take();
+        if let (Some(last), Some(terminator)) = (last_byte, terminator) {
+            if last != terminator {
+                handle.write_all(&[terminator]).await?;
+            }
+        }
+        handle.write_all(s).await?;
         handle.flush().await?;
 
         Ok(())
     }
+
+    /// Writes zero bytes to the file, checking that it is still open for writing.
+    pub(crate) async fn probe(&self) -> Result<(), io::Error> {
+        let mut handle = self.file.lock().await;
+        let _ = handle.write(&[]).await?;
+        handle.flush().await
+    }
+
+    /// Syncs all the data written so far to the storage device.
+    pub(crate) async fn sync_data(&self) -> Result<(), io::Error> {
+        self.file.lock().await.sync_data().await
+    }
+}
+
+/// Writes the output into a named pipe (FIFO), eg. one read by a supervisor process.
+///
+/// Opening a named pipe for writing blocks until a reader attaches, so the pipe is only
+/// opened, without blocking, when the first artifact is written. If no reader is attached
+/// at that point, the write fails with [`WriterError::NoReader`], unless a timeout to wait
+/// for the reader was given. When the reader goes away, writes fail with
+/// [`WriterError::BrokenPipe`] and the pipe is opened again on the next write.
+///
+/// Note that the process must not be killed by `SIGPIPE` for the latter; Rust binaries
+/// ignore this signal by default.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct FifoWriter {
+    path: PathBuf,
+    wait: Option<Duration>,
+    sender: Mutex<Option<tokio::net::unix::pipe::Sender>>,
+}
+
+#[cfg(unix)]
+impl FifoWriter {
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+    /// Writes into the named pipe at `path`, which must already exist.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FifoWriter {
+            path: path.as_ref().to_path_buf(),
+            wait: None,
+            sender: Mutex::new(None),
+        }
+    }
+
+    /// Checks that the path is an existing named pipe, without opening it.
+    pub(crate) fn check_path(&self) -> Result<(), io::Error> {
+        use std::os::unix::fs::FileTypeExt;
+
+        if std::fs::metadata(&self.path)?.file_type().is_fifo() {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "not a named pipe",
+        ))
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Same as [`FifoWriter::new`], but waits up to `timeout` for a reader to attach
+    /// when opening the pipe.
+    pub fn with_timeout<P: AsRef<Path>>(path: P, timeout: Duration) -> Self {
+        FifoWriter {
+            wait: Some(timeout),
+            ..Self::new(path)
+        }
+    }
+
+    pub async fn write(&self, s: &str) -> Result<(), io::Error> {
+        self.write_raw(format!("{}\n", s).as_bytes()).await
+    }
+
+    pub(crate) async fn write_raw(&self, s: &[u8]) -> Result<(), io::Error> {
+        let mut handle = self.sender.lock().await;
+
+        let sender = match &mut *handle {
+            Some(sender) => sender,
+            None => handle.insert(self.open().await?),
+        };
+
+        match sender.write_all(s).await {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {
+                *handle = None;
+                Err(WriterError::BrokenPipe {
+                    artifact: ArtifactContext::default(),
+                    path: self.path.clone(),
+                }
+                .into())
+            }
+            result => result,
+        }
+    }
+
+    /// Opens the pipe, if not already open, checking that a reader is attached.
+    pub(crate) async fn probe(&self) -> Result<(), io::Error> {
+        let mut handle = self.sender.lock().await;
+        if handle.is_none() {
+            *handle = Some(self.open().await?);
+        }
+        Ok(())
+    }
+
+    /// Closes the pipe, if open; the next write opens it again.
+    pub(crate) async fn close(&self) {
+        self.sender.lock().await.take();
+    }
+
+    async fn open(&self) -> Result<tokio::net::unix::pipe::Sender, io::Error> {
+        let deadline = self.wait.map(|wait| tokio::time::Instant::now() + wait);
+        loop {
+            match tokio::net::unix::pipe::OpenOptions::new().open_sender(&self.path) {
+                // ENXIO: the pipe has no reader yet
+                Err(e) if e.raw_os_error() == Some(libc::ENXIO) => {
+                    if deadline.map_or(true, |d| tokio::time::Instant::now() >= d) {
+                        return Err(WriterError::NoReader {
+                            artifact: ArtifactContext::default(),
+                            path: self.path.clone(),
+                        }
+                        .into());
+                    }
+                    tokio::time::sleep(Self::POLL_INTERVAL).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// File output whose path is a template, filled in when the test run starts.
+/// See [`crate::output::ConfigBuilder::with_file_output_template`].
+pub struct FileTemplateWriter {
+    template: String,
+    file: tokio::sync::OnceCell<FileWriter>,
+}
+
+impl FileTemplateWriter {
+    pub(crate) const PLACEHOLDERS: [&'static str; 3] = ["run_name", "dut_id", "date"];
+
+    /// Checks that the braces in `template` only delimit the known placeholders.
+    pub(crate) fn new(template: &str) -> Result<Self, String> {
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                return Err("unmatched }".to_owned());
+            }
+            let Some(len) = rest[start..].find('}') else {
+                return Err("unmatched {".to_owned());
+            };
+            let name = &rest[start + 1..start + len];
+            if !Self::PLACEHOLDERS.contains(&name) {
+                return Err(format!("unknown placeholder {{{}}}", name));
+            }
+            rest = &rest[start + len + 1..];
+        }
+
+        Ok(FileTemplateWriter {
+            template: template.to_owned(),
+            file: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    /// Creates the output file, if not already created by a previous run sharing this
+    /// output. If the file already exists, a numeric suffix is added to the name.
+    pub(crate) async fn open(
+        &self,
+        run_name: &str,
+        dut_id: &str,
+        date: chrono::NaiveDate,
+    ) -> Result<(), io::Error> {
+        self.file
+            .get_or_try_init(|| async {
+                // note: the values must not introduce new path components
+                let sanitize = |s: &str| s.replace(['/', '\\'], "_");
+                let path = self
+                    .template
+                    .replace("{run_name}", &sanitize(run_name))
+                    .replace("{dut_id}", &sanitize(dut_id))
+                    .replace("{date}", &date.format("%Y-%m-%d").to_string());
+                create_unique(PathBuf::from(path)).await
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Returns the output file, once created by [`FileTemplateWriter::open`].
+    pub(crate) fn get(&self) -> Result<&FileWriter, io::Error> {
+        self.file.get().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                "the output file is only created when the test run starts",
+            )
+        })
+    }
+}
+
+async fn create_unique(path: PathBuf) -> Result<FileWriter, io::Error> {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+
+    let mut candidate = path.clone();
+    let mut suffix = 0;
+    loop {
+        // note: create_new fails if the file exists, so concurrent runs never share a file
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+            .await
+        {
+            Ok(file) => return Ok(FileWriter::from_std(file.into_std().await)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                suffix += 1;
+                candidate.set_file_name(format!("{}_{}{}", stem, suffix, extension));
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 /// TODO: docs
@@ -72,7 +603,317 @@ impl BufferWriter {
     }
 }
 
-/// TODO: docs
+/// Writer that captures the artifacts as typed [`spec::Root`] values, before
+/// serialization, so that tests can match on them directly.
+///
+/// The artifacts are captured as emitted: the emit hooks, transforms, output format and
+/// size limit don't apply to them.
+#[derive(Debug)]
+pub struct TypedBufferWriter {
+    buffer: Arc<Mutex<Vec<spec::Root>>>,
+}
+
+impl TypedBufferWriter {
+    pub fn new(buffer: Arc<Mutex<Vec<spec::Root>>>) -> Self {
+        Self { buffer }
+    }
+
+    pub async fn write(&self, root: spec::Root) -> Result<(), Infallible> {
+        self.buffer.lock().await.push(root);
+        Ok(())
+    }
+}
+
+/// What the [`BoundedBuffer`] does with a new artifact when it is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Evict the oldest artifact to make room for the new one. This is the default.
+    #[default]
+    DropOldest,
+    /// Discard the new artifact.
+    DropNewest,
+    /// Fail the emitting call with [`WriterError::BufferFull`].
+    Error,
+}
+
+/// In-memory output holding at most `capacity` artifacts.
+///
+/// The buffer is a cheap handle over shared storage; clone it before handing it to the
+/// config to inspect the output later. Artifacts discarded by the overflow policy are
+/// counted, so the consumer can report the gaps.
+///
+/// # Examples
+///
+/// ```rust
+/// # tokio_test::block_on(async {
+/// # use ocptv::output::*;
+/// let buffer = BoundedBuffer::new(2, OverflowPolicy::DropOldest);
+/// buffer.write("a").await?;
+/// buffer.write("b").await?;
+/// buffer.write("c").await?;
+///
+/// assert_eq!(buffer.entries().await, vec!["b", "c"]);
+/// assert_eq!(buffer.dropped(), 1);
+///
+/// # Ok::<(), WriterError>(())
+/// # });
+/// ```
+#[derive(Debug, Clone)]
+pub struct BoundedBuffer {
+    inner: Arc<BoundedBufferInner>,
+}
+
+#[derive(Debug)]
+struct BoundedBufferInner {
+    entries: Mutex<VecDeque<String>>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+impl BoundedBuffer {
+    /// Creates a buffer for `capacity` artifacts. A zero capacity is treated as one.
+    pub fn new(capacity: usize, policy: OverflowPolicy) -> Self {
+        let capacity = capacity.max(1);
+        BoundedBuffer {
+            inner: Arc::new(BoundedBufferInner {
+                entries: Mutex::new(VecDeque::with_capacity(capacity)),
+                capacity,
+                policy,
+                dropped: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub async fn write(&self, s: &str) -> Result<(), WriterError> {
+        let mut entries = self.inner.entries.lock().await;
+        if entries.len() >= self.inner.capacity {
+            match self.inner.policy {
+                OverflowPolicy::DropOldest => {
+                    entries.pop_front();
+                }
+                OverflowPolicy::DropNewest => {
+                    self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                OverflowPolicy::Error => {
+                    return Err(WriterError::BufferFull {
+                        // note: filled in by the emitter
+                        artifact: ArtifactContext::default(),
+                        capacity: self.inner.capacity,
+                    });
+                }
+            }
+            self.inner.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+
+        entries.push_back(s.to_owned());
+        Ok(())
+    }
+
+    /// Returns a copy of the retained artifacts, oldest first.
+    pub async fn entries(&self) -> Vec<String> {
+        self.inner.entries.lock().await.iter().cloned().collect()
+    }
+
+    /// Removes and returns the retained artifacts, oldest first. This makes room for
+    /// new artifacts, but does not reset the dropped count.
+    pub async fn drain(&self) -> Vec<String> {
+        self.inner.entries.lock().await.drain(..).collect()
+    }
+
+    /// Returns how many artifacts were discarded by the overflow policy so far.
+    /// Artifacts rejected with [`OverflowPolicy::Error`] are reported to the caller
+    /// instead, and are not counted here.
+    pub fn dropped(&self) -> u64 {
+        self.inner.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity
+    }
+}
+
+#[async_trait]
+impl Writer for FileWriter {
+    async fn write(&self, s: &str) -> Result<(), io::Error> {
+        FileWriter::write(self, s).await
+    }
+
+    async fn probe(&self) -> Result<(), io::Error> {
+        FileWriter::probe(self).await
+    }
+}
+
+#[async_trait]
+impl Writer for BoundedBuffer {
+    async fn write(&self, s: &str) -> Result<(), io::Error> {
+        Ok(BoundedBuffer::write(self, s).await?)
+    }
+}
+
+#[async_trait]
+impl Writer for BufferWriter {
+    async fn write(&self, s: &str) -> Result<(), io::Error> {
+        BufferWriter::write(self, s).await.unwrap_infallible();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Writer for StdoutWriter {
+    async fn write(&self, s: &str) -> Result<(), io::Error> {
+        StdoutWriter::write(self, s).await.unwrap_infallible();
+        Ok(())
+    }
+}
+
+/// When the [`AsyncWriter`] flushes the underlying sink.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Flush after every artifact. This is the default.
+    #[default]
+    EveryWrite,
+    /// Only flush when the test run ends.
+    OnEnd,
+}
+
+/// Writer into any [`AsyncWrite`] sink, like an open file or a network stream.
+pub struct AsyncWriter {
+    sink: Mutex<Box<dyn AsyncWrite + Send + Unpin>>,
+    policy: FlushPolicy,
+}
+
+impl AsyncWriter {
+    pub fn new(sink: Box<dyn AsyncWrite + Send + Unpin>, policy: FlushPolicy) -> Self {
+        AsyncWriter {
+            sink: Mutex::new(sink),
+            policy,
+        }
+    }
+
+    pub(crate) async fn write_raw(&self, s: &[u8]) -> Result<(), io::Error> {
+        let mut sink = self.sink.lock().await;
+
+        sink.write_all(s).await?;
+        if self.policy == FlushPolicy::EveryWrite {
+            sink.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn flush(&self) -> Result<(), io::Error> {
+        self.sink.lock().await.flush().await
+    }
+
+    pub(crate) async fn shutdown(&self) -> Result<(), io::Error> {
+        self.sink.lock().await.shutdown().await
+    }
+
+    pub(crate) async fn probe(&self) -> Result<(), io::Error> {
+        let mut sink = self.sink.lock().await;
+        let _ = sink.write(&[]).await?;
+        sink.flush().await
+    }
+}
+
+/// Writer that routes each artifact to one of two writers, based on the artifact kind.
+///
+/// The selector receives the artifact kind, qualified by its container
+/// (eg. `testStepArtifact.measurement`, see [`ArtifactContext`]). Sequence numbers are
+/// assigned before routing, so they remain global across both outputs and the full
+/// stream can be reconstructed by merging on `sequenceNumber`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use tokio::sync::Mutex;
+/// # use ocptv::output::*;
+/// let errors = Arc::new(Mutex::new(vec![]));
+/// let other = Arc::new(Mutex::new(vec![]));
+///
+/// let writer = SplitWriter::new(
+///     |kind| kind.ends_with(".error"),
+///     Box::new(BufferWriter::new(errors)),
+///     Box::new(BufferWriter::new(other)),
+/// );
+/// let config = Config::builder().with_split_writer(writer).build();
+/// ```
+pub struct SplitWriter {
+    selector: Box<dyn Fn(&str) -> bool + Send + Sync + 'static>,
+    selected: Box<dyn Writer + Send + Sync + 'static>,
+    other: Box<dyn Writer + Send + Sync + 'static>,
+}
+
+impl SplitWriter {
+    /// Artifacts for which `selector` returns true go to `selected`, the rest to `other`.
+    pub fn new<F>(
+        selector: F,
+        selected: Box<dyn Writer + Send + Sync + 'static>,
+        other: Box<dyn Writer + Send + Sync + 'static>,
+    ) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        SplitWriter {
+            selector: Box::new(selector),
+            selected,
+            other,
+        }
+    }
+
+    /// Routes the measurement artifacts (`measurement`, `measurementSeriesStart`,
+    /// `measurementSeriesElement` and `measurementSeriesEnd`) to `measurements`, and
+    /// everything else to `other`.
+    pub fn measurements(
+        measurements: Box<dyn Writer + Send + Sync + 'static>,
+        other: Box<dyn Writer + Send + Sync + 'static>,
+    ) -> Self {
+        Self::new(
+            |kind| kind.starts_with("testStepArtifact.measurement"),
+            measurements,
+            other,
+        )
+    }
+
+    pub(crate) async fn write(&self, kind: &str, s: &str) -> Result<(), io::Error> {
+        if (self.selector)(kind) {
+            self.selected.write(s).await
+        } else {
+            self.other.write(s).await
+        }
+    }
+
+    pub(crate) async fn probe(&self) -> Result<(), io::Error> {
+        self.selected.probe().await?;
+        self.other.probe().await
+    }
+}
+
+/// Writer that captures the raw bytes of the output stream, including the record framing.
+#[derive(Debug)]
+pub struct BytesWriter {
+    buffer: Arc<Mutex<Vec<u8>>>,
+}
+
+impl BytesWriter {
+    pub fn new(buffer: Arc<Mutex<Vec<u8>>>) -> Self {
+        Self { buffer }
+    }
+
+    pub async fn write(&self, s: &[u8]) -> Result<(), Infallible> {
+        self.buffer.lock().await.extend_from_slice(s);
+        Ok(())
+    }
+}
+
+/// Writer to the process standard output. This is the default output.
+///
+/// Each artifact is written, together with its framing, by a single write while holding
+/// the stdout lock, so it is never spliced by other writes from the same process,
+/// eg. `println!` calls in other tasks or concurrent steps emitting at the same time.
 #[derive(Debug, Clone)]
 pub struct StdoutWriter {}
 
@@ -83,7 +924,14 @@ impl StdoutWriter {
     }
 
     pub async fn write(&self, s: &str) -> Result<(), Infallible> {
-        println!("{}", s);
+        self.write_raw(&format!("{}\n", s)).await
+    }
+
+    pub(crate) async fn write_raw(&self, s: &str) -> Result<(), Infallible> {
+        // note: print! takes the stdout lock for the duration of the call, and a single
+        // argument results in a single write_all on the locked stdout; this also keeps
+        // the output capture in tests working, unlike writing to io::stdout() directly
+        print!("{}", s);
         Ok(())
     }
 }
@@ -103,6 +951,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_separator_framing() -> Result<()> {
+        assert_eq!(Separator::Newline.frame("{}"), "{}\n");
+        assert_eq!(Separator::Rs.frame("{}"), "\x1e{}\n");
+        assert_eq!(Separator::NulByte.frame("{}"), "{}\0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_policy() -> Result<()> {
+        assert!(!SyncPolicy::Never.sync_after(1));
+        assert!(!SyncPolicy::OnEnd.sync_after(1));
+        assert!(SyncPolicy::EveryWrite.sync_after(1));
+        assert!((1..=6)
+            .map(|i| SyncPolicy::EveryN(3).sync_after(i))
+            .eq([false, false, true, false, false, true]));
+        assert!(SyncPolicy::EveryN(0).sync_after(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bounded_buffer_zero_capacity() -> Result<()> {
+        let buffer = BoundedBuffer::new(0, OverflowPolicy::DropOldest);
+        buffer.write("a").await?;
+        buffer.write("b").await?;
+
+        assert_eq!(buffer.capacity(), 1);
+        assert_eq!(buffer.entries().await, vec!["b"]);
+        assert_eq!(buffer.dropped(), 1);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_ocptv_error_has_public_source() -> Result<()> {
         let dut = DutInfo::builder("dut_id").build();
@@ -116,8 +996,8 @@ mod tests {
         assert!(actual.is_err());
 
         match &actual {
-            Err(OcptvError::IoError(ioe)) => {
-                assert_eq!(ioe.kind(), io::ErrorKind::Other);
+            Err(OcptvError::Writer(WriterError::Io { source, .. })) => {
+                assert_eq!(source.kind(), io::ErrorKind::Other);
             }
             _ => panic!("unknown error"),
         }
@@ -0,0 +1,405 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+//! Utilities for reading back an OCPTV artifact stream.
+//!
+//! The input format is detected from the leading bytes of the stream, so plain `.jsonl`
+//! files, compressed files and raw streams (eg. stdin) can all be consumed the same way.
+//! Decompression support is enabled by the `gzip` and `zstd` cargo features; note that
+//! the writer side can only compress with zstd, gzip streams come from external tools.
+//! Concatenated compressed members, as produced by rotating and then joining files, are
+//! read back as a single stream.
+//!
+//! Binary encoded streams (see [`tv::OutputFormat`]) are not detected, and are read
+//! with the dedicated functions, eg. `from_msgpack_reader` or `from_cbor_reader`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+use crate::output as tv;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+const HEADER_LEN: usize = ZSTD_MAGIC.len();
+
+#[derive(Debug, PartialEq)]
+enum Format {
+    Plain,
+    Gzip,
+    Zstd,
+}
+
+impl Format {
+    fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&GZIP_MAGIC) {
+            return Format::Gzip;
+        }
+        if header.starts_with(&ZSTD_MAGIC) {
+            return Format::Zstd;
+        }
+
+        Format::Plain
+    }
+}
+
+enum Decoder {
+    Lines(Box<dyn BufRead + Send>),
+    #[cfg(feature = "msgpack")]
+    MessagePack(Box<dyn BufRead + Send>),
+    #[cfg(feature = "cbor")]
+    Cbor(Box<dyn BufRead + Send>),
+}
+
+/// Iterator over the artifacts of an OCPTV output stream.
+///
+/// Each item is a single artifact, deserialized as a [`tv::Value`]. Empty lines are skipped.
+pub struct Reader {
+    decoder: Decoder,
+}
+
+impl Reader {
+    fn new(inner: Box<dyn BufRead + Send>) -> Self {
+        Reader {
+            decoder: Decoder::Lines(inner),
+        }
+    }
+}
+
+impl Iterator for Reader {
+    type Item = Result<tv::Value, tv::OcptvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.decoder {
+            Decoder::Lines(inner) => next_line(inner),
+            #[cfg(feature = "msgpack")]
+            Decoder::MessagePack(inner) => next_msgpack(inner),
+            #[cfg(feature = "cbor")]
+            Decoder::Cbor(inner) => next_cbor(inner),
+        }
+    }
+}
+
+fn next_line(inner: &mut Box<dyn BufRead + Send>) -> Option<Result<tv::Value, tv::OcptvError>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match inner.read_line(&mut line) {
+            Ok(0) => return None,
+            Ok(_) if line.trim().is_empty() => continue,
+            Ok(_) => {
+                return Some(
+                    serde_json::from_str(&line).map_err(|e| tv::OcptvError::Format(Box::new(e))),
+                )
+            }
+            Err(e) => return Some(Err(e.into())),
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+fn next_msgpack(inner: &mut Box<dyn BufRead + Send>) -> Option<Result<tv::Value, tv::OcptvError>> {
+    match inner.fill_buf() {
+        Ok([]) => return None,
+        Ok(_) => {}
+        Err(e) => return Some(Err(e.into())),
+    }
+
+    let mut len = [0u8; 4];
+    let mut payload = vec![];
+    let result = inner.read_exact(&mut len).and_then(|_| {
+        payload.resize(u32::from_be_bytes(len) as usize, 0);
+        inner.read_exact(&mut payload)
+    });
+    if let Err(e) = result {
+        return Some(Err(e.into()));
+    }
+
+    Some(rmp_serde::from_slice(&payload).map_err(|e| tv::OcptvError::Format(Box::new(e))))
+}
+
+#[cfg(feature = "cbor")]
+fn next_cbor(inner: &mut Box<dyn BufRead + Send>) -> Option<Result<tv::Value, tv::OcptvError>> {
+    match inner.fill_buf() {
+        Ok([]) => return None,
+        Ok(_) => {}
+        Err(e) => return Some(Err(e.into())),
+    }
+
+    // note: cbor items are self delimiting, so a sequence needs no framing
+    Some(ciborium::from_reader(inner).map_err(|e| match e {
+        ciborium::de::Error::Io(e) => tv::OcptvError::IoError(e),
+        e => tv::OcptvError::Format(Box::new(e)),
+    }))
+}
+
+/// Opens the file at `path` and returns an iterator over its artifacts, regardless
+/// of compression.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// for artifact in ocptv::reader::open("output.jsonl")? {
+///     println!("{}", artifact?);
+/// }
+///
+/// # Ok::<(), ocptv::output::OcptvError>(())
+/// ```
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Reader, tv::OcptvError> {
+    from_reader(fs::File::open(path)?)
+}
+
+/// Returns an iterator over the artifacts read from any byte stream, regardless
+/// of compression.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// for artifact in ocptv::reader::from_reader(std::io::stdin())? {
+///     println!("{}", artifact?);
+/// }
+///
+/// # Ok::<(), ocptv::output::OcptvError>(())
+/// ```
+pub fn from_reader<R: Read + Send + 'static>(mut reader: R) -> Result<Reader, tv::OcptvError> {
+    // note: a single read may return fewer bytes than the magic numbers, eg. on pipes,
+    // so the header is read separately and put back in front of the stream
+    let header = read_header(&mut reader)?;
+    let format = Format::detect(&header);
+    let reader = BufReader::new(io::Cursor::new(header).chain(reader));
+
+    match format {
+        Format::Plain => Ok(Reader::new(Box::new(reader))),
+        Format::Gzip => gzip(reader),
+        Format::Zstd => zstd(reader),
+    }
+}
+
+/// Returns an iterator over the artifacts of a MessagePack encoded stream, as written
+/// with [`tv::OutputFormat::MessagePack`]. Requires the `msgpack` feature.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let file = std::fs::File::open("output.msgpack")?;
+/// for artifact in ocptv::reader::from_msgpack_reader(file) {
+///     println!("{}", artifact?);
+/// }
+///
+/// # Ok::<(), ocptv::output::OcptvError>(())
+/// ```
+#[cfg(feature = "msgpack")]
+pub fn from_msgpack_reader<R: Read + Send + 'static>(reader: R) -> Reader {
+    Reader {
+        decoder: Decoder::MessagePack(Box::new(BufReader::new(reader))),
+    }
+}
+
+/// Returns an iterator over the artifacts of a CBOR sequence, as written with
+/// [`tv::OutputFormat::Cbor`]. Requires the `cbor` feature.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let file = std::fs::File::open("output.cbor")?;
+/// for artifact in ocptv::reader::from_cbor_reader(file) {
+///     println!("{}", artifact?);
+/// }
+///
+/// # Ok::<(), ocptv::output::OcptvError>(())
+/// ```
+#[cfg(feature = "cbor")]
+pub fn from_cbor_reader<R: Read + Send + 'static>(reader: R) -> Reader {
+    Reader {
+        decoder: Decoder::Cbor(Box::new(BufReader::new(reader))),
+    }
+}
+
+/// Returns the group of each grouped step in the artifacts, keyed by the step id.
+///
+/// Step groups are recorded by [`tv::StartedTestRun::step_in_group`] as a step extension
+/// artifact named `ocptv_step_group`; steps without a group are not in the result.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let artifacts = ocptv::reader::open("output.jsonl")?.collect::<Result<Vec<_>, _>>()?;
+/// for (step_id, group) in ocptv::reader::step_groups(&artifacts) {
+///     println!("{}: {}", step_id, group);
+/// }
+///
+/// # Ok::<(), ocptv::output::OcptvError>(())
+/// ```
+pub fn step_groups<'a, I>(artifacts: I) -> BTreeMap<String, String>
+where
+    I: IntoIterator<Item = &'a tv::Value>,
+{
+    artifacts
+        .into_iter()
+        .filter_map(|artifact| {
+            let step = &artifact["testStepArtifact"];
+            let extension = &step["extension"];
+            if extension["name"] != tv::STEP_GROUP_EXTENSION {
+                return None;
+            }
+            let id = step["testStepId"].as_str()?;
+            let group = extension["content"]["group"].as_str()?;
+            Some((id.to_owned(), group.to_owned()))
+        })
+        .collect()
+}
+
+/// Reads up to [`HEADER_LEN`] bytes, fewer only if the stream ends before that.
+fn read_header<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut header = [0u8; HEADER_LEN];
+    let mut len = 0;
+    while len < header.len() {
+        match reader.read(&mut header[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(header[..len].to_vec())
+}
+
+#[cfg(feature = "gzip")]
+fn gzip<R: BufRead + Send + 'static>(reader: R) -> Result<Reader, tv::OcptvError> {
+    // note: the multi-member decoder handles concatenated gzip files
+    let decoder = flate2::bufread::MultiGzDecoder::new(reader);
+    Ok(Reader::new(Box::new(BufReader::new(decoder))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip<R: BufRead + Send + 'static>(_reader: R) -> Result<Reader, tv::OcptvError> {
+    Err(tv::OcptvError::IoError(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "gzip input detected, but the `gzip` feature is not enabled",
+    )))
+}
+
+#[cfg(feature = "zstd")]
+fn zstd<R: BufRead + Send + 'static>(reader: R) -> Result<Reader, tv::OcptvError> {
+    // note: the decoder reads all the concatenated frames
+    let decoder = zstd::stream::read::Decoder::with_buffer(reader)?;
+    Ok(Reader::new(Box::new(BufReader::new(decoder))))
+}
+
+#[cfg(not(feature = "zstd"))]
+fn zstd<R: BufRead + Send + 'static>(_reader: R) -> Result<Reader, tv::OcptvError> {
+    Err(tv::OcptvError::IoError(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "zstd input detected, but the `zstd` feature is not enabled",
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_format_detection() -> Result<()> {
+        assert_eq!(Format::detect(b"{\"a\": 1}"), Format::Plain);
+        assert_eq!(Format::detect(&[0x1f, 0x8b, 0x08]), Format::Gzip);
+        assert_eq!(Format::detect(&[0x28, 0xb5, 0x2f, 0xfd]), Format::Zstd);
+        assert_eq!(Format::detect(&[]), Format::Plain);
+        Ok(())
+    }
+
+    // yields a single byte per read, like a slow pipe
+    struct ByteReader(Cursor<Vec<u8>>);
+
+    impl Read for ByteReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    fn test_header_read_across_short_reads() -> Result<()> {
+        let mut reader = ByteReader(Cursor::new(vec![0x28, 0xb5, 0x2f, 0xfd, 0x00]));
+        let header = read_header(&mut reader)?;
+        assert_eq!(Format::detect(&header), Format::Zstd);
+
+        let mut reader = ByteReader(Cursor::new(vec![0x1f]));
+        assert_eq!(read_header(&mut reader)?, vec![0x1f]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_keeps_header_bytes() -> Result<()> {
+        let input = "{\"a\": 1}\n{\"b\": 2}\n";
+        let reader = ByteReader(Cursor::new(input.as_bytes().to_vec()));
+        let values = from_reader(reader)?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            values,
+            vec![serde_json::json!({"a": 1}), serde_json::json!({"b": 2})]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_skips_empty_lines() -> Result<()> {
+        let input = "{\"a\": 1}\n\n{\"b\": 2}\n";
+        let values = from_reader(Cursor::new(input))?.collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            values,
+            vec![serde_json::json!({"a": 1}), serde_json::json!({"b": 2})]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_msgpack_reader_reports_truncated_record() -> Result<()> {
+        // length says 4 bytes, but only 2 follow
+        let mut reader = from_msgpack_reader(Cursor::new(vec![0, 0, 0, 4, 0x81, 0xa1]));
+
+        assert!(matches!(
+            reader.next(),
+            Some(Err(tv::OcptvError::IoError(_)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_groups() -> Result<()> {
+        let input = concat!(
+            r#"{"testStepArtifact":{"testStepId":"step0","testStepStart":{"name":"fans"}}}"#,
+            "\n",
+            r#"{"testStepArtifact":{"testStepId":"step0","extension":{"name":"ocptv_step_group","content":{"group":"thermal"}}}}"#,
+            "\n",
+            r#"{"testStepArtifact":{"testStepId":"step1","extension":{"name":"other","content":{"group":"storage"}}}}"#,
+            "\n",
+        );
+        let artifacts = from_reader(Cursor::new(input))?.collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(
+            step_groups(&artifacts),
+            BTreeMap::from([("step0".to_owned(), "thermal".to_owned())])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_reports_malformed_line() -> Result<()> {
+        let mut reader = from_reader(Cursor::new("{\"a\": \n"))?;
+
+        assert!(matches!(
+            reader.next(),
+            Some(Err(tv::OcptvError::Format(_)))
+        ));
+        Ok(())
+    }
+}
@@ -4,6 +4,12 @@
 // license that can be found in the LICENSE file or at
 // <https://opensource.org/licenses/MIT.>
 
+//! Typed representation of the OCPTV output artifacts, mirroring the json spec.
+//!
+//! These are the values serialized by the emitter; they are mostly useful for
+//! inspecting the output in tests, see [`crate::output::ConfigBuilder::with_typed_buffer_output`].
+//! Use the builders in [`crate::output`] to produce the artifacts.
+
 use std::collections::BTreeMap;
 
 use chrono::DateTime;
@@ -167,7 +173,9 @@ pub enum TestResult {
 /// schema url: <https://github.com/opencomputeproject/ocp-diag-core/blob/main/json_spec/output/log.json>
 ///
 /// schema ref: <https://github.com/opencomputeproject/ocp-diag-core/log/$defs/severity>
-#[derive(Debug, Serialize, Clone, PartialEq)]
+///
+/// The severities are ordered from the least severe, `Debug`, to the most severe, `Fatal`.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[non_exhaustive]
 pub enum LogSeverity {
     #[serde(rename = "DEBUG")]
@@ -217,6 +225,7 @@ pub struct Root {
     pub seqno: u64,
 }
 
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Serialize, PartialEq, Clone)]
 #[non_exhaustive]
 pub enum RootImpl {
@@ -230,6 +239,40 @@ pub enum RootImpl {
     TestStepArtifact(TestStepArtifact),
 }
 
+impl RootImpl {
+    /// Short name of the artifact type, qualified by its container (eg. `testStepArtifact.log`).
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            RootImpl::SchemaVersion(_) => "schemaVersion",
+            RootImpl::TestRunArtifact(a) => match a.artifact {
+                TestRunArtifactImpl::TestRunStart(_) => "testRunArtifact.testRunStart",
+                TestRunArtifactImpl::TestRunEnd(_) => "testRunArtifact.testRunEnd",
+                TestRunArtifactImpl::Log(_) => "testRunArtifact.log",
+                TestRunArtifactImpl::Error(_) => "testRunArtifact.error",
+            },
+            RootImpl::TestStepArtifact(a) => match a.artifact {
+                TestStepArtifactImpl::TestStepStart(_) => "testStepArtifact.testStepStart",
+                TestStepArtifactImpl::TestStepEnd(_) => "testStepArtifact.testStepEnd",
+                TestStepArtifactImpl::Measurement(_) => "testStepArtifact.measurement",
+                TestStepArtifactImpl::MeasurementSeriesStart(_) => {
+                    "testStepArtifact.measurementSeriesStart"
+                }
+                TestStepArtifactImpl::MeasurementSeriesEnd(_) => {
+                    "testStepArtifact.measurementSeriesEnd"
+                }
+                TestStepArtifactImpl::MeasurementSeriesElement(_) => {
+                    "testStepArtifact.measurementSeriesElement"
+                }
+                TestStepArtifactImpl::Diagnosis(_) => "testStepArtifact.diagnosis",
+                TestStepArtifactImpl::Log(_) => "testStepArtifact.log",
+                TestStepArtifactImpl::Error(_) => "testStepArtifact.error",
+                TestStepArtifactImpl::File(_) => "testStepArtifact.file",
+                TestStepArtifactImpl::Extension(_) => "testStepArtifact.extension",
+            },
+        }
+    }
+}
+
 /// Low-level model for the `schemaVersion` spec object.
 /// Specifies the version that should be used to interpret following json outputs.
 ///
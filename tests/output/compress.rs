@@ -0,0 +1,117 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+#![cfg(feature = "zstd")]
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+use ocptv::output::{
+    Compression, Config, LogSeverity, OcptvError, StartedTestRun, TestResult, TestStatus,
+};
+use ocptv::reader;
+
+use super::fixture::*;
+
+async fn emit_series(run: &StartedTestRun) -> Result<(), OcptvError> {
+    let step = run.add_step("step").start().await?;
+    let series = step.add_measurement_series("temp").start().await?;
+    for i in 0..500 {
+        series.add_measurement(i).await?;
+    }
+    series.end().await?;
+    step.end(TestStatus::Complete).await
+}
+
+fn decode(content: Vec<u8>) -> Result<Vec<String>> {
+    Ok(reader::from_reader(Cursor::new(content))?
+        .map(|v| v.map(|v| v.to_string()))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+fn normalize(lines: &[String]) -> Result<Vec<String>> {
+    lines
+        .iter()
+        .map(|l| Ok(serde_json::from_str::<serde_json::Value>(l)?.to_string()))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_zstd_matches_uncompressed_output() -> Result<()> {
+    let golden: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run =
+        start_run_with_config(Config::builder().with_buffer_output(Arc::clone(&golden))).await?;
+    emit_series(&run).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    let run = start_run_with_config(
+        Config::builder()
+            .with_bytes_output(Arc::clone(&buffer))
+            .with_compression(Compression::Zstd { level: 3 }),
+    )
+    .await?;
+    emit_series(&run).await?;
+
+    // before the run ends, the completed frames already decode to a prefix of the output
+    let golden = normalize(&golden.lock().await)?;
+    let partial = decode(buffer.lock().await.clone())?;
+    assert!(!partial.is_empty() && partial.len() < golden.len());
+    assert_eq!(partial[..], golden[..partial.len()]);
+
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = buffer.lock().await.clone();
+    assert!(content.len() < golden.iter().map(|l| l.len()).sum::<usize>() / 4);
+    assert_eq!(decode(content)?, golden);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_zstd_frame_finished_on_drop() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    let run = start_run_with_config(
+        Config::builder()
+            .with_bytes_output(Arc::clone(&buffer))
+            .with_compression(Compression::Zstd { level: 3 }),
+    )
+    .await?;
+    run.add_log(LogSeverity::Info, "interrupted").await?;
+    assert!(buffer.lock().await.is_empty());
+
+    // the run never ends, eg. because the diagnostic bailed out early
+    drop(run);
+
+    let lines = decode(buffer.lock().await.clone())?;
+    assert_eq!(lines.len(), 3);
+    assert!(lines[2].contains("interrupted"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_zstd_needs_byte_output() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let actual = start_run_with_config(
+        Config::builder()
+            .with_buffer_output(buffer)
+            .with_compression(Compression::Zstd { level: 3 }),
+    )
+    .await;
+    match actual {
+        Err(OcptvError::Writer(ocptv::output::WriterError::Io { source, .. })) => {
+            assert_eq!(source.kind(), std::io::ErrorKind::Unsupported);
+        }
+        _ => panic!("expected unsupported output error"),
+    }
+
+    Ok(())
+}
@@ -4,9 +4,68 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
-#[cfg(coverage)]
+use std::sync::Mutex;
+
 use anyhow::Result;
 
+use ocptv::output::{Config, ConfigError};
+
+// note: the environment is process wide, so serialize the tests touching it
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+struct EnvGuard(&'static [&'static str]);
+
+impl EnvGuard {
+    fn set(vars: &'static [(&'static str, &'static str)]) -> Self {
+        for (k, v) in vars {
+            std::env::set_var(k, v);
+        }
+        EnvGuard(&[
+            "OCPTV_OUTPUT",
+            "OCPTV_APPEND",
+            "OCPTV_TIMEZONE",
+            "OCPTV_SEQUENCE_START",
+        ])
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        for k in self.0 {
+            std::env::remove_var(k);
+        }
+    }
+}
+
+#[test]
+fn test_config_from_env() -> Result<()> {
+    let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    {
+        let _env = EnvGuard::set(&[("OCPTV_OUTPUT", "stdout"), ("OCPTV_TIMEZONE", "UTC")]);
+        Config::from_env()?;
+    }
+    {
+        let _env = EnvGuard::set(&[("OCPTV_OUTPUT", "tcp:localhost:1234")]);
+        match Config::from_env() {
+            Err(ConfigError::InvalidEnv { var, value, .. }) => {
+                assert_eq!(var, "OCPTV_OUTPUT");
+                assert_eq!(value, "tcp:localhost:1234");
+            }
+            _ => panic!("expected invalid env error"),
+        }
+    }
+    {
+        let _env = EnvGuard::set(&[("OCPTV_OUTPUT", "file:/nonexistent/dir/output.jsonl")]);
+        assert!(matches!(
+            Config::from_env(),
+            Err(ConfigError::Output { .. })
+        ));
+    }
+
+    Ok(())
+}
+
 // reasoning: the coverage(off) attribute is experimental in llvm-cov, so because we cannot
 // disable the coverage itself, only run this test when in coverage mode because assert_fs
 // does ultimately assume there's a real filesystem somewhere
@@ -86,3 +145,345 @@ async fn test_config_builder_with_file() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_config_builder_with_file_append() -> Result<()> {
+    use std::fs;
+
+    use assert_fs::prelude::*;
+    use assert_json_diff::assert_json_eq;
+    use serde_json::json;
+
+    use ocptv::output::{Config, DutInfo, TestResult, TestRun, TestStatus};
+
+    use super::fixture::*;
+
+    let existing = [
+        json_schema_version(),
+        json!({
+            "testRunArtifact": {
+                "error": {
+                    "symptom": "interrupted"
+                }
+            },
+            "sequenceNumber": 1,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    let fs = assert_fs::TempDir::new()?;
+    let output_file = fs.child("output.jsonl");
+    // note: last line is intentionally not terminated
+    output_file.write_str(&format!("{}\n{}", existing[0], existing[1]))?;
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_file_output_append(output_file.path())
+                .await?
+                .with_sequence_start(2)
+                .emit_schema_version(false)
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let expected = [
+        existing[0].clone(),
+        existing[1].clone(),
+        json!({
+            "testRunArtifact": {
+                "testRunStart": {
+                    "dutInfo": {
+                        "dutInfoId": "dut_id"
+                    },
+                    "name": "run_name",
+                    "parameters": {},
+                    "version": "1.0",
+                    "commandLine": ""
+                }
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(3),
+    ];
+
+    let content = fs::read_to_string(output_file.path())?;
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), expected.len());
+    for (idx, entry) in lines.iter().enumerate() {
+        let value = serde_json::from_str::<serde_json::Value>(entry)?;
+        assert_json_eq!(value, expected[idx]);
+    }
+
+    Ok(())
+}
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_config_builder_with_file_sync_policy() -> Result<()> {
+    use std::fs;
+
+    use assert_fs::prelude::*;
+
+    use ocptv::output::{
+        Config, DutInfo, LogSeverity, SyncPolicy, TestResult, TestRun, TestStatus,
+    };
+
+    use super::fixture::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let output_file = fs.child("output.jsonl");
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_file_output(output_file.path())
+                .await?
+                .with_sync_policy(SyncPolicy::EveryWrite)
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    // every emitted artifact is readable right after the emit call returns
+    for i in 0..5 {
+        run.add_log(LogSeverity::Info, &format!("log {}", i))
+            .await?;
+
+        let content = fs::read_to_string(output_file.path())?;
+        let lines = content.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3 + i);
+        for line in lines {
+            serde_json::from_str::<serde_json::Value>(line)?;
+        }
+    }
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = fs::read_to_string(output_file.path())?;
+    assert_eq!(content.lines().count(), 8);
+
+    Ok(())
+}
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_config_builder_with_split_output() -> Result<()> {
+    use std::fs;
+
+    use assert_fs::prelude::*;
+
+    use ocptv::output::{Config, DutInfo, LogSeverity, TestResult, TestRun, TestStatus};
+
+    use super::fixture::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let measurements_file = fs.child("measurements.jsonl");
+    let other_file = fs.child("other.jsonl");
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_split_output(measurements_file.path(), other_file.path())
+                .await?
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    let step = run.add_step("step").start().await?;
+    step.add_measurement("fan", 1000).await?;
+    step.add_log(LogSeverity::Info, "log").await?;
+    let series = step.add_measurement_series("temp").start().await?;
+    series.add_measurement(40).await?;
+    series.end().await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let read_seqnos = |path: &std::path::Path| -> Result<Vec<(u64, serde_json::Value)>> {
+        fs::read_to_string(path)?
+            .lines()
+            .map(|l| {
+                let value = serde_json::from_str::<serde_json::Value>(l)?;
+                Ok((value["sequenceNumber"].as_u64().unwrap_or(u64::MAX), value))
+            })
+            .collect()
+    };
+
+    let measurements = read_seqnos(measurements_file.path())?;
+    let other = read_seqnos(other_file.path())?;
+
+    assert_eq!(
+        measurements.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+        vec![3, 5, 6, 7]
+    );
+    for (_, value) in &measurements {
+        let artifact = value["testStepArtifact"]
+            .as_object()
+            .ok_or_else(|| anyhow::anyhow!("not a step artifact"))?;
+        assert!(artifact.keys().any(|k| k.starts_with("measurement")));
+    }
+    assert_eq!(
+        other.iter().map(|(s, _)| *s).collect::<Vec<_>>(),
+        vec![0, 1, 2, 4, 8, 9]
+    );
+
+    Ok(())
+}
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_config_template_with_file_pattern() -> Result<()> {
+    use std::fs;
+
+    use assert_fs::prelude::*;
+
+    use ocptv::output::{Config, DutInfo, TestResult, TestRun, TestStatus};
+
+    use super::fixture::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let pattern = fs.path().join("output_{}.jsonl");
+
+    let template = Config::builder()
+        .with_timestamp_provider(Box::new(FixedTsProvider {}))
+        .with_emit_hook(Box::new(|value| {
+            if let Some(start) = value.pointer_mut("/testRunArtifact/testRunStart") {
+                start["metadata"] = serde_json::json!({"templated": true});
+            }
+        }))
+        .build_template(&pattern.to_string_lossy());
+
+    let shards = ["shard0", "shard1", "shard2"];
+    for shard in shards {
+        let dut = DutInfo::builder(shard).build();
+        let run = TestRun::builder("run_name", "1.0")
+            .config(template.config(shard)?)
+            .build()
+            .start(dut)
+            .await?;
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+    }
+
+    for shard in shards {
+        let content = fs::read_to_string(fs.child(format!("output_{}.jsonl", shard)).path())?;
+        let lines = content
+            .lines()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        assert_eq!(lines.len(), 3);
+        let start = &lines[1]["testRunArtifact"]["testRunStart"];
+        assert_eq!(start["dutInfo"]["dutInfoId"], shard);
+        assert_eq!(start["metadata"]["templated"], true);
+        assert_eq!(lines[1]["timestamp"], DATETIME_FORMATTED);
+    }
+
+    Ok(())
+}
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_config_from_env_with_file() -> Result<()> {
+    use std::fs;
+
+    use assert_fs::prelude::*;
+
+    use ocptv::output::{DutInfo, TestResult, TestRun, TestStatus};
+
+    let fs = assert_fs::TempDir::new()?;
+    let output_file = fs.child("output.jsonl");
+    output_file.write_str("{\"previous\": true}\n")?;
+
+    let config = {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let output = format!("file:{}", output_file.path().display());
+        std::env::set_var("OCPTV_OUTPUT", output);
+        let _env = EnvGuard::set(&[("OCPTV_APPEND", "true"), ("OCPTV_SEQUENCE_START", "1")]);
+        Config::from_env()?
+    };
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(config)
+        .build()
+        .start(dut)
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = fs::read_to_string(output_file.path())?;
+    let lines = content
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["previous"], true);
+    assert_eq!(lines[1]["sequenceNumber"], 1);
+
+    Ok(())
+}
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_config_builder_with_file_output_template() -> Result<()> {
+    use std::fs;
+
+    use ocptv::output::{DutInfo, TestResult, TestRun, TestStatus};
+
+    use super::fixture::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let template = format!(
+        "{}/{{run_name}}_{{dut_id}}_{{date}}.jsonl",
+        fs.path().display()
+    );
+
+    let run = |dut_id: &'static str| {
+        let template = template.clone();
+        async move {
+            let config = Config::builder()
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_file_output_template(&template)?
+                .build();
+
+            let run = TestRun::builder("run_name", "1.0")
+                .config(config)
+                .build()
+                .start(DutInfo::builder(dut_id).build())
+                .await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok::<_, anyhow::Error>(())
+        }
+    };
+    futures::future::try_join(run("dut0"), run("dut1")).await?;
+    // an existing file is never overwritten
+    run("dut0").await?;
+
+    for name in [
+        "run_name_dut0_1970-01-01.jsonl",
+        "run_name_dut1_1970-01-01.jsonl",
+        "run_name_dut0_1970-01-01_1.jsonl",
+    ] {
+        let content = fs::read_to_string(fs.path().join(name))?;
+        let lines = content
+            .lines()
+            .map(serde_json::from_str::<serde_json::Value>)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["sequenceNumber"], 0);
+    }
+
+    Ok(())
+}
@@ -7,7 +7,7 @@
 use anyhow::Result;
 use serde_json::json;
 
-use ocptv::output::{Diagnosis, DiagnosisType, Subcomponent};
+use ocptv::output::{Diagnosis, DiagnosisType, Subcomponent, SubcomponentType};
 
 use super::fixture::*;
 
@@ -80,3 +80,59 @@ async fn test_step_with_diagnosis_builder() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_step_with_diagnosis_builder_all_fields() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "diagnosis": {
+                    "verdict": "fan-speed-low",
+                    "type": "FAIL",
+                    "message": "fan speed below threshold",
+                    "hardwareInfoId": "hw0",
+                    "subcomponent": {
+                        "type": "ASIC",
+                        "name": "fan_ctrl",
+                        "location": "slot1",
+                        "version": "1",
+                        "revision": "A"
+                    },
+                    "sourceLocation": {
+                        "file": "fans.rs",
+                        "line": 42
+                    }
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    check_output_step(&expected, |s, dut| {
+        async move {
+            let subcomponent = Subcomponent::builder("fan_ctrl")
+                .subcomponent_type(SubcomponentType::Asic)
+                .location("slot1")
+                .version("1")
+                .revision("A")
+                .build();
+            let diagnosis = Diagnosis::builder("fan-speed-low", DiagnosisType::Fail)
+                .message("fan speed below threshold")
+                .hardware_info(dut.hardware_info("hw0").unwrap()) // must exist
+                .subcomponent(&subcomponent)
+                .source("fans.rs", 42)
+                .build();
+            s.add_diagnosis_detail(diagnosis).await?;
+
+            Ok(())
+        }
+    })
+    .await
+}
@@ -282,3 +282,221 @@ async fn test_testrun_step_error_with_details() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_testrun_step_error_with_foreign_software() -> Result<()> {
+    use ocptv::output::{DutInfo, OcptvError, SoftwareInfo};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json_run_pass(4),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let mut other = DutInfo::new("other_dut");
+        let sw_info = other.add_software_info(SoftwareInfo::builder("bios").build());
+        assert_eq!(sw_info.dut_id(), "other_dut");
+
+        let result = s
+            .add_error_detail(
+                Error::builder("symptom")
+                    .add_software_info(&sw_info)
+                    .build(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::ForeignSoftwareInfo(id, dut_id))
+                if id == "other_dut_sw_0" && dut_id == "other_dut"
+        ));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_end_with_error() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "error": {
+                    "message": "no tach signal",
+                    "symptom": "fan-missing"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(5),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let step = r.add_step("first step").start().await?;
+        step.end_with_error("fan-missing", Some("no tach signal"))
+            .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_end_with_error_detail() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "error": {
+                    "softwareInfoIds": [
+                        "sw0"
+                    ],
+                    "sourceLocation": {
+                        "file": "file",
+                        "line": 1
+                    },
+                    "symptom": "symptom"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(5),
+    ];
+
+    check_output_run(&expected, |r, dut| async move {
+        let step = r.add_step("first step").start().await?;
+        step.end_with_error_detail(
+            Error::builder("symptom")
+                .source("file", 1)
+                .add_software_info(dut.software_info("sw0").unwrap())
+                .build(),
+        )
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_end_with_error_detail_foreign_software() -> Result<()> {
+    use ocptv::output::{DutInfo, OcptvError, SoftwareInfo};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(4),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let mut other = DutInfo::new("other_dut");
+        let sw_info = other.add_software_info(SoftwareInfo::builder("bios").build());
+
+        // the error is rejected, but the step still ends
+        let step = r.add_step("first step").start().await?;
+        let result = step
+            .end_with_error_detail(
+                Error::builder("symptom")
+                    .add_software_info(&sw_info)
+                    .build(),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::ForeignSoftwareInfo(id, dut_id))
+                if id == "other_dut_sw_0" && dut_id == "other_dut"
+        ));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_error_references_registered_software() -> Result<()> {
+    use std::sync::Arc;
+
+    use tokio::sync::Mutex;
+
+    use ocptv::output::{Config, DutInfo, SoftwareInfo, TestResult, TestRun, TestStatus};
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let mut dut = DutInfo::builder("dut_id").build();
+    dut.add_software_info(SoftwareInfo::builder("bios").build());
+    // the handle returned on registration is all the error needs
+    let bmc = dut.add_software_info(SoftwareInfo::builder("bmc").version("2.1").build());
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    run.add_error_detail(
+        Error::builder("bmc-unresponsive")
+            .add_software_info(&bmc)
+            .build(),
+    )
+    .await?;
+    run.end(TestStatus::Complete, TestResult::Fail).await?;
+
+    let buffer = buffer.lock().await;
+    let start = serde_json::from_str::<serde_json::Value>(&buffer[1])?;
+    let software = &start["testRunArtifact"]["testRunStart"]["dutInfo"]["softwareInfos"];
+    assert_eq!(software[0]["softwareInfoId"], "dut_id_sw_0");
+    assert_eq!(software[1]["softwareInfoId"], "dut_id_sw_1");
+
+    let error = serde_json::from_str::<serde_json::Value>(&buffer[2])?;
+    assert_eq!(
+        error["testRunArtifact"]["error"]["softwareInfoIds"],
+        json!(["dut_id_sw_1"])
+    );
+
+    Ok(())
+}
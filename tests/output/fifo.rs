@@ -0,0 +1,129 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+// note: these need a real filesystem to create the named pipes, so only run them in
+// coverage mode, same as the file output tests in config.rs
+#![cfg(all(unix, coverage))]
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Result;
+use nix::sys::stat::Mode;
+use tokio::io::AsyncReadExt;
+use tokio::net::unix::pipe;
+
+use ocptv::output::{Config, Emitter, OcptvError, TestResult, TestStatus, WriterError};
+
+use super::fixture::*;
+
+fn make_fifo(fs: &assert_fs::TempDir) -> Result<PathBuf> {
+    let path = fs.path().join("output.fifo");
+    nix::unistd::mkfifo(&path, Mode::S_IRUSR | Mode::S_IWUSR)?;
+    Ok(path)
+}
+
+async fn read_all(path: &Path, delay: Duration) -> Result<String> {
+    tokio::time::sleep(delay).await;
+
+    let mut receiver = pipe::OpenOptions::new().open_receiver(path)?;
+    let mut content = String::new();
+    receiver.read_to_string(&mut content).await?;
+    Ok(content)
+}
+
+#[tokio::test]
+async fn test_fifo_output_waits_for_reader() -> Result<()> {
+    let fs = assert_fs::TempDir::new()?;
+    let path = make_fifo(&fs)?;
+
+    // the reader attaches only after the run has started emitting
+    let reader = tokio::spawn({
+        let path = path.clone();
+        async move { read_all(&path, Duration::from_millis(100)).await }
+    });
+
+    let config = Config::builder().with_fifo_output_timeout(&path, Duration::from_secs(10));
+    let run = start_run_with_config(config).await?;
+    for i in 0..10 {
+        let step = run.add_step(&format!("step{}", i)).start().await?;
+        step.add_measurement("fan", i).await?;
+        step.end(TestStatus::Complete).await?;
+    }
+    // ending the run closes the write end, so the reader sees the end of the stream
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = reader.await??;
+    let seqnos = content
+        .lines()
+        .map(|line| {
+            let value = serde_json::from_str::<serde_json::Value>(line)?;
+            Ok(value["sequenceNumber"].as_u64().unwrap())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    assert_eq!(seqnos, (0..33).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fifo_output_without_reader() -> Result<()> {
+    let fs = assert_fs::TempDir::new()?;
+    let path = make_fifo(&fs)?;
+
+    // building the config does not block, the pipe is opened on the first write
+    let config = Config::builder().with_fifo_output(&path);
+    let result = start_run_with_config(config).await;
+
+    match result {
+        Err(OcptvError::Writer(WriterError::NoReader { artifact, path: p })) => {
+            assert_eq!(artifact.kind, "schemaVersion");
+            assert_eq!(p, path);
+        }
+        _ => panic!("expected no reader error"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fifo_output_probe() -> Result<()> {
+    let fs = assert_fs::TempDir::new()?;
+    let path = make_fifo(&fs)?;
+
+    let emitter = Emitter::new(Config::builder().with_fifo_output(&path).build());
+    match emitter.probe().await {
+        Err(WriterError::NoReader { artifact, .. }) => assert_eq!(artifact.kind, "probe"),
+        _ => panic!("expected no reader error"),
+    }
+
+    let _receiver = pipe::OpenOptions::new().open_receiver(&path)?;
+    emitter.probe().await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fifo_output_broken_pipe() -> Result<()> {
+    let fs = assert_fs::TempDir::new()?;
+    let path = make_fifo(&fs)?;
+
+    let receiver = pipe::OpenOptions::new().open_receiver(&path)?;
+    let run = start_run_with_config(Config::builder().with_fifo_output(&path)).await?;
+    drop(receiver);
+
+    let result = run.add_error("symptom").await;
+    match result {
+        Err(OcptvError::Writer(WriterError::BrokenPipe { artifact, path: p })) => {
+            assert_eq!(artifact.kind, "testRunArtifact.error");
+            assert_eq!(artifact.seqno, 2);
+            assert_eq!(p, path);
+        }
+        _ => panic!("expected broken pipe error"),
+    }
+
+    Ok(())
+}
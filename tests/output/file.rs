@@ -7,7 +7,7 @@
 use anyhow::Result;
 use serde_json::json;
 
-use ocptv::output::{File, Uri};
+use ocptv::output::{File, OcptvError, Uri};
 
 use super::fixture::*;
 
@@ -82,3 +82,253 @@ async fn test_step_with_file_builder() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_step_with_http_file_builder() -> Result<()> {
+    let uri = Uri::parse("https://example.com/logs/dmesg.txt")?;
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "file": {
+                    "displayName": "name",
+                    "uri": uri.clone().as_str().to_owned(),
+                    "isSnapshot": true,
+                    "contentType": "text/plain",
+                    "description": "description",
+                    "metadata": {
+                        "key": "value"
+                    },
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let file = File::builder("name", uri)
+            .is_snapshot(true)
+            .content_type(mime::TEXT_PLAIN)
+            .description("description")
+            .add_metadata("key", "value")
+            .build();
+        s.add_file_detail(file).await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_file_unsupported_uri() -> Result<()> {
+    let mailto = Uri::parse("mailto:ops@example.com")?;
+    let ftp = Uri::parse("ftp://example.com/foo")?;
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json_run_pass(4),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let result = s.add_file("name", mailto).await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::UnsupportedFileUri(uri)) if uri == "mailto:ops@example.com"
+        ));
+
+        let file = File::builder("name", ftp).build();
+        let result = s.add_file_detail(file).await;
+        assert!(matches!(result, Err(OcptvError::UnsupportedFileUri(_))));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_file_from_path() -> Result<()> {
+    use assert_fs::prelude::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let dmesg = fs.child("dmesg.log");
+    dmesg.write_str("hello")?;
+    let uri = Uri::from_file_path(dmesg.path().canonicalize()?).unwrap();
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "file": {
+                    "displayName": "dmesg.log",
+                    "uri": uri.as_str(),
+                    "isSnapshot": true,
+                    "contentType": "text/plain"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "file": {
+                    "displayName": "dmesg.log",
+                    "uri": uri.as_str(),
+                    "isSnapshot": true,
+                    "contentType": "text/plain",
+                    "metadata": {
+                        "ocptv_file_size": 5,
+                        "ocptv_sha256": "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+                    }
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    let path = dmesg.path().to_owned();
+    check_output_step(&expected, |s, _| async move {
+        s.add_file_from_path(&path).await?;
+        s.add_file_from_path_with_digest(&path).await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_file_from_missing_path() -> Result<()> {
+    let fs = assert_fs::TempDir::new()?;
+    let missing = fs.path().join("missing.txt");
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json_run_pass(4),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let result = s.add_file_from_path_with_digest(&missing).await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::FileArtifact(path, _)) if path == missing
+        ));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_file_from_path_resolved() -> Result<()> {
+    use assert_fs::prelude::*;
+    use futures::FutureExt;
+
+    use ocptv::output::{TestResult, TestStatus};
+
+    let fs = assert_fs::TempDir::new()?;
+    let dmesg = fs.child("dmesg.log");
+    dmesg.write_str("hello")?;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "file": {
+                    "displayName": "dmesg.log",
+                    "uri": "https://storage.example.com/runs/dmesg.log",
+                    "isSnapshot": true,
+                    "contentType": "text/plain"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    let path = dmesg.path().to_owned();
+    check_output_with_config(
+        &expected,
+        |config| {
+            config.with_file_uri_resolver(Box::new(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                async move { Ok(format!("https://storage.example.com/runs/{}", name)) }.boxed()
+            }))
+        },
+        |run_builder, dut| async move {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+            step.add_file_from_path(&path).await?;
+            step.end(TestStatus::Complete).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_file_from_path_resolver_failure() -> Result<()> {
+    use assert_fs::prelude::*;
+    use futures::FutureExt;
+
+    use ocptv::output::{TestResult, TestStatus};
+
+    let fs = assert_fs::TempDir::new()?;
+    let dmesg = fs.child("dmesg.log");
+    dmesg.write_str("hello")?;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json_run_pass(4),
+    ];
+
+    let path = dmesg.path().to_owned();
+    check_output_with_config(
+        &expected,
+        |config| {
+            config.with_file_uri_resolver(Box::new(|_| {
+                async { Err(OcptvError::Other("upload failed".into())) }.boxed()
+            }))
+        },
+        |run_builder, dut| async move {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+
+            let result = step.add_file_from_path(&path).await;
+            assert!(matches!(
+                result,
+                Err(OcptvError::Other(e)) if e.to_string() == "upload failed"
+            ));
+
+            step.end(TestStatus::Complete).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
@@ -13,9 +13,9 @@ use serde_json::json;
 use tokio::sync::Mutex;
 
 use ocptv::output::{
-    Config, DutInfo, HardwareInfo, Ident, OcptvError, ScopedTestRun, ScopedTestStep, SoftwareInfo,
-    SoftwareType, TestResult, TestRun, TestRunBuilder, TestRunOutcome, TestStatus,
-    TimestampProvider, SPEC_VERSION,
+    Config, ConfigBuilder, DutInfo, HardwareInfo, Ident, OcptvError, ScopedTestRun, ScopedTestStep,
+    SoftwareInfo, SoftwareType, StartedTestRun, TestResult, TestRun, TestRunBuilder,
+    TestRunOutcome, TestStatus, TimestampProvider, SPEC_VERSION,
 };
 
 pub const DATETIME: chrono::DateTime<chrono::offset::Utc> =
@@ -116,6 +116,21 @@ pub async fn check_output<F, R>(expected: &[serde_json::Value], test_fn: F) -> R
 where
     R: Future<Output = Result<()>>,
     F: FnOnce(TestRunBuilder, DutInfo) -> R,
+{
+    check_output_with_config(expected, |config| config, test_fn).await
+}
+
+/// Same as [`check_output`], but allows customizing the run configuration.
+/// The buffer output and fixed timestamp provider are always set.
+pub async fn check_output_with_config<C, F, R>(
+    expected: &[serde_json::Value],
+    config_fn: C,
+    test_fn: F,
+) -> Result<()>
+where
+    C: FnOnce(ConfigBuilder) -> ConfigBuilder,
+    R: Future<Output = Result<()>>,
+    F: FnOnce(TestRunBuilder, DutInfo) -> R,
 {
     let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
     let mut dut = DutInfo::builder("dut_id").build();
@@ -134,7 +149,7 @@ where
     );
 
     let run_builder = TestRun::builder("run_name", "1.0").config(
-        Config::builder()
+        config_fn(Config::builder())
             .with_buffer_output(Arc::clone(&buffer))
             .with_timestamp_provider(Box::new(FixedTsProvider {}))
             .build(),
@@ -151,6 +166,22 @@ where
     Ok(())
 }
 
+/// Starts a default run with the given configuration and the fixed timestamp provider,
+/// for the tests reading the output themselves, eg. from a file or a pipe.
+pub async fn start_run_with_config(config: ConfigBuilder) -> Result<StartedTestRun, OcptvError> {
+    let dut = DutInfo::builder("dut_id").build();
+
+    TestRun::builder("run_name", "1.0")
+        .config(
+            config
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await
+}
+
 pub async fn check_output_run<F, R>(expected: &[serde_json::Value], test_fn: F) -> Result<()>
 where
     R: Future<Output = Result<(), OcptvError>> + Send + 'static,
@@ -0,0 +1,144 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+#![cfg(any(feature = "msgpack", feature = "cbor"))]
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use anyhow::Result;
+use assert_json_diff::assert_json_eq;
+use tokio::sync::Mutex;
+
+use ocptv::output::{
+    Config, ConfigBuilder, Measurement, OcptvError, OutputFormat, TestResult, TestStatus,
+    Validator, ValidatorType,
+};
+use ocptv::reader;
+
+use super::fixture::*;
+
+async fn run_with(config: ConfigBuilder) -> Result<(), OcptvError> {
+    let run = start_run_with_config(config).await?;
+
+    let step = run.add_step("step").start().await?;
+    step.add_measurement_detail(
+        Measurement::builder("fan", 1000.5)
+            .unit("RPM")
+            .add_validator(Validator::builder(ValidatorType::LessThan, 2000).build())
+            .add_metadata("key", "value")
+            .build(),
+    )
+    .await?;
+
+    let series = step.add_measurement_series("temp").start().await?;
+    for i in 0..3 {
+        series.add_measurement(40 + i).await?;
+    }
+    series.end().await?;
+
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+    Ok(())
+}
+
+/// Runs the same test with the json and the `format` outputs, and checks that the
+/// decoded artifacts are the same.
+async fn check_round_trip<F>(format: OutputFormat, decode: F) -> Result<()>
+where
+    F: FnOnce(Cursor<Vec<u8>>) -> reader::Reader,
+{
+    let json_buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    run_with(Config::builder().with_buffer_output(Arc::clone(&json_buffer))).await?;
+
+    let buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    run_with(
+        Config::builder()
+            .with_bytes_output(Arc::clone(&buffer))
+            .with_output_format(format),
+    )
+    .await?;
+
+    let content = buffer.lock().await.clone();
+    let expected = json_buffer.lock().await;
+    assert!(content.len() < expected.iter().map(|s| s.len()).sum::<usize>());
+
+    let actual = decode(Cursor::new(content)).collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual.len(), expected.len());
+    for (value, entry) in actual.iter().zip(expected.iter()) {
+        assert_json_eq!(value, serde_json::from_str::<serde_json::Value>(entry)?);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn test_msgpack_round_trip() -> Result<()> {
+    check_round_trip(OutputFormat::MessagePack, reader::from_msgpack_reader).await
+}
+
+#[cfg(feature = "cbor")]
+#[tokio::test]
+async fn test_cbor_round_trip() -> Result<()> {
+    check_round_trip(OutputFormat::Cbor, reader::from_cbor_reader).await
+}
+
+#[cfg(feature = "cbor")]
+#[tokio::test]
+async fn test_cbor_keeps_spec_names_and_timestamps() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    run_with(
+        Config::builder()
+            .with_bytes_output(Arc::clone(&buffer))
+            .with_output_format(OutputFormat::Cbor),
+    )
+    .await?;
+
+    // decode to the generic cbor model, so that the value types are not coerced by json
+    let content = buffer.lock().await.clone();
+    let mut cursor = Cursor::new(content);
+    let first: ciborium::Value = ciborium::from_reader(&mut cursor)?;
+    let start: ciborium::Value = ciborium::from_reader(&mut cursor)?;
+
+    let entries = first.as_map().ok_or_else(|| anyhow::anyhow!("not a map"))?;
+    let keys = entries
+        .iter()
+        .filter_map(|(k, _)| k.as_text())
+        .collect::<Vec<_>>();
+    assert!(keys.contains(&"schemaVersion"));
+    assert!(keys.contains(&"sequenceNumber"));
+
+    let timestamp = start
+        .as_map()
+        .and_then(|m| m.iter().find(|(k, _)| k.as_text() == Some("timestamp")))
+        .and_then(|(_, v)| v.as_text())
+        .ok_or_else(|| anyhow::anyhow!("missing timestamp string"))?;
+    assert_eq!(timestamp, DATETIME_FORMATTED);
+
+    Ok(())
+}
+
+#[cfg(feature = "msgpack")]
+#[tokio::test]
+async fn test_msgpack_needs_byte_output() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let actual = run_with(
+        Config::builder()
+            .with_buffer_output(buffer)
+            .with_output_format(OutputFormat::MessagePack),
+    )
+    .await;
+    match actual {
+        Err(OcptvError::Writer(ocptv::output::WriterError::Io { source, .. })) => {
+            assert_eq!(source.kind(), std::io::ErrorKind::Unsupported);
+        }
+        _ => panic!("expected unsupported output error"),
+    }
+
+    Ok(())
+}
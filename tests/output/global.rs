@@ -0,0 +1,120 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use assert_json_diff::assert_json_eq;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use ocptv::output::{self as tv, Config, DutInfo, OcptvError, TestResult, TestRun, TestStatus};
+
+use super::fixture::*;
+
+mod fans {
+    use ocptv::output::{global_log, LogSeverity, OcptvError};
+
+    pub async fn check() -> Result<(), OcptvError> {
+        global_log(LogSeverity::Info, "fans ok").await
+    }
+}
+
+mod power {
+    use ocptv::ocptv_error;
+    use ocptv::output::{global, OcptvError};
+
+    pub async fn check() -> Result<(), OcptvError> {
+        ocptv_error!(global()?, "psu-fault", "psu1 is down").await
+    }
+}
+
+// note: the global run is process wide, so this is the only test using it
+#[tokio::test]
+async fn test_global_run() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let builder = || {
+        TestRun::builder("run_name", "1.0").config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+    };
+
+    assert!(matches!(
+        fans::check().await,
+        Err(OcptvError::GlobalRunNotInitialized)
+    ));
+
+    tv::init_run(builder(), DutInfo::builder("dut_id").build()).await?;
+    assert!(matches!(
+        tv::init_run(builder(), DutInfo::builder("dut_id").build()).await,
+        Err(OcptvError::GlobalRunAlreadyInitialized)
+    ));
+
+    fans::check().await?;
+    power::check().await?;
+    tv::shutdown(TestStatus::Complete, TestResult::Fail).await?;
+
+    assert!(matches!(
+        fans::check().await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+
+    let expected = [
+        json_schema_version(),
+        json!({
+            "testRunArtifact": {
+                "testRunStart": {
+                    "dutInfo": {"dutInfoId": "dut_id"},
+                    "name": "run_name",
+                    "parameters": {},
+                    "version": "1.0",
+                    "commandLine": ""
+                }
+            },
+            "sequenceNumber": 1,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "log": {"message": "fans ok", "severity": "INFO"}
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "error": {
+                    "symptom": "psu-fault",
+                    "message": "psu1 is down",
+                    "sourceLocation": {
+                        "file": "tests/output/global.rs",
+                        "line": 31
+                    }
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {"result": "FAIL", "status": "COMPLETE"}
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    let lines = buffer.lock().await;
+    assert_eq!(lines.len(), expected.len());
+    for (line, expected) in lines.iter().zip(&expected) {
+        assert_json_eq!(serde_json::from_str::<serde_json::Value>(line)?, expected);
+    }
+
+    Ok(())
+}
@@ -144,3 +144,143 @@ async fn test_testrun_step_log_with_details() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_testrun_with_severity_logs() -> Result<()> {
+    let log = |severity: &str, seqno: i32| {
+        json!({
+            "testRunArtifact": {
+                "log": {
+                    "message": format!("{} message", severity),
+                    "severity": severity
+                }
+            },
+            "sequenceNumber": seqno,
+            "timestamp": DATETIME_FORMATTED
+        })
+    };
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        log("DEBUG", 2),
+        log("INFO", 3),
+        log("WARNING", 4),
+        log("ERROR", 5),
+        log("FATAL", 6),
+        json_run_pass(7),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        r.log_debug("DEBUG message").await?;
+        r.log_info("INFO message").await?;
+        r.log_warning("WARNING message").await?;
+        r.log_error("ERROR message").await?;
+        r.log_fatal("FATAL message").await
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_with_severity_logs() -> Result<()> {
+    let log = |severity: &str, seqno: i32| {
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": format!("{} message", severity),
+                    "severity": severity
+                }
+            },
+            "sequenceNumber": seqno,
+            "timestamp": DATETIME_FORMATTED
+        })
+    };
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        log("DEBUG", 3),
+        log("INFO", 4),
+        log("WARNING", 5),
+        log("ERROR", 6),
+        log("FATAL", 7),
+        json_step_complete(8),
+        json_run_pass(9),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        s.log_debug("DEBUG message").await?;
+        s.log_info("INFO message").await?;
+        s.log_warning("WARNING message").await?;
+        s.log_error("ERROR message").await?;
+        s.log_fatal("FATAL message").await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_with_min_log_severity() -> Result<()> {
+    use ocptv::output::{TestResult, TestStatus};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json!({
+            "testRunArtifact": {
+                "log": {
+                    "message": "run error",
+                    "severity": "ERROR"
+                }
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepStart": {
+                    "name": "first step"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": "step error",
+                    "severity": "ERROR"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_min_log_severity(LogSeverity::Warning),
+        |run_builder, dut| async {
+            let run = run_builder.build().start(dut).await?;
+            run.log_debug("run debug").await?;
+            run.log_info("run info").await?;
+            run.log_error("run error").await?;
+
+            let step = run.add_step("first step").start().await?;
+            step.log_debug("step debug").await?;
+            step.log_info("step info").await?;
+            step.log_error("step error").await?;
+            step.end(TestStatus::Complete).await?;
+
+            assert_eq!(run.stats().suppressed_logs, 4);
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
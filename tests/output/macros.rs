@@ -17,7 +17,7 @@ use ocptv::ocptv_error;
 use ocptv::output as tv;
 use ocptv::{
     ocptv_diagnosis_fail, ocptv_diagnosis_pass, ocptv_diagnosis_unknown, ocptv_log_debug,
-    ocptv_log_error, ocptv_log_fatal, ocptv_log_info, ocptv_log_warning,
+    ocptv_log_error, ocptv_log_fatal, ocptv_log_info, ocptv_log_warning, ocptv_logf,
 };
 use tv::{Config, DutInfo, StartedTestRun, StartedTestStep, TestRun};
 
@@ -179,6 +179,52 @@ async fn test_ocptv_log_info() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_ocptv_logf() -> Result<()> {
+    let expected = json!({
+        "testRunArtifact": {
+            "log": {
+                "message": "fan {0}: 3000 rpm, température 42.5°C ✓",
+                "severity": "WARNING"
+            }
+        },
+        "sequenceNumber": 2
+    });
+
+    let (fan, rpm, temp) = ("fan", 3000, 42.5);
+    check_output_run(&expected, "log", |run| async move {
+        ocptv_logf!(
+            run,
+            Warning,
+            "{fan} {{0}}: {} rpm, température {temp}°C ✓",
+            rpm
+        )
+        .await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_ocptv_logf_in_step() -> Result<()> {
+    let message = "x".repeat(10_000);
+    let expected = json!({
+        "testStepArtifact": {
+            "log": {
+                "message": format!("{{}}{}", message),
+                "severity": "DEBUG"
+            }
+        },
+        "sequenceNumber": 3
+    });
+
+    check_output_step(&expected, "log", |step| async move {
+        ocptv_logf!(step, Debug, "{{}}{}", message).await?;
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_ocptv_log_warning() -> Result<()> {
     let expected = json!({
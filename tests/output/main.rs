@@ -4,13 +4,23 @@
 // license that can be found in the LICENSE file or at
 // https://opensource.org/licenses/MIT.
 
+mod compress;
 mod config;
 mod diagnosis;
 mod error;
+mod fifo;
 mod file;
 mod fixture;
+mod format;
+mod global;
 mod log;
 mod macros;
 mod measure;
+mod reader;
+mod redact;
 mod run;
+mod schema;
+mod stdout;
 mod step;
+mod sync;
+mod writer;
@@ -8,8 +8,8 @@ use anyhow::Result;
 use serde_json::json;
 
 use ocptv::output::{
-    Ident, Measurement, MeasurementElementDetail, MeasurementSeriesDetail, Subcomponent, Validator,
-    ValidatorType,
+    Ident, Measurement, MeasurementElementDetail, MeasurementSeriesDetail, MeasurementValue,
+    OcptvError, Subcomponent, Unit, Validator, ValidatorError, ValidatorType,
 };
 
 use super::fixture::*;
@@ -43,6 +43,37 @@ async fn test_step_with_measurement() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_step_with_measurement_with_unit() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan_speed",
+                    "value": 8000,
+                    "unit": "rpm"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        s.add_measurement_with_unit("fan_speed", 8000, "rpm")
+            .await?;
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_step_with_measurement_builder() -> Result<()> {
     let expected = [
@@ -95,6 +126,174 @@ async fn test_step_with_measurement_builder() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_step_with_measurement_validator_details() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan_speed",
+                    "value": 8000,
+                    "unit": "RPM",
+                    "validators": [{
+                        "name": "min_speed",
+                        "type": "GREATER_THAN_OR_EQUAL",
+                        "value": 5000,
+                        "metadata": {
+                            "source": "datasheet",
+                            "revision": 2
+                        }
+                    }, {
+                        "type": "LESS_THAN",
+                        "value": 12000
+                    }]
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "fan_speed",
+                    "validators": [{
+                        "name": "allowed_modes",
+                        "type": "IN_SET",
+                        "value": ["auto", "max"],
+                        "metadata": {
+                            "source": "bmc"
+                        }
+                    }]
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 0
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(6),
+        json_run_pass(7),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let measurement = Measurement::builder("fan_speed", 8000)
+            .unit(Unit::RPM)
+            .add_validator(
+                Validator::builder(ValidatorType::GreaterThanOrEqual, 5000)
+                    .name("min_speed")
+                    .add_metadata("source", "datasheet")
+                    .add_metadata("revision", 2)
+                    .build(),
+            )
+            .add_validator(Validator::less_than(12000))
+            .build();
+        s.add_measurement_detail(measurement).await?;
+
+        let series = s
+            .add_measurement_series_detail(
+                MeasurementSeriesDetail::builder("fan_speed")
+                    .add_validator(
+                        Validator::builder(ValidatorType::InSet, ["auto", "max"])
+                            .name("allowed_modes")
+                            .add_metadata("source", "bmc")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .start()
+            .await?;
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_checked() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan_speed",
+                    "value": 8000,
+                    "validators": [{
+                        "name": "min_speed",
+                        "type": "GREATER_THAN_OR_EQUAL",
+                        "value": 5000
+                    }]
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan_speed",
+                    "value": 4200,
+                    "validators": [{
+                        "name": "min_speed",
+                        "type": "GREATER_THAN_OR_EQUAL",
+                        "value": 5000
+                    }]
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let validator = Validator::builder(ValidatorType::GreaterThanOrEqual, 5000)
+            .name("min_speed")
+            .build();
+
+        assert!(
+            s.add_measurement_checked("fan_speed", 8000, &validator)
+                .await?
+        );
+        assert!(
+            !s.add_measurement_checked("fan_speed", 4200, &validator)
+                .await?
+        );
+
+        // not emitted, since the validator can't be evaluated
+        let result = s
+            .add_measurement_checked("fan_speed", "fast", &validator)
+            .await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::Validation(ValidatorError::TypeMismatch(..)))
+        ));
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_step_with_measurement_series() -> Result<()> {
     let expected = [
@@ -136,6 +335,51 @@ async fn test_step_with_measurement_series() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_step_with_measurement_series_with_unit() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "temperature",
+                    "unit": "C"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 0
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s
+            .add_measurement_series_with_unit("temperature", "C")
+            .start()
+            .await?;
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_step_with_multiple_measurement_series() -> Result<()> {
     let expected = [
@@ -678,3 +922,1339 @@ async fn test_step_with_measurement_series_scope() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_step_with_measurement_series_scope_error() -> Result<()> {
+    use futures::FutureExt;
+
+    use ocptv::output::{TestResult, TestStatus};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "name"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": 0,
+                    "measurementSeriesId": "step0_series0",
+                    "value": 60,
+                    "timestamp": DATETIME_FORMATTED
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 1
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(7),
+    ];
+
+    check_output(&expected, |run_builder, dut| async move {
+        let run = run_builder.build().start(dut).await?;
+
+        // composes with the step scope: the series ends before the step does
+        let result = run
+            .add_step("first step")
+            .scope(|s| {
+                async move {
+                    s.add_measurement_series("name")
+                        .scope(|series| {
+                            async move {
+                                series.add_measurement(60).await?;
+                                Err(OcptvError::Other("sensor lost".into()))
+                            }
+                            .boxed()
+                        })
+                        .await?;
+
+                    Ok(TestStatus::Complete)
+                }
+                .boxed()
+            })
+            .await;
+        assert!(matches!(result, Err(OcptvError::Other(_))));
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_element_at() -> Result<()> {
+    let element = |seqno: i32, index: i32, value: i32, timestamp: &str| {
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": index,
+                    "measurementSeriesId": "step0_series0",
+                    "value": value,
+                    "timestamp": timestamp
+                }
+            },
+            "sequenceNumber": seqno,
+            "timestamp": DATETIME_FORMATTED
+        })
+    };
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "name"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        // note: out of order elements are allowed by default
+        element(4, 0, 60, "1970-01-01T00:00:02.000Z"),
+        element(5, 1, 70, "1970-01-01T00:00:01.000Z"),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 2
+                }
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(7),
+        json_run_pass(8),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let at =
+            |secs: i64| (DATETIME + chrono::Duration::seconds(secs)).with_timezone(&chrono_tz::UTC);
+
+        let series = s.add_measurement_series("name").start().await?;
+        series.add_measurement_at(at(2), 60).await?;
+        series.add_measurement_at(at(1), 70).await?;
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_strict_timestamps() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "name"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": 0,
+                    "measurementSeriesId": "step0_series0",
+                    "value": 60,
+                    "timestamp": "1970-01-01T00:00:02.000Z"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 1
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(6),
+        json_run_pass(7),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let at =
+            |secs: i64| (DATETIME + chrono::Duration::seconds(secs)).with_timezone(&chrono_tz::UTC);
+
+        let series = s
+            .add_measurement_series_detail(
+                MeasurementSeriesDetail::builder("name")
+                    .strict_timestamps(true)
+                    .build(),
+            )
+            .start()
+            .await?;
+        series.add_measurement_at(at(2), 60).await?;
+        assert!(matches!(
+            series.add_measurement_at(at(1), 70).await,
+            Err(OcptvError::OutOfOrderElement(id)) if id == "step0_series0"
+        ));
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_used_after_end() -> Result<()> {
+    use std::sync::{Arc, Mutex};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "name"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 0
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let leaked = Arc::new(Mutex::new(None));
+
+        let slot = Arc::clone(&leaked);
+        s.add_measurement_series("name")
+            .scope(|series| async move {
+                *slot.lock().unwrap() = Some(series);
+                Ok(())
+            })
+            .await?;
+
+        // the scoped series outlived the scope, which already ended the series
+        let series = leaked.lock().unwrap().take().unwrap();
+        assert!(matches!(
+            series.add_measurement(60).await,
+            Err(OcptvError::SeriesAlreadyEnded(id)) if id == "step0_series0"
+        ));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_batch() -> Result<()> {
+    let measurement = |seqno: i32, name: &str, value: serde_json::Value| {
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": name,
+                    "value": value
+                }
+            },
+            "sequenceNumber": seqno,
+            "timestamp": DATETIME_FORMATTED
+        })
+    };
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        measurement(3, "dimm_count", json!(16)),
+        measurement(4, "cpu_temp", json!(41.5)),
+        measurement(5, "bios_version", json!("1.2.3")),
+        measurement(6, "secure_boot", json!(true)),
+        json_step_complete(7),
+        json_run_pass(8),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        s.add_measurements(vec![
+            Measurement::new("dimm_count", 16),
+            Measurement::new("cpu_temp", 41.5),
+            Measurement::new("bios_version", "1.2.3"),
+            Measurement::new("secure_boot", true),
+        ])
+        .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_batch_interrupted() -> Result<()> {
+    use ocptv::output::{TestResult, TestStatus, WriterError};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan0",
+                    "value": 1000
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_max_artifact_bytes(1024),
+        |run_builder, dut| async {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+
+            let result = step
+                .add_measurements(vec![
+                    Measurement::new("fan0", 1000),
+                    Measurement::builder("fan1", 1000)
+                        .add_metadata("blob", "x".repeat(4096))
+                        .build(),
+                    Measurement::new("fan2", 1000),
+                ])
+                .await;
+            // the rejected artifact still consumed its sequence number
+            assert!(matches!(
+                result,
+                Err(OcptvError::BatchInterrupted(1, e))
+                    if matches!(*e, OcptvError::Writer(WriterError::ArtifactTooLarge { .. }))
+            ));
+
+            step.end(TestStatus::Complete).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+fn json_series_start(seqno: i32) -> serde_json::Value {
+    json!({
+        "testStepArtifact": {
+            "testStepId": "step0",
+            "measurementSeriesStart": {
+                "measurementSeriesId": "step0_series0",
+                "name": "name"
+            }
+        },
+        "sequenceNumber": seqno,
+        "timestamp": DATETIME_FORMATTED
+    })
+}
+
+fn json_series_element(seqno: usize, index: usize, value: usize) -> serde_json::Value {
+    json!({
+        "testStepArtifact": {
+            "testStepId": "step0",
+            "measurementSeriesElement": {
+                "index": index,
+                "measurementSeriesId": "step0_series0",
+                "value": value,
+                "timestamp": DATETIME_FORMATTED
+            }
+        },
+        "sequenceNumber": seqno,
+        "timestamp": DATETIME_FORMATTED
+    })
+}
+
+fn json_series_end(seqno: usize, total_count: usize) -> serde_json::Value {
+    json!({
+        "testStepArtifact": {
+            "testStepId": "step0",
+            "measurementSeriesEnd": {
+                "measurementSeriesId": "step0_series0",
+                "totalCount": total_count
+            }
+        },
+        "sequenceNumber": seqno,
+        "timestamp": DATETIME_FORMATTED
+    })
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_batch() -> Result<()> {
+    const COUNT: usize = 1000;
+
+    let mut expected = vec![
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_series_start(3),
+    ];
+    expected.extend((0..COUNT).map(|i| json_series_element(4 + i, i, 2 * i)));
+    expected.extend([
+        json_series_end(4 + COUNT, COUNT),
+        json_step_complete(5 + COUNT as i32),
+        json_run_pass(6 + COUNT as i32),
+    ]);
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s.add_measurement_series("name").start().await?;
+        series.add_measurements((0..COUNT).map(|i| 2 * i)).await?;
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_batch_interrupted() -> Result<()> {
+    use ocptv::output::{TestResult, TestStatus};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_series_start(3),
+        json_series_element(4, 0, 60),
+        // note: the rejected element still consumed its sequence number, not its index
+        json_series_element(6, 1, 80),
+        json_series_end(7, 2),
+        json_step_complete(8),
+        json_run_pass(9),
+    ];
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_max_artifact_bytes(1024),
+        |run_builder, dut| async {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+            let series = step.add_measurement_series("name").start().await?;
+
+            let result = series
+                .add_measurement_details([
+                    MeasurementElementDetail::builder(60).build(),
+                    MeasurementElementDetail::builder(70)
+                        .add_metadata("blob", "x".repeat(4096))
+                        .build(),
+                    MeasurementElementDetail::builder(80).build(),
+                ])
+                .await;
+            assert!(matches!(result, Err(OcptvError::BatchInterrupted(1, _))));
+
+            series.add_measurement(80).await?;
+            series.end().await?;
+            step.end(TestStatus::Complete).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_end_with_stats() -> Result<()> {
+    let values = [
+        json!(2),
+        json!(4),
+        json!(4),
+        json!("n/a"),
+        json!(4),
+        json!(5),
+        json!(5),
+        json!(7),
+        json!(9),
+    ];
+    let stat = |seqno: usize, name: &str, value: serde_json::Value| {
+        let mut measurement = json!({
+            "name": name,
+            "value": value,
+        });
+        if name != "temp_count" {
+            measurement["unit"] = json!("C");
+        }
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": measurement
+            },
+            "sequenceNumber": seqno,
+            "timestamp": DATETIME_FORMATTED
+        })
+    };
+
+    let mut expected = vec![
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "temp",
+                    "unit": "C"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+    expected.extend(values.iter().enumerate().map(|(i, value)| {
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": i,
+                    "measurementSeriesId": "step0_series0",
+                    "value": value,
+                    "timestamp": DATETIME_FORMATTED
+                }
+            },
+            "sequenceNumber": 4 + i,
+            "timestamp": DATETIME_FORMATTED
+        })
+    }));
+    expected.extend([
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": "skipped 1 non-numeric elements in the statistics of series step0_series0",
+                    "severity": "WARNING"
+                }
+            },
+            "sequenceNumber": 13,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        stat(14, "temp_min", json!(2.0)),
+        stat(15, "temp_max", json!(9.0)),
+        stat(16, "temp_mean", json!(5.0)),
+        stat(17, "temp_stddev", json!(2.0)),
+        stat(18, "temp_count", json!(8)),
+        json_series_end(19, 9),
+        json_step_complete(20),
+        json_run_pass(21),
+    ]);
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s
+            .add_measurement_series_with_unit("temp", "C")
+            .start()
+            .await?;
+        // both the single and batch additions count in the statistics
+        series.add_measurement(2).await?;
+        let values = values[1..]
+            .iter()
+            .cloned()
+            .map(MeasurementValue::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        series.add_measurements(values).await?;
+        series.end_with_stats().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_step_with_measurement_series_sample_every() -> Result<()> {
+    use std::time::Duration;
+
+    // note: 4 ticks in the first 35ms, then a single tick for the 100ms the runtime
+    // was held up, since missed ticks are skipped rather than sampled in a burst
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_series_start(3),
+        json_series_element(4, 0, 0),
+        json_series_element(5, 1, 1),
+        json_series_element(6, 2, 2),
+        json_series_element(7, 3, 3),
+        json_series_element(8, 4, 4),
+        json_series_end(9, 5),
+        json_step_complete(10),
+        json_run_pass(11),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let mut next = 0;
+        let sampler = s
+            .add_measurement_series("name")
+            .sample_every(Duration::from_millis(10), move || {
+                next += 1;
+                next - 1
+            })
+            .await?;
+
+        tokio::time::sleep(Duration::from_millis(35)).await;
+        tokio::time::advance(Duration::from_millis(100)).await;
+        tokio::time::sleep(Duration::from_millis(1)).await;
+
+        assert_eq!(sampler.stop().await?, 5);
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_from_stream() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_series_start(3),
+        json_series_element(4, 0, 60),
+        json_series_element(5, 1, 70),
+        json_series_element(6, 2, 80),
+        json_series_end(7, 3),
+        json_step_complete(8),
+        json_run_pass(9),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s.add_measurement_series("name").start().await?;
+        let count = series
+            .extend_from_stream(futures::stream::iter([60, 70, 80]))
+            .await?;
+        assert_eq!(count, 3);
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_step_with_measurement_series_from_stream_cancelled() -> Result<()> {
+    use std::time::Duration;
+
+    use futures::StreamExt;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_series_start(3),
+        json_series_element(4, 0, 60),
+        json_series_element(5, 1, 70),
+        json_series_end(6, 2),
+        json_step_complete(7),
+        json_run_pass(8),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s.add_measurement_series("name").start().await?;
+
+        // note: the sensor stalls after two readings, so draining it times out
+        let readings = futures::stream::iter([60, 70]).chain(futures::stream::pending());
+        let result =
+            tokio::time::timeout(Duration::from_secs(1), series.extend_from_stream(readings)).await;
+        assert!(result.is_err());
+
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_step_with_measurement_series_add_cancelled_mid_write() -> Result<()> {
+    use std::fs;
+
+    use assert_fs::prelude::*;
+    use futures::FutureExt;
+
+    use ocptv::output::{Config, DutInfo, SyncPolicy, TestResult, TestRun, TestStatus};
+
+    let fs = assert_fs::TempDir::new()?;
+    let output_file = fs.child("output.jsonl");
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_file_output(output_file.path())
+                .await?
+                .with_sync_policy(SyncPolicy::EveryWrite)
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    let step = run.add_step("step").start().await?;
+    let series = step.add_measurement_series("name").start().await?;
+
+    // note: polling once then dropping cancels the add while the element is being
+    // written or synced to the file, which must not give its index to a later element
+    const COUNT: usize = 20;
+    for i in 0..COUNT {
+        let _ = series.add_measurement(i as u64).now_or_never();
+    }
+    series.end().await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = fs::read_to_string(output_file.path())?;
+    let artifacts = content
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let indexes = artifacts
+        .iter()
+        .filter_map(|a| a.pointer("/testStepArtifact/measurementSeriesElement/index"))
+        .collect::<Vec<_>>();
+    let values = artifacts
+        .iter()
+        .filter_map(|a| a.pointer("/testStepArtifact/measurementSeriesElement/value"))
+        .collect::<Vec<_>>();
+    let total = artifacts
+        .iter()
+        .find_map(|a| a.pointer("/testStepArtifact/measurementSeriesEnd/totalCount"));
+
+    let indexes = indexes
+        .iter()
+        .map(|index| index.as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert!(indexes.windows(2).all(|w| w[0] < w[1]), "{:?}", indexes);
+    assert_eq!(values.len(), indexes.len());
+    // note: only the elements whose write completed count in the series
+    let total = total.and_then(serde_json::Value::as_u64).unwrap();
+    assert!(total as usize <= values.len());
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_step_with_measurement_series_add_racing_end() -> Result<()> {
+    use std::sync::Arc;
+
+    use tokio::sync::{oneshot, Mutex};
+
+    use ocptv::output::{Config, DutInfo, TestResult, TestRun, TestStatus};
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    let step = run.add_step("fans").start().await?;
+
+    let (task_tx, task_rx) = oneshot::channel();
+    step.add_measurement_series("fan")
+        .scope(|series| async move {
+            let (started_tx, started_rx) = oneshot::channel();
+            let task = tokio::spawn(async move {
+                let mut added = 0;
+                let mut started = Some(started_tx);
+                loop {
+                    match series.add_measurement(added).await {
+                        Ok(()) => added += 1,
+                        Err(OcptvError::SeriesAlreadyEnded(_)) => return added,
+                        Err(e) => panic!("unexpected error: {e}"),
+                    }
+                    if let Some(started) = started.take() {
+                        let _ = started.send(());
+                    }
+                }
+            });
+            let _ = task_tx.send(task);
+
+            // note: end the series while the task is still adding elements
+            let _ = started_rx.await;
+            Ok(())
+        })
+        .await?;
+    let added = task_rx.await?.await?;
+
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let step_artifacts = artifacts
+        .iter()
+        .map(|a| &a["testStepArtifact"])
+        .filter(|a| !a.is_null())
+        .collect::<Vec<_>>();
+    let end = step_artifacts
+        .iter()
+        .position(|a| !a["measurementSeriesEnd"].is_null())
+        .expect("series end was emitted");
+
+    assert!(step_artifacts[end + 1..]
+        .iter()
+        .all(|a| a["measurementSeriesElement"].is_null()));
+    assert_eq!(
+        step_artifacts[end]["measurementSeriesEnd"]["totalCount"],
+        added
+    );
+    assert_eq!(
+        step_artifacts
+            .iter()
+            .filter(|a| !a["measurementSeriesElement"].is_null())
+            .count(),
+        added
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_step_with_measurement_series_concurrent_adds() -> Result<()> {
+    use std::sync::Arc;
+
+    use futures::future::try_join_all;
+    use tokio::sync::Mutex;
+
+    use ocptv::output::{Config, DutInfo, TestResult, TestRun, TestStatus};
+
+    const TASKS: usize = 8;
+    const ADDS: usize = 200;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_max_artifact_bytes(1024)
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    let step = run.add_step("fans").start().await?;
+    let series = Arc::new(step.add_measurement_series("fan").start().await?);
+
+    let tasks = (0..TASKS).map(|_| {
+        let series = Arc::clone(&series);
+        tokio::spawn(async move {
+            let mut added = 0;
+            for j in 0..ADDS {
+                // note: every 7th element is too large to be written
+                let mut element = MeasurementElementDetail::builder(j);
+                if j % 7 == 0 {
+                    element = element.add_metadata("blob", "x".repeat(4096));
+                }
+                match series.add_measurement_detail(element.build()).await {
+                    Ok(()) => added += 1,
+                    Err(OcptvError::Writer(_)) => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok::<_, OcptvError>(added)
+        })
+    });
+    let added = try_join_all(tasks)
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<usize>, _>>()?
+        .into_iter()
+        .sum::<usize>();
+    assert_eq!(added, TASKS * (ADDS - ADDS.div_ceil(7)));
+
+    Arc::into_inner(series)
+        .expect("tasks are done")
+        .end()
+        .await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let indexes = artifacts
+        .iter()
+        .filter_map(|a| a["testStepArtifact"]["measurementSeriesElement"]["index"].as_u64())
+        .collect::<Vec<_>>();
+    // indexes are unique, consecutive and in write order
+    assert_eq!(indexes, (0..added as u64).collect::<Vec<_>>());
+
+    let end = artifacts
+        .iter()
+        .find_map(|a| a["testStepArtifact"]["measurementSeriesEnd"].as_object())
+        .expect("series end was emitted");
+    assert_eq!(end["totalCount"], added);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_step_with_non_finite_measurement_rejected() -> Result<()> {
+    use ocptv::output::MeasurementValueError;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_series_start(3),
+        json_series_element(4, 0, 60),
+        json_series_end(5, 1),
+        json_step_complete(6),
+        json_run_pass(7),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let result = s.add_measurement("temp", f64::NAN).await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::InvalidValue(MeasurementValueError::NonFinite(n))) if n.is_nan()
+        ));
+
+        let validator = Validator::builder(ValidatorType::LessThan, f64::INFINITY).build();
+        let result = s
+            .add_measurement_detail(
+                Measurement::builder("temp", 60)
+                    .add_validator(validator)
+                    .build(),
+            )
+            .await;
+        assert!(matches!(result, Err(OcptvError::InvalidValue(_))));
+
+        let series = s.add_measurement_series("name").start().await?;
+        let result = series.add_measurements([60.0, f64::NEG_INFINITY]).await;
+        assert!(matches!(result, Err(OcptvError::InvalidValue(_))));
+        // the rejected batch didn't use any index
+        series.add_measurement(60).await?;
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_non_finite_measurement_encoded() -> Result<()> {
+    use ocptv::output::NonFinitePolicy;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "temp",
+                    "value": "NaN",
+                    "validators": [{
+                        "type": "LESS_THAN",
+                        "value": "Inf",
+                        "metadata": {"ocptv_non_finite": true}
+                    }],
+                    "metadata": {"ocptv_non_finite": true}
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan",
+                    "value": 1200.5
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_series_start(5),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": 0,
+                    "measurementSeriesId": "step0_series0",
+                    "value": "-Inf",
+                    "timestamp": DATETIME_FORMATTED,
+                    "metadata": {"ocptv_non_finite": true}
+                }
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_series_end(7, 1),
+        json_step_complete(8),
+        json_run_pass(9),
+    ];
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_non_finite_policy(NonFinitePolicy::Encode),
+        |run_builder, dut| async {
+            use ocptv::output::{TestResult, TestStatus};
+
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+
+            let validator = Validator::builder(ValidatorType::LessThan, f64::INFINITY).build();
+            step.add_measurement_detail(
+                Measurement::builder("temp", f64::NAN)
+                    .add_validator(validator)
+                    .build(),
+            )
+            .await?;
+            // finite values are unaffected
+            step.add_measurement("fan", 1200.5).await?;
+
+            let series = step.add_measurement_series("name").start().await?;
+            series.add_measurement(f64::NEG_INFINITY).await?;
+            series.end().await?;
+
+            step.end(TestStatus::Complete).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+fn json_fan_series_element(
+    seqno: i32,
+    index: u64,
+    value: impl Into<serde_json::Value>,
+    failed: &[&str],
+) -> serde_json::Value {
+    let value = value.into();
+    let mut element = json!({
+        "index": index,
+        "measurementSeriesId": "step0_series0",
+        "value": value,
+        "timestamp": DATETIME_FORMATTED
+    });
+    if !failed.is_empty() {
+        element["metadata"] = json!({ "ocptv_failed_validators": failed });
+    }
+    json!({
+        "testStepArtifact": {
+            "testStepId": "step0",
+            "measurementSeriesElement": element
+        },
+        "sequenceNumber": seqno,
+        "timestamp": DATETIME_FORMATTED
+    })
+}
+
+fn fan_series_detail(enforce: bool) -> MeasurementSeriesDetail {
+    MeasurementSeriesDetail::builder("fan_speed")
+        .add_validator(Validator::greater_than(1000).named("min_speed"))
+        .add_validator(Validator::less_than(5000))
+        .enforce_validators(enforce)
+        .build()
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_enforced_validators() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "fan_speed",
+                    "validators": [{
+                        "name": "min_speed",
+                        "type": "GREATER_THAN",
+                        "value": 1000
+                    }, {
+                        "type": "LESS_THAN",
+                        "value": 5000
+                    }]
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_fan_series_element(4, 0, 1200, &[]),
+        json_fan_series_element(5, 1, 800, &["min_speed"]),
+        json_fan_series_element(6, 2, 1100, &[]),
+        json_fan_series_element(7, 3, 6000, &["LessThan"]),
+        json_fan_series_element(8, 4, "fast", &["min_speed", "LessThan"]),
+        json_fan_series_element(9, 5, 1500, &[]),
+        json_fan_series_element(10, 6, "fast", &["min_speed", "LessThan"]),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "diagnosis": {
+                    "verdict": "fan_speed_validation_failed",
+                    "type": "FAIL",
+                    "message": "4 of 7 elements of series step0_series0 failed its validators"
+                }
+            },
+            "sequenceNumber": 11,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 7
+                }
+            },
+            "sequenceNumber": 12,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(13),
+        json_run_pass(14),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s
+            .add_measurement_series_detail(fan_series_detail(true))
+            .start()
+            .await?;
+        series.add_measurement(1200).await?;
+        series.add_measurement(800).await?;
+        assert_eq!(series.violations(), 1);
+        series.add_measurements([1100, 6000]).await?;
+        assert_eq!(series.violations(), 2);
+
+        // the validators can't be evaluated against these, so they fail
+        series.add_measurement("fast").await?;
+        assert_eq!(series.violations(), 3);
+        series
+            .add_measurements([MeasurementValue::from(1500), MeasurementValue::from("fast")])
+            .await?;
+        assert_eq!(series.violations(), 4);
+
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_enforced_validators_non_finite() -> Result<()> {
+    use ocptv::output::{NonFinitePolicy, TestResult, TestStatus};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "fan_speed",
+                    "validators": [{
+                        "name": "min_speed",
+                        "type": "GREATER_THAN",
+                        "value": 1000
+                    }, {
+                        "type": "LESS_THAN",
+                        "value": 5000
+                    }]
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": 0,
+                    "measurementSeriesId": "step0_series0",
+                    "value": "NaN",
+                    "timestamp": DATETIME_FORMATTED,
+                    "metadata": {
+                        "ocptv_failed_validators": ["min_speed", "LessThan"],
+                        "ocptv_non_finite": true
+                    }
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_fan_series_element(5, 1, 1200, &[]),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "diagnosis": {
+                    "verdict": "fan_speed_validation_failed",
+                    "type": "FAIL",
+                    "message": "1 of 2 elements of series step0_series0 failed its validators"
+                }
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 2
+                }
+            },
+            "sequenceNumber": 7,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(8),
+        json_run_pass(9),
+    ];
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_non_finite_policy(NonFinitePolicy::Encode),
+        |run_builder, dut| async {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+
+            let series = step
+                .add_measurement_series_detail(fan_series_detail(true))
+                .start()
+                .await?;
+            // the encoded NaN can't be compared, so it fails the validators
+            series.add_measurement(f64::NAN).await?;
+            series.add_measurement(1200).await?;
+            assert_eq!(series.violations(), 1);
+            series.end().await?;
+
+            step.end(TestStatus::Complete).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_measurement_series_validators_not_enforced() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "fan_speed",
+                    "validators": [{
+                        "name": "min_speed",
+                        "type": "GREATER_THAN",
+                        "value": 1000
+                    }, {
+                        "type": "LESS_THAN",
+                        "value": 5000
+                    }]
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_fan_series_element(4, 0, 800, &[]),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 1
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(6),
+        json_run_pass(7),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let series = s
+            .add_measurement_series_detail(fan_series_detail(false))
+            .start()
+            .await?;
+        series.add_measurement(800).await?;
+        assert_eq!(series.violations(), 0);
+        series.end().await?;
+
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,93 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::io::Cursor;
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+use ocptv::output::{Config, TestResult, TestStatus};
+use ocptv::reader;
+
+use super::fixture::*;
+
+async fn run_output() -> Result<Vec<String>> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run =
+        start_run_with_config(Config::builder().with_buffer_output(Arc::clone(&buffer))).await?;
+
+    let step = run.add_step("first step").start().await?;
+    step.add_measurement("name", 50).await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let lines = buffer.lock().await.clone();
+    Ok(lines)
+}
+
+fn parse_lines(lines: &[String]) -> Result<Vec<serde_json::Value>> {
+    Ok(lines
+        .iter()
+        .map(|l| serde_json::from_str(l))
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+#[tokio::test]
+async fn test_reader_plain_stream() -> Result<()> {
+    let lines = run_output().await?;
+    let content = lines.join("\n") + "\n";
+
+    let actual = reader::from_reader(Cursor::new(content))?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, parse_lines(&lines)?);
+
+    Ok(())
+}
+
+#[cfg(feature = "gzip")]
+fn gzip(content: &str) -> Result<Vec<u8>> {
+    use std::io::Write;
+
+    let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    encoder.write_all(content.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn test_reader_gzip_stream() -> Result<()> {
+    let lines = run_output().await?;
+    let content = gzip(&(lines.join("\n") + "\n"))?;
+
+    let actual = reader::from_reader(Cursor::new(content))?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, parse_lines(&lines)?);
+
+    Ok(())
+}
+
+#[cfg(feature = "gzip")]
+#[tokio::test]
+async fn test_reader_concatenated_gzip_members() -> Result<()> {
+    let lines = run_output().await?;
+    let (first, second) = lines.split_at(lines.len() / 2);
+
+    let mut content = gzip(&(first.join("\n") + "\n"))?;
+    content.extend(gzip(&(second.join("\n") + "\n"))?);
+
+    let actual = reader::from_reader(Cursor::new(content))?.collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(actual, parse_lines(&lines)?);
+
+    Ok(())
+}
+
+#[cfg(not(feature = "gzip"))]
+#[tokio::test]
+async fn test_reader_gzip_without_feature() -> Result<()> {
+    let content = vec![0x1f, 0x8b, 0x08, 0x00];
+
+    assert!(reader::from_reader(Cursor::new(content)).is_err());
+    Ok(())
+}
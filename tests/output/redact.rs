@@ -0,0 +1,95 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use tokio::sync::Mutex;
+
+use ocptv::output::{
+    Config, DutInfo, Measurement, MeasurementElementDetail, TestResult, TestRun, TestStatus,
+};
+
+use super::fixture::*;
+
+#[tokio::test]
+async fn test_redaction_hides_secrets() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id")
+        .add_metadata(
+            "bmc",
+            serde_json::json!({"user": "root", "Password": "hunter2"}),
+        )
+        .build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_redaction(vec!["password".to_owned()])
+                // hooks run before redaction, so they can't leak the secret either
+                .with_emit_hook(Box::new(|artifact| {
+                    if let Some(start) = artifact.pointer_mut("/testRunArtifact/testRunStart") {
+                        start["metadata"]["password"] = "hunter2".into();
+                    }
+                }))
+                .build(),
+        )
+        .add_parameter("password", "hunter2")
+        .add_parameter("iterations", 10)
+        .command_line("diag --password 'hunter2 x' --iterations 10")
+        .build()
+        .start(dut)
+        .await?;
+
+    let step = run.add_step("step").start().await?;
+    step.add_measurement_detail(
+        Measurement::builder("fan", 1000)
+            .add_metadata("password", "hunter2")
+            .build(),
+    )
+    .await?;
+
+    let series = step.add_measurement_series("temp").start().await?;
+    series
+        .add_measurement_detail(
+            MeasurementElementDetail::builder(42)
+                .add_metadata("password", "hunter2")
+                .build(),
+        )
+        .await?;
+    series.end().await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let actual = buffer.lock().await;
+    assert!(
+        actual.iter().all(|l| !l.contains("hunter2")),
+        "{:#?}",
+        actual
+    );
+
+    let start = serde_json::from_str::<serde_json::Value>(&actual[1])?;
+    let start = &start["testRunArtifact"]["testRunStart"];
+    assert_eq!(start["parameters"]["password"], "<redacted>");
+    assert_eq!(start["parameters"]["iterations"], 10);
+    assert_eq!(start["metadata"]["password"], "<redacted>");
+    assert_eq!(
+        start["dutInfo"]["metadata"]["bmc"]["Password"],
+        "<redacted>"
+    );
+    assert_eq!(start["dutInfo"]["metadata"]["bmc"]["user"], "root");
+    assert_eq!(
+        start["commandLine"],
+        "diag --password <redacted> --iterations 10"
+    );
+
+    let redacted = actual.iter().filter(|l| l.contains("<redacted>")).count();
+    assert_eq!(redacted, 3);
+
+    Ok(())
+}
@@ -7,11 +7,15 @@
 use std::sync::Arc;
 
 use anyhow::Result;
-use assert_json_diff::assert_json_include;
+use assert_json_diff::{assert_json_eq, assert_json_include};
 use serde_json::json;
-use tokio::sync::Mutex;
+use tokio::sync::{broadcast, Mutex};
 
-use ocptv::output::{DutInfo, TestResult, TestRun, TestStatus};
+use ocptv::output::{
+    Config, DutInfo, Emitter, Error, HardwareInfo, Ident, LogSeverity, OcptvError, ResumeError,
+    RunSummary, SoftwareInfo, TestResult, TestRun, TestStatus, WriterError,
+};
+use ocptv::spec;
 
 use super::fixture::*;
 
@@ -64,6 +68,40 @@ async fn test_testrun_with_scope() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_testrun_with_scope_error() -> Result<()> {
+    use ocptv::output::OcptvError;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "result": "NOT_APPLICABLE",
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build();
+
+        let result = run
+            .scope(dut, |_r| async move {
+                Err(OcptvError::Other("dut not found".into()))
+            })
+            .await;
+        assert!(matches!(result, Err(OcptvError::Other(e)) if e.to_string() == "dut not found"));
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_testrun_instantiation_with_new() -> Result<()> {
     let expected = [
@@ -133,6 +171,63 @@ async fn test_testrun_metadata() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_testrun_with_environment_metadata() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .add_metadata("ocptv_user", "operator")
+        .with_environment_metadata()
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let lines = buffer.lock().await;
+    let start: serde_json::Value = serde_json::from_str(&lines[1])?;
+    let metadata = &start["testRunArtifact"]["testRunStart"]["metadata"];
+    for key in [
+        "ocptv_hostname",
+        "ocptv_os_release",
+        "ocptv_arch",
+        "ocptv_pid",
+    ] {
+        let value = metadata[key].as_str();
+        assert!(
+            value.is_some_and(|v| !v.is_empty()),
+            "bad {}: {}",
+            key,
+            metadata
+        );
+    }
+    assert_eq!(metadata["ocptv_pid"], std::process::id().to_string());
+    // user defined metadata is kept
+    assert_eq!(metadata["ocptv_user"], "operator");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_without_environment_metadata() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_run_pass(2),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_testrun_builder() -> Result<()> {
     let expected = [
@@ -187,3 +282,1784 @@ async fn test_testrun_builder() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_testrun_with_step_and_sequence_start() -> Result<()> {
+    let mut expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json_run_pass(4),
+    ];
+    for (i, artifact) in expected.iter_mut().enumerate() {
+        artifact["sequenceNumber"] = json!(10 + i);
+    }
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_sequence_start(10),
+        |run_builder, dut| async move {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+            step.end(TestStatus::Complete).await?;
+            let summary = run.end(TestStatus::Complete, TestResult::Pass).await?;
+            assert_eq!(summary.last_seqno, 14);
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_with_sequence_start() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<spec::Root>>> = Arc::new(Mutex::new(vec![]));
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_typed_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_sequence_start(100)
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    assert_eq!(run.sequence_number(), 102);
+
+    run.add_log(LogSeverity::Info, "resumed").await?;
+    assert_eq!(run.sequence_number(), 103);
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let actual = buffer.lock().await;
+    let seqnos = actual.iter().map(|a| a.seqno).collect::<Vec<_>>();
+    assert_eq!(seqnos, [100, 101, 102, 103]);
+
+    assert!(matches!(
+        actual[0].artifact,
+        spec::RootImpl::SchemaVersion(_)
+    ));
+    assert!(matches!(
+        &actual[1].artifact,
+        spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+            artifact: spec::TestRunArtifactImpl::TestRunStart(start),
+        }) if start.name == "run_name"
+    ));
+    assert!(matches!(
+        &actual[2].artifact,
+        spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+            artifact: spec::TestRunArtifactImpl::Log(log),
+        }) if log.message == "resumed" && log.severity == LogSeverity::Info
+    ));
+    assert!(matches!(
+        &actual[3].artifact,
+        spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+            artifact: spec::TestRunArtifactImpl::TestRunEnd(spec::TestRunEnd {
+                status: TestStatus::Complete,
+                result: TestResult::Pass,
+            }),
+        })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_shared_emitter() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<spec::Root>>> = Arc::new(Mutex::new(vec![]));
+
+    let emitter = Emitter::new(
+        Config::builder()
+            .with_typed_buffer_output(Arc::clone(&buffer))
+            .with_timestamp_provider(Box::new(FixedTsProvider {}))
+            .build(),
+    );
+
+    for name in ["first", "second"] {
+        let dut = DutInfo::builder("dut_id").build();
+        let run = TestRun::builder(name, "1.0")
+            .emitter(&emitter)
+            .build()
+            .start(dut)
+            .await?;
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+    }
+    assert_eq!(emitter.seqno(), 5);
+    assert_eq!(emitter.stats().count("testRunArtifact.testRunEnd"), 2);
+
+    let actual = buffer.lock().await;
+    let seqnos = actual.iter().map(|a| a.seqno).collect::<Vec<_>>();
+    assert_eq!(seqnos, [0, 1, 2, 3, 4]);
+
+    // only the first run emits the schema version
+    let names = actual
+        .iter()
+        .filter_map(|a| match &a.artifact {
+            spec::RootImpl::SchemaVersion(_) => Some("version"),
+            spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+                artifact: spec::TestRunArtifactImpl::TestRunStart(start),
+            }) => Some(start.name.as_str()),
+            spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+                artifact: spec::TestRunArtifactImpl::TestRunEnd(_),
+            }) => Some("end"),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["version", "first", "end", "second", "end"]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_emit_hook() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json!({
+            "testRunArtifact": {
+                "testRunStart": {
+                    "dutInfo": {
+                        "dutInfoId": "dut_id"
+                    },
+                    "metadata": {
+                        "fleet": "prod"
+                    },
+                    "name": "run_name",
+                    "parameters": {},
+                    "version": "1.0",
+                    "commandLine": ""
+                }
+            },
+            "sequenceNumber": 1,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(2),
+    ];
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_emit_hook(Box::new(|artifact| {
+                    if let Some(start) = artifact.pointer_mut("/testRunArtifact/testRunStart") {
+                        start["metadata"]["fleet"] = "prod".into();
+                    }
+                }))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let actual = buffer.lock().await;
+    assert_eq!(actual.len(), expected.len());
+    for (idx, entry) in actual.iter().enumerate() {
+        let value = serde_json::from_str::<serde_json::Value>(entry)?;
+        assert_json_eq!(value, expected[idx]);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_transform() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_transform(Box::new(|mut artifact| {
+                    artifact["site"] = "us-east".into();
+                    artifact["rackId"] = "r42".into();
+                    // not allowed to change, so this is undone by the emitter
+                    artifact["sequenceNumber"] = 1000.into();
+                    artifact
+                }))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    let step = run.add_step("step").start().await?;
+    step.add_measurement("fan", 1000).await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let actual = buffer.lock().await;
+    assert_eq!(actual.len(), 6);
+    for (idx, entry) in actual.iter().enumerate() {
+        let value = serde_json::from_str::<serde_json::Value>(entry)?;
+        assert_eq!(value["site"], "us-east");
+        assert_eq!(value["rackId"], "r42");
+        assert_eq!(value["sequenceNumber"], idx as u64);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_failing_transform() -> Result<()> {
+    let dut = DutInfo::builder("dut_id").build();
+    let actual = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::new(Mutex::new(vec![])))
+                .with_transform(Box::new(|_| serde_json::Value::Null))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await;
+
+    match actual {
+        Err(OcptvError::Writer(WriterError::Transform { artifact, message })) => {
+            assert_eq!(artifact.kind, "schemaVersion");
+            assert_eq!(message, "transform did not return a json object");
+        }
+        _ => panic!("expected transform error"),
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_without_schema_version() -> Result<()> {
+    let mut start = json_run_default_start();
+    start["sequenceNumber"] = 0.into();
+    let expected = [start, json_run_pass(1)];
+
+    check_output_with_config(
+        &expected,
+        |config| config.emit_schema_version(false),
+        |run_builder, dut| async {
+            let run = run_builder.build().start(dut).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_with_manual_schema_version() -> Result<()> {
+    let error = json!({
+        "testRunArtifact": {
+            "error": {
+                "symptom": "no-dut",
+            }
+        },
+        "sequenceNumber": 0,
+        "timestamp": DATETIME_FORMATTED
+    });
+    let mut version = json_schema_version();
+    version["sequenceNumber"] = 1.into();
+    let mut start = json_run_default_start();
+    start["sequenceNumber"] = 2.into();
+    let expected = [error, version, start, json_run_pass(3)];
+
+    check_output_with_config(
+        &expected,
+        |config| config.emit_schema_version(false),
+        |run_builder, dut| async {
+            let run = run_builder.build();
+            run.add_error("no-dut").await?;
+            run.emit_schema_version().await?;
+
+            let run = run.start(dut).await?;
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_testrun_stats() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    let run = Arc::new(run);
+
+    run.add_log(LogSeverity::Info, "run log").await?;
+    run.add_error("run error").await?;
+
+    let mut tasks = vec![];
+    for i in 0..8 {
+        let run = Arc::clone(&run);
+        tasks.push(tokio::spawn(async move {
+            let step = run.add_step(&format!("step {}", i)).start().await?;
+            step.add_log(LogSeverity::Debug, "step log").await?;
+            for j in 0..10 {
+                step.add_measurement("name", j).await?;
+            }
+            step.add_error("step error").await?;
+            step.end(TestStatus::Complete).await?;
+            Ok::<(), OcptvError>(())
+        }));
+    }
+    for task in tasks {
+        task.await??;
+    }
+
+    let stats = run.stats();
+    assert_eq!(stats.count("schemaVersion"), 1);
+    assert_eq!(stats.count("testRunArtifact.testRunStart"), 1);
+    assert_eq!(stats.count("testRunArtifact.log"), 1);
+    assert_eq!(stats.count("testRunArtifact.error"), 1);
+    assert_eq!(stats.count("testStepArtifact.testStepStart"), 8);
+    assert_eq!(stats.count("testStepArtifact.log"), 8);
+    assert_eq!(stats.count("testStepArtifact.measurement"), 80);
+    assert_eq!(stats.count("testStepArtifact.error"), 8);
+    assert_eq!(stats.count("testStepArtifact.testStepEnd"), 8);
+    assert_eq!(stats.count("testRunArtifact.testRunEnd"), 0);
+    assert_eq!(stats.total(), 116);
+
+    let lines = buffer.lock().await;
+    assert_eq!(lines.len() as u64, stats.total());
+    assert_eq!(
+        stats.bytes,
+        lines.iter().map(|l| l.len() as u64).sum::<u64>()
+    );
+    assert_eq!(
+        stats.first_timestamp,
+        Some(DATETIME.with_timezone(&chrono_tz::UTC))
+    );
+    assert_eq!(stats.last_timestamp, stats.first_timestamp);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_end_summary() -> Result<()> {
+    let emitter = Emitter::new(
+        Config::builder()
+            .with_buffer_output(Arc::new(Mutex::new(vec![])))
+            .with_timestamp_provider(Box::new(FixedTsProvider {}))
+            .build(),
+    );
+
+    // an earlier run on the same emitter is not part of the summary
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("first", "1.0")
+        .emitter(&emitter)
+        .build()
+        .start(dut)
+        .await?;
+    run.add_error("first error").await?;
+    run.end(TestStatus::Complete, TestResult::Fail).await?;
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("second", "1.0")
+        .emitter(&emitter)
+        .build()
+        .start(dut)
+        .await?;
+    run.add_log(LogSeverity::Info, "run log").await?;
+    run.add_error("run error").await?;
+    for i in 0..3 {
+        let step = run.add_step(&format!("step {}", i)).start().await?;
+        step.add_log(LogSeverity::Debug, "step log").await?;
+        step.add_measurement("fan", i).await?;
+        let series = step.add_measurement_series("temp").start().await?;
+        for j in 0..4 {
+            series.add_measurement(j).await?;
+        }
+        series.end().await?;
+        step.end(TestStatus::Complete).await?;
+    }
+    let summary = run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    assert_eq!(
+        summary,
+        RunSummary {
+            steps: 3,
+            errors: 1,
+            logs: 4,
+            measurements: 15,
+            series: 3,
+            duration: std::time::Duration::ZERO,
+            last_seqno: emitter.seqno() - 1,
+        }
+    );
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_testrun_end_summary_shared_emitter() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<spec::Root>>> = Arc::new(Mutex::new(vec![]));
+    let emitter = Emitter::new(
+        Config::builder()
+            .with_typed_buffer_output(Arc::clone(&buffer))
+            .build(),
+    );
+
+    // runs ending concurrently on the same emitter, while another one keeps emitting
+    let mut runs = vec![];
+    for name in ["first", "second", "busy"] {
+        let dut = DutInfo::builder("dut_id").build();
+        let run = TestRun::builder(name, "1.0")
+            .emitter(&emitter)
+            .build()
+            .start(dut)
+            .await?;
+        runs.push(run);
+    }
+    let busy = Arc::new(runs.pop().unwrap());
+    let logs = {
+        let busy = Arc::clone(&busy);
+        tokio::spawn(async move {
+            for _ in 0..200 {
+                busy.add_log(LogSeverity::Info, "busy").await?;
+            }
+            Ok::<_, OcptvError>(())
+        })
+    };
+    let ends = runs
+        .into_iter()
+        .zip([TestResult::Pass, TestResult::Fail])
+        .map(|(run, result)| {
+            tokio::spawn(async move {
+                let summary = run.end(TestStatus::Complete, result.clone()).await?;
+                Ok::<_, OcptvError>((summary.last_seqno, result))
+            })
+        });
+    let ends = futures::future::try_join_all(ends).await?;
+    logs.await??;
+
+    // the last sequence number of each run is the one of its own end
+    let buffer = buffer.lock().await;
+    for end in ends {
+        let (last_seqno, result) = end?;
+        let root = buffer.iter().find(|root| root.seqno == last_seqno).unwrap();
+        assert!(matches!(
+            &root.artifact,
+            spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+                artifact: spec::TestRunArtifactImpl::TestRunEnd(end),
+            }) if end.result == result
+        ));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_subscribe() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    let mut artifacts = run.subscribe();
+
+    let step = run.add_step("step").start().await?;
+    step.add_measurement("fan", 1000).await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let mut received = vec![];
+    loop {
+        match artifacts.recv().await {
+            Ok(artifact) => received.push(artifact),
+            Err(broadcast::error::RecvError::Closed) => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    // same as the output, except the artifacts emitted before subscribing
+    let lines = buffer.lock().await;
+    assert_eq!(received.len(), 4);
+    for (artifact, line) in received.iter().zip(&lines[2..]) {
+        assert_json_eq!(
+            serde_json::to_value(&**artifact)?,
+            serde_json::from_str::<serde_json::Value>(line)?
+        );
+    }
+    assert!(matches!(
+        &received[1].artifact,
+        spec::RootImpl::TestStepArtifact(spec::TestStepArtifact {
+            artifact: spec::TestStepArtifactImpl::Measurement(_),
+            ..
+        })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_subscriber_lagging() -> Result<()> {
+    let dut = DutInfo::builder("dut_id").build();
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::new(Mutex::new(vec![])))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    let mut artifacts = run.subscribe();
+
+    // the subscriber not reading does not block the emitting calls
+    for i in 0..2000 {
+        run.add_log(LogSeverity::Info, &format!("log {}", i))
+            .await?;
+    }
+
+    assert!(matches!(
+        artifacts.recv().await,
+        Err(broadcast::error::RecvError::Lagged(_))
+    ));
+    let artifact = artifacts.recv().await?;
+    assert!(matches!(
+        &artifact.artifact,
+        spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+            artifact: spec::TestRunArtifactImpl::Log(log),
+        }) if log.message.starts_with("log ")
+    ));
+
+    Ok(())
+}
+
+async fn start_guarded_run(
+    buffer: &Arc<Mutex<Vec<String>>>,
+) -> Result<ocptv::output::TestRunGuard> {
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    Ok(run.into_guard(TestStatus::Error, TestResult::NotApplicable))
+}
+
+async fn run_ends(buffer: &Arc<Mutex<Vec<String>>>) -> Result<Vec<serde_json::Value>> {
+    let mut ends = vec![];
+    for entry in buffer.lock().await.iter() {
+        let value = serde_json::from_str::<serde_json::Value>(entry)?;
+        if value.pointer("/testRunArtifact/testRunEnd").is_some() {
+            ends.push(value);
+        }
+    }
+    Ok(ends)
+}
+
+#[tokio::test]
+async fn test_testrun_guard_ends_run_on_panic() -> Result<()> {
+    use std::panic::AssertUnwindSafe;
+
+    use futures::FutureExt;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let run = start_guarded_run(&buffer).await?;
+    let result = AssertUnwindSafe(async move {
+        run.add_log(LogSeverity::Info, "about to fail").await?;
+        panic!("test body failed");
+        #[allow(unreachable_code)]
+        run.end(TestStatus::Complete, TestResult::Pass).await
+    })
+    .catch_unwind()
+    .await;
+    assert!(result.is_err());
+
+    assert_json_eq!(
+        run_ends(&buffer).await?,
+        json!([{
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "result": "NOT_APPLICABLE",
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_guard_disarmed_by_end() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let run = start_guarded_run(&buffer).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    assert_json_eq!(run_ends(&buffer).await?, json!([json_run_pass(2)]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_rejects_artifacts_after_end() -> Result<()> {
+    use ocptv::output::{ScopedTestRun, TestRunOutcome};
+
+    let leaked: Arc<Mutex<Option<ScopedTestRun>>> = Arc::new(Mutex::new(None));
+
+    check_output(
+        &[
+            json_schema_version(),
+            json_run_default_start(),
+            json_run_pass(2),
+        ],
+        |run_builder, dut| async {
+            let run = run_builder.build();
+            let leaked = Arc::clone(&leaked);
+            run.scope(dut, |r| async move {
+                *leaked.lock().await = Some(r);
+                Ok(TestRunOutcome {
+                    status: TestStatus::Complete,
+                    result: TestResult::Pass,
+                })
+            })
+            .await?;
+            Ok(())
+        },
+    )
+    .await?;
+
+    // the scoped run outlived the scope, which already ended the run
+    let r = leaked.lock().await.take().unwrap();
+    assert!(matches!(
+        r.add_log(LogSeverity::Info, "late").await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+    assert!(matches!(
+        r.add_error("late").await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+    assert!(matches!(
+        r.add_step("late").start().await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_rejects_step_artifacts_after_run_end() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("running").start().await?;
+    let pending = run.add_step("pending");
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    assert!(matches!(
+        step.add_measurement("fan", 1000).await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+    assert!(matches!(
+        step.end(TestStatus::Complete).await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+    assert!(matches!(
+        pending.start().await,
+        Err(OcptvError::RunAlreadyEnded)
+    ));
+
+    // nothing was written after the run end
+    let buffer = buffer.lock().await;
+    let last = serde_json::from_str::<serde_json::Value>(buffer.last().unwrap())?;
+    assert!(last.pointer("/testRunArtifact/testRunEnd").is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_parameters_from() -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Args {
+        device: String,
+        iterations: u32,
+        threshold: f64,
+        verbose: bool,
+    }
+
+    let expected = [
+        json_schema_version(),
+        json!({
+            "testRunArtifact": {
+                "testRunStart": {
+                    "commandLine": "cmd_line",
+                    "dutInfo": {
+                        "dutInfoId": "dut_id",
+                        "softwareInfos": [{
+                            "softwareInfoId": "sw0",
+                            "name": "ubuntu",
+                            "version": "22",
+                            "softwareType": "SYSTEM",
+                        }],
+                        "hardwareInfos": [{
+                            "hardwareInfoId": "hw0",
+                            "name": "fan",
+                            "location": "board0/fan"
+                        }]
+                    },
+                    "name": "run_name",
+                    "parameters": {
+                        "device": "/dev/nvme0",
+                        "iterations": 10,
+                        "threshold": 0.5,
+                        "verbose": true,
+                        "extra": "value"
+                    },
+                    "version": "1.0"
+                }
+            },
+            "sequenceNumber": 1,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(2),
+    ];
+
+    let args = Args {
+        device: "/dev/nvme0".to_owned(),
+        iterations: 10,
+        threshold: 0.5,
+        verbose: true,
+    };
+    check_output(&expected, |run_builder, dut| async move {
+        let run = run_builder
+            .parameters_from(&args)?
+            .add_parameter("extra", "value")
+            .command_line("cmd_line")
+            .build()
+            .start(dut)
+            .await?;
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[test]
+fn test_testrun_parameters_from_rejects_invalid() -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct Limits {
+        min: u32,
+        max: u32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct Nested {
+        name: String,
+        limits: Limits,
+    }
+
+    let nested = Nested {
+        name: "fan".to_owned(),
+        limits: Limits { min: 0, max: 100 },
+    };
+    assert!(matches!(
+        TestRun::builder("run_name", "1.0").parameters_from(&nested),
+        Err(OcptvError::Format(e)) if e.to_string() == "parameter limits is not a scalar value"
+    ));
+
+    assert!(matches!(
+        TestRun::builder("run_name", "1.0").parameters_from(&vec![1, 2]),
+        Err(OcptvError::Format(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_command_line_full() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<spec::Root>>> = Arc::new(Mutex::new(vec![]));
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_typed_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .command_line_full()
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let buffer = buffer.lock().await;
+    let spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+        artifact: spec::TestRunArtifactImpl::TestRunStart(start),
+    }) = &buffer[1].artifact
+    else {
+        panic!("expected the run start");
+    };
+    let program = std::env::args().next().unwrap();
+    assert!(start.command_line.starts_with(&program));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_parameters_and_metadata_maps() -> Result<()> {
+    use std::collections::HashMap;
+
+    let expected = [
+        json_schema_version(),
+        json!({
+            "testRunArtifact": {
+                "testRunStart": {
+                    "commandLine": "cmd_line",
+                    "dutInfo": {
+                        "dutInfoId": "dut_id",
+                        "softwareInfos": [{
+                            "softwareInfoId": "sw0",
+                            "name": "ubuntu",
+                            "version": "22",
+                            "softwareType": "SYSTEM",
+                        }],
+                        "hardwareInfos": [{
+                            "hardwareInfoId": "hw0",
+                            "name": "fan",
+                            "location": "board0/fan"
+                        }]
+                    },
+                    "metadata": {
+                        "owner": "team",
+                        "rack": 7
+                    },
+                    "name": "run_name",
+                    "parameters": {
+                        "device": "/dev/nvme0",
+                        "iterations": 20,
+                        "verbose": true
+                    },
+                    "version": "1.0"
+                }
+            },
+            "sequenceNumber": 1,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(2),
+    ];
+
+    let params: HashMap<String, serde_json::Value> = HashMap::from([
+        ("device".to_owned(), json!("/dev/nvme0")),
+        ("iterations".to_owned(), json!(10)),
+        ("verbose".to_owned(), json!(true)),
+    ]);
+    check_output(&expected, |run_builder, dut| async move {
+        let run = run_builder
+            .add_parameters(params)
+            // last one wins
+            .add_parameters([("iterations", 20)])
+            .add_metadata_map([("owner", json!("team")), ("rack", json!(7))])
+            .command_line("cmd_line")
+            .build()
+            .start(dut)
+            .await?;
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_rejects_complex_parameters() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let result = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .add_parameter("name", "fan")
+        .add_parameter("count", 3)
+        .add_parameter("enabled", false)
+        .add_parameter("unset", serde_json::Value::Null)
+        .add_parameter("limits", json!({"min": 0, "max": 100}))
+        .add_parameter("speeds", json!([1000, 2000]))
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await;
+
+    match result {
+        Err(OcptvError::InvalidParameters(keys)) => assert_eq!(keys, ["limits", "speeds"]),
+        _ => panic!("expected invalid parameters error"),
+    }
+    // nothing was emitted
+    assert!(buffer.lock().await.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_complex_parameters_allowed() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<spec::Root>>> = Arc::new(Mutex::new(vec![]));
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_typed_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .add_parameter("unset", serde_json::Value::Null)
+        .add_parameter("limits", json!({"min": 0, "max": 100}))
+        .allow_complex_parameters()
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let buffer = buffer.lock().await;
+    let spec::RootImpl::TestRunArtifact(spec::TestRunArtifact {
+        artifact: spec::TestRunArtifactImpl::TestRunStart(start),
+    }) = &buffer[1].artifact
+    else {
+        panic!("expected the run start");
+    };
+    assert_eq!(start.parameters["limits"], json!({"min": 0, "max": 100}));
+    assert_eq!(start.parameters["unset"], serde_json::Value::Null);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_planned_steps() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .plan_steps(&["check fans", "check voltages", "check memory"])
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    // out of order, and the second planned step is skipped
+    for index in [2, 0] {
+        let step = run.add_step_from_plan(index)?.start().await?;
+        step.end(TestStatus::Complete).await?;
+    }
+    assert!(matches!(
+        run.add_step_from_plan(3),
+        Err(OcptvError::UnknownPlannedStep(3))
+    ));
+    assert!(matches!(
+        run.add_step_from_plan(0),
+        Err(OcptvError::DuplicateStepId(_))
+    ));
+    assert_eq!(run.stats().started_steps, ["plan2", "plan0"]);
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|entry| serde_json::from_str::<serde_json::Value>(entry))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let plan = &artifacts[1]["testRunArtifact"]["testRunStart"]["metadata"]["ocptv_planned_steps"];
+    assert_json_eq!(
+        plan,
+        json!([
+            { "testStepId": "plan0", "name": "check fans" },
+            { "testStepId": "plan1", "name": "check voltages" },
+            { "testStepId": "plan2", "name": "check memory" },
+        ])
+    );
+
+    let starts = artifacts
+        .iter()
+        .filter(|a| a.pointer("/testStepArtifact/testStepStart").is_some())
+        .map(|a| {
+            (
+                a["testStepArtifact"]["testStepId"].clone(),
+                a["testStepArtifact"]["testStepStart"]["name"].clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        starts,
+        [
+            (json!("plan2"), json!("check memory")),
+            (json!("plan0"), json!("check fans")),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_with_record_duration() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json!({
+            "testRunArtifact": {
+                "log": {
+                    "message": "{\"durationMs\":0}",
+                    "severity": "INFO"
+                }
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(3),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.record_duration(true).build().start(dut).await?;
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[test]
+fn test_testrun_try_build_rejects_invalid_fields() -> Result<()> {
+    use ocptv::output::{BuildError, BuildIssue};
+
+    let issues = |name: &str, version: &str| match TestRun::builder(name, version).try_build() {
+        Err(BuildError { issues }) => issues,
+        Ok(_) => vec![],
+    };
+
+    assert_eq!(issues("", "1.0"), [BuildIssue::Empty("name")]);
+    assert_eq!(issues("run_name", ""), [BuildIssue::Empty("version")]);
+    assert_eq!(
+        issues("run\nname", ""),
+        [
+            BuildIssue::ControlCharacters("name"),
+            BuildIssue::Empty("version")
+        ]
+    );
+    assert!(TestRun::builder("run_name", "1.0").try_build().is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_start_rejects_invalid_fields() -> Result<()> {
+    use ocptv::output::{BuildError, BuildIssue};
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let config = || {
+        Config::builder()
+            .with_buffer_output(Arc::clone(&buffer))
+            .build()
+    };
+
+    // the convenience constructor is checked when starting
+    let result = TestRun::new("", "1.0")
+        .start(DutInfo::builder("dut_id").build())
+        .await;
+    assert!(matches!(
+        result,
+        Err(OcptvError::Build(BuildError { issues })) if issues == [BuildIssue::Empty("name")]
+    ));
+
+    let result = TestRun::builder("run_name", "1.0")
+        .config(config())
+        .build()
+        .start(DutInfo::builder("dut\u{7}").build())
+        .await;
+    assert!(matches!(
+        result,
+        Err(OcptvError::Build(BuildError { issues }))
+            if issues == [BuildIssue::ControlCharacters("dut id")]
+    ));
+    assert!(buffer.lock().await.is_empty());
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(config())
+        .try_build()?
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+    assert_eq!(buffer.lock().await.len(), 3);
+
+    Ok(())
+}
+
+async fn write_interrupted_run(path: &std::path::Path) -> Result<()> {
+    let mut dut = DutInfo::builder("dut_id").name("dut").build();
+    dut.add_hardware_info(
+        HardwareInfo::builder("fan")
+            .id(Ident::Exact("hw0".to_owned()))
+            .location("board0/fan")
+            .build(),
+    );
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_file_output(path)
+                .await?
+                .build(),
+        )
+        .add_parameter("param", "value")
+        .build()
+        .start(dut)
+        .await?;
+    let step = run.add_step("first step").start().await?;
+    step.add_measurement("fan", 1000).await?;
+    step.end(TestStatus::Complete).await?;
+    run.close().await?;
+
+    // note: the power cycle cut the last write short
+    let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+    std::io::Write::write_all(&mut file, br#"{"testRunArtifact":{"log":"#)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_resume() -> Result<()> {
+    use assert_fs::prelude::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let output_file = fs.child("output.jsonl");
+    write_interrupted_run(output_file.path()).await?;
+
+    let run = TestRun::resume(
+        Config::builder().with_timestamp_provider(Box::new(FixedTsProvider {})),
+        output_file.path(),
+    )
+    .await?;
+    assert_eq!(run.name(), "run_name");
+    assert!(run.dut().hardware_info("hw0").is_some());
+
+    let step = run.add_step("after reboot").start().await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = std::fs::read_to_string(output_file.path())?;
+    let artifacts = content
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .collect::<Vec<_>>();
+    let expected = [
+        json_schema_version(),
+        json!({
+            "testRunArtifact": {
+                "testRunStart": {
+                    "dutInfo": {
+                        "dutInfoId": "dut_id",
+                        "name": "dut",
+                        "hardwareInfos": [{
+                            "hardwareInfoId": "hw0",
+                            "name": "fan",
+                            "location": "board0/fan"
+                        }]
+                    },
+                    "name": "run_name",
+                    "parameters": {"param": "value"},
+                    "version": "1.0",
+                    "commandLine": ""
+                }
+            },
+            "sequenceNumber": 1,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {"name": "fan", "value": 1000}
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepStart": {"name": "after reboot"}
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepEnd": {"status": "COMPLETE"}
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(7),
+    ];
+    assert_eq!(artifacts.len(), expected.len());
+    for (actual, expected) in artifacts.iter().zip(&expected) {
+        assert_json_eq!(actual, expected);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_resume_after_end() -> Result<()> {
+    use assert_fs::prelude::*;
+
+    let fs = assert_fs::TempDir::new()?;
+    let output_file = fs.child("output.jsonl");
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_file_output(output_file.path())
+                .await?
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let result = TestRun::resume(Config::builder(), output_file.path()).await;
+    assert!(matches!(
+        result,
+        Err(OcptvError::Resume(ResumeError::AlreadyEnded))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_abort() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepStart": {"name": "ended step"}
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepEnd": {"status": "COMPLETE"}
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "error": {
+                    "symptom": "dut-unreachable",
+                    "message": "lost connection"
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {"status": "ERROR"}
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {"result": "FAIL", "status": "ERROR"}
+            },
+            "sequenceNumber": 7,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        let open = run.add_step("first step").start().await?;
+        let ended = run.add_step("ended step").start().await?;
+        ended.end(TestStatus::Complete).await?;
+        // never started, so not ended either
+        let _unstarted = run.add_step("unstarted step");
+
+        run.abort("dut-unreachable", "lost connection").await?;
+        assert!(matches!(
+            open.add_log(LogSeverity::Info, "too late").await,
+            Err(OcptvError::RunAlreadyEnded)
+        ));
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_abort_with() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json!({
+            "testRunArtifact": {
+                "error": {
+                    "symptom": "power-cycle",
+                    "message": "the dut rebooted",
+                    "softwareInfoIds": ["sw0"]
+                }
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {"result": "NOT_APPLICABLE", "status": "ERROR"}
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let error = Error::builder("power-cycle")
+            .message("the dut rebooted")
+            .add_software_info(dut.software_info("sw0").unwrap())
+            .build();
+        let run = run_builder.build().start(dut).await?;
+
+        run.abort_with(&error, TestResult::NotApplicable).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_abort_with_foreign_software() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {"result": "FAIL", "status": "ERROR"}
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let mut other = DutInfo::new("other_dut");
+        let sw_info = other.add_software_info(SoftwareInfo::builder("bios").build());
+        let error = Error::builder("power-cycle")
+            .add_software_info(&sw_info)
+            .build();
+        let run = run_builder.build().start(dut).await?;
+
+        // the error is rejected, but the run still ends
+        let result = run.abort_with(&error, TestResult::Fail).await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::ForeignSoftwareInfo(id, dut_id))
+                if id == "other_dut_sw_0" && dut_id == "other_dut"
+        ));
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_skip() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json!({
+            "testRunArtifact": {
+                "log": {
+                    "message": "unsupported platform",
+                    "severity": "INFO"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {"result": "NOT_APPLICABLE", "status": "SKIP"}
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        // skipping after some steps ran is allowed
+        let step = run.add_step("first step").start().await?;
+        step.end(TestStatus::Complete).await?;
+
+        run.skip("unsupported platform").await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_warns_on_open_steps() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepStart": {"name": "ended step"}
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepEnd": {"status": "COMPLETE"}
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "log": {
+                    "message": "steps not ended before the run end: step0",
+                    "severity": "WARNING"
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(6),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        let _open = run.add_step("first step").start().await?;
+        let ended = run.add_step("ended step").start().await?;
+        assert_eq!(run.active_steps(), ["step0", "step1"]);
+
+        ended.end(TestStatus::Complete).await?;
+        assert_eq!(run.active_steps(), ["step0"]);
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_testrun_ends_open_steps() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .end_open_steps(true)
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    let run = Arc::new(run);
+
+    // steps started from spawned tasks, half of them left open
+    let mut tasks = vec![];
+    for i in 0..8 {
+        let run = Arc::clone(&run);
+        tasks.push(tokio::spawn(async move {
+            let step = run.add_step(&format!("step {}", i)).start().await?;
+            step.add_measurement("fan", i).await?;
+            if i % 2 == 0 {
+                step.end(TestStatus::Complete).await?;
+                return Ok(None);
+            }
+            Ok::<_, OcptvError>(Some(step))
+        }));
+    }
+    let mut open = vec![];
+    for task in tasks {
+        open.extend(task.await??);
+    }
+    assert_eq!(run.active_steps().len(), 4);
+
+    Arc::into_inner(run)
+        .unwrap()
+        .end(TestStatus::Complete, TestResult::Pass)
+        .await?;
+
+    let ends = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|a| {
+            a["testStepArtifact"]["testStepEnd"]["status"]
+                .as_str()
+                .map(str::to_owned)
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(ends.len(), 8);
+    assert_eq!(ends.iter().filter(|status| *status == "ERROR").count(), 4);
+    drop(open);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_forgets_dropped_steps() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_run_pass(3),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        let started = run.add_step("first step").start().await?;
+        let unstarted = run.add_step("unstarted step");
+        assert_eq!(run.active_steps(), ["step0"]);
+
+        drop(unstarted);
+        drop(started);
+        assert!(run.active_steps().is_empty());
+
+        // no warning about the dropped steps
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_start_with_borrowed_dut() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_run_pass(2),
+    ];
+
+    check_output(&expected, |run_builder, dut| async move {
+        let run = run_builder.build().start(&dut).await?;
+        // the run has its own copy
+        assert_eq!(run.dut(), &dut);
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_end_auto_pass() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "diagnosis": {
+                    "verdict": "fan-ok",
+                    "type": "PASS"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        let step = run.add_step("first step").start().await?;
+        step.add_diagnosis("fan-ok", spec::DiagnosisType::Pass)
+            .await?;
+        step.end_auto().await?;
+        run.end_auto().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_end_auto_with_failed_diagnosis() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "diagnosis": {
+                    "verdict": "fan-stalled",
+                    "type": "FAIL"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "result": "FAIL",
+                    "status": "COMPLETE"
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        let step = run.add_step("first step").start().await?;
+        step.add_diagnosis("fan-stalled", spec::DiagnosisType::Fail)
+            .await?;
+        step.end_auto().await?;
+        run.end_auto().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_end_auto_with_step_error() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "error": {
+                    "symptom": "fan-missing"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "result": "FAIL",
+                    "status": "COMPLETE"
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        let step = run.add_step("first step").start().await?;
+        step.add_error("fan-missing").await?;
+        step.end_auto().await?;
+        run.end_auto().await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_end_auto_with_run_error() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json!({
+            "testRunArtifact": {
+                "error": {
+                    "symptom": "bmc-unreachable"
+                }
+            },
+            "sequenceNumber": 2,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "result": "FAIL",
+                    "status": "COMPLETE"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+        run.add_error("bmc-unreachable").await?;
+        run.end_auto().await?;
+
+        Ok(())
+    })
+    .await
+}
@@ -0,0 +1,97 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+#![cfg(feature = "schema-validation")]
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use ocptv::output::{
+    BufferWriter, Config, DiagnosisType, DutInfo, LogSeverity, Measurement, MeasurementValue,
+    OcptvError, TestResult, TestRun, TestStatus, Uri, ValidatingWriter, Validator, ValidatorType,
+    WriterError,
+};
+
+use super::fixture::*;
+
+fn validating_run(buffer: Arc<Mutex<Vec<String>>>) -> TestRun {
+    let writer = ValidatingWriter::new(Box::new(BufferWriter::new(buffer)));
+
+    TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_custom_output(Box::new(writer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+}
+
+#[tokio::test]
+async fn test_validating_writer_accepts_all_artifacts() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = validating_run(Arc::clone(&buffer)).start(dut).await?;
+    run.add_log(LogSeverity::Info, "run log").await?;
+
+    let step = run.add_step("first step").start().await?;
+    step.add_log(LogSeverity::Debug, "step log").await?;
+    step.add_error_msg("symptom", "message").await?;
+    step.add_measurement_detail(
+        Measurement::builder("fan_speed", 1000)
+            .unit("rpm")
+            .add_validator(Validator::builder(ValidatorType::InSet, vec![1000, 2000]).build())
+            .add_metadata("key", json!({"nested": [1, 2]}))
+            .build(),
+    )
+    .await?;
+
+    let series = step.add_measurement_series("temp").start().await?;
+    series.add_measurement(42.5).await?;
+    series.end().await?;
+
+    step.add_diagnosis("verdict", DiagnosisType::Pass).await?;
+    step.add_file("log.txt", Uri::parse("file:///tmp/log.txt")?)
+        .await?;
+    step.add_extension("ext", json!({"any": ["thing"]})).await?;
+    step.end(TestStatus::Complete).await?;
+
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    assert_eq!(buffer.lock().await.len(), 15);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_validating_writer_reports_violation() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = validating_run(Arc::clone(&buffer)).start(dut).await?;
+    let step = run.add_step("first step").start().await?;
+
+    // objects are not a supported measurement value type, so they can only be emitted unchecked
+    let value = MeasurementValue::raw_unchecked(json!({"a": 1}));
+    let actual = step.add_measurement("name", value).await;
+    match actual {
+        Err(OcptvError::Writer(WriterError::SchemaViolation {
+            artifact, pointer, ..
+        })) => {
+            assert_eq!(artifact.kind, "testStepArtifact.measurement");
+            assert_eq!(artifact.seqno, 3);
+            assert_eq!(pointer, "/testStepArtifact/measurement/value");
+        }
+        _ => panic!("expected schema violation, got: {:?}", actual),
+    }
+
+    // the invalid artifact must not have been written
+    assert_eq!(buffer.lock().await.len(), 3);
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::process::Command;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::future::try_join_all;
+
+use ocptv::output::{Config, DutInfo, Measurement, TestResult, TestRun, TestStatus};
+
+const CHILD_ENV: &str = "OCPTV_TEST_STDOUT_CHILD";
+const BEGIN: &str = "--- begin ---";
+const END: &str = "--- end ---";
+
+const STEPS: usize = 4;
+const MEASUREMENTS: usize = 50;
+
+// note: this only does something when spawned by test_stdout_lines_are_not_spliced,
+// since the output capture of the test harness would hide the stdout writes otherwise
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_stdout_child_emitter() -> Result<()> {
+    if std::env::var_os(CHILD_ENV).is_none() {
+        return Ok(());
+    }
+
+    println!("{}", BEGIN);
+
+    let dut = DutInfo::builder("dut_id").build();
+    let run = Arc::new(
+        TestRun::builder("run_name", "1.0")
+            .config(Config::builder().build())
+            .build()
+            .start(dut)
+            .await?,
+    );
+
+    let mut tasks = vec![];
+    for i in 0..STEPS {
+        let run = Arc::clone(&run);
+        tasks.push(tokio::spawn(async move {
+            let step = run.add_step(&format!("step{}", i)).start().await?;
+            for j in 0..MEASUREMENTS {
+                // large enough to not fit the stdout line buffer in one go
+                let measurement = Measurement::builder("fan", j)
+                    .add_metadata("blob", "x".repeat(4096))
+                    .build();
+                step.add_measurement_detail(measurement).await?;
+            }
+            step.end(TestStatus::Complete).await
+        }));
+        tasks.push(tokio::spawn(async move {
+            for j in 0..MEASUREMENTS {
+                println!("progress {} {}", i, j);
+                tokio::task::yield_now().await;
+            }
+            Ok(())
+        }));
+    }
+    for result in try_join_all(tasks).await? {
+        result?;
+    }
+
+    Arc::into_inner(run)
+        .unwrap()
+        .end(TestStatus::Complete, TestResult::Pass)
+        .await?;
+
+    println!("{}", END);
+    Ok(())
+}
+
+#[test]
+fn test_stdout_lines_are_not_spliced() -> Result<()> {
+    let output = Command::new(std::env::current_exe()?)
+        .args([
+            "stdout::test_stdout_child_emitter",
+            "--exact",
+            "--nocapture",
+            "--quiet",
+        ])
+        .env(CHILD_ENV, "1")
+        .output()?;
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let lines = stdout
+        .lines()
+        .skip_while(|line| *line != BEGIN)
+        .skip(1)
+        .take_while(|line| *line != END)
+        .collect::<Vec<_>>();
+
+    let mut artifacts = 0;
+    for line in lines {
+        if line.starts_with("progress ") {
+            continue;
+        }
+        serde_json::from_str::<serde_json::Value>(line)?;
+        artifacts += 1;
+    }
+    // schemaVersion, run start and end, and the step artifacts
+    assert_eq!(artifacts, 3 + STEPS * (MEASUREMENTS + 2));
+
+    Ok(())
+}
@@ -10,7 +10,7 @@ use anyhow::Result;
 use serde_json::json;
 use tokio::sync::Mutex;
 
-use ocptv::output::{Config, DutInfo, OcptvError, TestRun, TestStatus};
+use ocptv::output::{Config, DutInfo, OcptvError, TestResult, TestRun, TestStatus, TestStepGuard};
 
 use super::fixture::*;
 
@@ -51,6 +51,156 @@ async fn test_testrun_with_step() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_testrun_with_timed_step() -> Result<()> {
+    use ocptv::output::LogSeverity;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": "checking fans",
+                    "severity": "INFO"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "step_duration",
+                    "value": 0,
+                    "unit": "ms"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let step = r.add_step("first step").start_timed().await?;
+        step.add_log(LogSeverity::Info, "checking fans").await?;
+        step.end(TestStatus::Complete).await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_timed_step_duration_precedes_end() -> Result<()> {
+    use ocptv::output::LogSeverity;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("fans").start_timed().await?;
+    let handle = step.handle();
+    let task = tokio::spawn(async move {
+        loop {
+            match handle.add_log(LogSeverity::Info, "polling").await {
+                Ok(()) => {}
+                Err(OcptvError::StepAlreadyEnded(_)) => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+    });
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    step.end(TestStatus::Complete).await?;
+    task.await??;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let duration = artifacts
+        .iter()
+        .position(|a| a["testStepArtifact"]["measurement"]["name"] == "step_duration")
+        .expect("the step duration is in the output");
+
+    // no other artifact of the step comes between the duration and the step end
+    assert_eq!(
+        artifacts[duration]["testStepArtifact"]["measurement"]["unit"],
+        "ms"
+    );
+    assert!(artifacts[duration + 1]["testStepArtifact"]
+        .get("testStepEnd")
+        .is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_step_ids() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepStart": {
+                    "name": "second step"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step1",
+                "testStepEnd": {
+                    "status": "COMPLETE"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let first = r.add_step("first step").start().await?;
+        let second = r.add_step("second step");
+        assert_eq!(second.id(), "step1");
+        assert_eq!(second.name(), "second step");
+
+        let second = second.start().await?;
+        assert_eq!(first.id(), "step0");
+        assert_eq!(first.name(), "first step");
+        assert_eq!(first.dut_id(), "dut_id");
+        assert_eq!(second.id(), "step1");
+        assert_eq!(second.dut_id(), "dut_id");
+
+        second.end(TestStatus::Complete).await?;
+        first.end(TestStatus::Complete).await?;
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_testrun_step_scope_log() -> Result<()> {
     use ocptv::output::{LogSeverity, TestStatus};
@@ -141,6 +291,69 @@ async fn test_step_with_extension() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_step_with_nested_extension() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "extension": {
+                    "name": "fan_report",
+                    "content": {
+                        "zone": "front",
+                        "fans": [
+                            { "id": 0, "rpm": 8000 },
+                            { "id": 1, "rpm": 7900 }
+                        ]
+                    }
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "extension": {
+                    "name": "retries",
+                    "content": 3
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(5),
+        json_run_pass(6),
+    ];
+
+    #[derive(serde::Serialize)]
+    struct Fan {
+        id: u32,
+        rpm: u32,
+    }
+
+    #[derive(serde::Serialize)]
+    struct FanReport {
+        zone: String,
+        fans: Vec<Fan>,
+    }
+
+    check_output_step(&expected, |s, _| async move {
+        let report = FanReport {
+            zone: "front".to_owned(),
+            fans: vec![Fan { id: 0, rpm: 8000 }, Fan { id: 1, rpm: 7900 }],
+        };
+        s.add_extension("fan_report", report).await?;
+        s.add_extension("retries", 3).await?;
+
+        Ok(())
+    })
+    .await
+}
+
 #[tokio::test]
 async fn test_step_with_extension_which_fails() -> Result<()> {
     #[derive(thiserror::Error, Debug, PartialEq)]
@@ -189,3 +402,990 @@ async fn test_step_with_extension_which_fails() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_step_rejects_artifacts_after_end() -> Result<()> {
+    use ocptv::output::{LogSeverity, ScopedTestStep};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_complete(3),
+        json_run_pass(4),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let leaked: Arc<Mutex<Option<ScopedTestStep>>> = Arc::new(Mutex::new(None));
+
+        let slot = Arc::clone(&leaked);
+        r.add_step("first step")
+            .scope(|s| async move {
+                *slot.lock().await = Some(s);
+                Ok(TestStatus::Complete)
+            })
+            .await?;
+
+        // the scoped step outlived the scope, which already ended the step
+        let s = leaked.lock().await.take().unwrap();
+        assert!(matches!(
+            s.add_log(LogSeverity::Info, "late").await,
+            Err(OcptvError::StepAlreadyEnded(id)) if id == "step0"
+        ));
+        assert!(matches!(
+            s.add_measurement_series("fan").start().await,
+            Err(OcptvError::StepAlreadyEnded(_))
+        ));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_scope_records_panic() -> Result<()> {
+    use std::panic::AssertUnwindSafe;
+
+    use futures::FutureExt;
+
+    use ocptv::output::{TestResult, TestRunOutcome};
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "error": {
+                    "symptom": "panic",
+                    "message": "fan controller exploded"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "error": {
+                    "symptom": "panic",
+                    "message": "fan controller exploded"
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testRunArtifact": {
+                "testRunEnd": {
+                    "result": "NOT_APPLICABLE",
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+    ];
+
+    check_output(&expected, |run_builder, dut| async move {
+        let result = AssertUnwindSafe(run_builder.build().scope(dut, |r| async move {
+            r.add_step("first step")
+                .scope(|_s| async move {
+                    if true {
+                        panic!("fan controller exploded");
+                    }
+                    Ok(TestStatus::Complete)
+                })
+                .await?;
+
+            Ok(TestRunOutcome {
+                status: TestStatus::Complete,
+                result: TestResult::Pass,
+            })
+        }))
+        .catch_unwind()
+        .await;
+
+        // the panic is resumed after the artifacts are emitted
+        let payload = result.expect_err("expected the scope to panic");
+        assert_eq!(
+            payload.downcast_ref::<&str>(),
+            Some(&"fan controller exploded")
+        );
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_custom_id() -> Result<()> {
+    let step = |id: &str, name: &str, seqno: i32| {
+        [
+            json!({
+                "testStepArtifact": {
+                    "testStepId": id,
+                    "testStepStart": {
+                        "name": name
+                    }
+                },
+                "sequenceNumber": seqno,
+                "timestamp": DATETIME_FORMATTED
+            }),
+            json!({
+                "testStepArtifact": {
+                    "testStepId": id,
+                    "testStepEnd": {
+                        "status": "COMPLETE"
+                    }
+                },
+                "sequenceNumber": seqno + 1,
+                "timestamp": DATETIME_FORMATTED
+            }),
+        ]
+    };
+    let mut expected = vec![json_schema_version(), json_run_default_start()];
+    expected.extend(step("step1", "custom", 2));
+    expected.extend(step("step0", "auto", 4));
+    // the custom id is skipped over by the generated ids
+    expected.extend(step("step2", "auto", 6));
+    expected.extend(step("fan_check", "fans", 8));
+    expected.push(json_run_pass(10));
+
+    check_output_run(&expected, |r, _| async move {
+        let steps = [
+            r.add_step_with_id("step1", "custom")?,
+            r.add_step("auto"),
+            r.add_step("auto"),
+            r.add_step_with_id("fan_check", "fans")?,
+        ];
+        for step in steps {
+            step.start().await?.end(TestStatus::Complete).await?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_duplicate_custom_id() -> Result<()> {
+    check_output_run(
+        &[
+            json_schema_version(),
+            json_run_default_start(),
+            json_run_pass(2),
+        ],
+        |r, _| async move {
+            r.add_step("auto");
+            r.add_step_with_id("fan_check", "fans")?;
+
+            assert!(matches!(
+                r.add_step_with_id("fan_check", "fans again"),
+                Err(OcptvError::DuplicateStepId(id)) if id == "fan_check"
+            ));
+            assert!(matches!(
+                r.add_step_with_id("step0", "generated"),
+                Err(OcptvError::DuplicateStepId(id)) if id == "step0"
+            ));
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[test]
+fn test_step_handles_are_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<ocptv::output::StartedTestRun>();
+    assert_send_sync::<ocptv::output::TestStep>();
+    assert_send_sync::<ocptv::output::StartedTestStep>();
+    assert_send_sync::<ocptv::output::StepHandle>();
+    assert_send_sync::<ocptv::output::MeasurementSeries>();
+    assert_send_sync::<ocptv::output::StartedMeasurementSeries>();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_steps_from_spawned_tasks() -> Result<()> {
+    use std::collections::BTreeMap;
+
+    use futures::future::try_join_all;
+
+    const STEPS: usize = 8;
+    const MEASUREMENTS: usize = 100;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = Arc::new(
+        TestRun::builder("run_name", "1.0")
+            .config(
+                Config::builder()
+                    .with_buffer_output(Arc::clone(&buffer))
+                    .build(),
+            )
+            .build()
+            .start(DutInfo::builder("dut_id").build())
+            .await?,
+    );
+
+    let tasks = (0..STEPS).map(|i| {
+        let run = Arc::clone(&run);
+        tokio::spawn(async move {
+            let step = run.add_step(&format!("socket{}", i)).start().await?;
+            let series = step.add_measurement_series("temperature").start().await?;
+            for j in 0..MEASUREMENTS {
+                step.add_measurement("fan", j).await?;
+                series.add_measurement(j).await?;
+                tokio::task::yield_now().await;
+            }
+            series.end().await?;
+            step.end(TestStatus::Complete).await
+        })
+    });
+    for result in try_join_all(tasks).await? {
+        result?;
+    }
+    Arc::into_inner(run)
+        .unwrap()
+        .end(TestStatus::Complete, TestResult::Pass)
+        .await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    // schemaVersion, run start and end; step start and end, series start and end
+    assert_eq!(artifacts.len(), 3 + STEPS * (4 + 2 * MEASUREMENTS));
+
+    let seqnos = artifacts
+        .iter()
+        .map(|a| a["sequenceNumber"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert!(seqnos.windows(2).all(|w| w[0] < w[1]));
+
+    // every step artifact comes after its step start, and before its step end
+    let mut steps = BTreeMap::<&str, (bool, bool)>::new();
+    for artifact in &artifacts {
+        let Some(step) = artifact.get("testStepArtifact") else {
+            continue;
+        };
+        let id = step["testStepId"].as_str().unwrap();
+        let (started, ended) = steps.entry(id).or_default();
+        assert!(!*ended, "artifact after the end of {}", id);
+        if step.get("testStepStart").is_some() {
+            *started = true;
+        } else {
+            assert!(*started, "artifact before the start of {}", id);
+        }
+        if step.get("testStepEnd").is_some() {
+            *ended = true;
+        }
+    }
+    assert_eq!(steps.len(), STEPS);
+    assert!(steps.values().all(|(started, ended)| *started && *ended));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_measurements_from_step_handles() -> Result<()> {
+    use futures::future::try_join_all;
+
+    const TASKS: usize = 8;
+    const MEASUREMENTS: usize = 1000;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("fans").start().await?;
+    let tasks = (0..TASKS).map(|i| {
+        let handle = step.handle();
+        tokio::spawn(async move {
+            for j in 0..MEASUREMENTS {
+                handle.add_measurement(&format!("fan{}", i), j).await?;
+            }
+            Ok::<_, OcptvError>(handle)
+        })
+    });
+    let handles = try_join_all(tasks)
+        .await?
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?;
+    step.end(TestStatus::Complete).await?;
+
+    // the handles outlive the step, but can't emit in it anymore
+    let result = handles[0].add_measurement("fan0", 0).await;
+    assert!(matches!(result, Err(OcptvError::StepAlreadyEnded(_))));
+
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    // schemaVersion, run start and end, step start and end
+    assert_eq!(artifacts.len(), 5 + TASKS * MEASUREMENTS);
+
+    let seqnos = artifacts
+        .iter()
+        .map(|a| a["sequenceNumber"].as_u64().unwrap())
+        .collect::<Vec<_>>();
+    assert!(seqnos.windows(2).all(|w| w[0] < w[1]));
+
+    for i in 0..TASKS {
+        let name = format!("fan{}", i);
+        let values = artifacts
+            .iter()
+            .filter(|a| a["testStepArtifact"]["measurement"]["name"] == name.as_str())
+            .map(|a| {
+                a["testStepArtifact"]["measurement"]["value"]
+                    .as_u64()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(values, (0..MEASUREMENTS as u64).collect::<Vec<_>>());
+    }
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_step_handles_racing_step_end() -> Result<()> {
+    use futures::future::try_join_all;
+
+    const TASKS: usize = 8;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("fans").start().await?;
+    let tasks = (0..TASKS)
+        .map(|i| {
+            let handle = step.handle();
+            tokio::spawn(async move {
+                let mut written = 0;
+                loop {
+                    match handle.add_measurement(&format!("fan{}", i), written).await {
+                        Ok(()) => written += 1,
+                        Err(OcptvError::StepAlreadyEnded(_)) => return Ok(written),
+                        Err(e) => return Err(e),
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    step.end(TestStatus::Complete).await?;
+
+    let written = try_join_all(tasks)
+        .await?
+        .into_iter()
+        .sum::<Result<usize, _>>()?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let artifacts = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?;
+    let end = artifacts
+        .iter()
+        .position(|a| a["testStepArtifact"].get("testStepEnd").is_some())
+        .expect("the step end is in the output");
+    let measurements = artifacts
+        .iter()
+        .enumerate()
+        .filter(|(_, a)| a["testStepArtifact"].get("measurement").is_some())
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    // every accepted measurement was written, all of them before the step end
+    assert_eq!(measurements.len(), written);
+    assert!(measurements.iter().all(|i| *i < end));
+
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_steps_racing_run_end() -> Result<()> {
+    use futures::future::try_join_all;
+
+    const TASKS: usize = 8;
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("fans").start().await?;
+    let tasks = (0..TASKS)
+        .map(|i| {
+            let handle = step.handle();
+            tokio::spawn(async move {
+                let mut written = 0;
+                loop {
+                    match handle.add_measurement(&format!("fan{}", i), written).await {
+                        Ok(()) => written += 1,
+                        Err(OcptvError::RunAlreadyEnded) => return Ok(written),
+                        Err(e) => return Err(e),
+                    }
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let written = try_join_all(tasks)
+        .await?
+        .into_iter()
+        .sum::<Result<usize, _>>()?;
+
+    let artifacts = buffer.lock().await.clone();
+    let last = serde_json::from_str::<serde_json::Value>(artifacts.last().unwrap())?;
+    assert!(last["testRunArtifact"].get("testRunEnd").is_some());
+    let measurements = artifacts
+        .iter()
+        .filter(|line| line.contains("\"measurement\""))
+        .count();
+    assert_eq!(measurements, written);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_step_end_retried_after_failed_write() -> Result<()> {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let inflated = AtomicBool::new(false);
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_max_artifact_bytes(1024)
+                // the first step end is too large to be written
+                .with_emit_hook(Box::new(move |artifact| {
+                    if let Some(end) = artifact.pointer_mut("/testStepArtifact/testStepEnd") {
+                        if !inflated.swap(true, Ordering::AcqRel) {
+                            end["padding"] = "x".repeat(4096).into();
+                        }
+                    }
+                }))
+                .build(),
+        )
+        .end_open_steps(true)
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("first step").start().await?;
+    // note: the run only holds weak handles, so keep the step alive past its failed end
+    let _series = step.add_measurement_series("fan");
+    let result = step.end(TestStatus::Complete).await;
+    assert!(matches!(result, Err(OcptvError::Writer(_))));
+
+    // the step is still open, so the run end can still end it
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let ends = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|a| a["testStepArtifact"].get("testStepEnd").cloned())
+        .collect::<Vec<_>>();
+    assert_eq!(ends, [json!({"status": "ERROR"})]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_testrun_step_skip() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": "missing firmware",
+                    "severity": "INFO"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "SKIP"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(5),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let step = r.add_step("first step").start().await?;
+        step.skip("missing firmware").await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_skip_with_failed_log() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "SKIP"
+                }
+            },
+            // note: the rejected log still consumed its sequence number
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(5),
+    ];
+
+    check_output_with_config(
+        &expected,
+        |config| config.with_max_artifact_bytes(1024),
+        |run_builder, dut| async move {
+            let run = run_builder.build().start(dut).await?;
+            let step = run.add_step("first step").start().await?;
+
+            // the reason is too large to be written, but the step still ends
+            let result = step.skip(&"x".repeat(4096)).await;
+            assert!(matches!(result, Err(OcptvError::Writer(_))));
+
+            run.end(TestStatus::Complete, TestResult::Pass).await?;
+            Ok(())
+        },
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_skip_before_start() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": "NVMe not present",
+                    "severity": "INFO"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "SKIP"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(5),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        r.add_step("first step")
+            .skip_before_start("NVMe not present")
+            .await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_started_step_scope() -> Result<()> {
+    use ocptv::output::LogSeverity;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "log": {
+                    "message": "in scope",
+                    "severity": "INFO"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let step = r.add_step("first step").start().await?;
+        step.scope(|s| async move {
+            s.add_log(LogSeverity::Info, "in scope").await?;
+            Ok(TestStatus::Complete)
+        })
+        .await
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_scope_ends_step_on_error() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "testStepEnd": {
+                    "status": "ERROR"
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_run_pass(4),
+    ];
+
+    check_output_run(&expected, |r, _| async move {
+        let step = r.add_step("first step").start().await?;
+        let result = step
+            .scope(|_s| async move {
+                // an early return through `?`
+                Err(OcptvError::Other("sensor read failed".into()))?;
+                Ok(TestStatus::Complete)
+            })
+            .await;
+        assert!(matches!(result, Err(OcptvError::Other(_))));
+
+        Ok(())
+    })
+    .await
+}
+
+async fn read_fan(step: TestStepGuard, fail: bool) -> Result<(), OcptvError> {
+    step.add_measurement("fan", 1000).await?;
+    if fail {
+        // the guard ends the step on this early return
+        Err(OcptvError::Other("sensor read failed".into()))?;
+    }
+    step.end(TestStatus::Complete).await
+}
+
+async fn step_ends(buffer: &Arc<Mutex<Vec<String>>>) -> Result<Vec<serde_json::Value>> {
+    let mut ends = vec![];
+    for entry in buffer.lock().await.iter() {
+        let value = serde_json::from_str::<serde_json::Value>(entry)?;
+        if let Some(end) = value.pointer("/testStepArtifact/testStepEnd") {
+            ends.push(end.clone());
+        }
+    }
+    Ok(ends)
+}
+
+#[tokio::test]
+async fn test_step_guard() -> Result<()> {
+    for (fail, status) in [(true, "ERROR"), (false, "COMPLETE")] {
+        let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+        let run = TestRun::builder("run_name", "1.0")
+            .config(
+                Config::builder()
+                    .with_buffer_output(Arc::clone(&buffer))
+                    .build(),
+            )
+            .build()
+            .start(DutInfo::builder("dut_id").build())
+            .await?;
+
+        let step = run.add_step("first step").start().await?;
+        let result = read_fan(step.into_guard(TestStatus::Error), fail).await;
+        assert_eq!(result.is_err(), fail);
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+        assert_eq!(step_ends(&buffer).await?, [json!({ "status": status })]);
+    }
+
+    Ok(())
+}
+
+async fn read_fan_series(step: TestStepGuard, fail: bool) -> Result<(), OcptvError> {
+    let series = step
+        .add_measurement_series("fan")
+        .start()
+        .await?
+        .into_guard();
+    series.add_measurement(1000).await?;
+    series.add_measurement(1010).await?;
+    if fail {
+        // both guards end their artifact on this early return, the series first
+        Err(OcptvError::Other("sensor read failed".into()))?;
+    }
+
+    series.end().await?;
+    step.end(TestStatus::Complete).await
+}
+
+#[tokio::test]
+async fn test_measurement_series_guard() -> Result<()> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .build(),
+        )
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+
+    let step = run.add_step("first step").start().await?;
+    let result = read_fan_series(step.into_guard(TestStatus::Error), true).await;
+    assert!(matches!(result, Err(OcptvError::Other(_))));
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let ends = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .filter_map(|value| {
+            let step = value.get("testStepArtifact")?;
+            step.get("measurementSeriesEnd")
+                .or_else(|| step.get("testStepEnd"))
+                .cloned()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        ends,
+        [
+            json!({ "measurementSeriesId": "step0_series0", "totalCount": 2 }),
+            json!({ "status": "ERROR" }),
+        ]
+    );
+
+    Ok(())
+}
+
+fn json_step_log(seqno: i32, severity: &str, message: &str) -> serde_json::Value {
+    json!({
+        "testStepArtifact": {
+            "testStepId": "step0",
+            "log": {
+                "message": message,
+                "severity": severity
+            }
+        },
+        "sequenceNumber": seqno,
+        "timestamp": DATETIME_FORMATTED
+    })
+}
+
+#[tokio::test]
+async fn test_step_with_retries() -> Result<()> {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_log(3, "WARNING", "attempt 1 of 3 failed: sensor busy"),
+        json_step_log(4, "WARNING", "attempt 2 of 3 failed: sensor busy"),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan_speed",
+                    "value": 8000
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(6),
+        json_run_pass(7),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let calls = AtomicU32::new(0);
+        let rpm = s
+            .with_retries(3, Duration::ZERO, || async {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 | 1 => Err("sensor busy"),
+                    _ => Ok(8000),
+                }
+            })
+            .await?;
+        s.add_measurement("fan_speed", rpm).await?;
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_step_with_retries_exhausted() -> Result<()> {
+    use std::time::Duration;
+
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json_step_log(3, "WARNING", "attempt 1 of 2 failed: sensor busy"),
+        json_step_log(4, "WARNING", "attempt 2 of 2 failed: sensor busy"),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "error": {
+                    "symptom": "retries-exhausted",
+                    "message": "failed after 2 attempts: sensor busy"
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_log(6, "WARNING", "attempt 1 of 1 failed: no reply"),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "error": {
+                    "symptom": "retries-exhausted",
+                    "message": "failed after 1 attempts: no reply"
+                }
+            },
+            "sequenceNumber": 7,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(8),
+        json_run_pass(9),
+    ];
+
+    check_output_step(&expected, |s, _| async move {
+        let result = s
+            .with_retries(2, Duration::ZERO, || async { Err::<(), _>("sensor busy") })
+            .await;
+        assert!(matches!(
+            result,
+            Err(OcptvError::RetriesExhausted(2, e)) if e.to_string() == "sensor busy"
+        ));
+
+        // the last error is kept as the source
+        let result = s
+            .with_retries(1, Duration::ZERO, || async {
+                Err::<(), _>(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    "no reply",
+                ))
+            })
+            .await;
+        let source = std::error::Error::source(&result.unwrap_err())
+            .and_then(|e| e.downcast_ref::<std::io::Error>())
+            .map(std::io::Error::kind);
+        assert_eq!(source, Some(std::io::ErrorKind::TimedOut));
+
+        Ok(())
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_testrun_step_in_group() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "extension": {
+                    "name": "ocptv_step_group",
+                    "content": {
+                        "group": "thermal"
+                    }
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(4),
+        json_run_pass(5),
+    ];
+
+    check_output(&expected, |run_builder, dut| async {
+        let run = run_builder.build().start(dut).await?;
+
+        let step = run.step_in_group("thermal", "first step");
+        assert_eq!(step.group(), Some("thermal"));
+        let step = step.start().await?;
+        assert_eq!(step.group(), Some("thermal"));
+        // the grouped start marks the step as started, like a plain one
+        assert_eq!(run.active_steps(), ["step0"]);
+        step.end(TestStatus::Complete).await?;
+
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok(())
+    })
+    .await
+}
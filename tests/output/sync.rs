@@ -0,0 +1,176 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+#![cfg(feature = "sync")]
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use assert_json_diff::assert_json_eq;
+use serde_json::json;
+use tokio::sync::Mutex;
+
+use ocptv::output::{
+    sync, Config, DutInfo, HardwareInfo, Ident, SoftwareInfo, SoftwareType, TestResult, TestRun,
+    TestStatus,
+};
+
+use super::fixture::*;
+
+#[test]
+fn test_sync_run_step_measurement() -> Result<()> {
+    let expected = [
+        json_schema_version(),
+        json_run_default_start(),
+        json_step_default_start(),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "name",
+                    "value": 50
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesStart": {
+                    "measurementSeriesId": "step0_series0",
+                    "name": "series"
+                }
+            },
+            "sequenceNumber": 4,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesElement": {
+                    "index": 0,
+                    "measurementSeriesId": "step0_series0",
+                    "value": 60,
+                    "timestamp": DATETIME_FORMATTED
+                }
+            },
+            "sequenceNumber": 5,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurementSeriesEnd": {
+                    "measurementSeriesId": "step0_series0",
+                    "totalCount": 1
+                }
+            },
+            "sequenceNumber": 6,
+            "timestamp": DATETIME_FORMATTED
+        }),
+        json_step_complete(7),
+        json_run_pass(8),
+    ];
+
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let mut dut = DutInfo::builder("dut_id").build();
+    dut.add_software_info(
+        SoftwareInfo::builder("ubuntu")
+            .id(Ident::Exact("sw0".to_owned()))
+            .version("22")
+            .software_type(SoftwareType::System)
+            .build(),
+    );
+    dut.add_hardware_info(
+        HardwareInfo::builder("fan")
+            .id(Ident::Exact("hw0".to_owned()))
+            .location("board0/fan")
+            .build(),
+    );
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build();
+
+    let run = sync::TestRun::from_async(run)?.start(dut)?;
+    let step = run.add_step("first step").start()?;
+    step.add_measurement("name", 50)?;
+
+    let series = step.add_measurement_series("series").start()?;
+    series.add_measurement(60)?;
+    series.end()?;
+
+    step.end(TestStatus::Complete)?;
+    run.end(TestStatus::Complete, TestResult::Pass)?;
+
+    let actual = buffer.blocking_lock();
+    assert_eq!(actual.len(), expected.len());
+    for (i, entry) in actual.iter().enumerate() {
+        let value = serde_json::from_str::<serde_json::Value>(entry)?;
+        assert_json_eq!(value, expected[i]);
+    }
+
+    Ok(())
+}
+
+fn bytes_run(buffer: &Arc<Mutex<Vec<u8>>>) -> TestRun {
+    TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_bytes_output(Arc::clone(buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+}
+
+#[test]
+fn test_sync_output_matches_async() -> Result<()> {
+    let async_buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    rt.block_on(async {
+        let run = bytes_run(&async_buffer)
+            .start(DutInfo::builder("dut_id").build())
+            .await?;
+        let step = run.add_step("first step").start().await?;
+        step.add_measurement("name", 50).await?;
+
+        let series = step.add_measurement_series("series").start().await?;
+        series.add_measurement(60).await?;
+        series.end().await?;
+
+        step.end(TestStatus::Complete).await?;
+        run.end(TestStatus::Complete, TestResult::Pass).await?;
+        Ok::<(), anyhow::Error>(())
+    })?;
+
+    let sync_buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    let run = sync::TestRun::from_async(bytes_run(&sync_buffer))?
+        .start(DutInfo::builder("dut_id").build())?;
+    let step = run.add_step("first step").start()?;
+    step.add_measurement("name", 50)?;
+
+    let series = step.add_measurement_series("series").start()?;
+    series.add_measurement(60)?;
+    series.end()?;
+
+    step.end(TestStatus::Complete)?;
+    run.end(TestStatus::Complete, TestResult::Pass)?;
+
+    let expected = async_buffer.blocking_lock();
+    assert!(!expected.is_empty());
+    assert_eq!(*sync_buffer.blocking_lock(), *expected);
+
+    Ok(())
+}
@@ -0,0 +1,688 @@
+// (c) Meta Platforms, Inc. and affiliates.
+//
+// Use of this source code is governed by an MIT-style
+// license that can be found in the LICENSE file or at
+// https://opensource.org/licenses/MIT.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use assert_json_diff::assert_json_eq;
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncWrite, BufWriter, DuplexStream};
+use tokio::sync::Mutex;
+
+use ocptv::output::{
+    BoundedBuffer, BufferWriter, Config, DutInfo, Emitter, FlushPolicy, LogSeverity, Measurement,
+    OcptvError, OverflowPolicy, OversizePolicy, Separator, SplitWriter, StartedTestRun, TestResult,
+    TestRun, TestStatus, Writer, WriterError,
+};
+
+use super::fixture::*;
+
+async fn run_output(separator: Separator) -> Result<Vec<u8>> {
+    let buffer: Arc<Mutex<Vec<u8>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_bytes_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_record_separator(separator)
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    // control characters in the payload must never show up unescaped in the output
+    run.add_log(LogSeverity::Info, "rs:\x1e nul:\0 newline:\n")
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let bytes = buffer.lock().await.clone();
+    Ok(bytes)
+}
+
+fn check_records(records: &[&[u8]]) -> Result<()> {
+    assert_eq!(records.len(), 4);
+    for record in records {
+        serde_json::from_slice::<serde_json::Value>(record)?;
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_newline_separator() -> Result<()> {
+    let bytes = run_output(Separator::Newline).await?;
+
+    assert_eq!(bytes.last(), Some(&b'\n'));
+    assert!(!bytes.contains(&0x1e));
+    assert!(!bytes.contains(&0));
+
+    let records = bytes[..bytes.len() - 1]
+        .split(|b| *b == b'\n')
+        .collect::<Vec<_>>();
+    check_records(&records)
+}
+
+#[tokio::test]
+async fn test_writer_rs_separator() -> Result<()> {
+    let bytes = run_output(Separator::Rs).await?;
+
+    assert_eq!(bytes.first(), Some(&0x1e));
+    assert!(!bytes.contains(&0));
+
+    let records = bytes[1..].split(|b| *b == 0x1e).collect::<Vec<_>>();
+    for record in &records {
+        assert_eq!(record.last(), Some(&b'\n'));
+        assert_eq!(record.iter().filter(|b| **b == b'\n').count(), 1);
+    }
+    check_records(&records)
+}
+
+#[tokio::test]
+async fn test_writer_nul_separator() -> Result<()> {
+    let bytes = run_output(Separator::NulByte).await?;
+
+    assert_eq!(bytes.last(), Some(&0));
+    assert!(!bytes.contains(&0x1e));
+    assert!(!bytes.contains(&b'\n'));
+
+    let records = bytes[..bytes.len() - 1]
+        .split(|b| *b == 0)
+        .collect::<Vec<_>>();
+    check_records(&records)
+}
+
+struct StalledWriter {}
+
+#[async_trait]
+impl Writer for StalledWriter {
+    async fn write(&self, _s: &str) -> Result<(), std::io::Error> {
+        std::future::pending().await
+    }
+}
+
+#[tokio::test]
+async fn test_writer_timeout() -> Result<()> {
+    let timeout = Duration::from_millis(50);
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_custom_output(Box::new(StalledWriter {}))
+                .with_write_timeout(timeout)
+                .build(),
+        )
+        .build();
+
+    let start = Instant::now();
+    let actual = run.start(dut).await;
+    let elapsed = start.elapsed();
+
+    match actual {
+        Err(OcptvError::Writer(WriterError::Timeout {
+            artifact,
+            timeout: t,
+        })) => {
+            assert_eq!(t, timeout);
+            assert_eq!(artifact.kind, "schemaVersion");
+            assert_eq!(artifact.seqno, 0);
+        }
+        _ => panic!("expected write timeout"),
+    }
+    // generous bound for slow CI machines, but far from blocking forever
+    assert!(elapsed < timeout * 20, "took {:?}", elapsed);
+
+    Ok(())
+}
+
+struct FailingWriter {
+    remaining: AtomicUsize,
+}
+
+#[async_trait]
+impl Writer for FailingWriter {
+    async fn write(&self, _s: &str) -> Result<(), std::io::Error> {
+        // succeed for the first few writes, then fail all the rest
+        match self
+            .remaining
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+        {
+            Ok(_) => Ok(()),
+            Err(_) => Err(std::io::Error::other("disk full")),
+        }
+    }
+
+    async fn probe(&self) -> Result<(), std::io::Error> {
+        match self.remaining.load(Ordering::Acquire) {
+            0 => Err(std::io::Error::other("disk full")),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_writer_probe_before_start() -> Result<()> {
+    let emitter = Emitter::new(
+        Config::builder()
+            .with_custom_output(Box::new(FailingWriter {
+                remaining: AtomicUsize::new(0),
+            }))
+            .build(),
+    );
+
+    match emitter.probe().await {
+        Err(WriterError::Io { artifact, source }) => {
+            assert_eq!(artifact.kind, "probe");
+            assert_eq!(source.to_string(), "disk full");
+        }
+        _ => panic!("expected io writer error"),
+    }
+
+    let dut = DutInfo::builder("dut_id").build();
+    let actual = TestRun::builder("run_name", "1.0")
+        .emitter(&emitter)
+        .probe_output(true)
+        .build()
+        .start(dut)
+        .await;
+    assert!(matches!(
+        actual,
+        Err(OcptvError::Writer(WriterError::Io { .. }))
+    ));
+    // nothing was emitted, not even the schema version
+    assert_eq!(emitter.seqno(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_error_has_artifact_context() -> Result<()> {
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_custom_output(Box::new(FailingWriter {
+                    remaining: AtomicUsize::new(4),
+                }))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    let step = run.add_step("step").start().await?;
+    let series = step.add_measurement_series("series").start().await?;
+
+    let actual = series.add_measurement(42).await;
+    match &actual {
+        Err(OcptvError::Writer(WriterError::Io { artifact, source })) => {
+            assert_eq!(artifact.kind, "testStepArtifact.measurementSeriesElement");
+            assert_eq!(artifact.seqno, 4);
+            assert_eq!(source.to_string(), "disk full");
+        }
+        _ => panic!("expected io writer error"),
+    }
+
+    let formatted = actual.unwrap_err().to_string();
+    assert!(
+        formatted.contains("testStepArtifact.measurementSeriesElement seqno=4"),
+        "{}",
+        formatted
+    );
+
+    Ok(())
+}
+
+async fn run_bounded(buffer: &BoundedBuffer) -> Result<(), OcptvError> {
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_bounded_buffer_output(buffer.clone())
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    for i in 0..5 {
+        run.add_log(LogSeverity::Info, &format!("log {}", i))
+            .await?;
+    }
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+    Ok(())
+}
+
+async fn retained_seqnos(buffer: &BoundedBuffer) -> Result<Vec<u64>> {
+    buffer
+        .entries()
+        .await
+        .iter()
+        .map(|e| {
+            let value = serde_json::from_str::<serde_json::Value>(e)?;
+            value["sequenceNumber"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("missing sequenceNumber"))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_writer_bounded_buffer_drop_oldest() -> Result<()> {
+    let buffer = BoundedBuffer::new(3, OverflowPolicy::DropOldest);
+    run_bounded(&buffer).await?;
+
+    assert_eq!(retained_seqnos(&buffer).await?, vec![5, 6, 7]);
+    assert_eq!(buffer.dropped(), 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_bounded_buffer_drop_newest() -> Result<()> {
+    let buffer = BoundedBuffer::new(3, OverflowPolicy::DropNewest);
+    run_bounded(&buffer).await?;
+
+    assert_eq!(retained_seqnos(&buffer).await?, vec![0, 1, 2]);
+    assert_eq!(buffer.dropped(), 5);
+
+    // draining makes room for new artifacts
+    assert_eq!(buffer.drain().await.len(), 3);
+    buffer.write("{}").await?;
+    assert_eq!(buffer.entries().await, vec!["{}"]);
+    assert_eq!(buffer.dropped(), 5);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_bounded_buffer_error() -> Result<()> {
+    let buffer = BoundedBuffer::new(3, OverflowPolicy::Error);
+
+    let actual = run_bounded(&buffer).await;
+    match &actual {
+        Err(OcptvError::Writer(WriterError::BufferFull { artifact, capacity })) => {
+            assert_eq!(artifact.kind, "testRunArtifact.log");
+            assert_eq!(artifact.seqno, 3);
+            assert_eq!(*capacity, 3);
+        }
+        _ => panic!("expected buffer full error"),
+    }
+
+    assert_eq!(retained_seqnos(&buffer).await?, vec![0, 1, 2]);
+    assert_eq!(buffer.dropped(), 0);
+    Ok(())
+}
+
+fn artifact_keys(lines: &[String]) -> Result<Vec<(u64, String)>> {
+    lines
+        .iter()
+        .map(|l| {
+            let value = serde_json::from_str::<serde_json::Value>(l)?;
+            let seqno = value["sequenceNumber"]
+                .as_u64()
+                .ok_or_else(|| anyhow::anyhow!("missing sequenceNumber"))?;
+            let kind = ["testRunArtifact", "testStepArtifact"]
+                .iter()
+                .find_map(|c| value[c].as_object())
+                .and_then(|a| a.keys().find(|k| !k.ends_with("Id")).cloned())
+                .unwrap_or_else(|| "schemaVersion".to_owned());
+            Ok((seqno, kind))
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_writer_split_measurements() -> Result<()> {
+    let measurements: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let other: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_split_writer(SplitWriter::measurements(
+                    Box::new(BufferWriter::new(Arc::clone(&measurements))),
+                    Box::new(BufferWriter::new(Arc::clone(&other))),
+                ))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    let step = run.add_step("step").start().await?;
+    step.add_log(LogSeverity::Info, "before").await?;
+    step.add_measurement("fan", 1000).await?;
+    let series = step.add_measurement_series("temp").start().await?;
+    series.add_measurement(40).await?;
+    series.add_measurement(41).await?;
+    series.end().await?;
+    step.add_error("overheat").await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let measurements = artifact_keys(&measurements.lock().await)?;
+    let other = artifact_keys(&other.lock().await)?;
+
+    assert_eq!(measurements.len(), 5);
+    assert!(measurements
+        .iter()
+        .all(|(_, k)| k.starts_with("measurement")));
+    assert_eq!(other.len(), 7);
+    assert!(other.iter().all(|(_, k)| !k.starts_with("measurement")));
+
+    // each output is ordered, and merging them gives back the full sequence
+    for part in [&measurements, &other] {
+        assert!(part.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+    let mut seqnos = measurements
+        .iter()
+        .chain(other.iter())
+        .map(|(s, _)| *s)
+        .collect::<Vec<_>>();
+    seqnos.sort();
+    assert_eq!(seqnos, (0..12).collect::<Vec<_>>());
+
+    Ok(())
+}
+
+async fn run_oversized(
+    policy: OversizePolicy,
+) -> Result<(Result<(), OcptvError>, Vec<serde_json::Value>)> {
+    let buffer: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(vec![]));
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_buffer_output(Arc::clone(&buffer))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .with_max_artifact_bytes(1024)
+                .with_oversize_policy(policy)
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    let step = run.add_step("step").start().await?;
+    let result = step
+        .add_measurement_detail(
+            Measurement::builder("fan", 1000)
+                .add_metadata("blob", "x".repeat(4096))
+                .build(),
+        )
+        .await;
+    step.add_measurement("fan", 1000).await?;
+    step.end(TestStatus::Complete).await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let output = buffer
+        .lock()
+        .await
+        .iter()
+        .map(|l| serde_json::from_str(l))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((result, output))
+}
+
+#[tokio::test]
+async fn test_writer_oversized_artifact_rejected() -> Result<()> {
+    let (result, output) = run_oversized(OversizePolicy::Reject).await?;
+
+    match result {
+        Err(OcptvError::Writer(WriterError::ArtifactTooLarge {
+            artifact,
+            size,
+            limit,
+        })) => {
+            assert_eq!(artifact.kind, "testStepArtifact.measurement");
+            assert!(size > 4096);
+            assert_eq!(limit, 1024);
+        }
+        _ => panic!("expected artifact too large error"),
+    }
+
+    // only the second, small measurement was written
+    let measurements = output
+        .iter()
+        .filter(|v| v["testStepArtifact"]["measurement"].is_object())
+        .collect::<Vec<_>>();
+    assert_eq!(measurements.len(), 1);
+    assert!(measurements[0]["testStepArtifact"]["measurement"]["metadata"].is_null());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_oversized_artifact_truncated() -> Result<()> {
+    let (result, output) = run_oversized(OversizePolicy::Truncate).await?;
+    result?;
+
+    assert!(output.iter().all(|v| v.to_string().len() <= 1024));
+
+    // the truncated measurement, followed by the error describing it
+    assert_json_eq!(
+        output[3],
+        serde_json::json!({
+            "testStepArtifact": {
+                "testStepId": "step0",
+                "measurement": {
+                    "name": "fan",
+                    "value": 1000
+                }
+            },
+            "sequenceNumber": 3,
+            "timestamp": DATETIME_FORMATTED
+        })
+    );
+    let error = &output[4]["testStepArtifact"]["error"];
+    assert_eq!(output[4]["testStepArtifact"]["testStepId"], "step0");
+    assert_eq!(error["symptom"], "ocptv-artifact-truncated");
+    assert!(error["message"]
+        .as_str()
+        .is_some_and(|m| m.contains("testStepArtifact.measurement seqno=3")));
+    assert_eq!(output[4]["sequenceNumber"], 4);
+
+    Ok(())
+}
+
+async fn read_available(reader: &mut DuplexStream) -> Result<String> {
+    let mut content = vec![0u8; 64 * 1024];
+    match tokio::time::timeout(Duration::from_millis(50), reader.read(&mut content)).await {
+        Ok(n) => Ok(String::from_utf8(content[..n?].to_vec())?),
+        Err(_) => Ok(String::new()),
+    }
+}
+
+#[tokio::test]
+async fn test_writer_async_sink() -> Result<()> {
+    let (sink, mut reader) = tokio::io::duplex(64 * 1024);
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                // buffered, so that the content only shows up in the reader when flushed
+                .with_async_writer(Box::new(BufWriter::new(sink)))
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+
+    // every artifact is flushed as soon as it's written
+    assert_eq!(read_available(&mut reader).await?.lines().count(), 2);
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = read_available(&mut reader).await?;
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 1);
+    assert_json_eq!(
+        serde_json::from_str::<serde_json::Value>(lines[0])?,
+        json_run_pass(2)
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_async_sink_flush_on_end() -> Result<()> {
+    let (sink, mut reader) = tokio::io::duplex(64 * 1024);
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_async_writer_flush(Box::new(BufWriter::new(sink)), FlushPolicy::OnEnd)
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    run.add_log(LogSeverity::Info, "log").await?;
+
+    assert_eq!(read_available(&mut reader).await?, "");
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    let content = read_available(&mut reader).await?;
+    assert_eq!(content.lines().count(), 4);
+    for line in content.lines() {
+        serde_json::from_str::<serde_json::Value>(line)?;
+    }
+
+    Ok(())
+}
+
+async fn start_buffered_run(
+    sink: Box<dyn AsyncWrite + Send + Unpin>,
+) -> Result<StartedTestRun, OcptvError> {
+    let dut = DutInfo::builder("dut_id").build();
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(
+            Config::builder()
+                .with_async_writer_flush(sink, FlushPolicy::OnEnd)
+                .with_timestamp_provider(Box::new(FixedTsProvider {}))
+                .build(),
+        )
+        .build()
+        .start(dut)
+        .await?;
+    run.add_log(LogSeverity::Info, "log").await?;
+    Ok(run)
+}
+
+#[tokio::test]
+async fn test_writer_async_sink_close() -> Result<()> {
+    let (sink, mut reader) = tokio::io::duplex(64 * 1024);
+    let run = start_buffered_run(Box::new(BufWriter::new(sink))).await?;
+
+    assert_eq!(read_available(&mut reader).await?, "");
+    run.close().await?;
+
+    // the sink was shut down, so the stream ends after the flushed artifacts
+    let mut content = String::new();
+    reader.read_to_string(&mut content).await?;
+    assert_eq!(content.lines().count(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_writer_async_sink_flushed_on_drop() -> Result<()> {
+    let (sink, mut reader) = tokio::io::duplex(64 * 1024);
+    let run = start_buffered_run(Box::new(BufWriter::new(sink))).await?;
+    let step = run.add_step("step").start().await?;
+
+    // the run never ends, eg. because the diagnostic bailed out early
+    drop(run);
+    assert_eq!(read_available(&mut reader).await?, "");
+    drop(step);
+
+    let mut content = String::new();
+    reader.read_to_string(&mut content).await?;
+    let lines = content.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[3].contains("testStepStart"));
+
+    Ok(())
+}
+
+// same as the above, but with a file output; only run in coverage mode, since this
+// needs a real filesystem (see the file output tests in config.rs)
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_writer_file_flushed_on_drop() -> Result<()> {
+    let fs = assert_fs::TempDir::new()?;
+    let path = fs.path().join("output.jsonl");
+
+    let file = tokio::fs::File::create(&path).await?;
+    let run = start_buffered_run(Box::new(BufWriter::new(file))).await?;
+    drop(run);
+
+    let content = std::fs::read_to_string(&path)?;
+    assert_eq!(content.lines().count(), 3);
+    for line in content.lines() {
+        serde_json::from_str::<serde_json::Value>(line)?;
+    }
+
+    Ok(())
+}
+
+// appends a test run to a file whose last record is not terminated
+#[cfg(coverage)]
+async fn append_run(existing: &[u8], config: ocptv::output::ConfigBuilder) -> Result<Vec<u8>> {
+    let fs = assert_fs::TempDir::new()?;
+    let path = fs.path().join("output");
+    std::fs::write(&path, existing)?;
+
+    let run = TestRun::builder("run_name", "1.0")
+        .config(config.with_file_output_append(&path).await?.build())
+        .build()
+        .start(DutInfo::builder("dut_id").build())
+        .await?;
+    run.end(TestStatus::Complete, TestResult::Pass).await?;
+
+    Ok(std::fs::read(&path)?)
+}
+
+#[cfg(coverage)]
+#[tokio::test]
+async fn test_writer_file_append_uses_separator() -> Result<()> {
+    let config = Config::builder().with_record_separator(Separator::NulByte);
+    let bytes = append_run(b"{\"a\":1}", config).await?;
+
+    assert!(bytes.starts_with(b"{\"a\":1}\0{"));
+    assert!(!bytes.contains(&b'\n'));
+
+    Ok(())
+}
+
+#[cfg(all(coverage, feature = "msgpack"))]
+#[tokio::test]
+async fn test_writer_file_append_binary_format() -> Result<()> {
+    use ocptv::output::OutputFormat;
+
+    let config = Config::builder().with_output_format(OutputFormat::MessagePack);
+    let bytes = append_run(b"abc", config).await?;
+
+    // note: the records follow the existing content, without a newline in between
+    assert!(bytes.starts_with(b"abc"));
+    let records = ocptv::reader::from_msgpack_reader(std::io::Cursor::new(bytes[3..].to_vec()))
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(records.len(), 3);
+
+    Ok(())
+}